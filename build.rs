@@ -0,0 +1,47 @@
+use std::{env, fs, path::Path};
+
+// The modules shared with the crate refer to these names through 'crate::' and 'alloc::', like in
+// the crate.
+extern crate alloc;
+
+type Bitboard = u64;
+type Index = usize;
+
+#[allow(dead_code)]
+#[path = "src/masks.rs"]
+mod masks;
+
+#[allow(dead_code)]
+#[path = "src/eval/kpk_gen.rs"]
+mod kpk_gen;
+
+use kpk_gen::generate_kpk;
+use masks::{king_mask, white_king_pawn_mask};
+
+fn main()
+{
+    // Generate the KPK bitbase, so that the crate embeds it instead of generating it at runtime.
+    println!("cargo:rerun-if-changed=src/masks.rs");
+    println!("cargo:rerun-if-changed=src/eval/kpk_gen.rs");
+    let words: Vec<String> = generate_kpk().iter().map(|word| format!("{:#018x}", word)).collect();
+    let mut array = String::from("[\n");
+    for line in words.chunks(4)
+    {
+        array.push_str(&format!("    {},\n", line.join(", ")));
+    }
+    array.push_str("]\n");
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("kpk_bitbase.rs"), array)
+        .expect("Unable to write the KPK bitbase.");
+
+    // Generate the C header of the bindings.
+    #[cfg(feature = "ffi")]
+    {
+        let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+        println!("cargo:rerun-if-changed=src/ffi.rs");
+        println!("cargo:rerun-if-changed=cbindgen.toml");
+        cbindgen::generate(&crate_dir)
+            .expect("Unable to generate the C bindings.")
+            .write_to_file(format!("{}/include/barnarok.h", crate_dir));
+    }
+}
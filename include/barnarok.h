@@ -0,0 +1,57 @@
+/* Generated by cbindgen from src/ffi.rs, do not edit. */
+
+#ifndef BARNAROK_H
+#define BARNAROK_H
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef struct barnarok_board barnarok_board;
+
+typedef struct Direction Direction;
+
+typedef struct Score Score;
+
+typedef struct Variant Variant;
+
+// Create a board in the initial position. It must be freed with `barnarok_board_free`.
+barnarok_board *barnarok_board_new(void);
+
+// Free a board created by `barnarok_board_new`. Does nothing if `board` is null.
+//
+// # Safety
+// `board` must be null or come from `barnarok_board_new`, and must not be used afterwards.
+void barnarok_board_free(barnarok_board *board);
+
+// Set up the position of a FEN string. Returns 0, or -1 if the FEN is invalid, in which case the
+// board is unchanged.
+//
+// # Safety
+// `board` must be a valid board and `fen` a NUL-terminated string.
+int barnarok_board_set_fen(barnarok_board *board, const char *fen);
+
+// Write the legal moves, separated by spaces, as a NUL-terminated string in `buffer` of `size`
+// bytes. Returns the number of moves, or -1 if the buffer is too small.
+//
+// # Safety
+// `board` must be a valid board and `buffer` must point to `size` writable bytes.
+int barnarok_board_legal_moves(barnarok_board *board, char *buffer, size_t size);
+
+// Play a legal move given in UCI. Returns 0, or -1 if the move is not legal.
+//
+// # Safety
+// `board` must be a valid board and `uci` a NUL-terminated string.
+int barnarok_board_make_move(barnarok_board *board, const char *uci);
+
+// Search the best move at the given depth, and write it in UCI as a NUL-terminated string in
+// `buffer` of `size` bytes (6 bytes are always enough). Returns 0, or -1 if there are no legal
+// moves or the buffer is too small.
+//
+// # Safety
+// `board` must be a valid board and `buffer` must point to `size` writable bytes.
+int barnarok_best_move(barnarok_board *board, uint8_t depth, char *buffer, size_t size);
+
+#endif  /* BARNAROK_H */
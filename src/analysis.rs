@@ -0,0 +1,137 @@
+use std::time::Instant;
+
+use crate::*;
+
+// Search the position with iterative deepening, printing a table row for each line after each
+// iteration: depth, line number, score, nodes, elapsed time and principal variation in SAN.
+// Unless 'search_moves' is empty, only the root moves it contains are searched.
+// The hit rate of the evaluation cache is printed at the end.
+pub fn print_analysis(
+    board: &mut Board,
+    depth: u8,
+    multipv: usize,
+    search_moves: &[Move],
+    search: &mut Search,
+) -> Vec<PvLine>
+{
+    let start = Instant::now();
+    print_analysis_header();
+    let lines = search.analyze(board, depth, multipv, search_moves, |d, board, lines, info| {
+        print_analysis_rows(d, board, lines, info, start)
+    });
+    println!("evaluation cache hit rate: {:.1}%", 100.0 * search.info.eval_cache_hit_rate());
+    return lines;
+}
+
+// Same as 'print_analysis', but search in the background until the stop flag of the search is set,
+// or until 'INFINITE_DEPTH'. The search is given back at the end.
+pub fn print_infinite_analysis(
+    board: &Board,
+    multipv: usize,
+    search_moves: &[Move],
+    search: Search,
+) -> (Vec<PvLine>, Search)
+{
+    let start = Instant::now();
+    print_analysis_header();
+    let analysis = InfiniteAnalysis::start(
+        board,
+        multipv,
+        search_moves,
+        search,
+        move |d, board, lines, info| print_analysis_rows(d, board, lines, info, start),
+    );
+    return analysis.wait();
+}
+
+// Print the best line found by an analysis, in SAN.
+pub fn print_best_line(board: &mut Board, lines: &[PvLine])
+{
+    match lines.first()
+    {
+        Some(line) =>
+        {
+            println!("best line: {} {}", line.score, moves_to_san(board, &line.moves))
+        },
+        None => println!("No legal move to search."),
+    }
+}
+
+fn print_analysis_header()
+{
+    println!("{:>7} {:>4} {:>10} {:>10} {:>8}  pv", "depth", "line", "score", "nodes", "time");
+}
+
+// Print a table row for each line found by an iteration of the analysis, with the depth followed
+// by the selective depth.
+fn print_analysis_rows(
+    depth: u8,
+    board: &mut Board,
+    lines: &[PvLine],
+    info: &SearchInfo,
+    start: Instant,
+)
+{
+    for (i, line) in lines.iter().enumerate()
+    {
+        println!(
+            "{:>7} {:>4} {:>10} {:>10} {:>6}ms  {}",
+            format!("{}/{}", depth, info.seldepth),
+            i + 1,
+            line.score.to_string(),
+            info.nodes,
+            start.elapsed().as_millis(),
+            moves_to_san(board, &line.moves)
+        );
+    }
+}
+
+// Search the given root move, or the best move if none is given, and the best alternative to it,
+// and print both lines in SAN with how much worse the alternative is.
+pub fn print_alternative(board: &mut Board, depth: u8, excluded: Option<Move>, search: &mut Search)
+{
+    let Some(result) = search.alternative(board, depth, excluded)
+    else
+    {
+        println!("No legal move to search.");
+        return;
+    };
+    println!(
+        "{:<12}{:>10}  {}",
+        "move",
+        result.excluded.score.to_string(),
+        moves_to_san(board, &result.excluded.moves)
+    );
+    match (&result.alternative, result.loss())
+    {
+        (Some(line), Some(loss)) =>
+        {
+            println!(
+                "{:<12}{:>10}  {}",
+                "alternative",
+                line.score.to_string(),
+                moves_to_san(board, &line.moves)
+            );
+            println!("{:<12}{:>10}", "difference", loss.to_string());
+        },
+        _ => println!("No alternative: it is the only legal move."),
+    }
+    println!("{} nodes", search.info.nodes);
+}
+
+// Search the position after each move of a game, and set the evaluation of the move to the score
+// found, from white's perspective. The positions where the game is over keep their evaluation.
+pub fn attach_evals(record: &mut GameRecord, depth: u8, search: &mut Search) -> Result<(), String>
+{
+    let mut board = record.start_board()?;
+    for recorded in record.moves.iter_mut()
+    {
+        board.make_move(recorded.mv);
+        let (score, best_move) = search.search(&mut board, depth);
+        if best_move.is_some()
+        {
+            recorded.annotation.eval = Some(if board.white_to_play { score } else { -score });
+        }
+    }
+    return Ok(());
+}
@@ -0,0 +1,233 @@
+use crate::*;
+
+// Scores beyond this many centipawns, mates included, count as this many when measuring how much a
+// move loses: a move keeping a winning position is not a mistake, even if it wins more slowly.
+const MAX_REVIEW_CENTIPAWNS: i32 = 1000;
+
+// How bad a move is, from the centipawns it loses compared to the best move.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum MoveClass
+{
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl MoveClass
+{
+    pub fn name(&self) -> &'static str
+    {
+        return match self
+        {
+            MoveClass::Inaccuracy => "Inaccuracy",
+            MoveClass::Mistake => "Mistake",
+            MoveClass::Blunder => "Blunder",
+        };
+    }
+
+    // Get the numeric annotation glyph of the class: "?!", "?" or "??".
+    pub fn nag(&self) -> u8
+    {
+        return match self
+        {
+            MoveClass::Inaccuracy => 6,
+            MoveClass::Mistake => 2,
+            MoveClass::Blunder => 4,
+        };
+    }
+}
+
+// Minimum number of centipawns lost by a move of each class.
+#[derive(Debug, Clone, Copy)]
+pub struct LossThresholds
+{
+    pub inaccuracy: i32,
+    pub mistake: i32,
+    pub blunder: i32,
+}
+
+impl Default for LossThresholds
+{
+    fn default() -> Self
+    {
+        return LossThresholds { inaccuracy: 50, mistake: 100, blunder: 300 };
+    }
+}
+
+impl LossThresholds
+{
+    pub fn classify(&self, loss: i32) -> Option<MoveClass>
+    {
+        if loss >= self.blunder
+        {
+            return Some(MoveClass::Blunder);
+        }
+        if loss >= self.mistake
+        {
+            return Some(MoveClass::Mistake);
+        }
+        if loss >= self.inaccuracy
+        {
+            return Some(MoveClass::Inaccuracy);
+        }
+        return None;
+    }
+}
+
+// What the engine thinks of a move of a game. The scores are from the perspective of the side that
+// played it.
+#[derive(Debug, Clone, Copy)]
+pub struct MoveReview
+{
+    // Whether white played the move.
+    pub white: bool,
+    // Game phase of the position where the move was played.
+    pub phase: i32,
+    // The move the engine prefers, if the position had a legal move.
+    pub best_move: Option<Move>,
+    pub best_score: Score,
+    pub played_score: Score,
+    // Centipawns lost by the move compared to the best one, never negative.
+    pub loss: i32,
+    pub class: Option<MoveClass>,
+}
+
+// Search every position of a game, and review each move against the best move of its position.
+// The evaluation after each move is attached to it, and the inaccuracies, mistakes and blunders
+// get their glyph and a comment naming the best move.
+// Return the review of each move.
+pub fn annotate_game(
+    record: &mut GameRecord,
+    depth: u8,
+    thresholds: &LossThresholds,
+    search: &mut Search,
+) -> Result<Vec<MoveReview>, String>
+{
+    // Search the position before each move, and the final position.
+    let mut board = record.start_board()?;
+    let mut searches = vec![];
+    for recorded in record.moves.iter()
+    {
+        searches.push(search.search(&mut board, depth));
+        board.make_move(recorded.mv);
+    }
+    searches.push(search.search(&mut board, depth));
+
+    let mut board = record.start_board()?;
+    let mut reviews = vec![];
+    for (i, recorded) in record.moves.iter_mut().enumerate()
+    {
+        let (best_score, best_move) = searches[i];
+        let (next_score, next_best_move) = searches[i + 1];
+        let played_score = -next_score;
+        // The search of the next position can disagree with the search of this one, so the best
+        // move never loses anything.
+        let loss = if best_move == Some(recorded.mv)
+        {
+            0
+        }
+        else
+        {
+            (review_centipawns(best_score) - review_centipawns(played_score)).max(0)
+        };
+        let class = thresholds.classify(loss);
+        let white = board.white_to_play;
+        let phase = game_phase(&board);
+
+        let annotation = &mut recorded.annotation;
+        // No evaluation is written once the game is over on the board.
+        if next_best_move.is_some()
+        {
+            annotation.eval = Some(if board.white_to_play { played_score } else { -played_score });
+        }
+        if let (Some(class), Some(best_move)) = (class, best_move)
+        {
+            if !annotation.nags.contains(&class.nag())
+            {
+                annotation.nags.push(class.nag());
+            }
+            let text = format!("{}. {} was best.", class.name(), best_move.to_san(&mut board));
+            annotation.comment = Some(match annotation.comment.take()
+            {
+                Some(comment) => format!("{} {}", comment, text),
+                None => text,
+            });
+        }
+        board.make_move(recorded.mv);
+        reviews.push(MoveReview { white, phase, best_move, best_score, played_score, loss, class });
+    }
+    return Ok(reviews);
+}
+
+// Summary of the moves of a player in a reviewed game.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PlayerReport
+{
+    pub moves: usize,
+    pub inaccuracies: usize,
+    pub mistakes: usize,
+    pub blunders: usize,
+    // Average number of centipawns lost by a move.
+    pub average_loss: f64,
+    // Between 0 and 100, where 100 means that every move kept the winning chances of the best one.
+    pub accuracy: f64,
+}
+
+impl PlayerReport
+{
+    // Summarize the moves of white or black. The accuracy of each move decreases exponentially with
+    // the expected points it loses, in percent, and the accuracy of the game is the average of the
+    // arithmetic and the harmonic means of the accuracies of its moves, so that a few bad moves
+    // weigh more than in a plain average, like on lichess.
+    pub fn from_reviews(reviews: &[MoveReview], white: bool) -> Self
+    {
+        let mut report = PlayerReport::default();
+        let mut total_accuracy = 0.0;
+        let mut total_inverse_accuracy = 0.0;
+        for review in reviews.iter().filter(|review| review.white == white)
+        {
+            report.moves += 1;
+            match review.class
+            {
+                Some(MoveClass::Inaccuracy) => report.inaccuracies += 1,
+                Some(MoveClass::Mistake) => report.mistakes += 1,
+                Some(MoveClass::Blunder) => report.blunders += 1,
+                None => (),
+            }
+            report.average_loss += review.loss as f64;
+
+            let lost_points = if review.loss == 0
+            {
+                0.0
+            }
+            else
+            {
+                let best = review.best_score.expected_points(review.phase);
+                (100.0 * (best - review.played_score.expected_points(review.phase))).max(0.0)
+            };
+            let accuracy = (103.1668 * (-0.04354 * lost_points).exp() - 3.1669).clamp(0.0, 100.0);
+            total_accuracy += accuracy;
+            // A move of accuracy 0 would make the harmonic mean 0, so it counts as accuracy 1.
+            total_inverse_accuracy += 1.0 / accuracy.max(1.0);
+        }
+        if report.moves > 0
+        {
+            let moves = report.moves as f64;
+            report.average_loss /= moves;
+            report.accuracy = (total_accuracy / moves + moves / total_inverse_accuracy) / 2.0;
+        }
+        return report;
+    }
+}
+
+// Get the centipawns of a score, capped at 'MAX_REVIEW_CENTIPAWNS' for either side. The scores of
+// the searches are normalized this way before they are compared or averaged, so that the distance
+// of a mate or a huge advantage doesn't change the statistics.
+pub fn review_centipawns(score: Score) -> i32
+{
+    if score.is_mate()
+    {
+        return MAX_REVIEW_CENTIPAWNS * score.centipawns().signum();
+    }
+    return score.centipawns().clamp(-MAX_REVIEW_CENTIPAWNS, MAX_REVIEW_CENTIPAWNS);
+}
@@ -0,0 +1,100 @@
+use crate::{
+    Board, Move, Score, evaluate, generate_captures, generate_check_evasions, is_king_attacked,
+};
+
+// Search the position with a plain alpha-beta to 'depth', then the captures until the position is
+// quiet. It keeps no table between the nodes and needs nothing from the standard library, so it
+// is the search of the core, e.g. on the embedded targets; the full search is 'Search'.
+pub fn basic_search(board: &mut Board, depth: u8) -> (Score, Option<Move>)
+{
+    return basic_alpha_beta(board, -Score::INFINITE, Score::INFINITE, depth, 0);
+}
+
+fn basic_alpha_beta(
+    board: &mut Board,
+    mut alpha: Score,
+    beta: Score,
+    depth: u8,
+    ply: u8,
+) -> (Score, Option<Move>)
+{
+    if depth == 0
+    {
+        return (basic_quiesce(board, alpha, beta, ply), None);
+    }
+    let in_check = is_king_attacked(board, false);
+    let mut moves = if in_check { generate_check_evasions(board) } else { board.get_legal_moves() };
+    if moves.is_empty()
+    {
+        if board.variant_win().is_some()
+        {
+            return (Score::mate_in(ply), None);
+        }
+        if in_check || board.variant_loss().is_some()
+        {
+            return (Score::mated_in(ply), None);
+        }
+        return (Score::DRAW, None);
+    }
+    // The captures are searched first, since they cut off the most often.
+    moves.sort_by_key(|mv| mv.capture.is_none());
+
+    let mut max = -Score::INFINITE;
+    let mut best = None;
+    for mv in moves.iter()
+    {
+        board.make_move(*mv);
+        let score = -basic_alpha_beta(board, -beta, -alpha, depth - 1, ply + 1).0;
+        board.unmake_move(*mv);
+        if score > max
+        {
+            max = score;
+            best = Some(*mv);
+        }
+        if score > alpha
+        {
+            alpha = score;
+        }
+        if alpha >= beta
+        {
+            break;
+        }
+    }
+    return (max, best);
+}
+
+fn basic_quiesce(board: &mut Board, mut alpha: Score, beta: Score, ply: u8) -> Score
+{
+    if board.variant_loss().is_some()
+    {
+        return Score::mated_in(ply);
+    }
+    let mut best_value = evaluate(board);
+    if best_value >= beta
+    {
+        return best_value;
+    }
+    if best_value > alpha
+    {
+        alpha = best_value;
+    }
+    for mv in generate_captures(board).iter()
+    {
+        board.make_move(*mv);
+        let score = -basic_quiesce(board, -beta, -alpha, ply + 1);
+        board.unmake_move(*mv);
+        if score >= beta
+        {
+            return score;
+        }
+        if score > best_value
+        {
+            best_value = score;
+        }
+        if score > alpha
+        {
+            alpha = score;
+        }
+    }
+    return best_value;
+}
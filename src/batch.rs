@@ -0,0 +1,124 @@
+use std::{
+    fs,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+use crate::*;
+
+// Settings of the processing of a list of positions.
+#[derive(Debug, Clone, Copy)]
+pub struct BatchOptions
+{
+    // Depth of the search of each position. At depth 0, the static evaluation is given instead.
+    pub depth: u8,
+    // Number of positions searched at the same time, each one on its own thread.
+    pub concurrency: usize,
+}
+
+// The score of a position from the side to move's perspective, and its principal variation, which
+// is empty for a static evaluation or when there is no legal move.
+#[derive(Debug, Clone)]
+pub struct BatchResult
+{
+    pub score: Score,
+    pub pv: Vec<Move>,
+}
+
+// Read a file of positions, one FEN per line. Empty lines and lines starting with '#' are skipped.
+pub fn read_fens(path: &str) -> Result<Vec<String>, String>
+{
+    let text = fs::read_to_string(path).map_err(|err| format!("Can't read '{}': {}", path, err))?;
+    return Ok(text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(String::from)
+        .collect());
+}
+
+// Search or evaluate every position, and return the results in the order of the positions.
+// Each position is searched with an empty transposition table and history, so that its result
+// doesn't depend on the positions searched before it by the same thread.
+pub fn run_batch(fens: &[String], options: &BatchOptions) -> Vec<Result<BatchResult, String>>
+{
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(vec![None; fens.len()]);
+    let worker = || {
+        let mut search = Search::default();
+        loop
+        {
+            let i = next.fetch_add(1, Ordering::Relaxed);
+            let Some(fen) = fens.get(i)
+            else
+            {
+                return;
+            };
+            search.clear();
+            let result = process_position(fen, options.depth, &mut search);
+            results.lock().unwrap()[i] = Some(result);
+        }
+    };
+    thread::scope(|scope| {
+        for _ in 0 .. options.concurrency.max(1)
+        {
+            scope.spawn(worker);
+        }
+    });
+    return results.into_inner().unwrap().into_iter().map(Option::unwrap).collect();
+}
+
+fn process_position(fen: &str, depth: u8, search: &mut Search) -> Result<BatchResult, String>
+{
+    let mut board = Board::from_fen(fen)?;
+    if depth == 0
+    {
+        return Ok(BatchResult { score: evaluate_with(&board, &engine_config().eval), pv: vec![] });
+    }
+    let lines = search.multipv(&mut board, depth, 1);
+    return Ok(match lines.into_iter().next()
+    {
+        Some(line) => BatchResult { score: line.score, pv: line.moves },
+        // Without legal moves, the search gives the score of the checkmate or the stalemate.
+        None =>
+        {
+            let (score, _) = search.search(&mut board, 1);
+            BatchResult { score, pv: vec![] }
+        },
+    });
+}
+
+// Write the results in CSV, one line per position with its FEN, its score in centipawns or as a
+// mate distance in moves, its best move and its principal variation in UCI. The other fields of
+// the positions that couldn't be read are empty.
+pub fn batch_to_csv(fens: &[String], results: &[Result<BatchResult, String>]) -> String
+{
+    let mut csv = String::from("fen,score,best_move,pv\n");
+    for (fen, result) in fens.iter().zip(results.iter())
+    {
+        match result
+        {
+            Ok(result) =>
+            {
+                let pv: Vec<String> = result.pv.iter().map(|mv| mv.to_uci()).collect();
+                let score = match result.score.kind()
+                {
+                    ScoreKind::Mate(moves) => format!("#{}", moves),
+                    ScoreKind::Centipawns(centipawns) => centipawns.to_string(),
+                };
+                csv.push_str(&format!(
+                    "{},{},{},{}\n",
+                    fen,
+                    score,
+                    pv.first().map(String::as_str).unwrap_or(""),
+                    pv.join(" ")
+                ));
+            },
+            Err(_) => csv.push_str(&format!("{},,,\n", fen)),
+        }
+    }
+    return csv;
+}
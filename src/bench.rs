@@ -0,0 +1,113 @@
+use std::time::{Duration, Instant};
+
+use crate::*;
+
+// Positions searched by the benchmark: the opening, middlegames with many captures and checks,
+// and endgames where the transposition table matters more.
+pub const BENCH_POSITIONS: [&str; 9] = [
+    START_FEN,
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - -",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ -",
+    "r1bq1rk1/pp2bppp/2n1pn2/3p4/2PP4/2N1PN2/PP1B1PPP/R2QKB1R w KQ -",
+    "2r2rk1/pp1bqpp1/2nppn1p/8/3NP3/1BN1B3/PPPQ1PPP/2KR3R w - -",
+    "r1b2rk1/2q1bppp/p2p1n2/np2p3/3PP3/5N1P/PPBN1PP1/R1BQR1K1 w - -",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -",
+    "6k1/5ppp/8/8/8/8/5PPP/3R2K1 w - -",
+    "8/8/4k3/3p4/3P4/4K3/8/8 w - -",
+];
+
+// The statistics of the searches of all the positions added up, and the time they took.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct BenchReport
+{
+    pub positions: usize,
+    pub info: SearchInfo,
+    pub elapsed: Duration,
+}
+
+impl BenchReport
+{
+    pub fn nodes_per_second(&self) -> f64
+    {
+        return self.info.nodes as f64 / self.elapsed.as_secs_f64().max(1e-9);
+    }
+
+    // Print the counters of the searches, one per line.
+    pub fn to_table(&self) -> String
+    {
+        let info = &self.info;
+        let percent = |count: u64, total: u64| 100.0 * count as f64 / total.max(1) as f64;
+        let mut table = String::new();
+        let mut row = |name: &str, value: String| {
+            table.push_str(&format!("{:<24}{}\n", name, value));
+        };
+        row("positions", self.positions.to_string());
+        row("nodes", info.nodes.to_string());
+        row("time", format!("{:.3}s", self.elapsed.as_secs_f64()));
+        row("nodes/second", format!("{:.0}", self.nodes_per_second()));
+        row(
+            "quiescence nodes",
+            format!(
+                "{} ({:.1}%)",
+                info.quiescence_nodes,
+                percent(info.quiescence_nodes, info.nodes)
+            ),
+        );
+        row("quiescence depth cuts", info.quiescence_depth_cuts.to_string());
+        row("selective depth", info.seldepth.to_string());
+        row(
+            "tt probes",
+            format!(
+                "{}, {:.1}% hits, {:.1}% cutoffs",
+                info.tt_probes,
+                100.0 * info.tt_hit_rate(),
+                percent(info.tt_cutoffs, info.tt_probes)
+            ),
+        );
+        row(
+            "eval cache probes",
+            format!("{}, {:.1}% hits", info.eval_cache_probes, 100.0 * info.eval_cache_hit_rate()),
+        );
+
+        let total_cutoffs: u64 = info.beta_cutoffs.iter().sum();
+        let slots: Vec<String> = info
+            .beta_cutoffs
+            .iter()
+            .map(|count| format!("{:.1}%", percent(*count, total_cutoffs)))
+            .collect();
+        row("beta cutoffs", total_cutoffs.to_string());
+        row(&format!("  by move 1 .. {}+", CUTOFF_MOVE_SLOTS), slots.join(" "));
+        row("check extensions", info.check_extensions.to_string());
+        row("singular extensions", info.singular_extensions.to_string());
+        row("reverse futility prunes", info.reverse_futility_prunes.to_string());
+        row("probcuts", info.probcuts.to_string());
+        return table;
+    }
+}
+
+// Search each position of 'BENCH_POSITIONS' to 'depth' with an empty transposition table, and
+// call 'report' after each one with its FEN and the statistics of its search. There is no opening
+// variety, no root score noise and the full strength, so that the number of nodes only changes
+// with the search.
+pub fn run_bench(depth: u8, mut report: impl FnMut(&str, &SearchInfo)) -> BenchReport
+{
+    let mut search = Search::new(SearchOptions {
+        opening_variety: 0.0,
+        root_score_noise: Score(0),
+        skill_level: MAX_SKILL_LEVEL,
+        ..SearchOptions::default()
+    });
+    let mut total = BenchReport::default();
+    for fen in BENCH_POSITIONS
+    {
+        let mut board = Board::from_fen(fen).expect("The bench positions are valid.");
+        search.clear();
+        let start = Instant::now();
+        search.search(&mut board, depth);
+        total.elapsed += start.elapsed();
+        total.positions += 1;
+        total.info.add(&search.info);
+        report(fen, &search.info);
+    }
+    return total;
+}
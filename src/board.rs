@@ -1,11 +1,28 @@
-use crate::{Move, MoveContext, defines::*, get_legal_moves, get_piece_type_on_square};
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+
+#[cfg(feature = "std")]
+use rand::{Rng, seq::IndexedRandom};
+
+#[cfg(feature = "std")]
+use crate::DisplayOptions;
+use crate::{
+    CastlingInfo, Move, MoveContext, Variant, defines::*, get_legal_moves,
+    get_piece_type_on_square, king_mask, parse_square,
+};
+
+// FEN string of the initial position.
+pub const START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -";
 
 // This struct represents the current state of the board.
 // Bitboards and indices are used to give information on the positions of the
 // pieces.
 // Some data is redundant, but it should help calculating possible moves without
 // looking for each piece manually.
-#[derive(Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Board
 {
     // White pieces positions by type.
@@ -37,12 +54,22 @@ pub struct Board
     // These rights are granted when the game begins, but are lost indefinitely when the king or
     // the corresponding rook moves.
     // The other rules (no piece in the way, no check...) will be checked manually, and not stored.
-    pub white_queen_side_castling_right: bool,
-    pub white_king_side_castling_right: bool,
-    pub black_queen_side_castling_right: bool,
-    pub black_king_side_castling_right: bool,
+    pub castling_rights: CastlingRights,
+
+    // Number of plies since the last capture or pawn move, for the fifty-move rule.
+    pub halfmove_clock: u32,
+    // Number of the current move, starting at 1 and incremented after each move of black.
+    pub fullmove_number: u32,
 
     pub white_to_play: bool,
+
+    // The rules of the game played on the board.
+    pub variant: Variant,
+
+    // Zobrist hash of the position, updated incrementally when moves are made and unmade.
+    pub hash: u64,
+    // Zobrist hash of the pawns only, which keys the pawn table of the evaluation.
+    pub pawn_hash: u64,
 }
 
 impl Board
@@ -52,21 +79,107 @@ impl Board
         return get_legal_moves(self);
     }
 
-    // Apply a move a update the board data.
-    pub fn make_move(&mut self, mv: Move)
+    // Get a move with everything the board can tell about it: the moving piece, the captured
+    // piece, the context of the en passant captures, double steps and castles, the state to restore
+    // when the move is unmade, and the pieces exploded in Atomic. Only the squares and the promoted
+    // piece are kept from the move. Return None when the move doesn't start on a piece of the side
+    // to move, when its promotion piece is missing or shouldn't be there, or when a king moving two
+    // squares can't castle: a pawn reaching the last rank must be given a rook, a knight, a bishop
+    // or a queen, no other move can have one, and a castle needs its right and its rook.
+    pub fn complete_move(&self, mv: Move) -> Option<Move>
+    {
+        let friendly = if self.white_to_play { self.white_pieces } else { self.black_pieces };
+        if mv.start >= 64 || mv.end >= 64 || friendly & (1u64 << mv.start) == 0
+        {
+            return None;
+        }
+        let piece = get_piece_type_on_square(self, mv.start);
+        let last_rank = if self.white_to_play { 7 } else { 0 };
+        let promotes = piece == PAWN && mv.end / 8 == last_rank;
+        let valid_promotion = match mv.context
+        {
+            MoveContext::Promotion(promoted) => promotes && is_promotion_piece(promoted),
+            _ => !promotes,
+        };
+        if !valid_promotion
+        {
+            return None;
+        }
+        let context = match piece
+        {
+            PAWN if mv.start % 8 != mv.end % 8 && self.en_passant_target == Some(mv.end) =>
+            {
+                MoveContext::EnPassant
+            },
+            // Only the double steps from the second rank allow en passant captures, not those from
+            // the first rank in Horde.
+            PAWN if mv.start.abs_diff(mv.end) == 16 && matches!(mv.start / 8, 1 | 6) =>
+            {
+                MoveContext::DoubleStep
+            },
+            PAWN if promotes => mv.context,
+            KING if mv.start.abs_diff(mv.end) == 2 =>
+            {
+                let rooks = if self.white_to_play { self.white_rooks } else { self.black_rooks };
+                let castle = CastlingInfo::of_side(self.white_to_play).iter().find(|castle| {
+                    castle.king_from == mv.start
+                        && castle.king_to == mv.end
+                        && self.castling_rights.contains(castle.right)
+                        && rooks & (1u64 << castle.rook_from) != 0
+                })?;
+                castle.context
+            },
+            _ => MoveContext::None,
+        };
+        let enemy = if self.white_to_play { self.black_pieces } else { self.white_pieces };
+        let capture =
+            (enemy & (1u64 << mv.end) != 0).then(|| get_piece_type_on_square(self, mv.end));
+        let captures = capture.is_some() || context == MoveContext::EnPassant;
+        return Some(Move {
+            start: mv.start,
+            end: mv.end,
+            piece,
+            context,
+            previous_ep_target: self.en_passant_target,
+            previous_castling_rights: self.castling_rights,
+            previous_halfmove_clock: self.halfmove_clock,
+            capture,
+            exploded: self.explosion(mv.start, mv.end, captures),
+        });
+    }
+
+    // Apply a move a update the board data. The captured piece and the state to restore are taken
+    // from the board rather than from the move, so that a move built elsewhere, like those of the
+    // transposition table, can't corrupt the board. The move completed this way is returned, for
+    // 'unmake_move'. Panics on a move that 'complete_move' rejects.
+    pub fn make_move(&mut self, mv: Move) -> Move
     {
+        let Some(mv) = self.complete_move(mv)
+        else
+        {
+            panic!("make_move: {} can't be played in {}", mv.to_uci(), self.to_fen());
+        };
         let from = mv.start;
         let to = mv.end;
         let from_mask = 1u64 << from;
         let to_mask = 1u64 << to;
 
+        // Store the hash keys of the squares changed by the move, to update the hash at the end.
+        let changed = changed_squares(mv, self.white_to_play);
+        let previous_parts = self.hash_parts(changed);
+        let previous_pawn_parts = self.pawn_hash_parts(changed);
+
+        // Captures and pawn moves reset the halfmove clock.
+        let resets_clock =
+            mv.capture.is_some() || mv.context == MoveContext::EnPassant || mv.piece == PAWN;
+
         if self.white_to_play
         {
             // Update white pieces position by removing the 'from' bit and adding the 'to' bit.
             self.white_pieces = (self.white_pieces & !from_mask) | to_mask;
 
             // Update the bitboard corresponding to the piece that was moved.
-            if self.white_pawns & from_mask != 0
+            if mv.piece == PAWN
             {
                 self.white_pawns = (self.white_pawns & !from_mask) | to_mask;
                 if let MoveContext::Promotion(promoted) = mv.context
@@ -97,60 +210,33 @@ impl Board
                     }
                 }
             }
-            else if self.white_rooks & from_mask != 0
+            else if mv.piece == ROOK
             {
-                // Rook moved from A1, so white loses its queen side castling right.
-                if from == 0
-                {
-                    self.white_queen_side_castling_right = false;
-                }
-                // Rook moved from H1, so white loses its king side castling right.
-                else if from == 7
-                {
-                    self.white_king_side_castling_right = false;
-                }
                 self.white_rooks = (self.white_rooks & !from_mask) | to_mask;
             }
-            else if self.white_knights & from_mask != 0
+            else if mv.piece == KNIGHT
             {
                 self.white_knights = (self.white_knights & !from_mask) | to_mask;
             }
-            else if self.white_bishops & from_mask != 0
+            else if mv.piece == BISHOP
             {
                 self.white_bishops = (self.white_bishops & !from_mask) | to_mask;
             }
-            else if self.white_queens & from_mask != 0
+            else if mv.piece == QUEEN
             {
                 self.white_queens = (self.white_queens & !from_mask) | to_mask;
             }
-            else if self.white_king == from
+            else if mv.piece == KING
             {
-                // The king moved, so white loses all its castling rights.
-                self.white_king_side_castling_right = false;
-                self.white_queen_side_castling_right = false;
                 // Remove king from old square, add to new.
                 self.white_pieces &= !(1u64 << self.white_king);
                 self.white_king = to;
                 self.white_pieces |= 1u64 << self.white_king;
-                if mv.context == MoveContext::QueenSideCastle
+                // Move the rook of a castle.
+                if let Some(castle) = CastlingInfo::of_move(&mv, true)
                 {
-                    // Move rook from a1 (0) to d1 (3).
-                    const ROOK_OLD: u64 = 1u64;
-                    const ROOK_NEW: u64 = 1u64 << 3;
-                    self.white_rooks &= !ROOK_OLD;
-                    self.white_rooks |= ROOK_NEW;
-                    self.white_pieces &= !ROOK_OLD;
-                    self.white_pieces |= ROOK_NEW;
-                }
-                else if mv.context == MoveContext::KingSideCastle
-                {
-                    // Move rook from h1 (7) to f1 (5).
-                    const ROOK_OLD: u64 = 1u64 << 7;
-                    const ROOK_NEW: u64 = 1u64 << 5;
-                    self.white_rooks &= !ROOK_OLD;
-                    self.white_rooks |= ROOK_NEW;
-                    self.white_pieces &= !ROOK_OLD;
-                    self.white_pieces |= ROOK_NEW;
+                    self.white_rooks ^= castle.rook_squares();
+                    self.white_pieces ^= castle.rook_squares();
                 }
             }
             else
@@ -169,6 +255,8 @@ impl Board
                     KNIGHT => self.black_knights &= !to_mask,
                     BISHOP => self.black_bishops &= !to_mask,
                     QUEEN => self.black_queens &= !to_mask,
+                    // Kings are only captured in Antichess.
+                    KING => self.black_king = NO_SQUARE,
                     _ =>
                     {},
                 }
@@ -192,7 +280,7 @@ impl Board
             self.black_pieces = (self.black_pieces & !from_mask) | to_mask;
 
             // Update the bitboard corresponding to the piece that was moved.
-            if self.black_pawns & from_mask != 0
+            if mv.piece == PAWN
             {
                 self.black_pawns = (self.black_pawns & !from_mask) | to_mask;
                 if let MoveContext::Promotion(promoted) = mv.context
@@ -223,60 +311,33 @@ impl Board
                     }
                 }
             }
-            else if self.black_rooks & from_mask != 0
+            else if mv.piece == ROOK
             {
-                // Rook moved from A8, so black loses its queen side castling right.
-                if from == 56
-                {
-                    self.black_queen_side_castling_right = false;
-                }
-                // Rook moved from H8, so black loses its king side castling right.
-                else if from == 63
-                {
-                    self.black_king_side_castling_right = false;
-                }
                 self.black_rooks = (self.black_rooks & !from_mask) | to_mask;
             }
-            else if self.black_knights & from_mask != 0
+            else if mv.piece == KNIGHT
             {
                 self.black_knights = (self.black_knights & !from_mask) | to_mask;
             }
-            else if self.black_bishops & from_mask != 0
+            else if mv.piece == BISHOP
             {
                 self.black_bishops = (self.black_bishops & !from_mask) | to_mask;
             }
-            else if self.black_queens & from_mask != 0
+            else if mv.piece == QUEEN
             {
                 self.black_queens = (self.black_queens & !from_mask) | to_mask;
             }
-            else if self.black_king == from
+            else if mv.piece == KING
             {
-                // The king moved, so black loses all its castling rights.
-                self.black_king_side_castling_right = false;
-                self.black_queen_side_castling_right = false;
                 // Remove king from old square, add to new.
                 self.black_pieces &= !(1u64 << self.black_king);
                 self.black_king = to;
                 self.black_pieces |= 1u64 << self.black_king;
-                if mv.context == MoveContext::QueenSideCastle
+                // Move the rook of a castle.
+                if let Some(castle) = CastlingInfo::of_move(&mv, false)
                 {
-                    // Move rook from a8 (56) to d8 (59).
-                    const ROOK_OLD: u64 = 1u64 << 56;
-                    const ROOK_NEW: u64 = 1u64 << 59;
-                    self.black_rooks &= !ROOK_OLD;
-                    self.black_rooks |= ROOK_NEW;
-                    self.black_pieces &= !ROOK_OLD;
-                    self.black_pieces |= ROOK_NEW;
-                }
-                else if mv.context == MoveContext::KingSideCastle
-                {
-                    // Move rook from h8 (63) to f8 (61).
-                    const ROOK_OLD: u64 = 1u64 << 63;
-                    const ROOK_NEW: u64 = 1u64 << 61;
-                    self.black_rooks &= !ROOK_OLD;
-                    self.black_rooks |= ROOK_NEW;
-                    self.black_pieces &= !ROOK_OLD;
-                    self.black_pieces |= ROOK_NEW;
+                    self.black_rooks ^= castle.rook_squares();
+                    self.black_pieces ^= castle.rook_squares();
                 }
             }
             else
@@ -294,6 +355,7 @@ impl Board
                     KNIGHT => self.white_knights &= !to_mask,
                     BISHOP => self.white_bishops &= !to_mask,
                     QUEEN => self.white_queens &= !to_mask,
+                    KING => self.white_king = NO_SQUARE,
                     _ =>
                     {},
                 }
@@ -312,30 +374,38 @@ impl Board
                 if mv.context == MoveContext::DoubleStep { Some(to + 8) } else { None };
         }
 
-        if mv.end == 0
-        {
-            self.white_queen_side_castling_right = false;
-        }
-        else if mv.end == 7
+        // In Atomic, the capture explodes the capturing piece and its neighbors.
+        if !mv.exploded.is_none()
         {
-            self.white_king_side_castling_right = false;
+            self.explode(to);
         }
-        else if mv.end == 56
-        {
-            self.black_queen_side_castling_right = false;
-        }
-        else if mv.end == 63
+
+        // A king or a rook leaving its initial square, or a rook being captured on it, removes the
+        // corresponding castling rights.
+        self.castling_rights &= CASTLING_RIGHTS_MASKS[from] & CASTLING_RIGHTS_MASKS[to];
+
+        self.halfmove_clock = if resets_clock { 0 } else { self.halfmove_clock + 1 };
+        if !self.white_to_play
         {
-            self.black_king_side_castling_right = false;
+            self.fullmove_number += 1;
         }
 
         // Update the global piece bitboard using the sided bitboards.
         self.pieces = self.white_pieces | self.black_pieces;
 
         self.white_to_play = !self.white_to_play;
+
+        // Update the hash by replacing the keys of the changed squares.
+        self.hash ^= previous_parts ^ self.hash_parts(changed);
+        self.pawn_hash ^= previous_pawn_parts ^ self.pawn_hash_parts(changed);
+
+        #[cfg(feature = "audit")]
+        self.audit("make_move", mv);
+        return mv;
     }
 
-    // Go back to the previous state of the board, before the move was applied.
+    // Go back to the previous state of the board, before the move was applied. The move must hold
+    // the captured piece and the state to restore, like the move returned by 'make_move'.
     pub fn unmake_move(&mut self, mv: Move)
     {
         let from = mv.start;
@@ -343,11 +413,23 @@ impl Board
         let from_mask = 1u64 << from;
         let to_mask = 1u64 << to;
 
+        // Store the hash keys of the squares changed by the move, to update the hash at the end.
+        let changed = changed_squares(mv, !self.white_to_play);
+        let previous_parts = self.hash_parts(changed);
+        let previous_pawn_parts = self.pawn_hash_parts(changed);
+
         // Flip the playing side.
         self.white_to_play = !self.white_to_play;
+        if !self.white_to_play
+        {
+            self.fullmove_number -= 1;
+        }
 
-        // Store the piece type on the destination square.
-        let moved_piece_type;
+        // Put back the pieces exploded in Atomic, the capturing piece on the destination square.
+        if !mv.exploded.is_none()
+        {
+            self.restore_explosion(&mv);
+        }
 
         // Undo the move.
         if self.white_to_play
@@ -355,7 +437,6 @@ impl Board
             // Handle promotion: if there was a promotion, the moved piece was originally a pawn.
             if let MoveContext::Promotion(promoted) = mv.context
             {
-                moved_piece_type = PAWN;
                 // Remove the promoted piece from the destination square.
                 match promoted
                 {
@@ -367,56 +448,37 @@ impl Board
                 }
             }
             // Remove the white piece on 'to' square, add it back to 'from' square.
-            else if self.white_pawns & to_mask != 0
+            else if mv.piece == PAWN
             {
-                moved_piece_type = PAWN;
                 self.white_pawns &= !to_mask;
             }
-            else if self.white_rooks & to_mask != 0
+            else if mv.piece == ROOK
             {
-                moved_piece_type = ROOK;
                 self.white_rooks &= !to_mask;
             }
-            else if self.white_knights & to_mask != 0
+            else if mv.piece == KNIGHT
             {
-                moved_piece_type = KNIGHT;
                 self.white_knights &= !to_mask;
             }
-            else if self.white_bishops & to_mask != 0
+            else if mv.piece == BISHOP
             {
-                moved_piece_type = BISHOP;
                 self.white_bishops &= !to_mask;
             }
-            else if self.white_queens & to_mask != 0
+            else if mv.piece == QUEEN
             {
-                moved_piece_type = QUEEN;
                 self.white_queens &= !to_mask;
             }
-            else if self.white_king == to
+            else if mv.piece == KING
             {
-                moved_piece_type = KING;
                 // Remove king from new square, restore to old.
                 self.white_pieces &= !(1u64 << self.white_king);
                 self.white_king = from;
                 self.white_pieces |= 1u64 << self.white_king;
-                // Unmake castling for white.
-                if mv.context == MoveContext::QueenSideCastle
-                {
-                    const ROOK_OLD: u64 = 1u64 << 3;
-                    const ROOK_NEW: u64 = 1u64;
-                    self.white_rooks &= !ROOK_OLD;
-                    self.white_rooks |= ROOK_NEW;
-                    self.white_pieces &= !ROOK_OLD;
-                    self.white_pieces |= ROOK_NEW;
-                }
-                else if mv.context == MoveContext::KingSideCastle
+                // Move back the rook of a castle.
+                if let Some(castle) = CastlingInfo::of_move(&mv, true)
                 {
-                    const ROOK_OLD: u64 = 1u64 << 5;
-                    const ROOK_NEW: u64 = 1u64 << 7;
-                    self.white_rooks &= !ROOK_OLD;
-                    self.white_rooks |= ROOK_NEW;
-                    self.white_pieces &= !ROOK_OLD;
-                    self.white_pieces |= ROOK_NEW;
+                    self.white_rooks ^= castle.rook_squares();
+                    self.white_pieces ^= castle.rook_squares();
                 }
             }
             else
@@ -452,7 +514,7 @@ impl Board
             }
 
             // Restore the white piece back to 'from'.
-            match moved_piece_type
+            match mv.piece
             {
                 PAWN => self.white_pawns |= from_mask,
                 ROOK => self.white_rooks |= from_mask,
@@ -469,7 +531,6 @@ impl Board
             // Handle promotion: if there was a promotion, the moved piece was originally a pawn.
             if let MoveContext::Promotion(piece) = mv.context
             {
-                moved_piece_type = PAWN;
                 // Remove the promoted piece from the destination square.
                 match piece
                 {
@@ -481,56 +542,37 @@ impl Board
                 }
             }
             // Remove the black piece on 'to' square, add it back to 'from' square.
-            else if self.black_pawns & to_mask != 0
+            else if mv.piece == PAWN
             {
-                moved_piece_type = PAWN;
                 self.black_pawns &= !to_mask;
             }
-            else if self.black_rooks & to_mask != 0
+            else if mv.piece == ROOK
             {
-                moved_piece_type = ROOK;
                 self.black_rooks &= !to_mask;
             }
-            else if self.black_knights & to_mask != 0
+            else if mv.piece == KNIGHT
             {
-                moved_piece_type = KNIGHT;
                 self.black_knights &= !to_mask;
             }
-            else if self.black_bishops & to_mask != 0
+            else if mv.piece == BISHOP
             {
-                moved_piece_type = BISHOP;
                 self.black_bishops &= !to_mask;
             }
-            else if self.black_queens & to_mask != 0
+            else if mv.piece == QUEEN
             {
-                moved_piece_type = QUEEN;
                 self.black_queens &= !to_mask;
             }
-            else if self.black_king == to
+            else if mv.piece == KING
             {
-                moved_piece_type = KING;
                 // Remove king from new square, restore to old.
                 self.black_pieces &= !(1u64 << self.black_king);
                 self.black_king = from;
                 self.black_pieces |= 1u64 << self.black_king;
-                // Unmake castling for black.
-                if mv.context == MoveContext::QueenSideCastle
+                // Move back the rook of a castle.
+                if let Some(castle) = CastlingInfo::of_move(&mv, false)
                 {
-                    const ROOK_OLD: u64 = 1u64 << 59;
-                    const ROOK_NEW: u64 = 1u64 << 56;
-                    self.black_rooks &= !ROOK_OLD;
-                    self.black_rooks |= ROOK_NEW;
-                    self.black_pieces &= !ROOK_OLD;
-                    self.black_pieces |= ROOK_NEW;
-                }
-                else if mv.context == MoveContext::KingSideCastle
-                {
-                    const ROOK_OLD: u64 = 1u64 << 61;
-                    const ROOK_NEW: u64 = 1u64 << 63;
-                    self.black_rooks &= !ROOK_OLD;
-                    self.black_rooks |= ROOK_NEW;
-                    self.black_pieces &= !ROOK_OLD;
-                    self.black_pieces |= ROOK_NEW;
+                    self.black_rooks ^= castle.rook_squares();
+                    self.black_pieces ^= castle.rook_squares();
                 }
             }
             else
@@ -566,7 +608,7 @@ impl Board
             }
 
             // Restore the black piece back to 'from'.
-            match moved_piece_type
+            match mv.piece
             {
                 PAWN => self.black_pawns |= from_mask,
                 ROOK => self.black_rooks |= from_mask,
@@ -585,46 +627,134 @@ impl Board
         // Rebuild global occupancy bitboard.
         self.pieces = self.white_pieces | self.black_pieces;
 
-        self.white_queen_side_castling_right = mv.previous_wqs;
-        self.white_king_side_castling_right = mv.previous_wks;
-        self.black_queen_side_castling_right = mv.previous_bqs;
-        self.black_king_side_castling_right = mv.previous_bks;
-    }
+        self.castling_rights = mv.previous_castling_rights;
+        self.halfmove_clock = mv.previous_halfmove_clock;
 
-    pub fn evaluate(&self) -> i32
-    {
-        const PAWN_VALUE: u32 = 1;
-        const ROOK_VALUE: u32 = 5;
-        const KNIGHT_VALUE: u32 = 3;
-        const BISHOP_VALUE: u32 = 4;
-        const QUEEN_VALUE: u32 = 9;
-
-        let white_eval = PAWN_VALUE * self.white_pawns.count_ones()
-            + ROOK_VALUE * self.white_rooks.count_ones()
-            + KNIGHT_VALUE * self.white_knights.count_ones()
-            + BISHOP_VALUE * self.white_bishops.count_ones()
-            + QUEEN_VALUE * self.white_queens.count_ones();
+        // Update the hash by replacing the keys of the changed squares.
+        self.hash ^= previous_parts ^ self.hash_parts(changed);
+        self.pawn_hash ^= previous_pawn_parts ^ self.pawn_hash_parts(changed);
 
-        let black_eval = PAWN_VALUE * self.black_pawns.count_ones()
-            + ROOK_VALUE * self.black_rooks.count_ones()
-            + KNIGHT_VALUE * self.black_knights.count_ones()
-            + BISHOP_VALUE * self.black_bishops.count_ones()
-            + QUEEN_VALUE * self.black_queens.count_ones();
+        #[cfg(feature = "audit")]
+        self.audit("unmake_move", mv);
+    }
 
-        return if self.white_to_play
+    // Check that the bitboards updated by the moves agree with each other, and that the hashes
+    // agree with the hashes computed from scratch, and panic with the move that broke them
+    // otherwise. The check runs after every move made and unmade with the 'audit' feature.
+    #[cfg(feature = "audit")]
+    fn audit(&self, action: &str, mv: Move)
+    {
+        let white = [
+            self.white_pawns,
+            self.white_rooks,
+            self.white_knights,
+            self.white_bishops,
+            self.white_queens,
+            square_bitboard(self.white_king),
+        ];
+        let black = [
+            self.black_pawns,
+            self.black_rooks,
+            self.black_knights,
+            self.black_bishops,
+            self.black_queens,
+            square_bitboard(self.black_king),
+        ];
+        let union =
+            |bitboards: &[Bitboard]| bitboards.iter().fold(0, |all, bitboard| all | bitboard);
+        let count: u32 =
+            white.iter().chain(black.iter()).map(|bitboard| bitboard.count_ones()).sum();
+        let problem = if self.white_pieces != union(&white)
+        {
+            "the white pieces don't match the white bitboards"
+        }
+        else if self.black_pieces != union(&black)
         {
-            white_eval as i32 - black_eval as i32
+            "the black pieces don't match the black bitboards"
+        }
+        else if self.pieces != self.white_pieces | self.black_pieces
+        {
+            "the pieces don't match the pieces of each side"
+        }
+        else if count != self.pieces.count_ones()
+        {
+            "two pieces stand on the same square"
+        }
+        else if self.hash != self.compute_hash()
+        {
+            "the hash doesn't match the position"
+        }
+        else if self.pawn_hash != self.compute_pawn_hash()
+        {
+            "the pawn hash doesn't match the pawns"
         }
         else
         {
-            black_eval as i32 - white_eval as i32
+            return;
         };
+        panic!("audit: after {} {}, {} ({})", action, mv.to_uci(), problem, self.to_fen());
+    }
+
+    // Tell whether neither side has enough material left to checkmate: only kings, a single
+    // minor piece, or bishops all standing on squares of the same color.
+    // The variants can be won without checkmate, so this only applies to standard chess.
+    pub fn has_insufficient_material(&self) -> bool
+    {
+        if self.variant != Variant::Standard
+        {
+            return false;
+        }
+        let heavy_pieces = self.white_pawns
+            | self.black_pawns
+            | self.white_rooks
+            | self.black_rooks
+            | self.white_queens
+            | self.black_queens;
+        if heavy_pieces != 0
+        {
+            return false;
+        }
+
+        let knights = self.white_knights | self.black_knights;
+        let bishops = self.white_bishops | self.black_bishops;
+        if (knights | bishops).count_ones() <= 1
+        {
+            return true;
+        }
+        return knights == 0 && (bishops & LIGHT_SQUARES == 0 || bishops & !LIGHT_SQUARES == 0);
+    }
+
+    // Get the bitboard of the king of a side, empty if the side has no king.
+    pub fn king_bitboard(&self, white: bool) -> Bitboard
+    {
+        return square_bitboard(if white { self.white_king } else { self.black_king });
     }
 
     // Return a new board in the initial state.
     pub fn new() -> Result<Self, String>
     {
-        return Self::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -");
+        return Self::from_fen(START_FEN);
+    }
+
+    // Create a position by playing up to 'plies' random legal moves from the initial position.
+    // Fewer moves are played if the game ends before. This is meant for fuzzing and property
+    // tests, which can pass a seeded generator to reproduce their positions.
+    #[cfg(feature = "std")]
+    pub fn random_position<R: Rng + ?Sized>(rng: &mut R, plies: usize) -> Board
+    {
+        let mut board = Board::new().expect("The starting position is valid.");
+        for _ in 0 .. plies
+        {
+            match board.get_legal_moves().choose(rng)
+            {
+                Some(mv) =>
+                {
+                    board.make_move(*mv);
+                },
+                None => break,
+            }
+        }
+        return board;
     }
 
     // Create a new Board from a FEN string.
@@ -633,15 +763,24 @@ impl Board
     // - Side to move
     // - Castling rights
     // - En passant target square
-    // Later, I will have to add the move counters to manage draw rules.
+    // The move counters may follow, as in the FEN strings sent by GUIs: the halfmove clock, and the
+    // fullmove number. Without them, the clock starts at 0 and the move number at 1.
     pub fn from_fen(fen: &str) -> Result<Self, String>
     {
-        // Read the 4 fields.
+        return Self::from_fen_variant(fen, Variant::Standard);
+    }
+
+    // Create a new Board from a FEN string, for a game of the given variant.
+    // A king can only be missing in the variants that allow it, and only Antichess allows both
+    // kings to be missing.
+    pub fn from_fen_variant(fen: &str, variant: Variant) -> Result<Self, String>
+    {
+        // Read the 4 fields, and the optional move counters.
         let parts: Vec<&str> = fen.split_whitespace().collect();
-        if parts.len() != 4
+        if parts.len() != 4 && parts.len() != 6
         {
             // Return an error if too few or too many fields were provided.
-            return Err("FEN strings must have exactly 4 fields.".into());
+            return Err("FEN strings must have 4 or 6 fields.".into());
         }
 
         // Store the fields in explicit variables.
@@ -649,6 +788,23 @@ impl Board
         let active_color = parts[1];
         let castling = parts[2];
         let en_passant = parts[3];
+        let halfmove_clock = match parts.get(4)
+        {
+            Some(clock) =>
+            {
+                clock.parse::<u32>().map_err(|_| format!("Invalid halfmove clock '{}'.", clock))?
+            },
+            None => 0,
+        };
+        // Some tools write 0 for the first move.
+        let fullmove_number = match parts.get(5)
+        {
+            Some(number) => number
+                .parse::<u32>()
+                .map_err(|_| format!("Invalid fullmove number '{}'.", number))?
+                .max(1),
+            None => 1,
+        };
 
         // Start with empty bitboards.
         let mut wp = 0u64;
@@ -684,7 +840,7 @@ impl Board
             for c in rank_str.chars()
             {
                 // If the character is a digit, the given amount of files is skipped.
-                if c.is_digit(10)
+                if c.is_ascii_digit()
                 {
                     file += c.to_digit(10).unwrap() as usize;
                 }
@@ -743,24 +899,28 @@ impl Board
         }
 
         // Return an error if a king is missing.
-        let white_king = wk_sq.ok_or("Missing white king.")?;
-        let black_king = bk_sq.ok_or("Missing black king.")?;
+        let (white_king, black_king) = match (wk_sq, bk_sq)
+        {
+            (Some(white_king), Some(black_king)) => (white_king, black_king),
+            (None, Some(black_king)) if variant.allows_missing_king() => (NO_SQUARE, black_king),
+            (Some(white_king), None) if variant.allows_missing_king() => (white_king, NO_SQUARE),
+            (None, None) if variant == Variant::Antichess => (NO_SQUARE, NO_SQUARE),
+            (None, _) => return Err("Missing white king.".into()),
+            (_, None) => return Err("Missing black king.".into()),
+        };
 
         // Set castling rights.
-        let mut wks = false;
-        let mut wqs = false;
-        let mut bks = false;
-        let mut bqs = false;
+        let mut castling_rights = NO_CASTLING_RIGHTS;
         if castling != "-"
         {
             for ch in castling.chars()
             {
                 match ch
                 {
-                    'K' => wks = true,
-                    'Q' => wqs = true,
-                    'k' => bks = true,
-                    'q' => bqs = true,
+                    'K' => castling_rights |= WHITE_KING_SIDE,
+                    'Q' => castling_rights |= WHITE_QUEEN_SIDE,
+                    'k' => castling_rights |= BLACK_KING_SIDE,
+                    'q' => castling_rights |= BLACK_QUEEN_SIDE,
                     _ => return Err(format!("Invalid castling char '{}'.", ch)),
                 }
             }
@@ -773,25 +933,19 @@ impl Board
         }
         else
         {
-            // Convert each character of the square name into file and rank indices.
-            let file = (en_passant.as_bytes()[0] - b'a') as usize;
-            let rank = (en_passant.as_bytes()[1] - b'1') as usize;
-            // Return an error if an index is invalid.
-            if file > 7 || rank > 7
-            {
-                return Err(format!("Invalid en passant square '{}'.", en_passant));
-            }
-            // Set the square index using the file and rank indices.
-            Some(rank * 8 + file)
+            // Return an error if the field is not the name of a square.
+            let target = parse_square(en_passant)
+                .ok_or_else(|| format!("Invalid en passant square '{}'.", en_passant))?;
+            Some(target)
         };
 
         // Aggregate piece bitboards to create broader bitboards.
-        let white_pieces = wp | wr | wn | wb | wq | (1u64 << white_king);
-        let black_pieces = bp | br | bn | bb | bq | (1u64 << black_king);
+        let white_pieces = wp | wr | wn | wb | wq | square_bitboard(white_king);
+        let black_pieces = bp | br | bn | bb | bq | square_bitboard(black_king);
         let all_pieces = white_pieces | black_pieces;
 
         // Create the Board object using the data we gathered from the FEN string.
-        return Ok(Board {
+        let mut board = Board {
             white_pawns: wp,
             white_rooks: wr,
             white_knights: wn,
@@ -812,10 +966,11 @@ impl Board
 
             en_passant_target,
 
-            white_queen_side_castling_right: wqs,
-            white_king_side_castling_right: wks,
-            black_queen_side_castling_right: bqs,
-            black_king_side_castling_right: bks,
+            castling_rights,
+
+            halfmove_clock,
+
+            fullmove_number,
 
             // Use the last field of the FEN string to determine the side to move.
             white_to_play: match active_color
@@ -825,72 +980,145 @@ impl Board
                 // Return an error if the character is invalid.
                 _ => return Err(format!("Invalid active color `{}`.", active_color)),
             },
-        });
+
+            variant,
+
+            hash: 0,
+            pawn_hash: 0,
+        };
+        // An en passant target that no pawn can legally capture on is dropped, like in X-FEN.
+        board.en_passant_target = board.legal_en_passant_target();
+        board.hash = board.compute_hash();
+        board.pawn_hash = board.compute_pawn_hash();
+        return Ok(board);
     }
 
-    pub fn display(&self)
+    // Write the position as a FEN string, with its 6 fields.
+    pub fn to_fen(&self) -> String
     {
+        let mut placement = String::new();
         for rank in (0 .. 8).rev()
         {
+            let mut empty = 0;
             for file in 0 .. 8
             {
                 let sq = rank * 8 + file;
-                let ch = if (self.white_pawns >> sq) & 1 != 0
-                {
-                    'P'
-                }
-                else if (self.white_rooks >> sq) & 1 != 0
-                {
-                    'R'
-                }
-                else if (self.white_knights >> sq) & 1 != 0
-                {
-                    'N'
-                }
-                else if (self.white_bishops >> sq) & 1 != 0
-                {
-                    'B'
-                }
-                else if (self.white_queens >> sq) & 1 != 0
-                {
-                    'Q'
-                }
-                else if self.white_king == sq
-                {
-                    'K'
-                }
-                else if (self.black_pawns >> sq) & 1 != 0
-                {
-                    'p'
-                }
-                else if (self.black_rooks >> sq) & 1 != 0
-                {
-                    'r'
-                }
-                else if (self.black_knights >> sq) & 1 != 0
+                let letter = match get_piece_type_on_square(self, sq)
                 {
-                    'n'
-                }
-                else if (self.black_bishops >> sq) & 1 != 0
-                {
-                    'b'
-                }
-                else if (self.black_queens >> sq) & 1 != 0
-                {
-                    'q'
-                }
-                else if self.black_king == sq
+                    PAWN => 'p',
+                    ROOK => 'r',
+                    KNIGHT => 'n',
+                    BISHOP => 'b',
+                    QUEEN => 'q',
+                    KING => 'k',
+                    _ =>
+                    {
+                        empty += 1;
+                        continue;
+                    },
+                };
+                if empty > 0
                 {
-                    'k'
+                    placement.push_str(&empty.to_string());
+                    empty = 0;
                 }
-                else
-                {
-                    '·'
-                };
-                print!("{} ", ch);
+                let white = self.white_pieces & (1u64 << sq) != 0;
+                placement.push(if white { letter.to_ascii_uppercase() } else { letter });
+            }
+            if empty > 0
+            {
+                placement.push_str(&empty.to_string());
+            }
+            if rank > 0
+            {
+                placement.push('/');
+            }
+        }
+
+        let mut castling = String::new();
+        for (right, letter) in [
+            (WHITE_KING_SIDE, 'K'),
+            (WHITE_QUEEN_SIDE, 'Q'),
+            (BLACK_KING_SIDE, 'k'),
+            (BLACK_QUEEN_SIDE, 'q'),
+        ]
+        {
+            if self.castling_rights.contains(right)
+            {
+                castling.push(letter);
             }
-            println!();
         }
+        if castling.is_empty()
+        {
+            castling.push('-');
+        }
+
+        // The en passant target is only written when a pawn can take on it.
+        let en_passant = match self.legal_en_passant_target()
+        {
+            Some(sq) => format!("{}{}", (b'a' + (sq % 8) as u8) as char, sq / 8 + 1),
+            None => "-".into(),
+        };
+
+        return format!(
+            "{} {} {} {} {} {}",
+            placement,
+            if self.white_to_play { "w" } else { "b" },
+            castling,
+            en_passant,
+            self.halfmove_clock,
+            self.fullmove_number
+        );
+    }
+
+    // Print the board from white's perspective, with letters for the pieces.
+    #[cfg(feature = "std")]
+    pub fn display(&self)
+    {
+        self.display_with(&DisplayOptions::default());
+    }
+
+    // Return the same position seen from the other side: the board is mirrored vertically, the
+    // colors of the pieces are swapped, and so are the castling rights and the side to move.
+    pub fn flipped(&self) -> Board
+    {
+        let mut board = Board {
+            white_pawns: self.black_pawns.swap_bytes(),
+            white_rooks: self.black_rooks.swap_bytes(),
+            white_knights: self.black_knights.swap_bytes(),
+            white_bishops: self.black_bishops.swap_bytes(),
+            white_queens: self.black_queens.swap_bytes(),
+            white_king: flip_square(self.black_king),
+
+            black_pawns: self.white_pawns.swap_bytes(),
+            black_rooks: self.white_rooks.swap_bytes(),
+            black_knights: self.white_knights.swap_bytes(),
+            black_bishops: self.white_bishops.swap_bytes(),
+            black_queens: self.white_queens.swap_bytes(),
+            black_king: flip_square(self.white_king),
+
+            white_pieces: self.black_pieces.swap_bytes(),
+            black_pieces: self.white_pieces.swap_bytes(),
+            pieces: self.pieces.swap_bytes(),
+
+            en_passant_target: self.en_passant_target.map(|sq| sq ^ 56),
+
+            castling_rights: self.castling_rights.flipped(),
+
+            halfmove_clock: self.halfmove_clock,
+
+            fullmove_number: self.fullmove_number,
+
+            white_to_play: !self.white_to_play,
+
+            variant: self.variant,
+
+            hash: 0,
+            pawn_hash: 0,
+        };
+        board.hash = board.compute_hash();
+        board.pawn_hash = board.compute_pawn_hash();
+        return board;
     }
 
     // Get the piece type on a certain square.
@@ -899,3 +1127,84 @@ impl Board
         return get_piece_type_on_square(self, sq);
     }
 }
+
+// Get a bitboard of the squares whose content is changed by a move of the given side: the start
+// and end squares, the square of a pawn taken en passant, the rook squares of a castle, and the
+// squares around the destination of an explosion.
+fn changed_squares(mv: Move, white_moved: bool) -> Bitboard
+{
+    let mut squares = (1u64 << mv.start) | (1u64 << mv.end);
+    if mv.context == MoveContext::EnPassant
+    {
+        squares |= if white_moved { 1u64 << (mv.end - 8) } else { 1u64 << (mv.end + 8) };
+    }
+    if let Some(castle) = CastlingInfo::of_move(&mv, white_moved)
+    {
+        squares |= castle.rook_squares();
+    }
+    if !mv.exploded.is_none()
+    {
+        squares |= king_mask(mv.end);
+    }
+    return squares;
+}
+
+// Get the bitboard of a square, empty for 'NO_SQUARE'.
+fn square_bitboard(sq: Index) -> Bitboard
+{
+    return if sq == NO_SQUARE { 0 } else { 1u64 << sq };
+}
+
+// Get the square symmetric to another with respect to the middle of the board.
+fn flip_square(sq: Index) -> Index
+{
+    return if sq == NO_SQUARE { NO_SQUARE } else { sq ^ 56 };
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::*;
+
+    #[test]
+    fn promotions_need_a_promotion_piece()
+    {
+        let board = Board::from_fen("4k3/P7/8/8/8/8/8/4K3 w - -").unwrap();
+        for piece in [ROOK, KNIGHT, BISHOP, QUEEN]
+        {
+            assert!(board.complete_move(Move::bare(48, 56, Some(piece))).is_some());
+        }
+        for piece in [EMPTY, PAWN, KING]
+        {
+            assert_eq!(board.complete_move(Move::bare(48, 56, Some(piece))), None);
+        }
+        assert_eq!(board.complete_move(Move::bare(48, 56, None)), None);
+        // Only the pawns reaching the last rank promote.
+        assert_eq!(board.complete_move(Move::bare(4, 5, Some(QUEEN))), None);
+    }
+
+    #[test]
+    fn castles_need_the_right_and_the_rook()
+    {
+        // Both castles are legal with the rights and the rooks, and a king stepping two squares
+        // can't castle without them.
+        let castles = [Move::bare(4, 6, None), Move::bare(4, 2, None)];
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K2R w KQ -").unwrap();
+        for mv in castles
+        {
+            let completed = board.complete_move(mv).unwrap();
+            assert!(matches!(
+                completed.context,
+                MoveContext::KingSideCastle | MoveContext::QueenSideCastle
+            ));
+        }
+        for fen in ["4k3/8/8/8/8/8/8/R3K2R w - -", "4k3/8/8/8/8/8/8/4K3 w KQ -"]
+        {
+            let board = Board::from_fen(fen).unwrap();
+            for mv in castles
+            {
+                assert_eq!(board.complete_move(mv), None, "{} in {}", mv.to_uci(), fen);
+            }
+        }
+    }
+}
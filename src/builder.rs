@@ -0,0 +1,263 @@
+use alloc::{format, string::String, vec::Vec};
+use core::fmt;
+
+use crate::{
+    BISHOP, BLACK, Bitboard, Board, CastlingRights, Color, Index, KING, KNIGHT, NO_SQUARE, PAWN,
+    Piece, QUEEN, ROOK, Variant, Violation, WHITE, square_name,
+};
+
+// A reason why a position set up with a 'BoardBuilder' can't be built.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ValidationError
+{
+    // A piece was placed on a square outside of the board.
+    InvalidSquare(Index),
+    // The color or the type of a piece is not one of a chess piece.
+    InvalidPiece
+    {
+        sq: Index,
+        color: Color,
+        piece_type: Piece,
+    },
+    // A side has more than one king.
+    ExtraKing
+    {
+        white: bool,
+    },
+    // A side has no king, in a variant where it needs one.
+    MissingKing
+    {
+        white: bool,
+    },
+    // The position can't be reached in a game, with the reasons found by 'Board::validate'.
+    Unreachable(Vec<Violation>),
+}
+
+impl fmt::Display for ValidationError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let side = |white: bool| if white { "White" } else { "Black" };
+        return match self
+        {
+            ValidationError::InvalidSquare(sq) => write!(f, "Invalid square {}.", sq),
+            ValidationError::InvalidPiece { sq, color, piece_type } =>
+            {
+                write!(
+                    f,
+                    "Invalid piece of color {} and type {} on {}.",
+                    color,
+                    piece_type,
+                    square_name(*sq)
+                )
+            },
+            ValidationError::ExtraKing { white } =>
+            {
+                write!(f, "{} has more than one king.", side(*white))
+            },
+            ValidationError::MissingKing { white } => write!(f, "{} has no king.", side(*white)),
+            ValidationError::Unreachable(violations) =>
+            {
+                let reasons: Vec<String> =
+                    violations.iter().map(|violation| format!("{}", violation)).collect();
+                write!(f, "Illegal position: {}", reasons.join(" "))
+            },
+        };
+    }
+}
+
+impl core::error::Error for ValidationError {}
+
+// A position set up piece by piece, for the callers that would otherwise write a FEN string to
+// create it. The builder starts from an empty board with white to move, without castling rights,
+// and its settings are only checked when the board is built.
+#[derive(Debug, Default, Clone)]
+pub struct BoardBuilder
+{
+    // The pieces placed, in order: a piece replaces the one placed before it on its square.
+    pieces: Vec<(Index, Color, Piece)>,
+    black_to_play: bool,
+    castling_rights: CastlingRights,
+    en_passant_target: Option<Index>,
+    halfmove_clock: u32,
+    fullmove_number: u32,
+    variant: Variant,
+}
+
+impl BoardBuilder
+{
+    pub fn new() -> Self
+    {
+        return Self::default();
+    }
+
+    // Place a piece, given by its color (WHITE or BLACK) and its type (PAWN to KING), on a square.
+    pub fn piece(mut self, sq: Index, color: Color, piece_type: Piece) -> Self
+    {
+        self.pieces.push((sq, color, piece_type));
+        return self;
+    }
+
+    pub fn side_to_move(mut self, color: Color) -> Self
+    {
+        self.black_to_play = color == BLACK;
+        return self;
+    }
+
+    // Set the castling rights, a combination of WHITE_KING_SIDE, WHITE_QUEEN_SIDE, BLACK_KING_SIDE
+    // and BLACK_QUEEN_SIDE.
+    pub fn castling(mut self, rights: CastlingRights) -> Self
+    {
+        self.castling_rights = rights;
+        return self;
+    }
+
+    // Set the square behind a pawn that just moved two squares. Like in a FEN string, it is dropped
+    // when no pawn can legally capture on it.
+    pub fn en_passant(mut self, target: Option<Index>) -> Self
+    {
+        self.en_passant_target = target;
+        return self;
+    }
+
+    pub fn halfmove_clock(mut self, clock: u32) -> Self
+    {
+        self.halfmove_clock = clock;
+        return self;
+    }
+
+    // Set the number of the current move, 1 by default.
+    pub fn fullmove_number(mut self, number: u32) -> Self
+    {
+        self.fullmove_number = number;
+        return self;
+    }
+
+    pub fn variant(mut self, variant: Variant) -> Self
+    {
+        self.variant = variant;
+        return self;
+    }
+
+    // Create the board, and check that it can be reached in a game of its variant, like
+    // 'Board::from_fen_strict'.
+    pub fn build(&self) -> Result<Board, ValidationError>
+    {
+        let mut squares = [None; 64];
+        for &(sq, color, piece_type) in self.pieces.iter()
+        {
+            if sq >= 64
+            {
+                return Err(ValidationError::InvalidSquare(sq));
+            }
+            if (color != WHITE && color != BLACK) || !(PAWN ..= KING).contains(&piece_type)
+            {
+                return Err(ValidationError::InvalidPiece { sq, color, piece_type });
+            }
+            squares[sq] = Some((color == WHITE, piece_type));
+        }
+
+        // The bitboards of each side, indexed by piece type, and the square of its king.
+        let mut bitboards = [[0 as Bitboard; QUEEN as usize + 1]; 2];
+        let mut kings = [NO_SQUARE; 2];
+        for (sq, square) in squares.iter().enumerate()
+        {
+            let Some((white, piece_type)) = *square
+            else
+            {
+                continue;
+            };
+            let side = if white { 0 } else { 1 };
+            if piece_type == KING
+            {
+                if kings[side] != NO_SQUARE
+                {
+                    return Err(ValidationError::ExtraKing { white });
+                }
+                kings[side] = sq;
+            }
+            else
+            {
+                bitboards[side][piece_type as usize] |= 1u64 << sq;
+            }
+        }
+
+        // A king can only be missing in the variants that allow it, and only Antichess allows both
+        // kings to be missing.
+        let missing = [kings[0] == NO_SQUARE, kings[1] == NO_SQUARE];
+        let allowed = match missing
+        {
+            [false, false] => true,
+            [true, true] => self.variant == Variant::Antichess,
+            _ => self.variant.allows_missing_king(),
+        };
+        if !allowed
+        {
+            return Err(ValidationError::MissingKing { white: missing[0] });
+        }
+
+        let side_pieces = |side: usize| -> Bitboard {
+            let king = if kings[side] == NO_SQUARE { 0 } else { 1u64 << kings[side] };
+            return bitboards[side].iter().fold(king, |pieces, bitboard| pieces | bitboard);
+        };
+        let [white, black] = bitboards;
+        let mut board = Board {
+            white_pawns: white[PAWN as usize],
+            white_rooks: white[ROOK as usize],
+            white_knights: white[KNIGHT as usize],
+            white_bishops: white[BISHOP as usize],
+            white_queens: white[QUEEN as usize],
+            white_king: kings[0],
+            black_pawns: black[PAWN as usize],
+            black_rooks: black[ROOK as usize],
+            black_knights: black[KNIGHT as usize],
+            black_bishops: black[BISHOP as usize],
+            black_queens: black[QUEEN as usize],
+            black_king: kings[1],
+            white_pieces: side_pieces(0),
+            black_pieces: side_pieces(1),
+            pieces: side_pieces(0) | side_pieces(1),
+            en_passant_target: self.en_passant_target,
+            castling_rights: self.castling_rights,
+            halfmove_clock: self.halfmove_clock,
+            fullmove_number: self.fullmove_number.max(1),
+            white_to_play: !self.black_to_play,
+            variant: self.variant,
+            hash: 0,
+            pawn_hash: 0,
+        };
+        let violations = board.validate();
+        if !violations.is_empty()
+        {
+            return Err(ValidationError::Unreachable(violations));
+        }
+        board.en_passant_target = board.legal_en_passant_target();
+        board.hash = board.compute_hash();
+        board.pawn_hash = board.compute_pawn_hash();
+        return Ok(board);
+    }
+}
+
+impl Board
+{
+    // Create a builder holding the position, to change some of its pieces or settings.
+    pub fn to_builder(&self) -> BoardBuilder
+    {
+        let mut builder = BoardBuilder::new()
+            .side_to_move(if self.white_to_play { WHITE } else { BLACK })
+            .castling(self.castling_rights)
+            .en_passant(self.en_passant_target)
+            .halfmove_clock(self.halfmove_clock)
+            .fullmove_number(self.fullmove_number)
+            .variant(self.variant);
+        let mut remaining = self.pieces;
+        while remaining != 0
+        {
+            let sq = remaining.trailing_zeros() as Index;
+            let color = if self.white_pieces & (1u64 << sq) != 0 { WHITE } else { BLACK };
+            builder = builder.piece(sq, color, self.piece_at(sq));
+            remaining &= remaining - 1;
+        }
+        return builder;
+    }
+}
@@ -0,0 +1,340 @@
+use std::{
+    fs,
+    path::Path,
+    sync::{LazyLock, RwLock},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::*;
+
+// File read at startup from the working directory, when no other file is given.
+pub const DEFAULT_CONFIG_PATH: &str = "engine.toml";
+// Bound of the root score noise, in centipawns, so that the engine stays close to its best play.
+const MAX_ROOT_SCORE_NOISE: i32 = 100;
+
+// The settings of the engine that can be given in a TOML file. Every key is optional, the missing
+// ones keeping their default value. For example:
+//
+//     hash = 1048576
+//     memory = 64
+//     threads = 4
+//     book = "openings.txt"
+//
+//     [search]
+//     probcut = false
+//     contempt = 20
+//
+//     [eval]
+//     knight = { mg = 330, eg = 310 }
+//
+// The weights of the evaluation can also come from their own file, given by 'eval_file'.
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EngineConfig
+{
+    // Number of entries of the transposition table.
+    pub hash: usize,
+    // Memory budget of the tables of the searches in megabytes, split between them. When it is
+    // given, it sizes the transposition table, the evaluation cache and its pawn table instead of
+    // 'hash', 'search.eval_cache' and 'search.pawn_table'.
+    pub memory: Option<usize>,
+    // Number of threads of the commands processing several games or positions at the same time.
+    pub threads: usize,
+    // Time kept on each timed move for the communication with the GUI, in milliseconds.
+    pub move_overhead: u64,
+    // File with one opening per line, as a FEN or moves from the initial position, from which the
+    // games of the tournaments start.
+    pub book: Option<String>,
+    // File of evaluation weights, in TOML or JSON, written like the 'eval' section. Its weights
+    // replace the ones of the section, so that the weights of a tuning run can be tried as they
+    // are.
+    pub eval_file: Option<String>,
+    pub search: SearchConfig,
+    // The weights of the evaluation used by the searches.
+    pub eval: EvalParams,
+}
+
+// The settings of 'SearchOptions' that can be configured, see there for their meaning.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SearchConfig
+{
+    pub quiescence_check_plies: u8,
+    pub check_extension: bool,
+    pub singular_extension: bool,
+    pub reverse_futility: bool,
+    pub probcut: bool,
+    pub countermoves: bool,
+    pub continuation_history: bool,
+    pub stop_on_mate: bool,
+    pub stable_iterations: u8,
+    // In centipawns.
+    pub contempt: i32,
+    pub eval_cache: usize,
+    pub pawn_table: usize,
+    pub opening_variety: f32,
+    // In centipawns.
+    pub root_score_noise: i32,
+    pub skill_level: u8,
+}
+
+impl Default for EngineConfig
+{
+    fn default() -> Self
+    {
+        return EngineConfig {
+            hash: DEFAULT_TT_ENTRIES,
+            memory: None,
+            threads: 1,
+            move_overhead: DEFAULT_MOVE_OVERHEAD_MS,
+            book: None,
+            eval_file: None,
+            search: SearchConfig::default(),
+            eval: EvalParams::DEFAULT,
+        };
+    }
+}
+
+impl Default for SearchConfig
+{
+    fn default() -> Self
+    {
+        return SearchConfig {
+            quiescence_check_plies: QUIESCENCE_CHECK_PLIES,
+            check_extension: true,
+            singular_extension: true,
+            reverse_futility: true,
+            probcut: true,
+            countermoves: true,
+            continuation_history: true,
+            stop_on_mate: true,
+            stable_iterations: DEFAULT_STABLE_ITERATIONS,
+            contempt: 0,
+            eval_cache: DEFAULT_EVAL_CACHE_ENTRIES,
+            pawn_table: DEFAULT_PAWN_TABLE_ENTRIES,
+            opening_variety: 0.0,
+            root_score_noise: 0,
+            skill_level: MAX_SKILL_LEVEL,
+        };
+    }
+}
+
+// The configuration used by the defaults of the options of the searches, the tables and the
+// commands.
+static ENGINE_CONFIG: LazyLock<RwLock<EngineConfig>> =
+    LazyLock::new(|| RwLock::new(EngineConfig::default()));
+
+pub fn set_engine_config(config: EngineConfig)
+{
+    *ENGINE_CONFIG.write().unwrap() = config;
+}
+
+pub fn engine_config() -> EngineConfig
+{
+    return ENGINE_CONFIG.read().unwrap().clone();
+}
+
+impl EngineConfig
+{
+    // Read a configuration file, then apply the overrides, each one written "key=value" with the
+    // key of a section after its name and a dot, like "search.probcut=false". The values are
+    // written like in TOML, except that the quotes of a string can be left out. The file and the
+    // overrides are applied over the defaults, so a single weight of the evaluation can be given
+    // without the other one.
+    pub fn load(path: Option<&str>, overrides: &[String]) -> Result<EngineConfig, String>
+    {
+        let text =
+            match path
+            {
+                Some(path) => fs::read_to_string(path)
+                    .map_err(|err| format!("Can't read '{}': {}", path, err))?,
+                None => String::new(),
+            };
+        let file: toml::Table = toml::from_str(&text)
+            .map_err(|err| format!("Invalid configuration '{}': {}", path.unwrap_or(""), err))?;
+        let mut table =
+            toml::Table::try_from(EngineConfig::default()).map_err(|err| err.to_string())?;
+        merge_tables(&mut table, file);
+        for assignment in overrides.iter()
+        {
+            set_key(&mut table, assignment)?;
+        }
+        let config: EngineConfig = toml::Value::Table(table)
+            .try_into()
+            .map_err(|err| format!("Invalid configuration: {}", err))?;
+        return config.read_eval_file();
+    }
+
+    // Read the configuration from the file given, or from 'DEFAULT_CONFIG_PATH' if it exists.
+    // Return the configuration and the file it was read from.
+    pub fn load_default(
+        path: Option<&str>,
+        overrides: &[String],
+    ) -> Result<(EngineConfig, Option<String>), String>
+    {
+        let path = match path
+        {
+            Some(path) => Some(path.to_string()),
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => Some(DEFAULT_CONFIG_PATH.into()),
+            None => None,
+        };
+        let config = EngineConfig::load(path.as_deref(), overrides)?;
+        return Ok((config, path));
+    }
+
+    pub fn from_toml(text: &str) -> Result<EngineConfig, String>
+    {
+        let config: EngineConfig =
+            toml::from_str(text).map_err(|err| format!("Invalid configuration: {}", err))?;
+        return config.read_eval_file();
+    }
+
+    // Replace the weights of the evaluation with the ones of the evaluation file, if there is one,
+    // then validate the configuration.
+    fn read_eval_file(mut self) -> Result<EngineConfig, String>
+    {
+        if let Some(path) = &self.eval_file
+        {
+            self.eval = EvalParams::from_file(path)?;
+        }
+        self.validate()?;
+        return Ok(self);
+    }
+
+    // Write the whole configuration, the default values included, so that it can be edited.
+    pub fn to_toml(&self) -> Result<String, String>
+    {
+        return toml::to_string(self).map_err(|err| err.to_string());
+    }
+
+    fn validate(&self) -> Result<(), String>
+    {
+        if self.hash == 0
+        {
+            return Err("The transposition table needs at least one entry.".into());
+        }
+        if self.memory == Some(0)
+        {
+            return Err("The memory budget needs at least one megabyte.".into());
+        }
+        if !(0.0 ..= 1.0).contains(&self.search.opening_variety)
+        {
+            return Err("The opening variety must be between 0 and 1.".into());
+        }
+        if !(0 ..= MAX_ROOT_SCORE_NOISE).contains(&self.search.root_score_noise)
+        {
+            return Err(format!(
+                "The root score noise must be between 0 and {} centipawns.",
+                MAX_ROOT_SCORE_NOISE
+            ));
+        }
+        if self.search.skill_level > MAX_SKILL_LEVEL
+        {
+            return Err(format!("The skill level must be between 0 and {}.", MAX_SKILL_LEVEL));
+        }
+        if self.threads == 0
+        {
+            return Err("At least one thread is needed.".into());
+        }
+        return self.eval.validate();
+    }
+
+    // Get the sizes of the tables, from the memory budget if there is one.
+    pub fn table_sizes(&self) -> TableSizes
+    {
+        return match self.memory
+        {
+            Some(megabytes) => TableSizes::for_megabytes(megabytes),
+            None => TableSizes {
+                tt_entries: self.hash,
+                eval_cache_entries: self.search.eval_cache,
+                pawn_table_entries: self.search.pawn_table,
+            },
+        };
+    }
+
+    // Get the time kept on each timed move for the communication with the GUI.
+    pub fn move_overhead(&self) -> Duration
+    {
+        return Duration::from_millis(self.move_overhead);
+    }
+
+    // Get the options of the searches from the configuration, without deadline.
+    pub fn search_options(&self) -> SearchOptions
+    {
+        let search = &self.search;
+        return SearchOptions {
+            quiescence_check_plies: search.quiescence_check_plies,
+            check_extension: search.check_extension,
+            singular_extension: search.singular_extension,
+            reverse_futility: search.reverse_futility,
+            probcut: search.probcut,
+            countermoves: search.countermoves,
+            continuation_history: search.continuation_history,
+            deadline: None,
+            soft_deadline: None,
+            stop_on_mate: search.stop_on_mate,
+            stable_iterations: search.stable_iterations,
+            contempt: Score(search.contempt),
+            eval_cache_entries: self.table_sizes().eval_cache_entries,
+            pawn_table_entries: self.table_sizes().pawn_table_entries,
+            eval_params: self.eval,
+            opening_variety: search.opening_variety,
+            root_score_noise: Score(search.root_score_noise),
+            skill_level: search.skill_level,
+            seed: default_randomness().seed(),
+        };
+    }
+}
+
+// Copy the keys of 'other' into 'table', merging the sections present in both.
+fn merge_tables(table: &mut toml::Table, other: toml::Table)
+{
+    for (key, value) in other
+    {
+        match (table.get_mut(&key), value)
+        {
+            (Some(toml::Value::Table(inner)), toml::Value::Table(other_inner)) =>
+            {
+                merge_tables(inner, other_inner)
+            },
+            (_, value) =>
+            {
+                table.insert(key, value);
+            },
+        }
+    }
+}
+
+// Apply an override "key=value" to the table of a configuration file.
+fn set_key(table: &mut toml::Table, assignment: &str) -> Result<(), String>
+{
+    let Some((key, value)) = assignment.split_once('=')
+    else
+    {
+        return Err(format!("Invalid setting '{}': it must be written key=value.", assignment));
+    };
+    let value = value.trim();
+    let value = match toml::from_str::<toml::Table>(&format!("value = {}", value))
+    {
+        Ok(mut parsed) => parsed.remove("value").unwrap(),
+        Err(_) => toml::Value::String(value.to_string()),
+    };
+
+    let mut names: Vec<&str> = key.trim().split('.').collect();
+    let last = names.pop().unwrap();
+    let mut section = table;
+    for name in names
+    {
+        let entry = section.entry(name).or_insert_with(|| toml::Value::Table(toml::Table::new()));
+        section = match entry
+        {
+            toml::Value::Table(inner) => inner,
+            _ => return Err(format!("Invalid setting '{}': '{}' is not a section.", key, name)),
+        };
+    }
+    section.insert(last.to_string(), value);
+    return Ok(());
+}
@@ -0,0 +1,222 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+use rand::{rngs::StdRng, seq::IndexedRandom};
+
+use crate::*;
+
+// Size of a position in the binary format, in bytes.
+pub const BINARY_POSITION_SIZE: usize = 28;
+
+// Maximum number of plies of a game of the data generation, after which it is a draw.
+const DATAGEN_MAX_PLIES: usize = 400;
+
+// How the positions are written.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DataFormat
+{
+    // One line per position: "fen,score,result", with a header line.
+    Csv,
+    // 'BINARY_POSITION_SIZE' bytes per position, see 'encode_position'.
+    Binary,
+}
+
+impl DataFormat
+{
+    pub fn from_name(name: &str) -> Result<DataFormat, String>
+    {
+        return match name.to_ascii_lowercase().as_str()
+        {
+            "csv" => Ok(DataFormat::Csv),
+            "binary" | "bin" => Ok(DataFormat::Binary),
+            _ => Err(format!("Unknown data format '{}'. The formats are: csv, binary.", name)),
+        };
+    }
+}
+
+// Settings of the generation of training data by self-play.
+#[derive(Debug, Clone, Copy)]
+pub struct DatagenOptions
+{
+    pub games: usize,
+    // Depth of the search choosing each move and scoring each position.
+    pub depth: u8,
+    // Number of random moves played at the start of each game, so that the games differ.
+    pub random_plies: usize,
+    // Number of games played at the same time, each one on its own thread.
+    pub concurrency: usize,
+    pub format: DataFormat,
+}
+
+// A position of a game with its search score and the result of the game, both from white's
+// perspective. The result is 1 when white won, 0.5 for a draw and 0 when black won.
+#[derive(Debug, Clone, Copy)]
+pub struct DataPoint
+{
+    pub board: Board,
+    pub score: Score,
+    pub result: f32,
+}
+
+// Play self-play games and write their positions to 'path'. Return the number of positions.
+// The positions in check, the ones where the best move is a capture or a promotion, and the ones
+// with a mate score are skipped: their score is not a good target for a static evaluation.
+pub fn generate_data(path: &str, options: &DatagenOptions) -> Result<usize, String>
+{
+    let file = File::create(path).map_err(|err| format!("Can't create '{}': {}", path, err))?;
+    let writer = Mutex::new(BufWriter::new(file));
+    if options.format == DataFormat::Csv
+    {
+        writeln!(writer.lock().unwrap(), "fen,score,result").map_err(|err| err.to_string())?;
+    }
+
+    let next_game = AtomicUsize::new(0);
+    let positions = AtomicUsize::new(0);
+    let worker = || -> Result<(), String> {
+        let mut search = Search::default();
+        loop
+        {
+            let game = next_game.fetch_add(1, Ordering::Relaxed);
+            if game >= options.games
+            {
+                return Ok(());
+            }
+            search.clear();
+            let mut rng = default_randomness().rng(game as u64);
+            let points = play_datagen_game(options, &mut rng, &mut search);
+
+            let mut data = vec![];
+            for point in points.iter()
+            {
+                match options.format
+                {
+                    DataFormat::Csv => data.extend(
+                        format!("{},{},{:.1}\n", point.board.to_fen(), point.score.0, point.result)
+                            .bytes(),
+                    ),
+                    DataFormat::Binary => data.extend(encode_position(point)),
+                }
+            }
+            writer.lock().unwrap().write_all(&data).map_err(|err| err.to_string())?;
+            let total = positions.fetch_add(points.len(), Ordering::Relaxed) + points.len();
+            println!(
+                "Game {}/{}: {} positions, {} in total",
+                game + 1,
+                options.games,
+                points.len(),
+                total
+            );
+        }
+    };
+    thread::scope(|scope| {
+        let handles: Vec<_> =
+            (0 .. options.concurrency.max(1)).map(|_| scope.spawn(worker)).collect();
+        return handles.into_iter().try_for_each(|handle| handle.join().unwrap());
+    })?;
+    writer.into_inner().unwrap().flush().map_err(|err| err.to_string())?;
+    return Ok(positions.into_inner());
+}
+
+// Play a game from the initial position, starting with random moves, and return its positions
+// kept for training, with the result of the game.
+fn play_datagen_game(
+    options: &DatagenOptions,
+    rng: &mut StdRng,
+    search: &mut Search,
+) -> Vec<DataPoint>
+{
+    let mut board = Board::new().expect("The starting position is valid.");
+    for _ in 0 .. options.random_plies
+    {
+        // A random opening where the game is already over is started again.
+        match board.get_legal_moves().choose(rng)
+        {
+            Some(mv) =>
+            {
+                board.make_move(*mv);
+            },
+            None => return play_datagen_game(options, rng, search),
+        }
+    }
+
+    let mut points = vec![];
+    let mut hashes = vec![board.hash];
+    // The result from white's perspective, when the game ends.
+    let mut result = 0.5;
+    for _ in 0 .. DATAGEN_MAX_PLIES
+    {
+        let (score, best_move) = search.search(&mut board, options.depth);
+        let Some(mv) = best_move
+        else
+        {
+            if is_king_attacked(&board, false)
+            {
+                result = if board.white_to_play { 0.0 } else { 1.0 };
+            }
+            break;
+        };
+
+        let noisy = mv.capture.is_some() || matches!(mv.context, MoveContext::Promotion(_));
+        if !noisy && !score.is_mate() && !is_king_attacked(&board, false)
+        {
+            let white_score = if board.white_to_play { score } else { -score };
+            points.push(DataPoint { board, score: white_score, result: 0.0 });
+        }
+
+        board.make_move(mv);
+        hashes.push(board.hash);
+        if hashes.iter().filter(|hash| **hash == board.hash).count() >= 3
+            || board.halfmove_clock >= FIFTY_MOVE_PLIES
+            || board.has_insufficient_material()
+        {
+            break;
+        }
+    }
+
+    for point in points.iter_mut()
+    {
+        point.result = result;
+    }
+    return points;
+}
+
+// Encode a position in 'BINARY_POSITION_SIZE' bytes, the numbers being little-endian:
+// - the occupied squares, as a 64-bit bitboard;
+// - the piece on each occupied square, from a1 to h8, 4 bits each in 16 bytes, the low bits
+//   first: its type as in 'defines' (1 for a pawn to 6 for a king), plus 8 for a black piece;
+// - the side to move: 0 for white and 1 for black;
+// - the score from white's perspective in centipawns, as a 16-bit signed integer;
+// - the result: 0 when black won, 1 for a draw and 2 when white won.
+// The castling rights and the en passant square are not kept.
+pub fn encode_position(point: &DataPoint) -> [u8; BINARY_POSITION_SIZE]
+{
+    let board = &point.board;
+    let mut bytes = [0u8; BINARY_POSITION_SIZE];
+    let occupancy = board.white_pieces | board.black_pieces;
+    bytes[0 .. 8].copy_from_slice(&occupancy.to_le_bytes());
+
+    let mut bits = occupancy;
+    let mut i = 0;
+    while bits != 0 && i < 32
+    {
+        let sq = bits.trailing_zeros() as usize;
+        bits &= bits - 1;
+        let black = if board.black_pieces & (1u64 << sq) != 0 { 8 } else { 0 };
+        let nibble = board.piece_at(sq) | black;
+        bytes[8 + i / 2] |= nibble << (4 * (i % 2));
+        i += 1;
+    }
+
+    bytes[24] = if board.white_to_play { 0 } else { 1 };
+    let score = point.score.0.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+    bytes[25 .. 27].copy_from_slice(&score.to_le_bytes());
+    bytes[27] = (point.result * 2.0).round() as u8;
+    return bytes;
+}
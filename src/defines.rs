@@ -1,3 +1,5 @@
+use core::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign};
+
 pub type Piece = u8;
 
 // Each piece is represented by a 5 bit number (stored in a u8).
@@ -13,6 +15,120 @@ pub const KING: Piece = 6; // 0b00110
 pub const WHITE: Piece = 8; // 0b01000
 pub const BLACK: Piece = 16; // 0b10000
 
+// A color is either WHITE or BLACK.
+pub type Color = Piece;
+
+// Tell whether a pawn can promote to a piece type.
+pub const fn is_promotion_piece(piece: Piece) -> bool
+{
+    return matches!(piece, ROOK | KNIGHT | BISHOP | QUEEN);
+}
+
+// Castling rights, stored as 4 flags in a u8, one for each side of each color. The flags can only
+// be combined from the constants below, so that no other integer is taken for castling rights.
+#[repr(transparent)]
+#[derive(Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct CastlingRights(u8);
+
+pub const NO_CASTLING_RIGHTS: CastlingRights = CastlingRights(0);
+pub const WHITE_QUEEN_SIDE: CastlingRights = CastlingRights(1); // 0b0001
+pub const WHITE_KING_SIDE: CastlingRights = CastlingRights(2); // 0b0010
+pub const BLACK_QUEEN_SIDE: CastlingRights = CastlingRights(4); // 0b0100
+pub const BLACK_KING_SIDE: CastlingRights = CastlingRights(8); // 0b1000
+pub const ALL_CASTLING_RIGHTS: CastlingRights = CastlingRights(15); // 0b1111
+
+impl CastlingRights
+{
+    // Get the flags as an integer from 0 to 15, e.g. to index a table.
+    pub const fn bits(self) -> u8
+    {
+        return self.0;
+    }
+
+    // Get the castling rights of the given flags, if they are all castling rights.
+    pub const fn from_bits(bits: u8) -> Option<Self>
+    {
+        return if bits & !ALL_CASTLING_RIGHTS.0 == 0 { Some(CastlingRights(bits)) } else { None };
+    }
+
+    // Tell whether all the rights of 'other' are in these rights.
+    pub const fn contains(self, other: CastlingRights) -> bool
+    {
+        return self.0 & other.0 == other.0;
+    }
+
+    pub const fn insert(&mut self, other: CastlingRights)
+    {
+        self.0 |= other.0;
+    }
+
+    pub const fn remove(&mut self, other: CastlingRights)
+    {
+        self.0 &= !other.0;
+    }
+
+    // Swap the castling rights of white and black.
+    pub const fn flipped(self) -> Self
+    {
+        return CastlingRights((self.0 & 0b0011) << 2 | (self.0 & 0b1100) >> 2);
+    }
+}
+
+impl BitOr for CastlingRights
+{
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self
+    {
+        return CastlingRights(self.0 | other.0);
+    }
+}
+
+impl BitOrAssign for CastlingRights
+{
+    fn bitor_assign(&mut self, other: Self)
+    {
+        self.0 |= other.0;
+    }
+}
+
+impl BitAnd for CastlingRights
+{
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self
+    {
+        return CastlingRights(self.0 & other.0);
+    }
+}
+
+impl BitAndAssign for CastlingRights
+{
+    fn bitand_assign(&mut self, other: Self)
+    {
+        self.0 &= other.0;
+    }
+}
+
+// Castling rights kept when a move starts or ends on each square. Every square keeps all the
+// rights, except the initial squares of the kings and rooks: a move from or to one of them removes
+// the rights involving that piece.
+pub const CASTLING_RIGHTS_MASKS: [CastlingRights; 64] = make_castling_rights_masks();
+
+const fn make_castling_rights_masks() -> [CastlingRights; 64]
+{
+    let mut masks = [ALL_CASTLING_RIGHTS; 64];
+    masks[0].remove(WHITE_QUEEN_SIDE);
+    masks[4].remove(WHITE_QUEEN_SIDE);
+    masks[4].remove(WHITE_KING_SIDE);
+    masks[7].remove(WHITE_KING_SIDE);
+    masks[56].remove(BLACK_QUEEN_SIDE);
+    masks[60].remove(BLACK_QUEEN_SIDE);
+    masks[60].remove(BLACK_KING_SIDE);
+    masks[63].remove(BLACK_KING_SIDE);
+    return masks;
+}
+
 // Use bitwise AND to apply a filter to the piece and get its type.
 pub const fn get_piece_type(piece: Piece) -> Piece
 {
@@ -25,11 +141,17 @@ pub const fn get_piece_color(piece: Piece) -> Piece
     return piece & 0b11000;
 }
 
+// Squares of the same color as h1.
+pub const LIGHT_SQUARES: Bitboard = 0x55AA_55AA_55AA_55AA;
+
 // An Index represents a tile on the board.
 pub type Index = usize;
 
+// Index standing for the king of a side that has none, in variants such as Horde.
+pub const NO_SQUARE: Index = 64;
+
 // A bitboard is a 64 bit number, and each bit indicates the presence or absence
 // of a given piece on a tile. For a "white pawn bitboard", a '0' at the n-th
 // bit means that there is no white pawn at the n-th tile, and a '1' means that
 // there is one.
-pub type Bitboard = u64;
\ No newline at end of file
+pub type Bitboard = u64;
@@ -0,0 +1,170 @@
+use alloc::{format, string::String, vec::Vec};
+
+use crate::{
+    BISHOP, Bitboard, Board, Index, KING, KNIGHT, Move, PAWN, QUEEN, ROOK,
+    get_piece_type_on_square, is_king_attacked,
+};
+
+// ANSI escape codes used to color the squares.
+const ANSI_RESET: &str = "\x1b[0m";
+const ANSI_LIGHT_SQUARE: &str = "\x1b[30;47m";
+const ANSI_DARK_SQUARE: &str = "\x1b[30;100m";
+const ANSI_LAST_MOVE: &str = "\x1b[30;43m";
+const ANSI_CHECK: &str = "\x1b[30;41m";
+const ANSI_MARKED: &str = "\x1b[30;42m";
+
+// Options changing the way a board is printed.
+// The default options print the same board as 'Board::display'.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DisplayOptions
+{
+    // Use chess glyphs (♔, ♟...) instead of letters.
+    pub unicode: bool,
+    // Print the rank numbers and file letters around the board.
+    pub coordinates: bool,
+    // Print the board from black's perspective.
+    pub flipped: bool,
+    // Color the squares with ANSI escape codes. The squares of the last move and the king in
+    // check are highlighted.
+    pub colors: bool,
+    // The move that led to this position, highlighted when colors are enabled.
+    pub last_move: Option<Move>,
+    // Squares to mark, such as the legal destinations of a piece. Without colors, empty marked
+    // squares are printed as '*' and occupied ones as 'x'.
+    pub marked: Bitboard,
+    // Hide the pieces, for blindfold training: only the squares and the coordinates are printed,
+    // and the king in check is not highlighted.
+    pub blindfold: bool,
+}
+
+impl Board
+{
+    // Print the board using the given options.
+    #[cfg(feature = "std")]
+    pub fn display_with(&self, options: &DisplayOptions)
+    {
+        print!("{}", self.to_text(options));
+    }
+
+    // Get a bitboard of the squares that the piece on 'from' can legally move to.
+    // It is empty if there is no piece of the side to move on 'from'.
+    pub fn legal_destinations(&mut self, from: Index) -> Bitboard
+    {
+        let mut destinations = 0u64;
+        for mv in self.get_legal_moves().iter().filter(|mv| mv.start == from)
+        {
+            destinations |= 1u64 << mv.end;
+        }
+        return destinations;
+    }
+
+    // Print the board with the legal destinations of the piece on 'from' marked.
+    #[cfg(feature = "std")]
+    pub fn display_destinations(&mut self, from: Index, options: &DisplayOptions)
+    {
+        let marked = self.legal_destinations(from);
+        self.display_with(&DisplayOptions { marked, ..*options });
+    }
+
+    // Create the text representing the board with the given options, one line per rank.
+    pub fn to_text(&self, options: &DisplayOptions) -> String
+    {
+        // Find the king to highlight, if the side to move is in check.
+        let checked_king = if options.colors && !options.blindfold && is_king_attacked(self, false)
+        {
+            Some(if self.white_to_play { self.white_king } else { self.black_king })
+        }
+        else
+        {
+            None
+        };
+
+        let ranks: Vec<usize> =
+            if options.flipped { (0 .. 8).collect() } else { (0 .. 8).rev().collect() };
+        let files: Vec<usize> =
+            if options.flipped { (0 .. 8).rev().collect() } else { (0 .. 8).collect() };
+
+        let mut text = String::new();
+        for rank in ranks.iter()
+        {
+            if options.coordinates
+            {
+                text.push_str(&format!("{} ", rank + 1));
+            }
+            for file in files.iter()
+            {
+                let sq = rank * 8 + file;
+                let ch =
+                    if options.blindfold { '·' } else { self.square_char(sq, options.unicode) };
+                let marked = options.marked & (1u64 << sq) != 0;
+                if options.colors
+                {
+                    let color = if marked
+                    {
+                        ANSI_MARKED
+                    }
+                    else if checked_king == Some(sq)
+                    {
+                        ANSI_CHECK
+                    }
+                    else if options.last_move.is_some_and(|mv| mv.start == sq || mv.end == sq)
+                    {
+                        ANSI_LAST_MOVE
+                    }
+                    else if (rank + file) % 2 == 0
+                    {
+                        ANSI_DARK_SQUARE
+                    }
+                    else
+                    {
+                        ANSI_LIGHT_SQUARE
+                    };
+                    let ch = if ch == '·' { ' ' } else { ch };
+                    text.push_str(&format!("{}{} {}", color, ch, ANSI_RESET));
+                }
+                else if marked
+                {
+                    text.push_str(if ch == '·' { "* " } else { "x " });
+                }
+                else
+                {
+                    text.push_str(&format!("{} ", ch));
+                }
+            }
+            text.push('\n');
+        }
+
+        if options.coordinates
+        {
+            text.push_str("  ");
+            for file in files.iter()
+            {
+                text.push_str(&format!("{} ", (b'a' + *file as u8) as char));
+            }
+            text.push('\n');
+        }
+
+        return text;
+    }
+
+    // Get the character representing the piece on a square, or '·' for an empty square.
+    fn square_char(&self, sq: Index, unicode: bool) -> char
+    {
+        let white = self.white_pieces & (1u64 << sq) != 0;
+        let (letter, glyph) = match get_piece_type_on_square(self, sq)
+        {
+            PAWN => ('p', if white { '♙' } else { '♟' }),
+            ROOK => ('r', if white { '♖' } else { '♜' }),
+            KNIGHT => ('n', if white { '♘' } else { '♞' }),
+            BISHOP => ('b', if white { '♗' } else { '♝' }),
+            QUEEN => ('q', if white { '♕' } else { '♛' }),
+            KING => ('k', if white { '♔' } else { '♚' }),
+            _ => return '·',
+        };
+        if unicode
+        {
+            return glyph;
+        }
+        return if white { letter.to_ascii_uppercase() } else { letter };
+    }
+}
@@ -0,0 +1,64 @@
+use alloc::string::String;
+use core::fmt;
+
+// An error of the convenience functions of the library, for the callers that want to tell the
+// failures apart. The rest of the library reports its errors as messages.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ChessError
+{
+    // The FEN could not be parsed, with the reason.
+    InvalidFen(String),
+    // The position is checkmate or stalemate, or the variant game is over, so there is no move to
+    // play.
+    NoLegalMove,
+}
+
+impl fmt::Display for ChessError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        return match self
+        {
+            ChessError::InvalidFen(reason) => write!(f, "Invalid FEN: {}", reason),
+            ChessError::NoLegalMove => write!(f, "No legal move in the position."),
+        };
+    }
+}
+
+impl core::error::Error for ChessError {}
+
+// A move of a list that could not be played, with its position in the list, from 0.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum MoveError
+{
+    // The text is not a move written in UCI notation.
+    InvalidNotation
+    {
+        index: usize, text: String
+    },
+    // The move is not legal in the position reached by the moves before it.
+    Illegal
+    {
+        index: usize, text: String
+    },
+}
+
+impl fmt::Display for MoveError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        return match self
+        {
+            MoveError::InvalidNotation { index, text } =>
+            {
+                write!(f, "Move {} '{}' is not written in UCI notation.", index + 1, text)
+            },
+            MoveError::Illegal { index, text } =>
+            {
+                write!(f, "Move {} '{}' is illegal.", index + 1, text)
+            },
+        };
+    }
+}
+
+impl core::error::Error for MoveError {}
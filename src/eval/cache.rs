@@ -0,0 +1,62 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{Board, DEFAULT_PAWN_TABLE_ENTRIES, EvalParams, PawnTable, Score, evaluate_with_pawns};
+
+// Default number of entries of an evaluation cache.
+pub const DEFAULT_EVAL_CACHE_ENTRIES: usize = 1 << 14;
+// Memory used by each entry of an evaluation cache, in bytes.
+pub const EVAL_CACHE_ENTRY_BYTES: usize = size_of::<Option<(u64, Score)>>();
+
+// A small hash table of static evaluations, indexed by the Zobrist hash of the positions.
+// The same leaves are evaluated many times during a search, especially in quiescence, and reading
+// the cache is cheaper than evaluating them again. Each slot holds the last position stored in it.
+// The positions missing from the cache are evaluated with the pawn structures of its pawn table.
+pub struct EvalCache
+{
+    entries: Vec<Option<(u64, Score)>>,
+    pawns: PawnTable,
+}
+
+impl EvalCache
+{
+    // Create an empty cache with the given number of entries, and a pawn table with 'pawn_entries'
+    // entries. With 0 entries, nothing is cached.
+    pub fn new(size: usize, pawn_entries: usize) -> Self
+    {
+        return EvalCache { entries: vec![None; size], pawns: PawnTable::new(pawn_entries) };
+    }
+
+    // Evaluate the position with the given weights, reading the cache first. Return the score and
+    // whether it was cached. The cache must be cleared when the weights change.
+    pub fn evaluate(&mut self, board: &Board, params: &EvalParams) -> (Score, bool)
+    {
+        if self.entries.is_empty()
+        {
+            return (evaluate_with_pawns(board, params, &self.pawns.get(board)), false);
+        }
+        let index = (board.hash % self.entries.len() as u64) as usize;
+        if let Some((key, score)) = self.entries[index]
+            && key == board.hash
+        {
+            return (score, true);
+        }
+        let score = evaluate_with_pawns(board, params, &self.pawns.get(board));
+        self.entries[index] = Some((board.hash, score));
+        return (score, false);
+    }
+
+    // Remove all entries, the pawn structures included.
+    pub fn clear(&mut self)
+    {
+        self.entries.fill(None);
+        self.pawns.clear();
+    }
+}
+
+impl Default for EvalCache
+{
+    fn default() -> Self
+    {
+        return Self::new(DEFAULT_EVAL_CACHE_ENTRIES, DEFAULT_PAWN_TABLE_ENTRIES);
+    }
+}
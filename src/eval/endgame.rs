@@ -0,0 +1,306 @@
+use crate::{
+    Bitboard, Board, EvalParams, Index, LIGHT_SQUARES, NO_SQUARE, Tapered, Variant, kpk_win,
+};
+
+// Minimum material advantage, in centipawns, of a side facing a king without pawns for the mop-up
+// evaluation to help it mate.
+pub const MOP_UP_MIN_ADVANTAGE: i32 = 400;
+
+// Scale factors of the endgame part of the evaluation, out of 'SCALE_NORMAL': the bishops of
+// opposite colors can't fight for the same squares, alone or with other pieces, and a rook ending
+// with few pawns and at most one extra pawn is usually held.
+pub const SCALE_NORMAL: i32 = 64;
+const SCALE_OPPOSITE_BISHOPS: i32 = 24;
+const SCALE_OPPOSITE_BISHOPS_WITH_PIECES: i32 = 48;
+const SCALE_ROOK_ENDING: i32 = 40;
+// Maximum number of pawns of the stronger side for a rook ending to be scaled.
+const ROOK_ENDING_MAX_PAWNS: u32 = 3;
+
+// A drawish ending, in which the endgame part of the evaluation is multiplied by
+// 'factor / SCALE_NORMAL' so that the engine doesn't overestimate its winning chances.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct EndgameScale
+{
+    pub name: &'static str,
+    pub factor: i32,
+}
+
+impl EndgameScale
+{
+    // Scale the endgame part of a total of the evaluation.
+    pub fn apply(self, total: Tapered) -> Tapered
+    {
+        return Tapered::new(total.mg, total.eg * self.factor / SCALE_NORMAL);
+    }
+}
+
+// Recognize the drawish endings, given the total of the evaluation from white's perspective, which
+// tells the stronger side. Like the known draws, they are only recognized in standard chess.
+pub fn endgame_scale(board: &Board, white_total: Tapered) -> Option<EndgameScale>
+{
+    if board.variant != Variant::Standard
+    {
+        return None;
+    }
+    let strong = Side::new(board, white_total.eg >= 0);
+    let weak = Side::new(board, white_total.eg < 0);
+    let scale = |name: &'static str, factor: i32| Some(EndgameScale { name, factor });
+
+    let light_bishops = |side: &Side| (side.bishops & LIGHT_SQUARES).count_ones();
+    if strong.bishops.count_ones() == 1
+        && weak.bishops.count_ones() == 1
+        && light_bishops(&strong) != light_bishops(&weak)
+    {
+        let only_bishops = strong.pieces() == strong.bishops | strong.pawns
+            && weak.pieces() == weak.bishops | weak.pawns;
+        return if only_bishops
+        {
+            scale("opposite bishops", SCALE_OPPOSITE_BISHOPS)
+        }
+        else
+        {
+            scale("opposite bishops and pieces", SCALE_OPPOSITE_BISHOPS_WITH_PIECES)
+        };
+    }
+
+    let (strong_pawns, weak_pawns) = (strong.pawns.count_ones(), weak.pawns.count_ones());
+    if strong.rooks.count_ones() == 1
+        && weak.rooks.count_ones() == 1
+        && strong.pieces() == strong.rooks | strong.pawns
+        && weak.pieces() == weak.rooks | weak.pawns
+        && strong_pawns <= ROOK_ENDING_MAX_PAWNS
+        && strong_pawns <= weak_pawns + 1
+    {
+        return scale("rook ending", SCALE_ROOK_ENDING);
+    }
+    return None;
+}
+
+// Recognize the endings that are drawn whatever the moves, and return their name:
+// - the material is insufficient to mate;
+// - two knights can't force mate against a bare king;
+// - a bishop with pawns on a rook file can't drive out a king standing in front of them if the
+//   promotion square is not of the bishop's color;
+// - king and pawn against king endings that the bitbase says are drawn.
+// These endings can still be won in the variants, so only standard chess has known draws.
+pub fn known_draw(board: &Board) -> Option<&'static str>
+{
+    if board.variant != Variant::Standard
+    {
+        return None;
+    }
+    if board.has_insufficient_material()
+    {
+        return Some("insufficient material");
+    }
+    for white in [true, false]
+    {
+        let strong = Side::new(board, white);
+        let weak = Side::new(board, !white);
+        if !weak.is_bare_king()
+        {
+            continue;
+        }
+        if strong.pieces() == strong.knights && strong.knights.count_ones() == 2
+        {
+            return Some("KNN vs K");
+        }
+        if is_wrong_bishop_draw(&strong, weak.king)
+        {
+            return Some("wrong-color bishop");
+        }
+        if strong.pieces() == strong.pawns
+            && strong.pawns.count_ones() == 1
+            && kpk_win_for(board, &strong, &weak) == Some(false)
+        {
+            return Some("KPK");
+        }
+    }
+    return None;
+}
+
+// Compute the bonus of a side winning a king and pawn versus king ending according to the bitbase.
+pub(crate) fn kpk_term(board: &Board, white: bool, params: &EvalParams) -> Tapered
+{
+    if board.variant != Variant::Standard
+    {
+        return Tapered::default();
+    }
+    let strong = Side::new(board, white);
+    let weak = Side::new(board, !white);
+    if weak.is_bare_king()
+        && strong.pieces() == strong.pawns
+        && strong.pawns.count_ones() == 1
+        && kpk_win_for(board, &strong, &weak) == Some(true)
+    {
+        return params.kpk_win;
+    }
+    return Tapered::default();
+}
+
+// Compute the mop-up bonus of a side with a large material advantage against a king without pawns:
+// driving the enemy king to the edge of the board, where it can be mated, and bringing the own king
+// close to it. With a bishop and a knight only, the enemy king must go to a corner of the bishop's
+// color instead.
+pub(crate) fn mop_up_term(board: &Board, white: bool, params: &EvalParams) -> Tapered
+{
+    if board.variant != Variant::Standard
+    {
+        return Tapered::default();
+    }
+    let strong = Side::new(board, white);
+    let weak = Side::new(board, !white);
+    // A king can only be missing after being captured from an illegal position.
+    if weak.pawns != 0
+        || strong.material(params) - weak.material(params) < MOP_UP_MIN_ADVANTAGE
+        || strong.king == NO_SQUARE
+        || weak.king == NO_SQUARE
+    {
+        return Tapered::default();
+    }
+
+    let edge = if strong.pieces() == strong.bishops | strong.knights
+        && strong.bishops.count_ones() == 1
+        && strong.knights.count_ones() == 1
+    {
+        // Corners of the bishop's color: h1 and a8 for light squares, a1 and h8 for dark ones.
+        let corners: [Index; 2] =
+            if strong.bishops & LIGHT_SQUARES != 0 { [7, 56] } else { [0, 63] };
+        let corner_distance =
+            corners.iter().map(|corner| distance(weak.king, *corner)).min().unwrap_or(0);
+        // The distance to a corner goes from 0 to 7, and the center distance from 0 to 6.
+        6 - corner_distance.min(6)
+    }
+    else
+    {
+        center_distance(weak.king)
+    };
+    let closeness = 14 - manhattan_distance(strong.king, weak.king);
+    return params.mop_up_edge * edge + params.mop_up_kings * closeness;
+}
+
+// The pieces of one side.
+struct Side
+{
+    pawns: Bitboard,
+    knights: Bitboard,
+    bishops: Bitboard,
+    rooks: Bitboard,
+    queens: Bitboard,
+    king: Index,
+    white: bool,
+}
+
+impl Side
+{
+    fn new(board: &Board, white: bool) -> Side
+    {
+        if white
+        {
+            return Side {
+                pawns: board.white_pawns,
+                knights: board.white_knights,
+                bishops: board.white_bishops,
+                rooks: board.white_rooks,
+                queens: board.white_queens,
+                king: board.white_king,
+                white,
+            };
+        }
+        return Side {
+            pawns: board.black_pawns,
+            knights: board.black_knights,
+            bishops: board.black_bishops,
+            rooks: board.black_rooks,
+            queens: board.black_queens,
+            king: board.black_king,
+            white,
+        };
+    }
+
+    // Get the pieces of the side, its king excluded.
+    fn pieces(&self) -> Bitboard
+    {
+        return self.pawns | self.knights | self.bishops | self.rooks | self.queens;
+    }
+
+    fn is_bare_king(&self) -> bool
+    {
+        return self.pieces() == 0;
+    }
+
+    // Get the endgame material value of the pieces other than pawns.
+    fn material(&self, params: &EvalParams) -> i32
+    {
+        return params.knight.eg * self.knights.count_ones() as i32
+            + params.bishop.eg * self.bishops.count_ones() as i32
+            + params.rook.eg * self.rooks.count_ones() as i32
+            + params.queen.eg * self.queens.count_ones() as i32;
+    }
+}
+
+// Probe the bitbase for the king and pawn versus king ending, with 'strong' being the side with the
+// pawn. The bitbase only has the pawns of the ranks 2 to 7, so there is no result for a pawn on a
+// back rank, which a lenient FEN can set up.
+fn kpk_win_for(board: &Board, strong: &Side, weak: &Side) -> Option<bool>
+{
+    let pawn = strong.pawns.trailing_zeros() as usize;
+    if !(8 .. 56).contains(&pawn)
+    {
+        return None;
+    }
+    let strong_to_move = board.white_to_play == strong.white;
+    // See the position from white's side when black has the pawn.
+    let flip = if strong.white { 0 } else { 56 };
+    return Some(kpk_win(strong.king ^ flip, weak.king ^ flip, pawn ^ flip, strong_to_move));
+}
+
+fn is_wrong_bishop_draw(strong: &Side, weak_king: Index) -> bool
+{
+    const A_FILE: Bitboard = 0x0101_0101_0101_0101;
+    const H_FILE: Bitboard = 0x8080_8080_8080_8080;
+
+    if strong.bishops == 0 || strong.pawns == 0 || strong.pieces() != strong.bishops | strong.pawns
+    {
+        return false;
+    }
+    let file = if strong.pawns & !A_FILE == 0
+    {
+        0
+    }
+    else if strong.pawns & !H_FILE == 0
+    {
+        7
+    }
+    else
+    {
+        return false;
+    };
+    let promotion = if strong.white { 56 + file } else { file };
+    let promotion_is_light = LIGHT_SQUARES & (1u64 << promotion) != 0;
+    let bishops_on_light = strong.bishops & LIGHT_SQUARES != 0;
+    let bishops_on_dark = strong.bishops & !LIGHT_SQUARES != 0;
+    let controls_promotion = if promotion_is_light { bishops_on_light } else { bishops_on_dark };
+    return !controls_promotion && distance(weak_king, promotion) <= 1;
+}
+
+// Get the number of king moves between two squares.
+pub fn distance(a: Index, b: Index) -> i32
+{
+    let file_distance = (a % 8).abs_diff(b % 8);
+    let rank_distance = (a / 8).abs_diff(b / 8);
+    return file_distance.max(rank_distance) as i32;
+}
+
+pub fn manhattan_distance(a: Index, b: Index) -> i32
+{
+    return ((a % 8).abs_diff(b % 8) + (a / 8).abs_diff(b / 8)) as i32;
+}
+
+// Get the Manhattan distance from a square to the 4 central squares, from 0 to 6.
+pub fn center_distance(sq: Index) -> i32
+{
+    let file = (sq % 8) as i32;
+    let rank = (sq / 8) as i32;
+    return (3 - file).max(file - 4) + (3 - rank).max(rank - 4);
+}
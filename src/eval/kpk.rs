@@ -0,0 +1,31 @@
+use crate::{Index, KPK_SIZE, kpk_index};
+
+// Bitbase of the king and pawn versus king endgame: one bit per position, set when the side with
+// the pawn wins. It is generated by retrograde analysis in the build script, see 'generate_kpk'.
+// Positions are seen from the side with the pawn, which moves up the board, and the pawn stands on
+// the files a to d: the other positions are mirrored.
+static KPK_BITBASE: [u64; KPK_SIZE / 64] = include!(concat!(env!("OUT_DIR"), "/kpk_bitbase.rs"));
+
+// Tell whether the side with the pawn wins.
+// The squares are seen from that side, with the pawn moving up the board, and the position must be
+// legal: in particular, the pawn stands on the ranks 2 to 7.
+pub fn kpk_win(strong_king: Index, weak_king: Index, pawn: Index, strong_to_move: bool) -> bool
+{
+    // Mirror the position so that the pawn stands on the files a to d.
+    let (strong_king, weak_king, pawn) = if pawn % 8 > 3
+    {
+        (strong_king ^ 7, weak_king ^ 7, pawn ^ 7)
+    }
+    else
+    {
+        (strong_king, weak_king, pawn)
+    };
+    let index = kpk_index(strong_to_move, strong_king, weak_king, pawn);
+    return KPK_BITBASE[index / 64] & (1u64 << (index % 64)) != 0;
+}
+
+// Get the embedded bitbase, one bit per position in the order of 'kpk_index'.
+pub fn kpk_bitbase() -> &'static [u64]
+{
+    return &KPK_BITBASE;
+}
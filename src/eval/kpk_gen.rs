@@ -0,0 +1,191 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{Bitboard, Index, king_mask, white_king_pawn_mask};
+
+// Generation of the bitbase of the king and pawn versus king endgame, see 'kpk_win'. This module is
+// also compiled by the build script, which embeds the bitbase in the crate, so it only uses the
+// masks and the types of the crate root.
+
+// Number of pawn squares: 4 files, and the ranks 2 to 7.
+const PAWN_SQUARES: usize = 24;
+pub const KPK_SIZE: usize = 2 * PAWN_SQUARES * 64 * 64;
+
+// Results of the positions during the generation.
+const UNKNOWN: u8 = 0;
+const INVALID: u8 = 1;
+const DRAW: u8 = 2;
+const WIN: u8 = 3;
+
+// Get the index of a position in the bitbase, the pawn standing on the files a to d.
+pub fn kpk_index(strong_to_move: bool, strong_king: Index, weak_king: Index, pawn: Index) -> usize
+{
+    let pawn_index = (pawn / 8 - 1) * 4 + pawn % 8;
+    let side = if strong_to_move { 0 } else { 1 };
+    return ((side * PAWN_SQUARES + pawn_index) * 64 + strong_king) * 64 + weak_king;
+}
+
+// Get the position of an index: side to move, strong king, weak king and pawn.
+fn kpk_position(index: usize) -> (bool, Index, Index, Index)
+{
+    let weak_king = index % 64;
+    let strong_king = index / 64 % 64;
+    let pawn_index = index / (64 * 64) % PAWN_SQUARES;
+    let strong_to_move = index / (64 * 64 * PAWN_SQUARES) == 0;
+    let pawn = (pawn_index / 4 + 1) * 8 + pawn_index % 4;
+    return (strong_to_move, strong_king, weak_king, pawn);
+}
+
+// Generate the bitbase by retrograde analysis.
+pub fn generate_kpk() -> Vec<u64>
+{
+    let mut results: Vec<u8> = (0 .. KPK_SIZE).map(classify_initial).collect();
+
+    // Propagate the known results until nothing changes. The positions left unknown are draws,
+    // since the strong side can't force any progress from them.
+    let mut changed = true;
+    while changed
+    {
+        changed = false;
+        for index in 0 .. KPK_SIZE
+        {
+            if results[index] == UNKNOWN
+            {
+                let result = classify(index, &results);
+                if result != UNKNOWN
+                {
+                    results[index] = result;
+                    changed = true;
+                }
+            }
+        }
+    }
+
+    let mut bitbase = vec![0u64; KPK_SIZE / 64];
+    for (index, result) in results.iter().enumerate()
+    {
+        if *result == WIN
+        {
+            bitbase[index / 64] |= 1u64 << (index % 64);
+        }
+    }
+    return bitbase;
+}
+
+// Classify the positions whose result doesn't depend on the other positions.
+fn classify_initial(index: usize) -> u8
+{
+    let (strong_to_move, strong_king, weak_king, pawn) = kpk_position(index);
+
+    if strong_king == weak_king
+        || strong_king == pawn
+        || weak_king == pawn
+        || king_mask(strong_king) & (1u64 << weak_king) != 0
+        || (strong_to_move && white_king_pawn_mask(pawn) & (1u64 << weak_king) != 0)
+    {
+        return INVALID;
+    }
+
+    if strong_to_move
+    {
+        // The pawn promotes and the queen can't be taken.
+        let promotion = pawn + 8;
+        if pawn / 8 == 6
+            && promotion != strong_king
+            && promotion != weak_king
+            && (king_mask(weak_king) & (1u64 << promotion) == 0
+                || king_mask(strong_king) & (1u64 << promotion) != 0)
+        {
+            return WIN;
+        }
+    }
+    else
+    {
+        // The weak king takes the undefended pawn.
+        if king_mask(weak_king) & (1u64 << pawn) != 0
+            && king_mask(strong_king) & (1u64 << pawn) == 0
+        {
+            return DRAW;
+        }
+        // The weak king is stalemated.
+        let in_check = white_king_pawn_mask(pawn) & (1u64 << weak_king) != 0;
+        if weak_king_moves(strong_king, weak_king, pawn) == 0 && !in_check
+        {
+            return DRAW;
+        }
+    }
+    return UNKNOWN;
+}
+
+// Get the squares where the weak king can go, capturing the pawn excluded.
+fn weak_king_moves(strong_king: Index, weak_king: Index, pawn: Index) -> Bitboard
+{
+    return king_mask(weak_king)
+        & !king_mask(strong_king)
+        & !white_king_pawn_mask(pawn)
+        & !(1u64 << pawn);
+}
+
+// Classify a position from the results of the positions reached after each move: the strong side
+// wins if one of its moves wins, and the weak side draws if one of its moves draws.
+fn classify(index: usize, results: &[u8]) -> u8
+{
+    let (strong_to_move, strong_king, weak_king, pawn) = kpk_position(index);
+
+    // The result of the position for the side to move if one move reaches it, and the result if
+    // every move reaches the other one.
+    let (good, bad) = if strong_to_move { (WIN, DRAW) } else { (DRAW, WIN) };
+    let mut all_bad = true;
+    let mut check = |child: usize| -> bool {
+        let result = results[child];
+        all_bad &= result == bad;
+        return result == good;
+    };
+
+    if strong_to_move
+    {
+        let mut moves = king_mask(strong_king) & !king_mask(weak_king) & !(1u64 << pawn);
+        while moves != 0
+        {
+            let to = moves.trailing_zeros() as usize;
+            moves &= moves - 1;
+            if check(kpk_index(false, to, weak_king, pawn))
+            {
+                return good;
+            }
+        }
+        // Pushes to the last rank are promotions, which are classified initially.
+        let push = pawn + 8;
+        if pawn / 8 < 6 && push != strong_king && push != weak_king
+        {
+            if check(kpk_index(false, strong_king, weak_king, push))
+            {
+                return good;
+            }
+            let double_push = pawn + 16;
+            if pawn / 8 == 1
+                && double_push != strong_king
+                && double_push != weak_king
+                && check(kpk_index(false, strong_king, weak_king, double_push))
+            {
+                return good;
+            }
+        }
+    }
+    else
+    {
+        let mut moves = weak_king_moves(strong_king, weak_king, pawn);
+        while moves != 0
+        {
+            let to = moves.trailing_zeros() as usize;
+            moves &= moves - 1;
+            if check(kpk_index(true, strong_king, to, pawn))
+            {
+                return good;
+            }
+        }
+    }
+
+    // Without moves, the strong side is stalemated, and the weak king is checkmated since its
+    // stalemates are classified initially.
+    return if all_bad { bad } else { UNKNOWN };
+}
@@ -0,0 +1,351 @@
+pub mod cache;
+pub mod endgame;
+pub mod kpk;
+pub mod kpk_gen;
+pub mod params;
+pub mod pawns;
+pub mod score;
+pub mod threats;
+pub mod trace;
+pub mod zones;
+
+pub use cache::*;
+pub use endgame::*;
+pub use kpk::*;
+pub use kpk_gen::*;
+pub use params::*;
+pub use pawns::*;
+pub use score::*;
+pub use threats::*;
+pub use trace::*;
+pub use zones::*;
+
+use crate::{Bitboard, Board, NO_SQUARE};
+
+// The four squares in the middle of the board.
+const CENTER: Bitboard = 0x0000_0018_1800_0000;
+
+// Number of terms of the evaluation, and their names in the order of 'side_terms'.
+pub const EVAL_TERM_COUNT: usize = 19;
+pub const EVAL_TERM_NAMES: [&str; EVAL_TERM_COUNT] = [
+    "pawns",
+    "knights",
+    "bishops",
+    "rooks",
+    "queens",
+    "squares",
+    "bishop pair",
+    "pawn adj.",
+    "no pawns",
+    "kpk",
+    "mop-up",
+    "passed",
+    "weak pawns",
+    "king shield",
+    "rook files",
+    "space",
+    "center",
+    "threats",
+    "tempo",
+];
+
+// Evaluate the position statically with the default weights, from the side to move's perspective:
+// a positive score means that the side to move is better. Negamax relies on this convention,
+// since it negates the score of each child position. The side to move gets the tempo bonus, so a
+// symmetric position is worth the tempo for whichever side is to move, and flipping the colors
+// and the side to move of a position doesn't change its evaluation.
+// The evaluation never returns mate scores, which are only found by the search, the endings
+// recognized as drawn are scored 0, and the drawish ones are scaled down towards 0.
+pub fn evaluate(board: &Board) -> Score
+{
+    return evaluate_with(board, &EvalParams::DEFAULT);
+}
+
+// Evaluate the position like 'evaluate', with the given weights.
+pub fn evaluate_with(board: &Board, params: &EvalParams) -> Score
+{
+    return evaluate_with_pawns(board, params, &PawnStructure::new(board));
+}
+
+// Evaluate the position like 'evaluate_with', with its pawn structure already known.
+pub fn evaluate_with_pawns(board: &Board, params: &EvalParams, pawns: &PawnStructure) -> Score
+{
+    if known_draw(board).is_some()
+    {
+        return Score::DRAW;
+    }
+
+    let threats = Threats::new(board);
+    let white = side_terms(board, true, params, pawns, &threats);
+    let black = side_terms(board, false, params, pawns, &threats);
+    let mut total = Tapered::default();
+    for i in 0 .. EVAL_TERM_COUNT
+    {
+        total = total + white[i] - black[i];
+    }
+    if let Some(scale) = endgame_scale(board, total)
+    {
+        total = scale.apply(total);
+    }
+
+    let white_score = Score(total.taper(game_phase(board)));
+    return if board.white_to_play { white_score } else { -white_score };
+}
+
+// Get the phase of the game, from 'MAX_PHASE' with all the pieces on the board to 0 when only
+// kings and pawns are left.
+pub fn game_phase(board: &Board) -> i32
+{
+    let count = |pieces: Bitboard| pieces.count_ones() as i32;
+    let phase = KNIGHT_PHASE * count(board.white_knights | board.black_knights)
+        + BISHOP_PHASE * count(board.white_bishops | board.black_bishops)
+        + ROOK_PHASE * count(board.white_rooks | board.black_rooks)
+        + QUEEN_PHASE * count(board.white_queens | board.black_queens);
+    // Promotions can give more material than in the initial position.
+    return phase.min(MAX_PHASE);
+}
+
+// Compute the value of each term of the evaluation for one side, before tapering.
+pub(crate) fn side_terms(
+    board: &Board,
+    white: bool,
+    params: &EvalParams,
+    structure: &PawnStructure,
+    threats: &Threats,
+) -> [Tapered; EVAL_TERM_COUNT]
+{
+    let (pawns, knights, bishops, rooks, queens) = if white
+    {
+        (
+            board.white_pawns,
+            board.white_knights,
+            board.white_bishops,
+            board.white_rooks,
+            board.white_queens,
+        )
+    }
+    else
+    {
+        (
+            board.black_pawns,
+            board.black_knights,
+            board.black_bishops,
+            board.black_rooks,
+            board.black_queens,
+        )
+    };
+    let count = |pieces: Bitboard| pieces.count_ones() as i32;
+    let side = if white { 0 } else { 1 };
+
+    let extra_pawns = count(pawns) - PAWN_ADJUSTMENT_BASE;
+    let pawn_adjustment = (params.knight_pawn_adjustment * count(knights)
+        + params.rook_pawn_adjustment * count(rooks)
+        + params.queen_pawn_adjustment * count(queens))
+        * extra_pawns;
+
+    return [
+        params.pawn * count(pawns),
+        params.knight * count(knights),
+        params.bishop * count(bishops),
+        params.rook * count(rooks),
+        params.queen * count(queens),
+        piece_squares_term(board, white, params),
+        if count(bishops) >= 2 { params.bishop_pair } else { Tapered::default() },
+        pawn_adjustment,
+        if pawns == 0 { params.no_pawns } else { Tapered::default() },
+        kpk_term(board, white, params),
+        mop_up_term(board, white, params),
+        passed_pawn_term(white, params, structure),
+        params.isolated_pawn * count(structure.isolated[side])
+            + params.doubled_pawn * count(structure.doubled[side]),
+        king_shield_term(board, white, params, structure),
+        rook_files_term(rooks, white, params, structure),
+        params.space * structure.space[side] as i32,
+        params.center_control * count_attacks(board, white, CENTER) as i32,
+        params.threat_by_lesser * count(threats.attacked_by_lesser[1 - side])
+            + params.hanging_piece * count(threats.hanging[1 - side]),
+        if board.white_to_play == white { params.tempo } else { Tapered::default() },
+    ];
+}
+
+// Bonus of the pieces of a side for the squares they stand on, from the piece-square tables.
+fn piece_squares_term(board: &Board, white: bool, params: &EvalParams) -> Tapered
+{
+    let king = if white { board.white_king } else { board.black_king };
+    let king = if king == NO_SQUARE { 0 } else { 1u64 << king };
+    let pieces = if white
+    {
+        [
+            board.white_pawns,
+            board.white_knights,
+            board.white_bishops,
+            board.white_rooks,
+            board.white_queens,
+            king,
+        ]
+    }
+    else
+    {
+        [
+            board.black_pawns,
+            board.black_knights,
+            board.black_bishops,
+            board.black_rooks,
+            board.black_queens,
+            king,
+        ]
+    };
+    let mut total = Tapered::default();
+    for (mut squares, (_, table)) in pieces.into_iter().zip(params.square_tables())
+    {
+        while squares != 0
+        {
+            total = total + table.get(white, squares.trailing_zeros() as usize);
+            squares &= squares - 1;
+        }
+    }
+    return total;
+}
+
+// Bonus of the passed pawns of a side, growing with their advance.
+fn passed_pawn_term(white: bool, params: &EvalParams, structure: &PawnStructure) -> Tapered
+{
+    let mut total = Tapered::default();
+    let mut passed = structure.passed[if white { 0 } else { 1 }];
+    while passed != 0
+    {
+        let rank = (passed.trailing_zeros() / 8) as i32;
+        // The pawns of the Horde side start on the first rank, where they don't get a bonus.
+        let advance = if white { rank - 1 } else { 6 - rank };
+        total = total + params.passed_pawn * advance.max(0);
+        passed &= passed - 1;
+    }
+    return total;
+}
+
+// Bonus of a king still on its first two ranks for the pawns sheltering it.
+fn king_shield_term(
+    board: &Board,
+    white: bool,
+    params: &EvalParams,
+    structure: &PawnStructure,
+) -> Tapered
+{
+    let king = if white { board.white_king } else { board.black_king };
+    if king == NO_SQUARE
+    {
+        return Tapered::default();
+    }
+    let relative_rank = if white { king / 8 } else { 7 - king / 8 };
+    if relative_rank > 1
+    {
+        return Tapered::default();
+    }
+    let shield = structure.shield[if white { 0 } else { 1 }][king % 8];
+    return params.king_shield * shield as i32;
+}
+
+// Bonus of the rooks of a side on the files without pawns, or only with enemy pawns.
+fn rook_files_term(
+    mut rooks: Bitboard,
+    white: bool,
+    params: &EvalParams,
+    structure: &PawnStructure,
+) -> Tapered
+{
+    let semi_open_files = structure.semi_open_files[if white { 0 } else { 1 }];
+    let mut total = Tapered::default();
+    while rooks != 0
+    {
+        let file = 1u8 << (rooks.trailing_zeros() % 8);
+        if structure.open_files & file != 0
+        {
+            total = total + params.rook_open_file;
+        }
+        else if semi_open_files & file != 0
+        {
+            total = total + params.rook_semi_open_file;
+        }
+        rooks &= rooks - 1;
+    }
+    return total;
+}
+
+#[cfg(test)]
+mod tests
+{
+    use crate::*;
+
+    #[test]
+    fn flipped_positions_have_the_same_evaluation()
+    {
+        let mut rng = default_randomness().rng(0);
+        for plies in 0 .. 200
+        {
+            let board = Board::random_position(&mut rng, plies % 80);
+            let flipped = board.flipped();
+            assert_eq!(evaluate(&board), evaluate(&flipped), "{}", board.to_fen());
+            // The trace is from white's perspective, the evaluation from the side to move's.
+            let white_score =
+                if board.white_to_play { evaluate(&board) } else { -evaluate(&board) };
+            assert_eq!(white_score, evaluate_trace(&board).total, "{}", board.to_fen());
+            assert_eq!(evaluate_trace(&board).total, -evaluate_trace(&flipped).total);
+        }
+    }
+
+    #[test]
+    fn side_to_move_gets_the_tempo()
+    {
+        let tempo = Score(EvalParams::DEFAULT.tempo.taper(MAX_PHASE));
+        let white = Board::new().unwrap();
+        let black =
+            Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq -").unwrap();
+        assert_eq!(evaluate(&white), tempo);
+        assert_eq!(evaluate(&black), tempo);
+
+        // Without the tempo, the symmetric initial position is balanced.
+        let params = EvalParams { tempo: Tapered::default(), ..EvalParams::DEFAULT };
+        assert_eq!(evaluate_with(&white, &params), Score::DRAW);
+        assert_eq!(evaluate_with(&black, &params), Score::DRAW);
+    }
+
+    #[test]
+    fn piece_square_tables_are_seen_from_each_side()
+    {
+        // A bonus for a knight on a1 goes to the knights of black on a8. Only the kings and the
+        // knights are on the board, and the kings are not given any bonus.
+        let mut values = [[0; 8]; 8];
+        values[7][0] = 50;
+        let without = EvalParams {
+            knight_pst: SquareTable::default(),
+            king_pst: SquareTable::default(),
+            ..EvalParams::DEFAULT
+        };
+        let with = EvalParams { knight_pst: SquareTable::uniform(values), ..without };
+        let squares = |fen: &str, params: &EvalParams| {
+            let trace = evaluate_trace_with(&Board::from_fen(fen).unwrap(), params);
+            let term = trace.terms.into_iter().find(|term| term.name == "squares").unwrap();
+            return (term.white, term.black);
+        };
+        for (fen, bonus) in
+            [("n3k3/8/8/8/8/8/8/N3K3 w - -", (50, 50)), ("7n/4k3/8/8/8/8/8/4K2N w - -", (0, 0))]
+        {
+            assert_eq!(squares(fen, &without), (0, 0), "{}", fen);
+            assert_eq!(squares(fen, &with), bonus, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn back_rank_pawns_are_not_probed_in_the_kpk_bitbase()
+    {
+        for fen in ["4k3/8/8/8/8/8/8/P3K3 w - -", "P3k3/8/8/8/8/8/8/4K3 w - -"]
+        {
+            for board in [Board::from_fen(fen).unwrap(), Board::from_fen(fen).unwrap().flipped()]
+            {
+                assert_eq!(known_draw(&board), None, "{}", board.to_fen());
+                evaluate(&board);
+                evaluate_trace(&board);
+            }
+        }
+    }
+}
@@ -0,0 +1,414 @@
+use alloc::{format, string::String, vec::Vec};
+use core::ops::{Add, Mul, Neg, Sub};
+
+use serde::{Deserialize, Deserializer, Serialize, de::Error};
+
+use crate::Index;
+
+// A value for the middlegame and a value for the endgame, in centipawns. The evaluation
+// interpolates between them depending on the material left on the board.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct Tapered
+{
+    pub mg: i32,
+    pub eg: i32,
+}
+
+// A value for each square in the middlegame and in the endgame, in centipawns, for a piece standing
+// on it. The rows go from the eighth rank to the first one, like a diagram seen from white's side,
+// and the squares of black's pieces are mirrored vertically.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+pub struct SquareTable
+{
+    #[serde(deserialize_with = "deserialize_square_values")]
+    pub mg: [[i32; 8]; 8],
+    #[serde(deserialize_with = "deserialize_square_values")]
+    pub eg: [[i32; 8]; 8],
+}
+
+// Read the values of a piece-square table, refusing the tables without 8 rows of 8 values, which
+// TOML would otherwise truncate.
+fn deserialize_square_values<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<[[i32; 8]; 8], D::Error>
+{
+    let rows = Vec::<Vec<i32>>::deserialize(deserializer)?;
+    if rows.len() != 8 || rows.iter().any(|row| row.len() != 8)
+    {
+        return Err(D::Error::custom("a piece-square table must have 8 rows of 8 values"));
+    }
+    let mut values = [[0; 8]; 8];
+    for (row, values_row) in rows.iter().zip(values.iter_mut())
+    {
+        values_row.copy_from_slice(row);
+    }
+    return Ok(values);
+}
+
+impl SquareTable
+{
+    // Use the same values in the middlegame and in the endgame.
+    pub const fn uniform(values: [[i32; 8]; 8]) -> SquareTable
+    {
+        return SquareTable { mg: values, eg: values };
+    }
+
+    // Get the value of a piece of a side standing on a square.
+    pub const fn get(&self, white: bool, square: Index) -> Tapered
+    {
+        let row = if white { 7 - square / 8 } else { square / 8 };
+        let file = square % 8;
+        return Tapered::new(self.mg[row][file], self.eg[row][file]);
+    }
+}
+
+// Weight of each piece in the game phase. The phase is maximal with the material of the initial
+// position, and is 0 when only kings and pawns are left.
+pub const KNIGHT_PHASE: i32 = 1;
+pub const BISHOP_PHASE: i32 = 1;
+pub const ROOK_PHASE: i32 = 2;
+pub const QUEEN_PHASE: i32 = 4;
+pub const MAX_PHASE: i32 = 4 * KNIGHT_PHASE + 4 * BISHOP_PHASE + 4 * ROOK_PHASE + 2 * QUEEN_PHASE;
+
+impl Tapered
+{
+    pub const fn new(mg: i32, eg: i32) -> Tapered
+    {
+        return Tapered { mg, eg };
+    }
+
+    // Interpolate between the middlegame and endgame values, 'phase' going from 0 (endgame) to
+    // 'MAX_PHASE' (middlegame).
+    pub const fn taper(self, phase: i32) -> i32
+    {
+        return (self.mg * phase + self.eg * (MAX_PHASE - phase)) / MAX_PHASE;
+    }
+}
+
+impl Add for Tapered
+{
+    type Output = Tapered;
+
+    fn add(self, other: Tapered) -> Tapered
+    {
+        return Tapered::new(self.mg + other.mg, self.eg + other.eg);
+    }
+}
+
+impl Sub for Tapered
+{
+    type Output = Tapered;
+
+    fn sub(self, other: Tapered) -> Tapered
+    {
+        return Tapered::new(self.mg - other.mg, self.eg - other.eg);
+    }
+}
+
+impl Neg for Tapered
+{
+    type Output = Tapered;
+
+    fn neg(self) -> Tapered
+    {
+        return Tapered::new(-self.mg, -self.eg);
+    }
+}
+
+impl Mul<i32> for Tapered
+{
+    type Output = Tapered;
+
+    fn mul(self, factor: i32) -> Tapered
+    {
+        return Tapered::new(self.mg * factor, self.eg * factor);
+    }
+}
+
+// The weights of the evaluation. In a configuration file, the missing weights keep their default.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EvalParams
+{
+    // Material values of the pieces.
+    pub pawn: Tapered,
+    pub knight: Tapered,
+    pub bishop: Tapered,
+    pub rook: Tapered,
+    pub queen: Tapered,
+    // Bonuses of the pieces for the squares they stand on.
+    pub pawn_pst: SquareTable,
+    pub knight_pst: SquareTable,
+    pub bishop_pst: SquareTable,
+    pub rook_pst: SquareTable,
+    pub queen_pst: SquareTable,
+    pub king_pst: SquareTable,
+    // Bonus for having at least two bishops.
+    pub bishop_pair: Tapered,
+    // Adjustments of the value of each knight, rook and queen for each pawn of its side above
+    // 'PAWN_ADJUSTMENT_BASE', or below it for negative counts. Knights need pawns as outposts,
+    // while rooks and queens like the open files left when pawns are traded.
+    pub knight_pawn_adjustment: Tapered,
+    pub rook_pawn_adjustment: Tapered,
+    pub queen_pawn_adjustment: Tapered,
+    // Penalty for a side without pawns, which often can't win even with more material, so that
+    // the engine doesn't trade its last pawns into a dead draw.
+    pub no_pawns: Tapered,
+    // Bonus for the side winning a king and pawn versus king ending according to the bitbase.
+    pub kpk_win: Tapered,
+    // Mop-up bonuses of a side with a winning material advantage, for each step that the enemy
+    // king is away from the center, and for each step that the kings are closer.
+    pub mop_up_edge: Tapered,
+    pub mop_up_kings: Tapered,
+    // Bonus of a passed pawn for each rank it advanced from its initial rank.
+    pub passed_pawn: Tapered,
+    // Penalties of the isolated pawns, and of the pawns with another pawn of their side in front.
+    pub isolated_pawn: Tapered,
+    pub doubled_pawn: Tapered,
+    // Bonus of a king on one of its first two ranks for each pawn of its side sheltering it.
+    pub king_shield: Tapered,
+    // Bonuses of a rook on a file without any pawn, and on a file with only enemy pawns.
+    pub rook_open_file: Tapered,
+    pub rook_semi_open_file: Tapered,
+    // Bonus for each safe square behind the central pawns, and for each attack on the four center
+    // squares. Both only matter while there are pieces to use them.
+    pub space: Tapered,
+    pub center_control: Tapered,
+    // Bonuses for each enemy piece attacked by a piece of lower value, and for each enemy piece
+    // attacked and not defended.
+    pub threat_by_lesser: Tapered,
+    pub hanging_piece: Tapered,
+    // Bonus of the side to move, which can improve its position before its opponent does.
+    pub tempo: Tapered,
+}
+
+// Number of pawns of a side for which the pawn adjustments are 0.
+pub const PAWN_ADJUSTMENT_BASE: i32 = 5;
+
+// Bounds of the weights accepted from a file, in centipawns: the material values are positive and
+// below 'MAX_MATERIAL_WEIGHT', and the other weights are between '-MAX_TERM_WEIGHT' and
+// 'MAX_TERM_WEIGHT'. They keep the evaluation far from the mate scores, whatever the position.
+pub const MAX_MATERIAL_WEIGHT: i32 = 5000;
+pub const MAX_TERM_WEIGHT: i32 = 1000;
+
+impl EvalParams
+{
+    pub const DEFAULT: EvalParams = EvalParams {
+        pawn: Tapered::new(100, 120),
+        knight: Tapered::new(320, 300),
+        bishop: Tapered::new(330, 320),
+        rook: Tapered::new(500, 550),
+        queen: Tapered::new(950, 980),
+        pawn_pst: SquareTable {
+            mg: [
+                [0, 0, 0, 0, 0, 0, 0, 0],
+                [20, 20, 20, 20, 20, 20, 20, 20],
+                [8, 8, 12, 16, 16, 12, 8, 8],
+                [4, 4, 8, 12, 12, 8, 4, 4],
+                [0, 0, 4, 10, 10, 4, 0, 0],
+                [2, -2, -4, 0, 0, -4, -2, 2],
+                [2, 4, 4, -10, -10, 4, 4, 2],
+                [0, 0, 0, 0, 0, 0, 0, 0],
+            ],
+            eg: [
+                [0, 0, 0, 0, 0, 0, 0, 0],
+                [30, 30, 30, 30, 30, 30, 30, 30],
+                [16, 16, 16, 16, 16, 16, 16, 16],
+                [8, 8, 8, 8, 8, 8, 8, 8],
+                [4, 4, 4, 4, 4, 4, 4, 4],
+                [0, 0, 0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0, 0, 0],
+                [0, 0, 0, 0, 0, 0, 0, 0],
+            ],
+        },
+        knight_pst: SquareTable::uniform([
+            [-40, -25, -20, -20, -20, -20, -25, -40],
+            [-25, -10, 0, 0, 0, 0, -10, -25],
+            [-20, 0, 5, 8, 8, 5, 0, -20],
+            [-20, 3, 8, 10, 10, 8, 3, -20],
+            [-20, 0, 8, 10, 10, 8, 0, -20],
+            [-20, 3, 5, 8, 8, 5, 3, -20],
+            [-25, -10, 0, 3, 3, 0, -10, -25],
+            [-40, -25, -20, -20, -20, -20, -25, -40],
+        ]),
+        bishop_pst: SquareTable::uniform([
+            [-10, -5, -5, -5, -5, -5, -5, -10],
+            [-5, 0, 0, 0, 0, 0, 0, -5],
+            [-5, 0, 3, 5, 5, 3, 0, -5],
+            [-5, 3, 3, 5, 5, 3, 3, -5],
+            [-5, 0, 5, 5, 5, 5, 0, -5],
+            [-5, 5, 5, 5, 5, 5, 5, -5],
+            [-5, 3, 0, 0, 0, 0, 3, -5],
+            [-10, -5, -5, -5, -5, -5, -5, -10],
+        ]),
+        rook_pst: SquareTable::uniform([
+            [0, 0, 0, 0, 0, 0, 0, 0],
+            [5, 10, 10, 10, 10, 10, 10, 5],
+            [-3, 0, 0, 0, 0, 0, 0, -3],
+            [-3, 0, 0, 0, 0, 0, 0, -3],
+            [-3, 0, 0, 0, 0, 0, 0, -3],
+            [-3, 0, 0, 0, 0, 0, 0, -3],
+            [-3, 0, 0, 0, 0, 0, 0, -3],
+            [0, 0, 0, 3, 3, 0, 0, 0],
+        ]),
+        queen_pst: SquareTable::uniform([
+            [-10, -5, -5, -3, -3, -5, -5, -10],
+            [-5, 0, 0, 0, 0, 0, 0, -5],
+            [-5, 0, 3, 3, 3, 3, 0, -5],
+            [-3, 0, 3, 3, 3, 3, 0, -3],
+            [-3, 0, 3, 3, 3, 3, 0, -3],
+            [-5, 0, 3, 3, 3, 3, 0, -5],
+            [-5, 0, 0, 0, 0, 0, 0, -5],
+            [-10, -5, -5, -3, -3, -5, -5, -10],
+        ]),
+        // The king hides behind its pawns in the middlegame, and goes to the center in the
+        // endgame.
+        king_pst: SquareTable {
+            mg: [
+                [-15, -20, -20, -25, -25, -20, -20, -15],
+                [-15, -20, -20, -25, -25, -20, -20, -15],
+                [-15, -20, -20, -25, -25, -20, -20, -15],
+                [-15, -20, -20, -25, -25, -20, -20, -15],
+                [-10, -15, -15, -20, -20, -15, -15, -10],
+                [-5, -10, -10, -10, -10, -10, -10, -5],
+                [5, 5, 0, -5, -5, 0, 5, 5],
+                [8, 10, 5, 0, 0, 5, 10, 8],
+            ],
+            eg: [
+                [-25, -15, -10, -5, -5, -10, -15, -25],
+                [-15, -5, 0, 5, 5, 0, -5, -15],
+                [-10, 0, 10, 15, 15, 10, 0, -10],
+                [-5, 5, 15, 20, 20, 15, 5, -5],
+                [-5, 5, 15, 20, 20, 15, 5, -5],
+                [-10, 0, 10, 15, 15, 10, 0, -10],
+                [-15, -5, 0, 5, 5, 0, -5, -15],
+                [-25, -15, -10, -5, -5, -10, -15, -25],
+            ],
+        },
+        bishop_pair: Tapered::new(30, 50),
+        knight_pawn_adjustment: Tapered::new(6, 6),
+        rook_pawn_adjustment: Tapered::new(-12, -12),
+        queen_pawn_adjustment: Tapered::new(-6, -6),
+        no_pawns: Tapered::new(0, -80),
+        kpk_win: Tapered::new(400, 400),
+        mop_up_edge: Tapered::new(10, 10),
+        mop_up_kings: Tapered::new(4, 4),
+        passed_pawn: Tapered::new(5, 12),
+        isolated_pawn: Tapered::new(-10, -12),
+        doubled_pawn: Tapered::new(-8, -18),
+        king_shield: Tapered::new(10, 0),
+        rook_open_file: Tapered::new(25, 10),
+        rook_semi_open_file: Tapered::new(12, 6),
+        space: Tapered::new(2, 0),
+        center_control: Tapered::new(4, 0),
+        threat_by_lesser: Tapered::new(30, 25),
+        hanging_piece: Tapered::new(20, 15),
+        tempo: Tapered::new(20, 10),
+    };
+
+    // Get the weights with their name in a configuration file, the material values first.
+    pub fn weights(&self) -> [(&'static str, Tapered); 24]
+    {
+        return [
+            ("pawn", self.pawn),
+            ("knight", self.knight),
+            ("bishop", self.bishop),
+            ("rook", self.rook),
+            ("queen", self.queen),
+            ("bishop_pair", self.bishop_pair),
+            ("knight_pawn_adjustment", self.knight_pawn_adjustment),
+            ("rook_pawn_adjustment", self.rook_pawn_adjustment),
+            ("queen_pawn_adjustment", self.queen_pawn_adjustment),
+            ("no_pawns", self.no_pawns),
+            ("kpk_win", self.kpk_win),
+            ("mop_up_edge", self.mop_up_edge),
+            ("mop_up_kings", self.mop_up_kings),
+            ("passed_pawn", self.passed_pawn),
+            ("isolated_pawn", self.isolated_pawn),
+            ("doubled_pawn", self.doubled_pawn),
+            ("king_shield", self.king_shield),
+            ("rook_open_file", self.rook_open_file),
+            ("rook_semi_open_file", self.rook_semi_open_file),
+            ("space", self.space),
+            ("center_control", self.center_control),
+            ("threat_by_lesser", self.threat_by_lesser),
+            ("hanging_piece", self.hanging_piece),
+            ("tempo", self.tempo),
+        ];
+    }
+
+    // Get the piece-square tables with their name in a configuration file, in the order of the
+    // pieces from the pawn to the king.
+    pub fn square_tables(&self) -> [(&'static str, &SquareTable); 6]
+    {
+        return [
+            ("pawn_pst", &self.pawn_pst),
+            ("knight_pst", &self.knight_pst),
+            ("bishop_pst", &self.bishop_pst),
+            ("rook_pst", &self.rook_pst),
+            ("queen_pst", &self.queen_pst),
+            ("king_pst", &self.king_pst),
+        ];
+    }
+
+    // Check that the weights are within their bounds, so that weights read from a file can't make
+    // the search take a normal position for a mate. The values of the piece-square tables are
+    // bounded like the weights other than the material values.
+    pub fn validate(&self) -> Result<(), String>
+    {
+        for (i, (name, weight)) in self.weights().iter().enumerate()
+        {
+            let (min, max) =
+                if i < 5 { (1, MAX_MATERIAL_WEIGHT) } else { (-MAX_TERM_WEIGHT, MAX_TERM_WEIGHT) };
+            if !(min ..= max).contains(&weight.mg) || !(min ..= max).contains(&weight.eg)
+            {
+                return Err(format!(
+                    "The evaluation weight '{}' must be between {} and {} centipawns.",
+                    name, min, max
+                ));
+            }
+        }
+        let bounds = -MAX_TERM_WEIGHT ..= MAX_TERM_WEIGHT;
+        for (name, table) in self.square_tables()
+        {
+            if !table.mg.iter().chain(table.eg.iter()).flatten().all(|value| bounds.contains(value))
+            {
+                return Err(format!(
+                    "The values of the piece-square table '{}' must be between {} and {} \
+                     centipawns.",
+                    name, -MAX_TERM_WEIGHT, MAX_TERM_WEIGHT
+                ));
+            }
+        }
+        return Ok(());
+    }
+
+    // Read the weights from a TOML file, or from a JSON file when its name ends with '.json',
+    // written like the 'eval' section of the configuration: the missing weights keep their
+    // default.
+    #[cfg(feature = "std")]
+    pub fn from_file(path: &str) -> Result<EvalParams, String>
+    {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("Can't read '{}': {}", path, err))?;
+        let params: EvalParams = if path.to_ascii_lowercase().ends_with(".json")
+        {
+            serde_json::from_str(&text).map_err(|err| err.to_string())
+        }
+        else
+        {
+            toml::from_str(&text).map_err(|err| err.to_string())
+        }
+        .map_err(|err| format!("Invalid evaluation weights '{}': {}", path, err))?;
+        params.validate()?;
+        return Ok(params);
+    }
+}
+
+impl Default for EvalParams
+{
+    fn default() -> Self
+    {
+        return EvalParams::DEFAULT;
+    }
+}
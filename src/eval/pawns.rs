@@ -0,0 +1,167 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{
+    Bitboard, Board, adjacent_files_mask, black_king_pawn_mask, file_mask, front_span,
+    passed_pawn_mask, white_king_pawn_mask,
+};
+
+// Default number of entries of a pawn table.
+pub const DEFAULT_PAWN_TABLE_ENTRIES: usize = 1 << 12;
+// Memory used by each entry of a pawn table, in bytes.
+pub const PAWN_TABLE_ENTRY_BYTES: usize = size_of::<Option<(u64, PawnStructure)>>();
+
+// The two ranks in front of the first rank of each side, where the pawns shield a castled king.
+const WHITE_SHIELD_RANKS: Bitboard = 0x0000_0000_00FF_FF00;
+const BLACK_SHIELD_RANKS: Bitboard = 0x00FF_FF00_0000_0000;
+// The squares of the files c to f where each side can gain space, from its second rank to the
+// second rank of its opponent's half.
+const WHITE_SPACE_AREA: Bitboard = 0x0000_3C3C_3C3C_3C00;
+const BLACK_SPACE_AREA: Bitboard = 0x003C_3C3C_3C3C_0000;
+
+// What the evaluation needs to know about the pawns, which only depends on the squares of the
+// pawns, so that it can be cached by the pawn hash of the board. The arrays are indexed by side,
+// white first, and the files are bits, the a-file first.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct PawnStructure
+{
+    // Pawns without enemy pawn in front of them on their file and the adjacent files, nor a pawn
+    // of their side in front of them.
+    pub passed: [Bitboard; 2],
+    // Pawns without pawn of their side on the adjacent files.
+    pub isolated: [Bitboard; 2],
+    // Pawns with another pawn of their side in front of them on their file.
+    pub doubled: [Bitboard; 2],
+    // Files without any pawn.
+    pub open_files: u8,
+    // Files without pawn of each side.
+    pub semi_open_files: [u8; 2],
+    // Number of pawns of each side on the two ranks in front of its first rank, on the file of
+    // its king and on the adjacent files, for each file the king can stand on.
+    pub shield: [[u8; 8]; 2],
+    // Number of squares of the space area of each side behind its pawns, on their files, that no
+    // enemy pawn attacks. The further the central pawns advance, the more space they gain.
+    pub space: [u8; 2],
+}
+
+impl PawnStructure
+{
+    pub fn new(board: &Board) -> Self
+    {
+        let mut structure = PawnStructure::default();
+        let pawn_attacks = [
+            attacks_of(board.white_pawns, white_king_pawn_mask),
+            attacks_of(board.black_pawns, black_king_pawn_mask),
+        ];
+        for (side, white) in [(0, true), (1, false)]
+        {
+            let (pawns, enemy_pawns) = if white
+            {
+                (board.white_pawns, board.black_pawns)
+            }
+            else
+            {
+                (board.black_pawns, board.white_pawns)
+            };
+            let mut behind = 0;
+            let mut remaining = pawns;
+            while remaining != 0
+            {
+                let sq = remaining.trailing_zeros() as usize;
+                remaining &= remaining - 1;
+                let bit = 1u64 << sq;
+                let blocked_by_own = front_span(white, sq) & pawns != 0;
+                if !blocked_by_own && passed_pawn_mask(white, sq) & enemy_pawns == 0
+                {
+                    structure.passed[side] |= bit;
+                }
+                if adjacent_files_mask(sq) & pawns == 0
+                {
+                    structure.isolated[side] |= bit;
+                }
+                if blocked_by_own
+                {
+                    structure.doubled[side] |= bit;
+                }
+                behind |= front_span(!white, sq);
+            }
+            let space_area = if white { WHITE_SPACE_AREA } else { BLACK_SPACE_AREA };
+            let safe_behind = behind & space_area & !pawns & !pawn_attacks[1 - side];
+            structure.space[side] = safe_behind.count_ones() as u8;
+
+            let shield_ranks = if white { WHITE_SHIELD_RANKS } else { BLACK_SHIELD_RANKS };
+            for file in 0 .. 8
+            {
+                if file_mask(file) & pawns == 0
+                {
+                    structure.semi_open_files[side] |= 1 << file;
+                }
+                let shield_files = file_mask(file) | adjacent_files_mask(file);
+                structure.shield[side][file] =
+                    (pawns & shield_ranks & shield_files).count_ones() as u8;
+            }
+        }
+        structure.open_files = structure.semi_open_files[0] & structure.semi_open_files[1];
+        return structure;
+    }
+}
+
+// Get the squares attacked by the pawns, given the attacks of a pawn on each square.
+fn attacks_of(mut pawns: Bitboard, attacks: fn(usize) -> Bitboard) -> Bitboard
+{
+    let mut attacked = 0;
+    while pawns != 0
+    {
+        attacked |= attacks(pawns.trailing_zeros() as usize);
+        pawns &= pawns - 1;
+    }
+    return attacked;
+}
+
+// A hash table of pawn structures, indexed by the pawn hash of the positions. The pawns move
+// much less often than the pieces, so most of the positions of a search share their structure
+// with a position already evaluated. Each slot holds the last structure stored in it.
+pub struct PawnTable
+{
+    entries: Vec<Option<(u64, PawnStructure)>>,
+}
+
+impl PawnTable
+{
+    // Create an empty table with the given number of entries. With 0 entries, nothing is cached.
+    pub fn new(size: usize) -> Self
+    {
+        return PawnTable { entries: vec![None; size] };
+    }
+
+    // Get the pawn structure of the position, from the table when it is there.
+    pub fn get(&mut self, board: &Board) -> PawnStructure
+    {
+        if self.entries.is_empty()
+        {
+            return PawnStructure::new(board);
+        }
+        let index = (board.pawn_hash % self.entries.len() as u64) as usize;
+        if let Some((key, structure)) = self.entries[index]
+            && key == board.pawn_hash
+        {
+            return structure;
+        }
+        let structure = PawnStructure::new(board);
+        self.entries[index] = Some((board.pawn_hash, structure));
+        return structure;
+    }
+
+    // Remove all entries.
+    pub fn clear(&mut self)
+    {
+        self.entries.fill(None);
+    }
+}
+
+impl Default for PawnTable
+{
+    fn default() -> Self
+    {
+        return Self::new(DEFAULT_PAWN_TABLE_ENTRIES);
+    }
+}
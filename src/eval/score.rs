@@ -0,0 +1,212 @@
+use alloc::{format, string::String};
+use core::{
+    fmt,
+    ops::{Add, Neg, Sub},
+};
+
+#[cfg(feature = "std")]
+use crate::{MAX_PHASE, Tapered};
+
+// A score in centipawns, from the side to move's perspective.
+// Checkmates are scored near 'MATE': a side that mates in 'n' plies from the root of a search
+// scores 'MATE - n', so that shorter mates are preferred, and the mated side scores 'n - MATE'.
+#[derive(Debug, Default, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash)]
+pub struct Score(pub i32);
+
+// What a score stands for: an evaluation in centipawns, or a forced mate in a number of moves,
+// positive when the side the score is for mates and negative when it is mated.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ScoreKind
+{
+    Centipawns(i32),
+    Mate(i32),
+}
+
+// Maximum distance from the root, in plies, of the mates that can be told apart from normal scores.
+const MAX_MATE_PLY: i32 = 1000;
+
+// Parameters of the win probability model, in centipawns: the score at which a side wins half of
+// its games, and the spread of the logistic curve around it. An advantage is converted more
+// easily with less material, so both are lower in the endgame: an extra pawn wins about 2 games
+// out of 10 in the middlegame and 4 in the endgame, and an extra piece about 8 in the middlegame
+// and almost all of them in the endgame.
+#[cfg(feature = "std")]
+const WIN_MIDPOINT: Tapered = Tapered::new(200, 120);
+#[cfg(feature = "std")]
+const WIN_SPREAD: Tapered = Tapered::new(80, 60);
+
+impl Score
+{
+    // Score of a drawn position.
+    pub const DRAW: Score = Score(0);
+    // Score of the side that has already mated.
+    pub const MATE: Score = Score(1_000_000);
+    // Bound beyond every score, used as the initial window of the search.
+    pub const INFINITE: Score = Score(Score::MATE.0 + 1);
+
+    // Score of the side that mates at 'ply' plies from the root.
+    pub const fn mate_in(ply: u8) -> Score
+    {
+        return Score(Score::MATE.0 - ply as i32);
+    }
+
+    // Score of the side that is mated at 'ply' plies from the root.
+    pub const fn mated_in(ply: u8) -> Score
+    {
+        return Score(ply as i32 - Score::MATE.0);
+    }
+
+    pub const fn centipawns(self) -> i32
+    {
+        return self.0;
+    }
+
+    pub const fn abs(self) -> Score
+    {
+        return Score(self.0.abs());
+    }
+
+    // Tell whether the score is a forced mate, for either side.
+    pub const fn is_mate(self) -> bool
+    {
+        return self.0.abs() >= Score::MATE.0 - MAX_MATE_PLY && self.0.abs() <= Score::MATE.0;
+    }
+
+    // Get the number of moves until mate: positive when the side to move mates, negative when it
+    // is mated. Return None for other scores.
+    pub const fn mate_moves(self) -> Option<i32>
+    {
+        if !self.is_mate()
+        {
+            return None;
+        }
+        let moves = (Score::MATE.0 - self.0.abs() + 1) / 2;
+        return Some(if self.0 > 0 { moves } else { -moves });
+    }
+
+    // Tell apart the mates from the other scores, so that they are not shown as huge centipawns.
+    pub const fn kind(self) -> ScoreKind
+    {
+        return match self.mate_moves()
+        {
+            Some(moves) => ScoreKind::Mate(moves),
+            None => ScoreKind::Centipawns(self.0),
+        };
+    }
+
+    // Convert a score relative to a node at 'ply' plies from the root into a score relative to
+    // that node, to store it in the transposition table. Mate distances are counted from the root
+    // during the search, but the same position can be reached at different plies.
+    pub const fn to_tt(self, ply: u8) -> Score
+    {
+        if !self.is_mate()
+        {
+            return self;
+        }
+        return if self.0 > 0 { Score(self.0 + ply as i32) } else { Score(self.0 - ply as i32) };
+    }
+
+    // Convert a score read from the transposition table back into a score relative to the root,
+    // for a node at 'ply' plies from it.
+    pub const fn from_tt(self, ply: u8) -> Score
+    {
+        if !self.is_mate()
+        {
+            return self;
+        }
+        return if self.0 > 0 { Score(self.0 - ply as i32) } else { Score(self.0 + ply as i32) };
+    }
+
+    // Get the probability that the side the score is for wins, between 0 and 1, in a position of
+    // the given game phase, from 0 in pawn endings to 'MAX_PHASE' with all the pieces. The
+    // exponential comes from the standard library, like the rounding of 'wdl'.
+    #[cfg(feature = "std")]
+    pub fn win_probability(self, phase: i32) -> f64
+    {
+        if let Some(moves) = self.mate_moves()
+        {
+            return if moves > 0 { 1.0 } else { 0.0 };
+        }
+        let phase = phase.clamp(0, MAX_PHASE);
+        let midpoint = WIN_MIDPOINT.taper(phase) as f64;
+        let spread = WIN_SPREAD.taper(phase) as f64;
+        return 1.0 / (1.0 + ((midpoint - self.0 as f64) / spread).exp());
+    }
+
+    // Get the chances of a win, a draw and a loss of the side the score is for, per mille.
+    #[cfg(feature = "std")]
+    pub fn wdl(self, phase: i32) -> (u32, u32, u32)
+    {
+        let win = (1000.0 * self.win_probability(phase)).round() as u32;
+        let loss = (1000.0 * (-self).win_probability(phase)).round() as u32;
+        return (win, 1000u32.saturating_sub(win + loss), loss);
+    }
+
+    // Get the number of points the side the score is for expects, between 0 and 1, a draw being
+    // worth half a point.
+    #[cfg(feature = "std")]
+    pub fn expected_points(self, phase: i32) -> f64
+    {
+        let win = self.win_probability(phase);
+        let loss = (-self).win_probability(phase);
+        return win + (1.0 - win - loss) / 2.0;
+    }
+
+    // Format the score for the 'info' command of UCI: "cp <centipawns>", or "mate <moves>".
+    pub fn to_uci(self) -> String
+    {
+        return match self.kind()
+        {
+            ScoreKind::Mate(moves) => format!("mate {}", moves),
+            ScoreKind::Centipawns(centipawns) => format!("cp {}", centipawns),
+        };
+    }
+}
+
+impl Neg for Score
+{
+    type Output = Score;
+
+    fn neg(self) -> Score
+    {
+        return Score(-self.0);
+    }
+}
+
+impl Add for Score
+{
+    type Output = Score;
+
+    fn add(self, other: Score) -> Score
+    {
+        return Score(self.0 + other.0);
+    }
+}
+
+impl Sub for Score
+{
+    type Output = Score;
+
+    fn sub(self, other: Score) -> Score
+    {
+        return Score(self.0 - other.0);
+    }
+}
+
+// Write the score in pawns with 2 decimals, like "+1.25", or as a mate distance, like "#3" or
+// "#-2".
+impl fmt::Display for Score
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        return match self.kind()
+        {
+            ScoreKind::Mate(moves) => write!(f, "#{}", moves),
+            ScoreKind::Centipawns(centipawns) =>
+            {
+                let sign = if centipawns < 0 { "-" } else { "+" };
+                write!(f, "{}{}.{:02}", sign, centipawns.abs() / 100, centipawns.abs() % 100)
+            },
+        };
+    }
+}
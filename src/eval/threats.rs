@@ -0,0 +1,112 @@
+use crate::{
+    BISHOP, Bitboard, Board, KNIGHT, Move, PAWN, Piece, QUEEN, ROOK, bishop_attacks_hq,
+    black_king_pawn_mask, for_each_attack, is_quiet, knight_mask, queen_attacks_hq,
+    rook_attacks_hq, white_king_pawn_mask,
+};
+
+// The pieces of each side under threat, indexed by side, white first. Only the knights, bishops,
+// rooks and queens are considered: the pawns are too many to be worth it, and the king can't be
+// traded.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct Threats
+{
+    // Pieces attacked by an enemy piece of lower value, which wins material by taking them even
+    // when they are defended.
+    pub attacked_by_lesser: [Bitboard; 2],
+    // Pieces attacked by the enemy and not defended.
+    pub hanging: [Bitboard; 2],
+}
+
+impl Threats
+{
+    pub fn new(board: &Board) -> Self
+    {
+        // The squares attacked by each side with the pieces of each value class.
+        let mut attacks = [[0u64; 5]; 2];
+        for (side, white) in [(0, true), (1, false)]
+        {
+            for_each_attack(board, white, |piece, attacked| {
+                attacks[side][value_class(piece)] |= attacked;
+            });
+        }
+
+        let mut threats = Threats::default();
+        for (side, white) in [(0, true), (1, false)]
+        {
+            let enemy = attacks[1 - side];
+            let (knights, bishops, rooks, queens) = if white
+            {
+                (board.white_knights, board.white_bishops, board.white_rooks, board.white_queens)
+            }
+            else
+            {
+                (board.black_knights, board.black_bishops, board.black_rooks, board.black_queens)
+            };
+            threats.attacked_by_lesser[side] = (knights | bishops) & enemy[0]
+                | rooks & (enemy[0] | enemy[1])
+                | queens & (enemy[0] | enemy[1] | enemy[2]);
+            let defended = attacks[side].iter().fold(0, |all, attacked| all | attacked);
+            let attacked = enemy.iter().fold(0, |all, attacked| all | attacked);
+            threats.hanging[side] = (knights | bishops | rooks | queens) & attacked & !defended;
+        }
+        return threats;
+    }
+
+    // Get the pieces of a side under threat, hanging or attacked by a lesser piece.
+    pub fn threatened(&self, white: bool) -> Bitboard
+    {
+        let side = if white { 0 } else { 1 };
+        return self.attacked_by_lesser[side] | self.hanging[side];
+    }
+}
+
+// Tell whether a quiet move of the side to move attacks from its destination an enemy piece of
+// more value than the moved piece, the king aside.
+pub fn creates_threat(board: &Board, mv: Move) -> bool
+{
+    if !is_quiet(mv)
+    {
+        return false;
+    }
+    let occupied = board.pieces & !(1u64 << mv.start) | 1u64 << mv.end;
+    let attacks = match mv.piece
+    {
+        PAWN if board.white_to_play => white_king_pawn_mask(mv.end),
+        PAWN => black_king_pawn_mask(mv.end),
+        KNIGHT => knight_mask(mv.end),
+        BISHOP => bishop_attacks_hq(mv.end, occupied),
+        ROOK => rook_attacks_hq(mv.end, occupied),
+        QUEEN => queen_attacks_hq(mv.end, occupied),
+        _ => return false,
+    };
+    let (knights, bishops, rooks, queens) = if board.white_to_play
+    {
+        (board.black_knights, board.black_bishops, board.black_rooks, board.black_queens)
+    }
+    else
+    {
+        (board.white_knights, board.white_bishops, board.white_rooks, board.white_queens)
+    };
+    let more_valuable = match value_class(mv.piece)
+    {
+        0 => knights | bishops | rooks | queens,
+        1 => rooks | queens,
+        2 => queens,
+        _ => 0,
+    };
+    return attacks & more_valuable != 0;
+}
+
+// Rank the pieces by value: the pawns, the knights and bishops, the rooks, the queens and the
+// kings.
+fn value_class(piece: Piece) -> usize
+{
+    return match piece
+    {
+        PAWN => 0,
+        KNIGHT | BISHOP => 1,
+        ROOK => 2,
+        QUEEN => 3,
+        _ => 4,
+    };
+}
@@ -0,0 +1,104 @@
+use alloc::{format, string::String, vec, vec::Vec};
+
+use crate::{
+    Board, EVAL_TERM_COUNT, EVAL_TERM_NAMES, EndgameScale, EvalParams, MAX_PHASE, PawnStructure,
+    SCALE_NORMAL, Score, Tapered, Threats, ZoneAttacks, endgame_scale, game_phase, known_draw,
+    side_terms,
+};
+
+// The value of an evaluation term for each side, in centipawns, tapered with the game phase.
+#[derive(Debug, Clone)]
+pub struct EvalTerm
+{
+    pub name: &'static str,
+    pub white: i32,
+    pub black: i32,
+}
+
+// The detail of an evaluation: the value of each term, the game phase, the known draw recognized if
+// there is one, the scaling of a drawish ending, the attacks of each side on the zones of the
+// board, and the total from white's perspective.
+// The total is tapered once from the sum of the terms, like the evaluation, so it can differ by
+// rounding from the sum of the tapered terms.
+#[derive(Debug, Clone)]
+pub struct EvalTrace
+{
+    pub terms: Vec<EvalTerm>,
+    pub phase: i32,
+    pub known_draw: Option<&'static str>,
+    pub scale: Option<EndgameScale>,
+    pub zones: ZoneAttacks,
+    pub total: Score,
+}
+
+impl EvalTrace
+{
+    // Write the terms as a table, one line per term.
+    pub fn to_table(&self) -> String
+    {
+        let mut table = format!("{:<12}{:>8}{:>8}{:>8}\n", "term", "white", "black", "total");
+        for term in self.terms.iter()
+        {
+            table.push_str(&format!(
+                "{:<12}{:>8}{:>8}{:>8}\n",
+                term.name,
+                term.white,
+                term.black,
+                term.white - term.black
+            ));
+        }
+        table.push_str(&format!("{:<12}{:>21}/{}\n", "phase", self.phase, MAX_PHASE));
+        if let Some(name) = self.known_draw
+        {
+            table.push_str(&format!("known draw: {}\n", name));
+        }
+        if let Some(scale) = self.scale
+        {
+            table.push_str(&format!(
+                "scaled ending: {}, endgame part {}/{}\n",
+                scale.name, scale.factor, SCALE_NORMAL
+            ));
+        }
+        table.push_str(&format!("{:<12}{:>24}\n", "total", self.total.centipawns()));
+        table.push_str(&self.zones.to_table());
+        return table;
+    }
+}
+
+// Evaluate the position like 'evaluate', but keep the value of each term for each side.
+// The total is from white's perspective.
+pub fn evaluate_trace(board: &Board) -> EvalTrace
+{
+    return evaluate_trace_with(board, &EvalParams::DEFAULT);
+}
+
+// Evaluate the position like 'evaluate_with', but keep the value of each term for each side.
+pub fn evaluate_trace_with(board: &Board, params: &EvalParams) -> EvalTrace
+{
+    let phase = game_phase(board);
+    let pawns = PawnStructure::new(board);
+    let threats = Threats::new(board);
+    let white = side_terms(board, true, params, &pawns, &threats);
+    let black = side_terms(board, false, params, &pawns, &threats);
+
+    let mut terms = vec![];
+    let mut total = Tapered::default();
+    for i in 0 .. EVAL_TERM_COUNT
+    {
+        terms.push(EvalTerm {
+            name: EVAL_TERM_NAMES[i],
+            white: white[i].taper(phase),
+            black: black[i].taper(phase),
+        });
+        total = total + white[i] - black[i];
+    }
+    let scale = endgame_scale(board, total);
+    if let Some(scale) = scale
+    {
+        total = scale.apply(total);
+    }
+    let known_draw = known_draw(board);
+    let total = if known_draw.is_some() { Score::DRAW } else { Score(total.taper(phase)) };
+    let zones = ZoneAttacks::new(board);
+    return EvalTrace { terms, phase, known_draw, scale, zones, total };
+}
@@ -0,0 +1,139 @@
+use alloc::{format, string::String};
+
+use crate::{
+    BISHOP, Bitboard, Board, KING, KNIGHT, PAWN, Piece, QUEEN, ROOK, bishop_attacks_hq,
+    black_king_pawn_mask, king_mask, knight_mask, queen_attacks_hq, rook_attacks_hq,
+    white_king_pawn_mask,
+};
+
+// The board is split in three zones of files: the queenside from a to c, the center with d and e,
+// and the kingside from f to h.
+pub const ZONE_COUNT: usize = 3;
+pub const ZONE_NAMES: [&str; ZONE_COUNT] = ["queenside", "center", "kingside"];
+pub const ZONE_MASKS: [Bitboard; ZONE_COUNT] =
+    [0x0707_0707_0707_0707, 0x1818_1818_1818_1818, 0xE0E0_E0E0_E0E0_E0E0];
+
+// Number of piece types, from 'PAWN' to 'KING'.
+const PIECE_TYPE_COUNT: usize = 6;
+
+// The number of attacks of each side on the squares of each zone, counted for each type of piece.
+// A square attacked by two pieces counts twice, and the squares of the pieces of the attacking
+// side are counted too, since defending them matters as much as attacking the enemy ones.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub struct ZoneAttacks
+{
+    // Indexed by zone, then by piece type minus one.
+    pub white: [[u32; PIECE_TYPE_COUNT]; ZONE_COUNT],
+    pub black: [[u32; PIECE_TYPE_COUNT]; ZONE_COUNT],
+}
+
+impl ZoneAttacks
+{
+    // Count the attacks of all the pieces of the board, once per evaluation.
+    pub fn new(board: &Board) -> Self
+    {
+        return ZoneAttacks { white: side_attacks(board, true), black: side_attacks(board, false) };
+    }
+
+    // Get the number of attacks of a type of piece of a side on a zone.
+    pub fn by_piece(&self, white: bool, zone: usize, piece: Piece) -> u32
+    {
+        let counts = if white { &self.white } else { &self.black };
+        return counts[zone][(piece - PAWN) as usize];
+    }
+
+    // Get the number of attacks of all the pieces of a side on a zone.
+    pub fn total(&self, white: bool, zone: usize) -> u32
+    {
+        let counts = if white { &self.white } else { &self.black };
+        return counts[zone].iter().sum();
+    }
+
+    // Write the attacks of each side on each zone as a table, one line per zone.
+    pub fn to_table(&self) -> String
+    {
+        let mut table = format!("{:<12}{:>8}{:>8}\n", "attacks", "white", "black");
+        for (zone, name) in ZONE_NAMES.iter().enumerate()
+        {
+            table.push_str(&format!(
+                "{:<12}{:>8}{:>8}\n",
+                name,
+                self.total(true, zone),
+                self.total(false, zone)
+            ));
+        }
+        return table;
+    }
+}
+
+fn side_attacks(board: &Board, white: bool) -> [[u32; PIECE_TYPE_COUNT]; ZONE_COUNT]
+{
+    let mut counts = [[0u32; PIECE_TYPE_COUNT]; ZONE_COUNT];
+    for_each_attack(board, white, |piece, attacked| {
+        for (zone_counts, mask) in counts.iter_mut().zip(ZONE_MASKS)
+        {
+            zone_counts[(piece - PAWN) as usize] += (attacked & mask).count_ones();
+        }
+    });
+    return counts;
+}
+
+// Count the attacks of the pieces of a side on the squares of 'mask', a square attacked by two
+// pieces counting twice.
+pub fn count_attacks(board: &Board, white: bool, mask: Bitboard) -> u32
+{
+    let mut count = 0;
+    for_each_attack(board, white, |_, attacked| count += (attacked & mask).count_ones());
+    return count;
+}
+
+// Call 'visit' with the type and the attacked squares of each piece of a side.
+pub(crate) fn for_each_attack(board: &Board, white: bool, mut visit: impl FnMut(Piece, Bitboard))
+{
+    let (pawns, rooks, knights, bishops, queens) = if white
+    {
+        (
+            board.white_pawns,
+            board.white_rooks,
+            board.white_knights,
+            board.white_bishops,
+            board.white_queens,
+        )
+    }
+    else
+    {
+        (
+            board.black_pawns,
+            board.black_rooks,
+            board.black_knights,
+            board.black_bishops,
+            board.black_queens,
+        )
+    };
+    let occ = board.pieces;
+    let pawn_attacks = |sq: usize| {
+        return if white { white_king_pawn_mask(sq) } else { black_king_pawn_mask(sq) };
+    };
+
+    visit_pieces(&mut visit, PAWN, pawns, pawn_attacks);
+    visit_pieces(&mut visit, ROOK, rooks, |sq| rook_attacks_hq(sq, occ));
+    visit_pieces(&mut visit, KNIGHT, knights, knight_mask);
+    visit_pieces(&mut visit, BISHOP, bishops, |sq| bishop_attacks_hq(sq, occ));
+    visit_pieces(&mut visit, QUEEN, queens, |sq| queen_attacks_hq(sq, occ));
+    visit_pieces(&mut visit, KING, board.king_bitboard(white), king_mask);
+}
+
+// Call 'visit' with the attacks of each piece of 'pieces', of type 'piece'.
+fn visit_pieces(
+    visit: &mut impl FnMut(Piece, Bitboard),
+    piece: Piece,
+    mut pieces: Bitboard,
+    attacks: impl Fn(usize) -> Bitboard,
+)
+{
+    while pieces != 0
+    {
+        visit(piece, attacks(pieces.trailing_zeros() as usize));
+        pieces &= pieces - 1;
+    }
+}
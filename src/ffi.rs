@@ -0,0 +1,155 @@
+// C bindings of the engine, declared in the generated header include/barnarok.h.
+// The moves are written in UCI, and the functions returning an int return a negative value on
+// failure.
+
+use std::{
+    ffi::{CStr, c_char, c_int},
+    ptr,
+};
+
+use crate::*;
+
+/// Create a board in the initial position. It must be freed with `barnarok_board_free`.
+#[unsafe(no_mangle)]
+pub extern "C" fn barnarok_board_new() -> *mut Board
+{
+    let board = Board::new().expect("The starting position is valid.");
+    return Box::into_raw(Box::new(board));
+}
+
+/// Free a board created by `barnarok_board_new`. Does nothing if `board` is null.
+///
+/// # Safety
+/// `board` must be null or come from `barnarok_board_new`, and must not be used afterwards.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn barnarok_board_free(board: *mut Board)
+{
+    if !board.is_null()
+    {
+        drop(unsafe { Box::from_raw(board) });
+    }
+}
+
+/// Set up the position of a FEN string. Returns 0, or -1 if the FEN is invalid, in which case the
+/// board is unchanged.
+///
+/// # Safety
+/// `board` must be a valid board and `fen` a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn barnarok_board_set_fen(board: *mut Board, fen: *const c_char) -> c_int
+{
+    let (Some(board), Some(fen)) = (unsafe { board.as_mut() }, unsafe { read_str(fen) })
+    else
+    {
+        return -1;
+    };
+    match Board::from_fen(fen)
+    {
+        Ok(new_board) =>
+        {
+            *board = new_board;
+            return 0;
+        },
+        Err(_) => return -1,
+    }
+}
+
+/// Write the legal moves, separated by spaces, as a NUL-terminated string in `buffer` of `size`
+/// bytes. Returns the number of moves, or -1 if the buffer is too small.
+///
+/// # Safety
+/// `board` must be a valid board and `buffer` must point to `size` writable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn barnarok_board_legal_moves(
+    board: *mut Board,
+    buffer: *mut c_char,
+    size: usize,
+) -> c_int
+{
+    let Some(board) = (unsafe { board.as_mut() })
+    else
+    {
+        return -1;
+    };
+    let moves = board.get_legal_moves();
+    let text: Vec<String> = moves.iter().map(|mv| mv.to_uci()).collect();
+    if unsafe { write_str(&text.join(" "), buffer, size) }
+    {
+        return moves.len() as c_int;
+    }
+    return -1;
+}
+
+/// Play a legal move given in UCI. Returns 0, or -1 if the move is not legal.
+///
+/// # Safety
+/// `board` must be a valid board and `uci` a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn barnarok_board_make_move(board: *mut Board, uci: *const c_char) -> c_int
+{
+    let (Some(board), Some(uci)) = (unsafe { board.as_mut() }, unsafe { read_str(uci) })
+    else
+    {
+        return -1;
+    };
+    match parse_uci_move(board, uci)
+    {
+        Some(mv) =>
+        {
+            board.make_move(mv);
+            return 0;
+        },
+        None => return -1,
+    }
+}
+
+/// Search the best move at the given depth, and write it in UCI as a NUL-terminated string in
+/// `buffer` of `size` bytes (6 bytes are always enough). Returns 0, or -1 if there are no legal
+/// moves or the buffer is too small.
+///
+/// # Safety
+/// `board` must be a valid board and `buffer` must point to `size` writable bytes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn barnarok_best_move(
+    board: *mut Board,
+    depth: u8,
+    buffer: *mut c_char,
+    size: usize,
+) -> c_int
+{
+    let Some(board) = (unsafe { board.as_mut() })
+    else
+    {
+        return -1;
+    };
+    let (_, best) = launch_alpha_beta_quiesce(board, depth);
+    match best
+    {
+        Some(mv) if unsafe { write_str(&mv.to_uci(), buffer, size) } => return 0,
+        _ => return -1,
+    }
+}
+
+// Read a NUL-terminated UTF-8 string, if the pointer isn't null.
+unsafe fn read_str<'a>(text: *const c_char) -> Option<&'a str>
+{
+    if text.is_null()
+    {
+        return None;
+    }
+    return unsafe { CStr::from_ptr(text) }.to_str().ok();
+}
+
+// Copy a string and its NUL terminator to a buffer, if it fits.
+unsafe fn write_str(text: &str, buffer: *mut c_char, size: usize) -> bool
+{
+    if buffer.is_null() || text.len() + 1 > size
+    {
+        return false;
+    }
+    unsafe {
+        ptr::copy_nonoverlapping(text.as_ptr() as *const c_char, buffer, text.len());
+        *buffer.add(text.len()) = 0;
+    }
+    return true;
+}
@@ -0,0 +1,116 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{Board, Move, MoveContext};
+
+// Bound of the continuation history scores. Each update moves a score towards the bound by a
+// fraction of the distance, so that old results fade away.
+const HISTORY_MAX: i32 = 16384;
+
+// Number of piece indices: 6 piece types for each color.
+const PIECE_INDICES: usize = 12;
+
+// A move played during the search, with the index of the piece that moved.
+#[derive(Debug, Clone, Copy)]
+pub struct PlayedMove
+{
+    pub mv: Move,
+    pub piece: usize,
+}
+
+impl PlayedMove
+{
+    // Describe a move before it is made on the board.
+    pub fn new(board: &Board, mv: Move) -> PlayedMove
+    {
+        let color_offset = if board.white_to_play { 0 } else { 6 };
+        let piece = color_offset + mv.piece as usize - 1;
+        return PlayedMove { mv, piece };
+    }
+
+    // Index of the piece and of its destination in the tables.
+    fn index(&self) -> usize
+    {
+        return self.piece * 64 + self.mv.end;
+    }
+}
+
+// Tell whether a move is quiet: neither a capture nor a promotion.
+pub fn is_quiet(mv: Move) -> bool
+{
+    return mv.capture.is_none()
+        && mv.context != MoveContext::EnPassant
+        && !matches!(mv.context, MoveContext::Promotion(_));
+}
+
+// Statistics on the quiet moves that caused beta cutoffs, indexed by the move played just before
+// them, used to order the quiet moves of the next nodes:
+// - the countermove of a move is the last quiet move that refuted it;
+// - the continuation history scores each pair of consecutive moves, by piece and destination, up
+//   when the second move caused a cutoff and down when it was tried before the move that did.
+pub struct History
+{
+    countermoves: Vec<Option<Move>>,
+    continuation: Vec<i32>,
+}
+
+impl History
+{
+    pub fn new() -> Self
+    {
+        return History {
+            countermoves: vec![None; PIECE_INDICES * 64],
+            continuation: vec![0; PIECE_INDICES * 64 * PIECE_INDICES * 64],
+        };
+    }
+
+    pub fn countermove(&self, previous: &PlayedMove) -> Option<Move>
+    {
+        return self.countermoves[previous.index()];
+    }
+
+    pub fn continuation_score(&self, previous: &PlayedMove, current: &PlayedMove) -> i32
+    {
+        return self.continuation[previous.index() * PIECE_INDICES * 64 + current.index()];
+    }
+
+    // Record that the quiet move 'best' caused a cutoff after 'previous', at the given remaining
+    // depth, while the quiet moves of 'tried' were searched before it without causing one.
+    pub fn update(
+        &mut self,
+        previous: &PlayedMove,
+        best: &PlayedMove,
+        tried: &[PlayedMove],
+        depth: u8,
+    )
+    {
+        self.countermoves[previous.index()] = Some(best.mv);
+
+        let bonus = (depth as i32 * depth as i32).min(HISTORY_MAX);
+        self.add_continuation(previous, best, bonus);
+        for played in tried
+        {
+            self.add_continuation(previous, played, -bonus);
+        }
+    }
+
+    fn add_continuation(&mut self, previous: &PlayedMove, current: &PlayedMove, bonus: i32)
+    {
+        let score = &mut self.continuation[previous.index() * PIECE_INDICES * 64 + current.index()];
+        *score += bonus - *score * bonus.abs() / HISTORY_MAX;
+    }
+
+    // Remove all statistics.
+    pub fn clear(&mut self)
+    {
+        self.countermoves.fill(None);
+        self.continuation.fill(0);
+    }
+}
+
+impl Default for History
+{
+    fn default() -> Self
+    {
+        return Self::new();
+    }
+}
@@ -0,0 +1,120 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::{Board, Move, PvLine, Search, SearchInfo, SharedDeadlines, TimeLimits};
+
+// Depth at which an infinite analysis ends if it was not stopped before.
+pub const INFINITE_DEPTH: u8 = 64;
+
+// An analysis searching in a background thread until it is told to stop, like the UCI
+// 'go infinite' command.
+pub struct InfiniteAnalysis
+{
+    stop: Arc<AtomicBool>,
+    deadlines: Arc<SharedDeadlines>,
+    handle: JoinHandle<(Vec<PvLine>, Search)>,
+}
+
+impl InfiniteAnalysis
+{
+    // Start analyzing the position with 'count' lines, searching only the root moves of
+    // 'search_moves' unless it is empty. 'report' is called from the background thread after each
+    // iteration of the iterative deepening, like with 'Search::analyze'.
+    // The search is moved to the thread, and given back when the analysis ends. Its stop flag and
+    // its shared deadlines are cleared first, so that a search stopped before can be started again.
+    pub fn start(
+        board: &Board,
+        count: usize,
+        search_moves: &[Move],
+        mut search: Search,
+        report: impl FnMut(u8, &mut Board, &[PvLine], &SearchInfo) + Send + 'static,
+    ) -> Self
+    {
+        let stop = search.stop_flag();
+        stop.store(false, Ordering::Relaxed);
+        let deadlines = search.shared_deadlines();
+        deadlines.clear();
+        let mut board = *board;
+        let search_moves = search_moves.to_vec();
+        let handle = thread::spawn(move || {
+            let lines = search.analyze(&mut board, INFINITE_DEPTH, count, &search_moves, report);
+            return (lines, search);
+        });
+        return InfiniteAnalysis { stop, deadlines, handle };
+    }
+
+    // Get the flag stopping the analysis when it is set, to stop it from another thread or from
+    // a signal handler.
+    pub fn stop_flag(&self) -> Arc<AtomicBool>
+    {
+        return self.stop.clone();
+    }
+
+    // Give time limits to the analysis from now on, so that it ends by itself like a timed search,
+    // e.g. when the opponent plays the expected move of a ponder search.
+    pub fn set_time_limits(&self, limits: TimeLimits)
+    {
+        self.deadlines.set(limits);
+    }
+
+    // Tell whether the analysis ended, because it was stopped or reached 'INFINITE_DEPTH'.
+    pub fn is_finished(&self) -> bool
+    {
+        return self.handle.is_finished();
+    }
+
+    // Stop the analysis, and return the lines of the last complete iteration and the search.
+    pub fn stop(self) -> (Vec<PvLine>, Search)
+    {
+        self.stop.store(true, Ordering::Relaxed);
+        return self.wait();
+    }
+
+    // Wait until the analysis ends, and return the lines of the last complete iteration and the
+    // search.
+    pub fn wait(self) -> (Vec<PvLine>, Search)
+    {
+        // A panic of the search is raised again in the calling thread.
+        return match self.handle.join()
+        {
+            Ok(result) => result,
+            Err(payload) => std::panic::resume_unwind(payload),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests
+{
+    use std::{
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::*;
+
+    #[test]
+    fn time_limits_end_a_running_analysis()
+    {
+        let board = Board::new().unwrap();
+        let analysis = InfiniteAnalysis::start(&board, 1, &[], Search::default(), |_, _, _, _| ());
+        thread::sleep(Duration::from_millis(50));
+        assert!(!analysis.is_finished());
+
+        // Like 'ponderhit' with 'go ponder movetime 200'.
+        let start = Instant::now();
+        analysis.set_time_limits(TimeLimits::fixed(Duration::from_millis(200), Duration::ZERO));
+        while !analysis.is_finished() && start.elapsed() < Duration::from_secs(10)
+        {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(analysis.is_finished());
+        let (lines, _) = analysis.wait();
+        assert_eq!(lines.len(), 1);
+    }
+}
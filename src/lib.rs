@@ -1,17 +1,128 @@
-pub mod ai;
+// Without the 'std' feature, only the core is built: the board, the move generation, the
+// evaluation and a basic search, which need an allocator but not the standard library.
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(feature = "std")]
+pub mod analysis;
+#[cfg(feature = "std")]
+pub mod annotate;
+pub mod basic;
+#[cfg(feature = "std")]
+pub mod batch;
+#[cfg(feature = "std")]
+pub mod bench;
 pub mod board;
+pub mod builder;
+#[cfg(feature = "std")]
+pub mod config;
+#[cfg(feature = "std")]
+pub mod datagen;
 pub mod defines;
+pub mod display;
+pub mod error;
+pub mod eval;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod history;
+#[cfg(feature = "std")]
+pub mod infinite;
+#[cfg(feature = "std")]
+pub mod logging;
 pub mod masks;
+pub mod mate;
 pub mod moves;
+#[cfg(feature = "std")]
+pub mod naive;
 pub mod piece;
+#[cfg(feature = "std")]
 pub mod play;
+#[cfg(feature = "std")]
+pub mod random;
+#[cfg(feature = "std")]
+pub mod record;
+#[cfg(feature = "std")]
+pub mod render;
+#[cfg(feature = "std")]
+pub mod repl;
+#[cfg(feature = "std")]
+pub mod search;
+#[cfg(feature = "std")]
+pub mod selftest;
+#[cfg(feature = "std")]
+pub mod tablebase;
+#[cfg(feature = "std")]
+pub mod tournament;
+#[cfg(feature = "std")]
+pub mod training;
+#[cfg(feature = "std")]
+pub mod uci;
 pub mod utils;
+pub mod validate;
+pub mod variant;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod zobrist;
 
-pub use ai::*;
+#[cfg(feature = "std")]
+pub use analysis::*;
+#[cfg(feature = "std")]
+pub use annotate::*;
+pub use basic::*;
+#[cfg(feature = "std")]
+pub use batch::*;
+#[cfg(feature = "std")]
+pub use bench::*;
 pub use board::*;
+pub use builder::*;
+#[cfg(feature = "std")]
+pub use config::*;
+#[cfg(feature = "std")]
+pub use datagen::*;
 pub use defines::*;
+pub use display::*;
+pub use error::*;
+pub use eval::*;
+#[cfg(feature = "ffi")]
+pub use ffi::*;
+pub use history::*;
+#[cfg(feature = "std")]
+pub use infinite::*;
+#[cfg(feature = "std")]
+pub use logging::*;
 pub use masks::*;
+pub use mate::*;
 pub use moves::*;
+#[cfg(feature = "std")]
+pub use naive::*;
 pub use piece::*;
+#[cfg(feature = "std")]
 pub use play::*;
+#[cfg(feature = "std")]
+pub use random::*;
+#[cfg(feature = "std")]
+pub use record::*;
+// The render module only has free functions with the png feature.
+#[cfg(feature = "png")]
+pub use render::*;
+#[cfg(feature = "std")]
+pub use repl::*;
+#[cfg(feature = "std")]
+pub use search::*;
+#[cfg(feature = "std")]
+pub use selftest::*;
+#[cfg(feature = "std")]
+pub use tablebase::*;
+#[cfg(feature = "std")]
+pub use tournament::*;
+#[cfg(feature = "std")]
+pub use training::*;
+#[cfg(feature = "std")]
+pub use uci::*;
 pub use utils::*;
+pub use validate::*;
+pub use variant::*;
+#[cfg(feature = "wasm")]
+pub use wasm::*;
+pub use zobrist::*;
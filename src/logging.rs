@@ -0,0 +1,90 @@
+use std::{
+    fs::File,
+    io::{LineWriter, Write},
+    str::FromStr,
+    sync::Mutex,
+    time::Instant,
+};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+// Writes each message on a line with the time since the start of the process, its level and the
+// module that logged it. The messages go to a file, or to the standard error without one, but
+// never to the standard output, which carries the UCI protocol.
+struct EngineLogger
+{
+    start: Instant,
+    file: Option<Mutex<LineWriter<File>>>,
+}
+
+impl Log for EngineLogger
+{
+    fn enabled(&self, metadata: &Metadata) -> bool
+    {
+        return metadata.level() <= log::max_level();
+    }
+
+    fn log(&self, record: &Record)
+    {
+        if !self.enabled(record.metadata())
+        {
+            return;
+        }
+        let line = format!(
+            "{:>10.3} {:<5} {}: {}",
+            self.start.elapsed().as_secs_f64(),
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        match &self.file
+        {
+            // A message that can't be written is lost, since there is nowhere to report it.
+            Some(file) =>
+            {
+                let _ = writeln!(file.lock().unwrap(), "{}", line);
+            },
+            None => eprintln!("{}", line),
+        }
+    }
+
+    fn flush(&self)
+    {
+        if let Some(file) = &self.file
+        {
+            let _ = file.lock().unwrap().flush();
+        }
+    }
+}
+
+// Get a level from its name: off, error, warn, info, debug or trace.
+pub fn parse_log_level(name: &str) -> Result<LevelFilter, String>
+{
+    return LevelFilter::from_str(name).map_err(|_| {
+        format!(
+            "Unknown log level '{}'. The levels are: off, error, warn, info, debug, trace.",
+            name
+        )
+    });
+}
+
+// Send the messages up to 'level' to the file at 'path', which is truncated, or to the standard
+// error. The UCI traffic is logged at the debug level, the iterations of the searches at the debug
+// level and their pruning decisions at the trace level. It can only be called once.
+pub fn init_logging(level: LevelFilter, path: Option<&str>) -> Result<(), String>
+{
+    let file = match path
+    {
+        Some(path) =>
+        {
+            let file =
+                File::create(path).map_err(|err| format!("Can't create '{}': {}", path, err))?;
+            Some(Mutex::new(LineWriter::new(file)))
+        },
+        None => None,
+    };
+    log::set_boxed_logger(Box::new(EngineLogger { start: Instant::now(), file }))
+        .map_err(|err| err.to_string())?;
+    log::set_max_level(level);
+    return Ok(());
+}
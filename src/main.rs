@@ -1,5 +1,10 @@
+use std::{
+    sync::{Arc, atomic::AtomicBool},
+    time::{Duration, Instant},
+};
+
 use barnarok::*;
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand, value_parser};
 
 #[derive(Parser)]
 #[command(name = "barnarok")]
@@ -8,18 +13,53 @@ struct Cli
 {
     #[command(subcommand)]
     command: Commands,
+    // Seed of the random choices, like the moves of the random strategy and the opening variety of
+    // the searches, so that the results can be reproduced.
+    #[arg(long, global = true)]
+    seed: Option<u64>,
+    // Configuration file of the engine, instead of 'engine.toml' in the working directory.
+    #[arg(long, global = true)]
+    config: Option<String>,
+    // Override a key of the configuration, like "hash=1048576" or "search.probcut=false".
+    #[arg(long = "set", global = true, value_name = "KEY=VALUE")]
+    settings: Vec<String>,
+    // Level of the messages logged: off, error, warn, info, debug or trace. It is debug with a log
+    // file, which then records the UCI traffic and the iterations of the searches, and warn
+    // without one.
+    #[arg(long, global = true)]
+    log_level: Option<String>,
+    // File where the messages are logged, instead of the standard error.
+    #[arg(long, global = true)]
+    log_file: Option<String>,
 }
 
 #[derive(Subcommand)]
 enum Commands
 {
-    Run,
+    // Show a position with its static evaluation, and its best move if a depth is given.
+    Run
+    {
+        #[arg(short, long, default_value = START_FEN)]
+        fen: String,
+        #[arg(short, long)]
+        depth: Option<u8>,
+    },
     Explore
     {
         #[arg(short, long)]
         depth: usize,
         #[arg(short, long)]
         verbose: bool,
+        // Position explored, the initial position of the variant by default.
+        #[arg(short, long)]
+        fen: Option<String>,
+        // The variant of the position: standard, kingofthehill, horde, atomic or antichess.
+        #[arg(long)]
+        variant: Option<String>,
+        // Number of entries of a table caching the node counts of the subtrees, to speed up deep
+        // counts. Nothing is cached by default.
+        #[arg(long)]
+        hash: Option<usize>,
     },
     Play
     {
@@ -27,52 +67,944 @@ enum Commands
         wstrat: String,
         #[arg(short, long)]
         bstrat: String,
+        // The variant played: standard, kingofthehill, horde, atomic or antichess.
+        #[arg(long, default_value = "standard")]
+        variant: String,
+        // File where the game is written with the scores of the strategies, in JSON if its name
+        // ends with ".json" and in PGN otherwise.
+        #[arg(long)]
+        record: Option<String>,
+        // Notation of the moves in the PGN: san, lan (e.g. Ng1-f3) or iccf (e.g. 7163).
+        #[arg(long, default_value = "san")]
+        notation: String,
+        // Print the moves instead of the pieces, to play without seeing the board.
+        #[arg(long)]
+        blindfold: bool,
+        #[command(flatten)]
+        game: GameArgs,
+    },
+    Analyze
+    {
+        #[command(flatten)]
+        args: AnalyzeArgs,
+    },
+    // Show the positions of a game in PGN or JSON, and score the moves guessed for them against
+    // the moves of the game.
+    Guess
+    {
+        path: String,
+        // The side whose moves are guessed: white or black. The moves of both sides are guessed
+        // without it.
+        #[arg(long)]
+        side: Option<String>,
+        // Depth of the searches scoring the guesses.
+        #[arg(short, long, default_value_t = 5)]
+        depth: u8,
+        #[arg(long)]
+        blindfold: bool,
+    },
+    // Search every position of a game in PGN or JSON, and flag the inaccuracies, mistakes and
+    // blunders with the best move in a comment.
+    Annotate
+    {
+        path: String,
+        #[arg(short, long, default_value_t = 5)]
+        depth: u8,
+        // Minimum centipawns lost by an inaccuracy, a mistake and a blunder.
+        #[arg(long, default_value_t = LossThresholds::default().inaccuracy)]
+        inaccuracy: i32,
+        #[arg(long, default_value_t = LossThresholds::default().mistake)]
+        mistake: i32,
+        #[arg(long, default_value_t = LossThresholds::default().blunder)]
+        blunder: i32,
+        // File where the annotated game is written, in JSON if its name ends with ".json" and in
+        // PGN otherwise. It is printed in PGN without it.
+        #[arg(short, long)]
+        output: Option<String>,
+        // Notation of the moves in the PGN: san, lan (e.g. Ng1-f3) or iccf (e.g. 7163).
+        #[arg(long, default_value = "san")]
+        notation: String,
+    },
+    // Search every position of a file with one FEN per line, and write the score, the best move
+    // and the principal variation of each one in CSV.
+    Batch
+    {
+        #[arg(short, long)]
+        input: String,
+        // Depth of the searches, or 0 for the static evaluation.
+        #[arg(short, long, default_value_t = 5)]
+        depth: u8,
+        // File where the CSV is written. It is printed without it.
+        #[arg(short, long)]
+        output: Option<String>,
+        // Number of positions searched at the same time, the configured threads by default.
+        #[arg(short, long)]
+        concurrency: Option<usize>,
+    },
+    // Play self-play games and write their positions with their search score and the result of
+    // the game, to train an evaluation.
+    Datagen
+    {
+        #[arg(short, long, default_value_t = 100)]
+        games: usize,
+        #[arg(short, long, default_value_t = 4)]
+        depth: u8,
+        // Number of random moves played at the start of each game.
+        #[arg(long, default_value_t = 8)]
+        random_plies: usize,
+        #[arg(short, long)]
+        output: String,
+        // Format of the positions: csv, or binary with 28 bytes per position.
+        #[arg(short, long, default_value = "csv")]
+        format: String,
+        // Number of games played at the same time, the configured threads by default.
+        #[arg(short, long)]
+        concurrency: Option<usize>,
+    },
+    // Search fixed positions to a fixed depth, and print the speed and the counters of the search.
+    Bench
+    {
+        #[arg(short, long, default_value_t = 5)]
+        depth: u8,
+    },
+    // Prove a forced mate of the side to move, or that there is none within the number of moves.
+    Mate
+    {
+        #[arg(short, long)]
+        fen: String,
+        // Maximum number of moves of the side to move, the mating one included.
+        #[arg(short = 'n', long, default_value_t = 3)]
+        max_moves: u8,
+        // Also try the moves that don't give check, to find the mates starting with a quiet move.
+        #[arg(long)]
+        all_moves: bool,
+    },
+    // Print the distance to mate of a position with a king and a queen, a rook or a pawn against a
+    // lone king, and the line played perfectly from it.
+    Dtm
+    {
+        #[arg(short, long)]
+        fen: String,
+    },
+    Uci,
+    Repl,
+    // Tell where the configuration comes from, or print it in TOML with all its values.
+    Config
+    {
+        #[arg(long)]
+        dump: bool,
+    },
+    // Print the detail of the static evaluation of a position.
+    Eval
+    {
+        #[arg(short, long, default_value = START_FEN)]
+        fen: String,
+    },
+    // Check the consistency of evaluation, make/unmake and hashing on many positions.
+    Selftest
+    {
+        // Number of plies explored from each test position.
+        #[arg(short, long, default_value_t = 2)]
+        depth: usize,
+        // Number of random positions checked along with the test positions.
+        #[arg(short, long, default_value_t = 100)]
+        random: usize,
+        // Skip the perft regression positions, which take a few seconds.
+        #[arg(long)]
+        skip_perft: bool,
     },
+    Render
+    {
+        #[arg(short, long, default_value = START_FEN)]
+        fen: String,
+        // Moves played from the position before drawing it, the last one being highlighted.
+        #[arg(short, long, num_args = 1 ..)]
+        moves: Vec<String>,
+        // File to write, in PNG if its name ends with ".png" and in SVG otherwise.
+        #[arg(short, long)]
+        output: String,
+        // Draw the board from black's side.
+        #[arg(long)]
+        flipped: bool,
+        #[arg(long)]
+        no_coordinates: bool,
+    },
+    Tournament
+    {
+        // The first strategy, whose score is reported.
+        #[arg(long)]
+        first: String,
+        #[arg(long)]
+        second: String,
+        #[arg(short, long, default_value_t = 10)]
+        games: usize,
+        // File with one opening per line, as a FEN or moves from the initial position. The
+        // configured book is used without it.
+        #[arg(short, long)]
+        openings: Option<String>,
+        // File where the games are written in PGN.
+        #[arg(short, long)]
+        pgn: Option<String>,
+        // Stop as soon as a sequential probability ratio test concludes.
+        #[arg(long)]
+        sprt: bool,
+        #[arg(long, default_value_t = 0.0)]
+        elo0: f64,
+        #[arg(long, default_value_t = 10.0)]
+        elo1: f64,
+        #[arg(long, default_value_t = 0.05)]
+        alpha: f64,
+        #[arg(long, default_value_t = 0.05)]
+        beta: f64,
+        // Number of games played at the same time, the configured threads by default.
+        #[arg(short, long)]
+        concurrency: Option<usize>,
+        #[command(flatten)]
+        game: GameArgs,
+    },
+}
+
+// Position and parameters of the 'analyze' command.
+#[derive(Args)]
+struct AnalyzeArgs
+{
+    #[arg(short, long, default_value = START_FEN)]
+    fen: String,
+    #[arg(
+        short,
+        long,
+        default_value_t = 5,
+        value_parser = value_parser!(u8).range(..= i64::from(MAX_DEPTH))
+    )]
+    depth: u8,
+    #[arg(short, long, default_value_t = 1)]
+    multipv: usize,
+    // Number of entries of the evaluation cache, 0 to disable it. It is configured by default.
+    #[arg(long)]
+    eval_cache: Option<usize>,
+    // Search only these root moves, in UCI notation or SAN.
+    #[arg(long, num_args = 1 ..)]
+    only: Vec<String>,
+    // Search the best alternative to a root move, or to the best move if none is given, and
+    // tell how much worse it is.
+    #[arg(long, conflicts_with_all = ["only", "multipv"])]
+    exclude: Option<Option<String>>,
+    // Number of entries of the transposition table, the configured one by default. When it is
+    // loaded from a file, it keeps the size it was saved with unless this is given.
+    #[arg(long)]
+    hash: Option<usize>,
+    // File the transposition table is loaded from if it exists, and saved to after the analysis,
+    // so that the analysis of a position can be resumed later.
+    #[arg(long)]
+    hash_file: Option<String>,
+    // Search until Ctrl-C is pressed, instead of up to the depth.
+    #[arg(long, conflicts_with_all = ["depth", "exclude"])]
+    infinite: bool,
+    // Game to analyze instead of the position, in PGN or JSON: the position after each move is
+    // searched and its score attached to the move.
+    #[arg(long, conflicts_with_all = ["fen", "multipv", "only", "exclude", "infinite"])]
+    game: Option<String>,
+    // File where the analyzed game is written, in JSON if its name ends with ".json" and in PGN
+    // otherwise.
+    #[arg(short, long, requires = "game")]
+    output: Option<String>,
+    // File where the first plies of the search tree of the last complete iteration are written, in
+    // the DOT language of GraphViz if its name ends with ".dot" or ".gv" and in JSON otherwise.
+    #[arg(long, conflicts_with = "game")]
+    tree: Option<String>,
+    // Number of plies of the search tree written to the tree file.
+    #[arg(long, default_value_t = DEFAULT_TREE_PLIES, requires = "tree")]
+    tree_plies: u8,
+}
+
+// Time control and adjudication of the games of 'play' and 'tournament'.
+#[derive(Args)]
+struct GameArgs
+{
+    // Time of each side for the whole game, in seconds. Games are untimed without it.
+    #[arg(short, long)]
+    time: Option<f64>,
+    // Time added after each move, in seconds.
+    #[arg(short, long, default_value_t = 0.0)]
+    increment: f64,
+    // Resign for a side when both strategies scored it at most minus this many centipawns for
+    // 'resign-moves' moves.
+    #[arg(long, allow_negative_numbers = true)]
+    resign_score: Option<i32>,
+    #[arg(long, default_value_t = 3)]
+    resign_moves: usize,
+    // Draw the game when both strategies scored it within this many centipawns of 0 for
+    // 'draw-moves' moves.
+    #[arg(long)]
+    draw_score: Option<i32>,
+    #[arg(long, default_value_t = 10)]
+    draw_moves: usize,
+    // Number of full moves after which the game is drawn.
+    #[arg(long, default_value_t = MAX_FULL_MOVES)]
+    max_moves: usize,
+}
+
+impl GameArgs
+{
+    fn to_options(&self, verbose: bool) -> GameOptions
+    {
+        let time_control = self.time.map(|time| TimeControl {
+            base: Duration::from_secs_f64(time),
+            increment: Duration::from_secs_f64(self.increment),
+        });
+        let adjudication = Adjudication {
+            resign: self
+                .resign_score
+                .map(|score| ScoreRule { score: Score(score), moves: self.resign_moves }),
+            draw: self
+                .draw_score
+                .map(|score| ScoreRule { score: Score(score), moves: self.draw_moves }),
+            max_moves: self.max_moves,
+        };
+        return GameOptions {
+            time_control,
+            adjudication,
+            verbose,
+            display: DisplayOptions::default(),
+        };
+    }
 }
 
 fn main()
 {
     let cli = Cli::parse();
+    let default_level = if cli.log_file.is_some() { "debug" } else { "warn" };
+    let logging = parse_log_level(cli.log_level.as_deref().unwrap_or(default_level))
+        .and_then(|level| init_logging(level, cli.log_file.as_deref()));
+    if let Err(err) = logging
+    {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    }
+    let config_path = match EngineConfig::load_default(cli.config.as_deref(), &cli.settings)
+    {
+        Ok((config, path)) =>
+        {
+            set_engine_config(config);
+            path
+        },
+        Err(err) =>
+        {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        },
+    };
+    if let Some(seed) = cli.seed
+    {
+        set_default_randomness(Randomness::Seeded(seed));
+    }
 
     match &cli.command
     {
-        Commands::Run =>
+        Commands::Run { fen, depth } => match Board::from_fen_strict(fen)
         {
-            match Board::from_fen("rnb2bnr/ppqppppp/8/3kq2R/8/5K2/PPPPPPPP/RNBQ1BN1 w - -")
+            Ok(mut board) =>
             {
-                Ok(board) =>
+                board.display();
+                println!("evaluation: {}", evaluate(&board));
+                if let Some(depth) = depth
                 {
-                    board.display();
-                    println!("evaluation: {}", board.evaluate());
-                },
-                Err(err) => eprint!("{}", err),
-            }
+                    match Search::default().search(&mut board, *depth)
+                    {
+                        (score, Some(mv)) =>
+                        {
+                            println!("best move: {} ({})", mv.to_san(&mut board), score)
+                        },
+                        _ => println!("No legal move to search."),
+                    }
+                }
+            },
+            Err(err) => eprintln!("{}", err),
         },
-        Commands::Explore { depth, verbose } =>
+        Commands::Explore { depth, verbose, fen, variant, hash } =>
         {
-            match Board::from_fen("8/8/8/3q4/8/4Q3/8/4K2k w - -")
+            let board = match variant
+            {
+                Some(name) => Variant::from_name(name).and_then(|variant| {
+                    Board::from_fen_variant(fen.as_deref().unwrap_or(variant.start_fen()), variant)
+                }),
+                None => Board::from_fen(fen.as_deref().unwrap_or(START_FEN)),
+            };
+            match board
             {
                 Ok(mut board) =>
                 {
                     board.display();
+                    println!(
+                        "{} to move, {} legal moves",
+                        if board.white_to_play { "white" } else { "black" },
+                        board.get_legal_moves().len()
+                    );
                     println!(
                         "number of positions at a depth of {}: {}",
                         depth,
-                        launch_explore(&mut board, *depth, *verbose)
+                        launch_explore(
+                            &mut board,
+                            *depth,
+                            *verbose,
+                            &mut PerftTable::new(hash.unwrap_or(0))
+                        )
                     );
                 },
-                Err(err) => eprint!("{}", err),
+                Err(err) => eprintln!("{}", err),
+            }
+        },
+        Commands::Play { wstrat, bstrat, variant, record, notation, blindfold, game } =>
+        {
+            let options = GameOptions {
+                display: DisplayOptions {
+                    blindfold: *blindfold,
+                    coordinates: *blindfold,
+                    ..DisplayOptions::default()
+                },
+                ..game.to_options(true)
+            };
+            let result = Variant::from_name(variant).and_then(|variant| {
+                let notation = Notation::from_name(notation)?;
+                let (outcome, game_record) =
+                    play(wstrat.as_str(), bstrat.as_str(), variant, &options)?;
+                println!("{}", outcome.description());
+                let [white_time, black_time] = game_record.think_times()?;
+                println!(
+                    "Thinking time: white {:.1}s, black {:.1}s.",
+                    white_time.as_secs_f64(),
+                    black_time.as_secs_f64()
+                );
+                if let Some(path) = record
+                {
+                    game_record.save_with(path, notation)?;
+                }
+                return Ok(());
+            });
+            if let Err(err) = result
+            {
+                eprintln!("{}", err);
+            }
+        },
+        Commands::Analyze { args } =>
+        {
+            if let Err(err) = analyze(args)
+            {
+                eprintln!("{}", err);
+            }
+        },
+        Commands::Guess { path, side, depth, blindfold } =>
+        {
+            if let Err(err) = guess(path, side.as_deref(), *depth, *blindfold)
+            {
+                eprintln!("{}", err);
+            }
+        },
+        Commands::Annotate { path, depth, inaccuracy, mistake, blunder, output, notation } =>
+        {
+            let thresholds =
+                LossThresholds { inaccuracy: *inaccuracy, mistake: *mistake, blunder: *blunder };
+            let result = Notation::from_name(notation).and_then(|notation| {
+                return annotate(path, *depth, &thresholds, output.as_deref(), notation);
+            });
+            if let Err(err) = result
+            {
+                eprintln!("{}", err);
             }
         },
-        Commands::Play { wstrat, bstrat } => match play(*&wstrat.as_str(), *&bstrat.as_str())
+        Commands::Batch { input, depth, output, concurrency } =>
         {
-            Ok(result) => match result
+            let options = BatchOptions {
+                depth: *depth,
+                concurrency: concurrency.unwrap_or(engine_config().threads),
+            };
+            if let Err(err) = batch(input, output.as_deref(), &options)
             {
-                GameResult::White => println!("White wins by checkmate."),
-                GameResult::Black => println!("Black wins by checkmate."),
-                GameResult::Stalemate => println!("The game ends in a draw by checkmate."),
+                eprintln!("{}", err);
+            }
+        },
+        Commands::Datagen { games, depth, random_plies, output, format, concurrency } =>
+        {
+            let result = DataFormat::from_name(format).and_then(|format| {
+                let options = DatagenOptions {
+                    games: *games,
+                    depth: *depth,
+                    random_plies: *random_plies,
+                    concurrency: concurrency.unwrap_or(engine_config().threads),
+                    format,
+                };
+                return generate_data(output, &options);
+            });
+            match result
+            {
+                Ok(positions) => println!("{} positions written to '{}'.", positions, output),
+                Err(err) => eprintln!("{}", err),
+            }
+        },
+        Commands::Bench { depth } =>
+        {
+            let report = run_bench(*depth, |fen, info| {
+                println!("{:>10} nodes  {}", info.nodes, fen);
+            });
+            print!("\n{}", report.to_table());
+        },
+        Commands::Mate { fen, max_moves, all_moves } =>
+        {
+            if let Err(err) = mate(fen, *max_moves, *all_moves)
+            {
+                eprintln!("{}", err);
+            }
+        },
+        Commands::Dtm { fen } =>
+        {
+            if let Err(err) = print_dtm(fen)
+            {
+                eprintln!("{}", err);
+            }
+        },
+        Commands::Uci => uci_loop(),
+        Commands::Repl => repl(),
+        Commands::Config { dump } =>
+        {
+            if *dump
+            {
+                match engine_config().to_toml()
+                {
+                    Ok(text) => print!("{}", text),
+                    Err(err) => eprintln!("{}", err),
+                }
+            }
+            else
+            {
+                match config_path
+                {
+                    Some(path) => println!("Configuration read from '{}'.", path),
+                    None => println!(
+                        "No configuration file, '{}' doesn't exist: the defaults are used.",
+                        DEFAULT_CONFIG_PATH
+                    ),
+                }
+                if !cli.settings.is_empty()
+                {
+                    println!("Overridden: {}", cli.settings.join(", "));
+                }
+                println!("Run 'barnarok config --dump' to print the whole configuration.");
+            }
+        },
+        Commands::Eval { fen } => match Board::from_fen_strict(fen)
+        {
+            Ok(board) =>
+            {
+                let params = engine_config().eval;
+                board.display();
+                print!("{}", evaluate_trace_with(&board, &params).to_table());
+                println!("evaluation (side to move): {}", evaluate_with(&board, &params));
             },
             Err(err) => eprintln!("{}", err),
         },
+        Commands::Selftest { depth, random, skip_perft } =>
+        {
+            let report = run_selftest(*depth, *random, !skip_perft);
+            println!("{} positions checked, {} failures.", report.positions, report.failures);
+            if report.failures > 0
+            {
+                std::process::exit(1);
+            }
+        },
+        Commands::Render { fen, moves, output, flipped, no_coordinates } =>
+        {
+            let options = DisplayOptions {
+                coordinates: !no_coordinates,
+                flipped: *flipped,
+                ..DisplayOptions::default()
+            };
+            if let Err(err) = render(fen, moves, output, options)
+            {
+                eprintln!("{}", err);
+            }
+        },
+        Commands::Tournament {
+            first,
+            second,
+            games,
+            openings,
+            pgn,
+            sprt,
+            elo0,
+            elo1,
+            alpha,
+            beta,
+            concurrency,
+            game,
+        } =>
+        {
+            let sprt = sprt.then_some(SprtOptions {
+                elo0: *elo0,
+                elo1: *elo1,
+                alpha: *alpha,
+                beta: *beta,
+            });
+            let openings = match openings.clone().or(engine_config().book)
+            {
+                Some(path) => read_openings(&path),
+                None => Ok(vec![]),
+            };
+            let result = openings.and_then(|openings| {
+                let options = TournamentOptions {
+                    games: *games,
+                    openings,
+                    pgn_path: pgn.clone(),
+                    sprt,
+                    concurrency: concurrency.unwrap_or(engine_config().threads),
+                    game_options: game.to_options(false),
+                };
+                return run_tournament(first, second, &options);
+            });
+            if let Err(err) = result
+            {
+                eprintln!("{}", err);
+            }
+        },
+    }
+}
+
+// Analyze a position, or each position of a game, loading and saving the transposition table if a
+// file is given.
+fn analyze(args: &AnalyzeArgs) -> Result<(), String>
+{
+    if let Some(path) = &args.game
+    {
+        return analyze_game(args, path);
+    }
+    let mut board = Board::from_fen_strict(&args.fen)?;
+    let mut search_moves = vec![];
+    for text in args.only.iter()
+    {
+        search_moves.push(parse_move(&mut board, text).ok_or(format!("Illegal move '{}'.", text))?);
+    }
+    let excluded = match &args.exclude
+    {
+        Some(Some(text)) =>
+        {
+            Some(parse_move(&mut board, text).ok_or(format!("Illegal move '{}'.", text))?)
+        },
+        _ => None,
+    };
+
+    let tt = match &args.hash_file
+    {
+        Some(path) if std::path::Path::new(path).exists() =>
+        {
+            let tt = TranspositionTable::load(path, args.hash)?;
+            println!("Loaded {} entries from '{}'.", tt.used(), path);
+            tt
+        },
+        _ => TranspositionTable::new(args.hash.unwrap_or(engine_config().table_sizes().tt_entries)),
+    };
+
+    board.display();
+    let mut options = SearchOptions::default();
+    if let Some(entries) = args.eval_cache
+    {
+        options.eval_cache_entries = entries;
+    }
+    let mut search = Search::with_tt(options, Arc::new(tt));
+    if args.tree.is_some()
+    {
+        search.tree_plies = args.tree_plies;
+    }
+    // Ctrl-C stops the search, and the result of the last complete iteration is printed.
+    stop_on_ctrl_c(search.stop_flag());
+    if args.exclude.is_some()
+    {
+        print_alternative(&mut board, args.depth, excluded, &mut search);
+    }
+    else if args.infinite
+    {
+        let lines;
+        (lines, search) = print_infinite_analysis(&board, args.multipv, &search_moves, search);
+        print_best_line(&mut board, &lines);
+    }
+    else
+    {
+        let lines =
+            print_analysis(&mut board, args.depth, args.multipv, &search_moves, &mut search);
+        print_best_line(&mut board, &lines);
+    }
+
+    if let (Some(path), Some(tree)) = (&args.tree, &search.tree)
+    {
+        let text = if path.ends_with(".dot") || path.ends_with(".gv")
+        {
+            tree.to_dot()
+        }
+        else
+        {
+            tree.to_json()?
+        };
+        std::fs::write(path, text).map_err(|err| format!("Can't write '{}': {}", path, err))?;
+        println!("Wrote {} nodes of the search tree to '{}'.", tree.node_count(), path);
+    }
+    if let Some(path) = &args.hash_file
+    {
+        search.tt.save(path)?;
+        println!("Saved {} entries to '{}'.", search.tt.used(), path);
+    }
+    return Ok(());
+}
+
+// Attach the score of the search to each move of a game, and print them in SAN.
+fn analyze_game(args: &AnalyzeArgs, path: &str) -> Result<(), String>
+{
+    let mut record = GameRecord::load(path)?;
+    let tt = TranspositionTable::new(args.hash.unwrap_or(engine_config().table_sizes().tt_entries));
+    let mut options = SearchOptions::default();
+    if let Some(entries) = args.eval_cache
+    {
+        options.eval_cache_entries = entries;
+    }
+    attach_evals(&mut record, args.depth, &mut Search::with_tt(options, Arc::new(tt)))?;
+
+    let moves: Vec<Move> = record.moves.iter().map(|recorded| recorded.mv).collect();
+    let numbered = number_moves(&record.start_board()?, &moves, Notation::San);
+    for (numbered, recorded) in numbered.iter().zip(record.moves.iter())
+    {
+        let name = format!("{} {}", numbered.number_text(), numbered.text);
+        let eval = recorded.annotation.eval.map(format_eval).unwrap_or("-".into());
+        println!("{:<16}{:>8}", name, eval);
+    }
+    if let Some(output) = &args.output
+    {
+        record.save(output)?;
+        println!("Saved the game to '{}'.", output);
+    }
+    return Ok(());
+}
+
+// Show the positions of a game and score the moves guessed in them.
+fn guess(path: &str, side: Option<&str>, depth: u8, blindfold: bool) -> Result<(), String>
+{
+    let side = match side
+    {
+        None => None,
+        Some("white") => Some(WHITE),
+        Some("black") => Some(BLACK),
+        Some(side) => return Err(format!("Unknown side '{}', expected white or black.", side)),
+    };
+    let record = GameRecord::load(path)?;
+    let options = GuessOptions {
+        side,
+        depth,
+        display: DisplayOptions { blindfold, coordinates: blindfold, ..DisplayOptions::default() },
+    };
+    let (points, max_points) = guess_the_move(&record, &options)?;
+    println!("You scored {} of {} points.", points, max_points);
+    return Ok(());
+}
+
+// Annotate a game and print its flagged moves and the report of each player, then write it to a
+// file or print it in PGN.
+fn annotate(
+    path: &str,
+    depth: u8,
+    thresholds: &LossThresholds,
+    output: Option<&str>,
+    notation: Notation,
+) -> Result<(), String>
+{
+    let mut record = GameRecord::load(path)?;
+    let reviews = annotate_game(&mut record, depth, thresholds, &mut Search::default())?;
+
+    let moves: Vec<Move> = record.moves.iter().map(|recorded| recorded.mv).collect();
+    let numbered = number_moves(&record.start_board()?, &moves, Notation::San);
+    for (numbered, review) in numbered.iter().zip(reviews.iter())
+    {
+        if let Some(class) = review.class
+        {
+            println!(
+                "{} {}: {}, loses {:.2}",
+                numbered.number_text(),
+                numbered.text,
+                class.name().to_lowercase(),
+                review.loss as f64 / 100.0
+            );
+        }
+    }
+
+    println!(
+        "\n{:<24} {:>5} {:>12} {:>8} {:>8} {:>9} {:>8}",
+        "player", "moves", "inaccuracies", "mistakes", "blunders", "avg. loss", "accuracy"
+    );
+    for (white, side) in [(true, "White"), (false, "Black")]
+    {
+        let report = PlayerReport::from_reviews(&reviews, white);
+        let name = record.tags.get(side).map(String::as_str).unwrap_or("?");
+        println!(
+            "{:<24} {:>5} {:>12} {:>8} {:>8} {:>9.1} {:>7.1}%",
+            format!("{} ({})", side, name),
+            report.moves,
+            report.inaccuracies,
+            report.mistakes,
+            report.blunders,
+            report.average_loss,
+            report.accuracy
+        );
+    }
+    println!();
+
+    match output
+    {
+        Some(output) =>
+        {
+            record.save_with(output, notation)?;
+            println!("Saved the annotated game to '{}'.", output);
+        },
+        None => print!("{}", record.to_pgn_with(notation)?),
+    }
+    return Ok(());
+}
+
+// Process the positions of a file, reporting the invalid ones, and write the results in CSV.
+fn batch(input: &str, output: Option<&str>, options: &BatchOptions) -> Result<(), String>
+{
+    let fens = read_fens(input)?;
+    let start = Instant::now();
+    let results = run_batch(&fens, options);
+    for (fen, result) in fens.iter().zip(results.iter())
+    {
+        if let Err(err) = result
+        {
+            eprintln!("Skipping '{}': {}", fen, err);
+        }
+    }
+
+    let csv = batch_to_csv(&fens, &results);
+    match output
+    {
+        Some(output) =>
+        {
+            std::fs::write(output, csv)
+                .map_err(|err| format!("Can't write '{}': {}", output, err))?;
+            println!(
+                "{} positions processed in {:.2}s, written to '{}'.",
+                fens.len(),
+                start.elapsed().as_secs_f64(),
+                output
+            );
+        },
+        None => print!("{}", csv),
+    }
+    return Ok(());
+}
+
+// Search a forced mate and print its line in SAN.
+fn mate(fen: &str, max_moves: u8, all_moves: bool) -> Result<(), String>
+{
+    let mut board = Board::from_fen_strict(fen)?;
+    board.display();
+    let start = Instant::now();
+    let mut search = MateSearch::new(all_moves);
+    match search.find(&mut board, max_moves)
+    {
+        Some(line) =>
+        {
+            println!("Mate in {}: {}", line.len().div_ceil(2), moves_to_san(&mut board, &line))
+        },
+        None if all_moves => println!("No mate within {} moves.", max_moves),
+        None => println!("No mate by checks within {} moves.", max_moves),
+    }
+    println!("{} nodes in {:.2}s", search.nodes, start.elapsed().as_secs_f64());
+    return Ok(());
+}
+
+// Print the distance to mate of a position from the tables, and the best line until the mate.
+fn print_dtm(fen: &str) -> Result<(), String>
+{
+    let mut board = Board::from_fen_strict(fen)?;
+    board.display();
+    if !has_dtm_table(&board)
+    {
+        return Err(
+            "There is no table for this position. The tables cover a king with a queen, a \
+                    rook or a pawn against a lone king."
+                .into(),
+        );
+    }
+    match dtm(&board)
+    {
+        None => println!("Draw."),
+        Some(0) => println!("The side to move is checkmated."),
+        Some(plies) if plies > 0 => println!("The side to move mates in {} plies.", plies),
+        Some(plies) => println!("The side to move is mated in {} plies.", -plies),
+    }
+    if dtm(&board).is_some_and(|plies| plies != 0)
+    {
+        let mut line = vec![];
+        while let Some(mv) = dtm_best_move(&mut board)
+        {
+            board.make_move(mv);
+            line.push(mv);
+        }
+        for mv in line.iter().rev()
+        {
+            board.unmake_move(*mv);
+        }
+        println!("{}", moves_to_san(&mut board, &line));
+    }
+    return Ok(());
+}
+
+// Set the flag when Ctrl-C is pressed, instead of ending the process. Pressing it again ends the
+// process, in case the flag is not checked.
+#[cfg_attr(target_arch = "wasm32", allow(unused_variables))]
+fn stop_on_ctrl_c(stop: Arc<AtomicBool>)
+{
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        use std::sync::atomic::Ordering;
+
+        let result = ctrlc::set_handler(move || {
+            if stop.swap(true, Ordering::Relaxed)
+            {
+                std::process::exit(130);
+            }
+        });
+        if let Err(err) = result
+        {
+            eprintln!("Can't handle Ctrl-C: {}", err);
+        }
     }
 }
+
+// Draw a position, after playing the given moves, to an SVG or PNG file.
+fn render(fen: &str, moves: &[String], output: &str, options: DisplayOptions)
+-> Result<(), String>
+{
+    let mut board = Board::from_fen(fen)?;
+    let mut last_move = None;
+    for name in moves.iter()
+    {
+        let mv = parse_move(&mut board, name).ok_or(format!("Illegal move '{}'.", name))?;
+        board.make_move(mv);
+        last_move = Some(mv);
+    }
+    let svg = board.to_svg(&DisplayOptions { last_move, ..options });
+
+    let data = if output.ends_with(".png")
+    {
+        #[cfg(feature = "png")]
+        {
+            svg_to_png(&svg)?
+        }
+        #[cfg(not(feature = "png"))]
+        {
+            return Err("PNG output needs barnarok to be built with the 'png' feature.".into());
+        }
+    }
+    else
+    {
+        svg.into_bytes()
+    };
+    return std::fs::write(output, data)
+        .map_err(|err| format!("Can't write '{}': {}", output, err));
+}
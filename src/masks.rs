@@ -5,10 +5,80 @@ const RANK_MASKS: [Bitboard; 64] = make_rank_masks();
 const FILE_MASKS: [Bitboard; 64] = make_file_masks();
 const ANTIDIAGONAL_MASKS: [Bitboard; 64] = make_antidiagonal_masks();
 const DIAGONAL_MASKS: [Bitboard; 64] = make_diagonal_masks();
-const KNIGHT_MASKS: [Bitboard; 64] = make_knight_masks();
+const KNIGHT_MASKS: [Bitboard; 64] = make_step_masks(&KNIGHT_STEPS);
 const KING_MASKS: [Bitboard; 64] = make_king_masks();
-const WHITE_KING_PAWN_MASKS: [Bitboard; 64] = make_white_king_pawn_masks();
-const BLACK_KING_PAWN_MASKS: [Bitboard; 64] = make_black_king_pawn_masks();
+// The squares from which a pawn of the other color attacks a king of each color.
+const WHITE_KING_PAWN_MASKS: [Bitboard; 64] = make_step_masks(&WHITE_PAWN_CAPTURE_STEPS);
+const BLACK_KING_PAWN_MASKS: [Bitboard; 64] = make_step_masks(&BLACK_PAWN_CAPTURE_STEPS);
+const ADJACENT_FILE_MASKS: [Bitboard; 64] = make_adjacent_file_masks();
+const WHITE_FRONT_SPANS: [Bitboard; 64] = make_front_spans(true);
+const BLACK_FRONT_SPANS: [Bitboard; 64] = make_front_spans(false);
+const WHITE_PASSED_PAWN_MASKS: [Bitboard; 64] = make_passed_pawn_masks(true);
+const BLACK_PASSED_PAWN_MASKS: [Bitboard; 64] = make_passed_pawn_masks(false);
+const RAY_MASKS: [[Bitboard; 64]; 8] = make_ray_masks();
+// The tables of pairs of squares are statics, so that they are not copied into each function using
+// them.
+static LINE_MASKS: [[Bitboard; 64]; 64] = make_line_masks();
+static BETWEEN_MASKS: [[Bitboard; 64]; 64] = make_between_masks();
+
+// The eight directions a ray can go to from a square, north being towards rank 8 and east towards
+// the h file.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Direction
+{
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction
+{
+    pub const ALL: [Direction; 8] = [
+        Direction::North,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+        Direction::South,
+        Direction::SouthWest,
+        Direction::West,
+        Direction::NorthWest,
+    ];
+
+    // Get the change of file and of rank of a step in this direction.
+    pub const fn steps(self) -> (isize, isize)
+    {
+        return match self
+        {
+            Direction::North => (0, 1),
+            Direction::NorthEast => (1, 1),
+            Direction::East => (1, 0),
+            Direction::SouthEast => (1, -1),
+            Direction::South => (0, -1),
+            Direction::SouthWest => (-1, -1),
+            Direction::West => (-1, 0),
+            Direction::NorthWest => (-1, 1),
+        };
+    }
+
+    pub const fn opposite(self) -> Direction
+    {
+        return Direction::ALL[(self as usize + 4) % 8];
+    }
+
+    // Tell whether a rook moves in this direction, or else a bishop.
+    pub const fn is_straight(self) -> bool
+    {
+        return matches!(
+            self,
+            Direction::North | Direction::East | Direction::South | Direction::West
+        );
+    }
+}
 
 // Getters for precomputed masks:
 
@@ -60,6 +130,52 @@ pub fn black_king_pawn_mask(sq: usize) -> Bitboard
     return BLACK_KING_PAWN_MASKS[sq];
 }
 
+// Get the files next to the file of a square, on every rank.
+#[inline(always)]
+pub fn adjacent_files_mask(sq: usize) -> Bitboard
+{
+    return ADJACENT_FILE_MASKS[sq];
+}
+
+// Get the squares in front of a square on its file, from the side of the given color.
+#[inline(always)]
+pub fn front_span(white: bool, sq: usize) -> Bitboard
+{
+    return if white { WHITE_FRONT_SPANS[sq] } else { BLACK_FRONT_SPANS[sq] };
+}
+
+// Get the squares in front of a pawn on its file and on the adjacent files. The pawn is passed when
+// there is no enemy pawn on them.
+#[inline(always)]
+pub fn passed_pawn_mask(white: bool, sq: usize) -> Bitboard
+{
+    return if white { WHITE_PASSED_PAWN_MASKS[sq] } else { BLACK_PASSED_PAWN_MASKS[sq] };
+}
+
+// Get the squares going from a square to the edge of the board in a direction, the square
+// excluded. The squares behind the first blocker 'b' of the ray are 'ray(b, direction)'.
+#[inline(always)]
+pub fn ray(sq: usize, direction: Direction) -> Bitboard
+{
+    return RAY_MASKS[direction as usize][sq];
+}
+
+// Get the whole rank, file or diagonal going through two squares, both included, or an empty mask
+// when they are the same square or are not aligned.
+#[inline(always)]
+pub fn line(a: usize, b: usize) -> Bitboard
+{
+    return LINE_MASKS[a][b];
+}
+
+// Get the squares strictly between two squares on the same rank, file or diagonal, or an empty
+// mask when they are not aligned.
+#[inline(always)]
+pub fn between(a: usize, b: usize) -> Bitboard
+{
+    return BETWEEN_MASKS[a][b];
+}
+
 const fn make_rank_masks() -> [Bitboard; 64]
 {
     // Create a mask representing all squares on the same rank as 'sq'.
@@ -196,70 +312,39 @@ const fn make_diagonal_masks() -> [Bitboard; 64]
     return masks;
 }
 
-const fn make_knight_masks() -> [Bitboard; 64]
+// The changes of file and of rank of the moves of a knight, and of the captures of the pawns.
+const KNIGHT_STEPS: [(isize, isize); 8] =
+    [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+const WHITE_PAWN_CAPTURE_STEPS: [(isize, isize); 2] = [(-1, 1), (1, 1)];
+const BLACK_PAWN_CAPTURE_STEPS: [(isize, isize); 2] = [(-1, -1), (1, -1)];
+
+// Create a mask representing the squares reached from 'sq' by each of the steps, those leaving the
+// board excluded.
+const fn make_step_mask(sq: usize, steps: &[(isize, isize)]) -> Bitboard
 {
-    // Create a mask representing all squares that a knight could go to, starting from 'sq'.
-    const fn create_mask(sq: usize) -> Bitboard
+    let mut m = 0u64;
+    let mut i = 0;
+    while i < steps.len()
     {
-        let file = (sq % 8) as isize; // File of sq.
-        let rank = (sq / 8) as isize; // Rank of sq.
-
-        let mut m = 0u64;
-
-        if file > 1
+        let f = (sq % 8) as isize + steps[i].0;
+        let r = (sq / 8) as isize + steps[i].1;
+        if f >= 0 && f < 8 && r >= 0 && r < 8
         {
-            if rank > 0
-            {
-                m |= 1u64 << (sq - 10);
-            }
-            if rank < 7
-            {
-                m |= 1u64 << (sq + 6);
-            }
+            m |= 1u64 << (r * 8 + f);
         }
-        if file > 0
-        {
-            if rank > 1
-            {
-                m |= 1u64 << (sq - 17);
-            }
-            if rank < 6
-            {
-                m |= 1u64 << (sq + 15);
-            }
-        }
-        if file < 6
-        {
-            if rank < 7
-            {
-                m |= 1u64 << (sq + 10);
-            }
-            if rank > 0
-            {
-                m |= 1u64 << (sq - 6);
-            }
-        }
-        if file < 7
-        {
-            if rank < 6
-            {
-                m |= 1u64 << (sq + 17);
-            }
-            if rank > 1
-            {
-                m |= 1u64 << (sq - 15);
-            }
-        }
-
-        return m;
+        i += 1;
     }
+    return m;
+}
 
-    // Use the helper to fill the array with masks.
+// Fill an array with the mask of the steps from each square.
+const fn make_step_masks(steps: &[(isize, isize)]) -> [Bitboard; 64]
+{
     let mut masks = [0u64; 64];
     let mut i = 0;
     while i < 64
     {
-        masks[i] = create_mask(i);
+        masks[i] = make_step_mask(i, steps);
         i += 1;
     }
     return masks;
@@ -267,47 +352,32 @@ const fn make_knight_masks() -> [Bitboard; 64]
 
 const fn make_king_masks() -> [Bitboard; 64]
 {
-    // Create a mask representing all squares that a king could go to, starting from 'sq'.
-    const fn create_mask(sq: usize) -> Bitboard
+    // A king goes one step in any direction.
+    let mut steps = [(0, 0); 8];
+    let mut d = 0;
+    while d < 8
     {
-        let file = (sq % 8) as isize; // File of sq.
-        let rank = (sq / 8) as isize; // Rank of sq.
+        steps[d] = Direction::ALL[d].steps();
+        d += 1;
+    }
+    return make_step_masks(&steps);
+}
 
+const fn make_adjacent_file_masks() -> [Bitboard; 64]
+{
+    // Create a mask representing the files on both sides of the file of 'sq'.
+    const fn create_mask(sq: usize) -> Bitboard
+    {
+        let file = sq % 8;
         let mut m = 0u64;
-
         if file > 0
         {
-            m |= 1u64 << (sq - 1);
-            if rank > 0
-            {
-                m |= 1u64 << (sq - 9);
-            }
-            if rank < 7
-            {
-                m |= 1u64 << (sq + 7);
-            }
+            m |= FILE_MASKS[sq - 1];
         }
         if file < 7
         {
-            m |= 1u64 << (sq + 1);
-            if rank > 0
-            {
-                m |= 1u64 << (sq - 7);
-            }
-            if rank < 7
-            {
-                m |= 1u64 << (sq + 9);
-            }
-        }
-        if rank > 0
-        {
-            m |= 1u64 << (sq - 8);
+            m |= FILE_MASKS[sq + 1];
         }
-        if rank < 7
-        {
-            m |= 1u64 << (sq + 8);
-        }
-
         return m;
     }
 
@@ -322,74 +392,288 @@ const fn make_king_masks() -> [Bitboard; 64]
     return masks;
 }
 
-const fn make_white_king_pawn_masks() -> [Bitboard; 64]
+// Create a mask representing the ranks in front of the rank of 'sq', towards rank 8 for white and
+// rank 1 for black.
+const fn ranks_in_front(white: bool, sq: usize) -> Bitboard
 {
-    // Create a mask representing all squares that white king could be attacked from.
-    const fn create_mask(sq: usize) -> Bitboard
+    let rank = sq / 8;
+    if white
     {
-        let file = (sq % 8) as isize; // File of sq.
-        let rank = (sq / 8) as isize; // Rank of sq.
+        return if rank == 7 { 0 } else { !0u64 << ((rank + 1) * 8) };
+    }
+    return if rank == 0 { 0 } else { !0u64 >> ((8 - rank) * 8) };
+}
+
+const fn make_front_spans(white: bool) -> [Bitboard; 64]
+{
+    // Fill the array with the squares of the files in front of each square.
+    let mut masks = [0u64; 64];
+    let mut i = 0;
+    while i < 64
+    {
+        masks[i] = FILE_MASKS[i] & ranks_in_front(white, i);
+        i += 1;
+    }
+    return masks;
+}
+
+const fn make_passed_pawn_masks(white: bool) -> [Bitboard; 64]
+{
+    // Fill the array with the squares of the files in front of each square.
+    let mut masks = [0u64; 64];
+    let mut i = 0;
+    while i < 64
+    {
+        masks[i] = (FILE_MASKS[i] | ADJACENT_FILE_MASKS[i]) & ranks_in_front(white, i);
+        i += 1;
+    }
+    return masks;
+}
+
+const fn make_ray_masks() -> [[Bitboard; 64]; 8]
+{
+    // Create a mask representing the squares from 'sq' to the edge of the board in 'direction'.
+    const fn create_mask(sq: usize, direction: Direction) -> Bitboard
+    {
+        let (file_step, rank_step) = direction.steps();
+        let mut f = (sq % 8) as isize + file_step;
+        let mut r = (sq / 8) as isize + rank_step;
 
         let mut m = 0u64;
+        while f >= 0 && f < 8 && r >= 0 && r < 8
+        {
+            m |= 1u64 << (r * 8 + f);
+            f += file_step;
+            r += rank_step;
+        }
+        return m;
+    }
 
-        if rank < 7
+    // Use the helper to fill the array with masks.
+    let mut masks = [[0u64; 64]; 8];
+    let mut d = 0;
+    while d < 8
+    {
+        let mut i = 0;
+        while i < 64
         {
-            if file > 0
-            {
-                m |= 1u64 << (sq + 7);
-            }
-            if file < 7
-            {
-                m |= 1u64 << (sq + 9);
-            }
+            masks[d][i] = create_mask(i, Direction::ALL[d]);
+            i += 1;
+        }
+        d += 1;
+    }
+    return masks;
+}
+
+// Create a mask representing the rank, file or diagonal shared by 'a' and 'b', if any.
+const fn create_line_mask(a: usize, b: usize) -> Bitboard
+{
+    let bit = 1u64 << b;
+    if a == b
+    {
+        return 0;
+    }
+    if RANK_MASKS[a] & bit != 0
+    {
+        return RANK_MASKS[a];
+    }
+    if FILE_MASKS[a] & bit != 0
+    {
+        return FILE_MASKS[a];
+    }
+    if DIAGONAL_MASKS[a] & bit != 0
+    {
+        return DIAGONAL_MASKS[a];
+    }
+    if ANTIDIAGONAL_MASKS[a] & bit != 0
+    {
+        return ANTIDIAGONAL_MASKS[a];
+    }
+    return 0;
+}
+
+const fn make_line_masks() -> [[Bitboard; 64]; 64]
+{
+    let mut masks = [[0u64; 64]; 64];
+    let mut a = 0;
+    while a < 64
+    {
+        let mut b = 0;
+        while b < 64
+        {
+            masks[a][b] = create_line_mask(a, b);
+            b += 1;
+        }
+        a += 1;
+    }
+    return masks;
+}
+
+const fn make_between_masks() -> [[Bitboard; 64]; 64]
+{
+    // Create a mask representing the squares strictly between 'a' and 'b' if they are aligned.
+    const fn create_mask(a: usize, b: usize) -> Bitboard
+    {
+        if create_line_mask(a, b) == 0
+        {
+            return 0;
         }
+        let file_step = ((b % 8) as isize - (a % 8) as isize).signum();
+        let rank_step = ((b / 8) as isize - (a / 8) as isize).signum();
+        let step = rank_step * 8 + file_step;
 
+        // Walk from 'a' towards 'b', without reaching it.
+        let mut m = 0u64;
+        let mut sq = a as isize + step;
+        while sq != b as isize
+        {
+            m |= 1u64 << sq;
+            sq += step;
+        }
         return m;
     }
 
     // Use the helper to fill the array with masks.
-    let mut masks = [0u64; 64];
-    let mut i = 0;
-    while i < 64
+    let mut masks = [[0u64; 64]; 64];
+    let mut a = 0;
+    while a < 64
     {
-        masks[i] = create_mask(i);
-        i += 1;
+        let mut b = 0;
+        while b < 64
+        {
+            masks[a][b] = create_mask(a, b);
+            b += 1;
+        }
+        a += 1;
     }
     return masks;
 }
 
-const fn make_black_king_pawn_masks() -> [Bitboard; 64]
+#[cfg(test)]
+mod tests
 {
-    // Create a mask representing all squares that black king could be attacked from.
-    const fn create_mask(sq: usize) -> Bitboard
+    use super::*;
+    use crate::{bishop_attacks_hq, queen_attacks_hq, rook_attacks_hq};
+
+    // Get the square at a file and a rank, if they are on the board.
+    fn square(file: isize, rank: isize) -> Option<usize>
     {
-        let file = (sq % 8) as isize; // File of sq.
-        let rank = (sq / 8) as isize; // Rank of sq.
+        return ((0 .. 8).contains(&file) && (0 .. 8).contains(&rank))
+            .then(|| (rank * 8 + file) as usize);
+    }
 
-        let mut m = 0u64;
+    // Get the squares reached from 'sq' by each of the steps of file and rank, the slow way.
+    fn reference_steps(sq: usize, steps: &[(isize, isize)]) -> Bitboard
+    {
+        let (file, rank) = ((sq % 8) as isize, (sq / 8) as isize);
+        return steps
+            .iter()
+            .filter_map(|(df, dr)| square(file + df, rank + dr))
+            .fold(0, |mask, target| mask | 1u64 << target);
+    }
 
-        if rank > 0
+    // Get the squares reached from 'sq' by sliding along each direction, up to the first occupied
+    // square included, the slow way.
+    fn reference_slides(sq: usize, occupancy: Bitboard, directions: &[(isize, isize)]) -> Bitboard
+    {
+        let mut mask = 0;
+        for (df, dr) in directions
         {
-            if file > 0
+            let (mut file, mut rank) = ((sq % 8) as isize + df, (sq / 8) as isize + dr);
+            while let Some(target) = square(file, rank)
             {
-                m |= 1u64 << (sq - 9);
+                mask |= 1u64 << target;
+                if occupancy & 1u64 << target != 0
+                {
+                    break;
+                }
+                file += df;
+                rank += dr;
             }
-            if file < 7
+        }
+        return mask;
+    }
+
+    // Random occupancies from a xorshift generator, with a few pieces on average.
+    fn occupancies() -> impl Iterator<Item = Bitboard>
+    {
+        let mut state = 0x2545_F491_4F6C_DD1Du64;
+        return (0 .. 200).map(move |_| {
+            let mut next = || {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                return state;
+            };
+            return next() & next() & next();
+        });
+    }
+
+    const STRAIGHT: [(isize, isize); 4] = [(0, 1), (1, 0), (0, -1), (-1, 0)];
+    const DIAGONAL: [(isize, isize); 4] = [(1, 1), (1, -1), (-1, -1), (-1, 1)];
+
+    #[test]
+    fn leaper_masks_match_reference()
+    {
+        let knight = [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+        let king = [(0, 1), (1, 1), (1, 0), (1, -1), (0, -1), (-1, -1), (-1, 0), (-1, 1)];
+        for sq in 0 .. 64
+        {
+            assert_eq!(knight_mask(sq), reference_steps(sq, &knight), "knight on {}", sq);
+            assert_eq!(king_mask(sq), reference_steps(sq, &king), "king on {}", sq);
+            // The pawns attacking a white king stand in front of it, those attacking a black king
+            // behind it.
+            assert_eq!(white_king_pawn_mask(sq), reference_steps(sq, &[(-1, 1), (1, 1)]));
+            assert_eq!(black_king_pawn_mask(sq), reference_steps(sq, &[(-1, -1), (1, -1)]));
+        }
+    }
+
+    #[test]
+    fn edge_squares_have_all_their_leaps()
+    {
+        // a1, a8, h1 and h8 in the corners, b1 and a2 next to them.
+        assert_eq!(knight_mask(0), 1u64 << 10 | 1u64 << 17);
+        assert_eq!(king_mask(0), 1u64 << 1 | 1u64 << 8 | 1u64 << 9);
+        assert_eq!(king_mask(63), 1u64 << 62 | 1u64 << 55 | 1u64 << 54);
+        assert_eq!(knight_mask(1).count_ones(), 3);
+        assert_eq!(knight_mask(8).count_ones(), 3);
+        assert_eq!(king_mask(8).count_ones(), 5);
+        assert_eq!(king_mask(7).count_ones(), 3);
+        assert_eq!(king_mask(56).count_ones(), 3);
+    }
+
+    #[test]
+    fn slider_attacks_match_reference()
+    {
+        for occupancy in occupancies().chain([0, !0])
+        {
+            for sq in 0 .. 64
             {
-                m |= 1u64 << (sq - 7);
+                let rook = reference_slides(sq, occupancy, &STRAIGHT);
+                let bishop = reference_slides(sq, occupancy, &DIAGONAL);
+                assert_eq!(
+                    rook_attacks_hq(sq, occupancy),
+                    rook,
+                    "rook on {} ({:#x})",
+                    sq,
+                    occupancy
+                );
+                assert_eq!(bishop_attacks_hq(sq, occupancy), bishop, "bishop on {}", sq);
+                assert_eq!(queen_attacks_hq(sq, occupancy), rook | bishop, "queen on {}", sq);
             }
         }
-
-        return m;
     }
 
-    // Use the helper to fill the array with masks.
-    let mut masks = [0u64; 64];
-    let mut i = 0;
-    while i < 64
+    #[test]
+    fn rays_match_reference()
     {
-        masks[i] = create_mask(i);
-        i += 1;
+        for sq in 0 .. 64
+        {
+            for direction in Direction::ALL
+            {
+                let steps = [direction.steps()];
+                assert_eq!(ray(sq, direction), reference_slides(sq, 0, &steps), "{:?}", direction);
+            }
+        }
     }
-    return masks;
 }
@@ -0,0 +1,110 @@
+use alloc::{vec, vec::Vec};
+
+use crate::{Board, Move, generate_check_evasions, is_king_attacked};
+
+// A search proving forced mates, without evaluating positions: a line is only returned when every
+// defense leads to mate. The attacking side only tries moves giving check, or also the other moves
+// with 'all_moves', which is slower but finds the mates starting with a quiet move. Its last move
+// must give mate, so it is always a check, or a move winning by a rule of the variant.
+pub struct MateSearch
+{
+    pub all_moves: bool,
+    // Number of positions visited.
+    pub nodes: u64,
+}
+
+impl MateSearch
+{
+    pub fn new(all_moves: bool) -> Self
+    {
+        return MateSearch { all_moves, nodes: 0 };
+    }
+
+    // Find the shortest forced mate of the side to move in at most 'max_moves' of its moves.
+    // Return the line up to the mate, where the defending side delays the mate as long as it can.
+    pub fn find(&mut self, board: &mut Board, max_moves: u8) -> Option<Vec<Move>>
+    {
+        for moves in 1 ..= max_moves
+        {
+            if let Some(line) = self.attack(board, moves)
+            {
+                return Some(line);
+            }
+        }
+        return None;
+    }
+
+    // Find a move of the side to move that mates in at most 'moves' moves.
+    fn attack(&mut self, board: &mut Board, moves: u8) -> Option<Vec<Move>>
+    {
+        self.nodes += 1;
+        let mut candidates = board.get_legal_moves();
+        if self.all_moves && moves > 1
+        {
+            // Try the checks first, since they leave the fewest defenses.
+            candidates.sort_by_key(|mv| !is_forcing(board, *mv));
+        }
+        else
+        {
+            candidates.retain(|mv| is_forcing(board, *mv));
+        }
+
+        for mv in candidates
+        {
+            board.make_move(mv);
+            let defense = self.defend(board, moves);
+            board.unmake_move(mv);
+            if let Some(mut line) = defense
+            {
+                line.insert(0, mv);
+                return Some(line);
+            }
+        }
+        return None;
+    }
+
+    // Tell whether the side to move is mated, or is mated after each of its moves within 'moves'
+    // moves of the attacking side, and return the line delaying the mate the longest.
+    fn defend(&mut self, board: &mut Board, moves: u8) -> Option<Vec<Move>>
+    {
+        self.nodes += 1;
+        let in_check = is_king_attacked(board, false);
+        let defenses =
+            if in_check { generate_check_evasions(board) } else { board.get_legal_moves() };
+        if defenses.is_empty()
+        {
+            // Without moves, the side to move is either mated, stalemated, or wins in Antichess.
+            let mated =
+                (in_check || board.variant_loss().is_some()) && board.variant_win().is_none();
+            return if mated { Some(vec![]) } else { None };
+        }
+        if moves == 1
+        {
+            return None;
+        }
+
+        let mut longest: Option<Vec<Move>> = None;
+        for mv in defenses
+        {
+            board.make_move(mv);
+            let attack = self.attack(board, moves - 1);
+            board.unmake_move(mv);
+            let mut line = attack?;
+            line.insert(0, mv);
+            if longest.as_ref().is_none_or(|longest| line.len() > longest.len())
+            {
+                longest = Some(line);
+            }
+        }
+        return longest;
+    }
+}
+
+// Tell whether a move gives check, or wins by a rule of the variant.
+fn is_forcing(board: &mut Board, mv: Move) -> bool
+{
+    board.make_move(mv);
+    let forcing = is_king_attacked(board, false) || board.variant_loss().is_some();
+    board.unmake_move(mv);
+    return forcing;
+}
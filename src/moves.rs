@@ -1,5 +1,15 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+
 use super::{board::*, defines::*, piece::*};
-use crate::{Bitboard, black_king_pawn_mask, king_mask, knight_mask, white_king_pawn_mask};
+use crate::{
+    Bitboard, Explosion, MoveError, Variant, between, black_king_pawn_mask,
+    get_piece_type_on_square, king_mask, knight_mask, line, parse_square, white_king_pawn_mask,
+};
 
 // Enum to add context to a special move.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -13,29 +23,92 @@ pub enum MoveContext
     Promotion(Piece),
 }
 
+// The notations moves are written in for people, in game records and listings.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Notation
+{
+    // Standard Algebraic Notation, e.g. Nf3.
+    San,
+    // Long Algebraic Notation, with the start square, e.g. Ng1-f3.
+    Lan,
+    // ICCF numeric notation, used in correspondence chess, e.g. 7163.
+    Iccf,
+}
+
+impl Notation
+{
+    pub fn from_name(name: &str) -> Result<Notation, String>
+    {
+        return match name.to_ascii_lowercase().as_str()
+        {
+            "san" => Ok(Notation::San),
+            "lan" => Ok(Notation::Lan),
+            "iccf" => Ok(Notation::Iccf),
+            _ => Err(format!("Unknown notation '{}'. The notations are: san, lan, iccf.", name)),
+        };
+    }
+}
+
 // A move consists of a start tile and an end tile.
 // I might need to add more fields when I start using it.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub struct Move
 {
     pub start: Index,
     pub end: Index,
+    // The type of the piece that moves, a pawn for the promotions.
+    pub piece: Piece,
     pub context: MoveContext,
     pub previous_ep_target: Option<Index>,
-    pub previous_wqs: bool,
-    pub previous_wks: bool,
-    pub previous_bqs: bool,
-    pub previous_bks: bool,
+    pub previous_castling_rights: CastlingRights,
+    pub previous_halfmove_clock: u32,
     pub capture: Option<Piece>,
+    // The pieces removed by the capture in Atomic.
+    pub exploded: Explosion,
 }
 
 impl Move
 {
+    // Create a move given only by its squares and its promotion piece, to be completed by
+    // 'Board::complete_move' or played by 'Board::make_move'.
+    pub fn bare(start: Index, end: Index, promotion: Option<Piece>) -> Move
+    {
+        return Move {
+            start,
+            end,
+            piece: EMPTY,
+            context: promotion.map_or(MoveContext::None, MoveContext::Promotion),
+            previous_ep_target: None,
+            previous_castling_rights: NO_CASTLING_RIGHTS,
+            previous_halfmove_clock: 0,
+            capture: None,
+            exploded: Explosion::NONE,
+        };
+    }
+
+    // Get the piece a pawn promotes to, if the move is a promotion.
+    pub fn promotion(&self) -> Option<Piece>
+    {
+        return match self.context
+        {
+            MoveContext::Promotion(piece) => Some(piece),
+            _ => None,
+        };
+    }
+
     // Return a string representing a move using the Standard Algebraic Notation.
-    // Examples: e4, Nf3, Bef4, R1xb1, axb7+, etc.
+    // Examples: e4, Nf3, Bef4, R1xb1, axb7+, e8=Q#, etc.
     pub fn to_san(&self, board: &mut Board) -> String
     {
-        let piece = board.piece_at(self.start);
+        let legal_moves = board.get_legal_moves();
+        return self.to_san_with(board, &legal_moves);
+    }
+
+    // Write a move in SAN, given the legal moves of the position, so that writing several moves of
+    // the same position generates them once. The moves are only needed to disambiguate pieces.
+    pub fn to_san_with(&self, board: &mut Board, legal_moves: &[Move]) -> String
+    {
+        let piece = self.piece;
         let piece_char = match piece
         {
             PAWN => "",
@@ -47,46 +120,33 @@ impl Move
             _ => "",
         };
 
-        // Special cases for castling.
-        if self.context == MoveContext::KingSideCastle
-        {
-            return "O-O".into();
-        }
-        if self.context == MoveContext::QueenSideCastle
-        {
-            return "O-O-O".into();
-        }
-
         let to_coord = Self::idx_to_coord(self.end);
         let from_coord = Self::idx_to_coord(self.start);
-        let is_capture = self.context == MoveContext::EnPassant || self.capture != None;
+        let is_capture = self.context == MoveContext::EnPassant || self.capture.is_some();
 
         let mut disambiguation = String::new();
 
         // Disambiguation logic for non-pawns.
         if piece != PAWN && piece != KING
         {
-            let all_moves = board.get_legal_moves();
             let mut same_dest_count = 0;
             let mut need_file = false;
             let mut need_rank = false;
 
-            for other_mv in all_moves.iter()
+            for other_mv in legal_moves.iter()
             {
-                if other_mv.end == self.end && other_mv.start != self.start
+                if other_mv.end == self.end
+                    && other_mv.start != self.start
+                    && other_mv.piece == piece
                 {
-                    let other_piece = board.piece_at(other_mv.start);
-                    if other_piece == piece
+                    same_dest_count += 1;
+                    if (other_mv.start % 8) != (self.start % 8)
                     {
-                        same_dest_count += 1;
-                        if (other_mv.start % 8) != (self.start % 8)
-                        {
-                            need_file = true;
-                        }
-                        if (other_mv.start / 8) != (self.start / 8)
-                        {
-                            need_rank = true;
-                        }
+                        need_file = true;
+                    }
+                    if (other_mv.start / 8) != (self.start / 8)
+                    {
+                        need_rank = true;
                     }
                 }
             }
@@ -110,8 +170,16 @@ impl Move
 
         let mut san = String::new();
 
+        if self.context == MoveContext::KingSideCastle
+        {
+            san.push_str("O-O");
+        }
+        else if self.context == MoveContext::QueenSideCastle
+        {
+            san.push_str("O-O-O");
+        }
         // Pawn moves.
-        if piece == PAWN
+        else if piece == PAWN
         {
             if is_capture
             {
@@ -135,22 +203,112 @@ impl Move
         {
             match promoted
             {
-                BISHOP => san.push('b'),
-                ROOK => san.push('r'),
-                KNIGHT => san.push('n'),
-                QUEEN => san.push('q'),
+                BISHOP => san.push_str("=B"),
+                ROOK => san.push_str("=R"),
+                KNIGHT => san.push_str("=N"),
+                QUEEN => san.push_str("=Q"),
                 _ => (),
             }
         }
 
-        board.make_move(*self);
-        if is_king_attacked(&board, false)
+        san.extend(self.check_suffix(board));
+        return san;
+    }
+
+    // Return a string representing a move using the Long Algebraic Notation, which gives the start
+    // square of every move and needs no disambiguation.
+    // Examples: e2-e4, Ng1-f3, Bc4xf7+, e7xd8=Q#, O-O, etc.
+    pub fn to_lan(&self, board: &mut Board) -> String
+    {
+        let mut lan = String::new();
+        if self.context == MoveContext::KingSideCastle
         {
-            san.push('+');
+            lan.push_str("O-O");
+        }
+        else if self.context == MoveContext::QueenSideCastle
+        {
+            lan.push_str("O-O-O");
+        }
+        else
+        {
+            lan.push_str(match self.piece
+            {
+                KNIGHT => "N",
+                BISHOP => "B",
+                ROOK => "R",
+                QUEEN => "Q",
+                KING => "K",
+                _ => "",
+            });
+            lan.push_str(&Self::idx_to_coord(self.start));
+            let is_capture = self.context == MoveContext::EnPassant || self.capture.is_some();
+            lan.push(if is_capture { 'x' } else { '-' });
+            lan.push_str(&Self::idx_to_coord(self.end));
         }
-        board.unmake_move(*self);
 
-        return san;
+        if let MoveContext::Promotion(promoted) = self.context
+        {
+            match promoted
+            {
+                BISHOP => lan.push_str("=B"),
+                ROOK => lan.push_str("=R"),
+                KNIGHT => lan.push_str("=N"),
+                QUEEN => lan.push_str("=Q"),
+                _ => (),
+            }
+        }
+
+        lan.extend(self.check_suffix(board));
+        return lan;
+    }
+
+    // Return a string representing a move using the ICCF numeric notation of correspondence chess:
+    // the file and the rank of the start and end squares as digits, then the promoted piece as 1
+    // for a queen, 2 for a rook, 3 for a bishop and 4 for a knight. Castling is a king move.
+    // Examples: 5254 (e4), 7163 (Nf3), 5171 (O-O), 57581 (e8=Q), etc.
+    pub fn to_iccf(&self) -> String
+    {
+        let mut iccf = String::new();
+        for sq in [self.start, self.end]
+        {
+            iccf.push_str(&format!("{}{}", sq % 8 + 1, sq / 8 + 1));
+        }
+        if let MoveContext::Promotion(promoted) = self.context
+        {
+            match promoted
+            {
+                QUEEN => iccf.push('1'),
+                ROOK => iccf.push('2'),
+                BISHOP => iccf.push('3'),
+                KNIGHT => iccf.push('4'),
+                _ => (),
+            }
+        }
+        return iccf;
+    }
+
+    // Write a move in the given notation.
+    pub fn to_notation(&self, board: &mut Board, notation: Notation) -> String
+    {
+        return match notation
+        {
+            Notation::San => self.to_san(board),
+            Notation::Lan => self.to_lan(board),
+            Notation::Iccf => self.to_iccf(),
+        };
+    }
+
+    // Get the suffix of a move in SAN and in LAN: '+' for a check, '#' for a checkmate.
+    fn check_suffix(&self, board: &mut Board) -> Option<char>
+    {
+        if !board.gives_check(*self)
+        {
+            return None;
+        }
+        board.make_move(*self);
+        let mated = board.get_legal_moves().is_empty();
+        board.unmake_move(*self);
+        return Some(if mated { '#' } else { '+' });
     }
 
     // Return a string representing a move using the Universal Chess Interface notation.
@@ -182,17 +340,197 @@ impl Move
     }
 }
 
+// Find the legal move written in UCI notation (e.g. e2e4, e7e8q), if there is one.
+pub fn parse_uci_move(board: &mut Board, text: &str) -> Option<Move>
+{
+    return board.get_legal_moves().into_iter().find(|mv| mv.to_uci() == text);
+}
+
+// Tell whether a text is written like a move in UCI notation: the start and end squares, and the
+// letter of the promoted piece for a promotion.
+fn is_uci_notation(text: &str) -> bool
+{
+    let squares = text.get(.. 2).zip(text.get(2 .. 4));
+    return squares
+        .is_some_and(|(from, to)| parse_square(from).is_some() && parse_square(to).is_some())
+        && matches!(&text[4 ..], "" | "q" | "r" | "b" | "n");
+}
+
+impl Board
+{
+    // Play a list of moves in UCI notation, like those following "moves" in the 'position'
+    // command. Each text can hold several moves separated by whitespace. The board is left
+    // unchanged when a move can't be played.
+    pub fn apply_uci_moves(&mut self, moves: &[&str]) -> Result<(), MoveError>
+    {
+        let mut board = *self;
+        for (index, text) in moves.iter().flat_map(|text| text.split_whitespace()).enumerate()
+        {
+            if !is_uci_notation(text)
+            {
+                return Err(MoveError::InvalidNotation { index, text: text.into() });
+            }
+            let mv = parse_uci_move(&mut board, text)
+                .ok_or(MoveError::Illegal { index, text: text.into() })?;
+            board.make_move(mv);
+        }
+        *self = board;
+        return Ok(());
+    }
+}
+
+// Find the legal move written in Standard Algebraic Notation (e.g. Nf3, exd5, O-O, e8=Q), if there
+// is one. Check and annotation symbols are ignored, and '0' can be used instead of 'O' for castles.
+pub fn parse_san_move(board: &mut Board, text: &str) -> Option<Move>
+{
+    let wanted = normalize_san(text);
+    let moves = board.get_legal_moves();
+    return moves
+        .iter()
+        .copied()
+        .find(|mv| normalize_san(&mv.to_san_with(board, &moves)) == wanted);
+}
+
+// Find the legal move written in Long Algebraic Notation (e.g. Ng1-f3, e7xd8=Q), if there is one.
+// Check and annotation symbols are ignored, like in SAN.
+pub fn parse_lan_move(board: &mut Board, text: &str) -> Option<Move>
+{
+    let wanted = normalize_san(text);
+    let moves = board.get_legal_moves();
+    return moves.into_iter().find(|mv| normalize_san(&mv.to_lan(board)) == wanted);
+}
+
+// Find the legal move written in ICCF numeric notation (e.g. 5254, 57581), if there is one.
+pub fn parse_iccf_move(board: &mut Board, text: &str) -> Option<Move>
+{
+    return board.get_legal_moves().into_iter().find(|mv| mv.to_iccf() == text);
+}
+
+// Find the legal move written in UCI notation, in SAN, in LAN or in ICCF numeric notation, if there
+// is one.
+pub fn parse_move(board: &mut Board, text: &str) -> Option<Move>
+{
+    return parse_uci_move(board, text)
+        .or_else(|| parse_san_move(board, text))
+        .or_else(|| parse_lan_move(board, text))
+        .or_else(|| parse_iccf_move(board, text));
+}
+
+// Remove the symbols that don't identify a SAN move, and write promotions as "e8q".
+fn normalize_san(text: &str) -> String
+{
+    let mut san: String = text.chars().filter(|c| !"+#!?=".contains(*c)).collect();
+    san = san.replace('0', "O");
+    if let Some(last) = san.pop()
+    {
+        let promotion = san.ends_with(|c: char| c.is_ascii_digit()) && "QRBN".contains(last);
+        san.push(if promotion { last.to_ascii_lowercase() } else { last });
+    }
+    return san;
+}
+
 // Get legal moves for the playing side.
+// There are none when the game was won by a rule of the variant.
 pub fn get_legal_moves(board: &mut Board) -> Vec<Move>
+{
+    if board.variant_loss().is_some()
+    {
+        return vec![];
+    }
+    // Captures are compulsory in Antichess, which has no castling.
+    if board.variant == Variant::Antichess
+    {
+        let captures = generate_captures(board);
+        if !captures.is_empty()
+        {
+            return captures;
+        }
+        return generate_moves_to(board, !0u64);
+    }
+    let mut all_moves = generate_moves_to(board, !0u64);
+
+    let mut castling_moves = generate_castling_moves(board);
+    all_moves.append(&mut castling_moves);
+
+    return all_moves;
+}
+
+// Get legal captures for the playing side, en passant included.
+// Quiescence search only looks at these moves, so they are generated directly instead of
+// filtering the full move list.
+pub fn generate_captures(board: &mut Board) -> Vec<Move>
+{
+    if board.variant_loss().is_some()
+    {
+        return vec![];
+    }
+    let enemy = if board.white_to_play { board.black_pieces } else { board.white_pieces };
+    return generate_moves_to(board, enemy);
+}
+
+// Get legal moves for the playing side when its king is in check.
+// Only king moves, captures of the checking piece and interpositions can be legal, so other
+// moves are not generated at all. In a double check, only king moves are generated.
+// The variants changing the check rules get all their legal moves, since an explosion can also
+// remove the checking piece or the enemy king.
+pub fn generate_check_evasions(board: &mut Board) -> Vec<Move>
+{
+    if board.variant_loss().is_some()
+    {
+        return vec![];
+    }
+    if board.variant.changes_check_rules()
+    {
+        return get_legal_moves(board);
+    }
+    let checkers = get_checkers(board);
+    let king_sq = if board.white_to_play { board.white_king } else { board.black_king };
+
+    // The king can always try to step out of the check.
+    let mut moves = generate_king_moves(board, !0u64);
+
+    // A double check can only be answered by a king move.
+    if checkers.count_ones() != 1
+    {
+        return moves;
+    }
+
+    // Other pieces must either capture the checking piece, or block the line between the
+    // checking slider and the king.
+    let checker_sq = checkers.trailing_zeros() as usize;
+    let checker_type = get_piece_type_on_square(board, checker_sq);
+    let mut target_mask = checkers;
+    if checker_type == ROOK || checker_type == BISHOP || checker_type == QUEEN
+    {
+        target_mask |= between(king_sq, checker_sq);
+    }
+
+    let mut pawn_moves = generate_pawn_moves(board, target_mask);
+    let mut rook_moves = generate_rook_moves_hq(board, target_mask);
+    let mut bishop_moves = generate_bishop_moves_hq(board, target_mask);
+    let mut queen_moves = generate_queen_moves_hq(board, target_mask);
+    let mut knight_moves = generate_knight_moves(board, target_mask);
+
+    moves.append(&mut pawn_moves);
+    moves.append(&mut rook_moves);
+    moves.append(&mut bishop_moves);
+    moves.append(&mut queen_moves);
+    moves.append(&mut knight_moves);
+
+    return moves;
+}
+
+// Get legal moves for the playing side that end on a square of 'target_mask', castling excluded.
+fn generate_moves_to(board: &mut Board, target_mask: Bitboard) -> Vec<Move>
 {
     let mut all_moves = vec![];
 
-    let mut pawn_moves = generate_pawn_moves(board);
-    let mut rook_moves = generate_rook_moves_hq(board);
-    let mut bishop_moves = generate_bishop_moves_hq(board);
-    let mut queen_moves = generate_queen_moves_hq(board);
-    let mut knight_moves = generate_knight_moves(board);
-    let mut king_moves = generate_king_moves(board);
+    let mut pawn_moves = generate_pawn_moves(board, target_mask);
+    let mut rook_moves = generate_rook_moves_hq(board, target_mask);
+    let mut bishop_moves = generate_bishop_moves_hq(board, target_mask);
+    let mut queen_moves = generate_queen_moves_hq(board, target_mask);
+    let mut knight_moves = generate_knight_moves(board, target_mask);
+    let mut king_moves = generate_king_moves(board, target_mask);
 
     all_moves.append(&mut pawn_moves);
     all_moves.append(&mut rook_moves);
@@ -204,7 +542,93 @@ pub fn get_legal_moves(board: &mut Board) -> Vec<Move>
     return all_moves;
 }
 
-// Return true if the square is attacked by the specified side.
+// Return a bitboard of the enemy pieces giving check to the playing king.
+pub fn get_checkers(board: &Board) -> Bitboard
+{
+    let white = board.white_to_play;
+    let sq = if white { board.white_king } else { board.black_king };
+    let mut checkers = 0u64;
+    if sq == NO_SQUARE
+    {
+        return checkers;
+    }
+
+    let enemy_pawns = if white { board.black_pawns } else { board.white_pawns };
+    checkers |=
+        enemy_pawns & (if white { white_king_pawn_mask(sq) } else { black_king_pawn_mask(sq) });
+
+    let enemy_knights = if white { board.black_knights } else { board.white_knights };
+    checkers |= enemy_knights & knight_mask(sq);
+
+    let enemy_straight_sliders = if white
+    {
+        board.black_rooks | board.black_queens
+    }
+    else
+    {
+        board.white_rooks | board.white_queens
+    };
+    checkers |= enemy_straight_sliders & rook_attacks_hq(sq, board.pieces);
+
+    let enemy_diagonal_sliders = if white
+    {
+        board.black_bishops | board.black_queens
+    }
+    else
+    {
+        board.white_bishops | board.white_queens
+    };
+    checkers |= enemy_diagonal_sliders & bishop_attacks_hq(sq, board.pieces);
+
+    return checkers;
+}
+
+// Return a bitboard of the pieces of the playing side pinned to their king by an enemy slider. A
+// pinned piece can only move along the line between the king and the pinning piece.
+pub fn get_pinned_pieces(board: &Board) -> Bitboard
+{
+    let white = board.white_to_play;
+    let sq = if white { board.white_king } else { board.black_king };
+    let mut pinned = 0u64;
+    if sq == NO_SQUARE
+    {
+        return pinned;
+    }
+    let own_pieces = if white { board.white_pieces } else { board.black_pieces };
+
+    // Look for the enemy sliders seen by the king through one of its own pieces.
+    let enemy_straight_sliders = if white
+    {
+        board.black_rooks | board.black_queens
+    }
+    else
+    {
+        board.white_rooks | board.white_queens
+    };
+    let enemy_diagonal_sliders = if white
+    {
+        board.black_bishops | board.black_queens
+    }
+    else
+    {
+        board.white_bishops | board.white_queens
+    };
+    let mut pinners = enemy_straight_sliders & xray_rook_attacks(sq, board.pieces, own_pieces)
+        | enemy_diagonal_sliders & xray_bishop_attacks(sq, board.pieces, own_pieces);
+    while pinners != 0
+    {
+        let pinner = pinners.trailing_zeros() as usize;
+        pinned |= between(sq, pinner) & own_pieces;
+        pinners &= pinners - 1;
+    }
+    return pinned;
+}
+
+// Return true if the square is attacked by a side: the side to move when 'by_playing_side' is
+// true, its opponent otherwise. So 'is_square_attacked(sq, board, false)' tells whether the side
+// to move can't put its king on 'sq'. The piece on the square, if any, doesn't matter, the sliders
+// are blocked by every piece, and the en passant captures are not counted. The kings don't attack
+// in Atomic.
 pub fn is_square_attacked(sq: usize, board: &Board, by_playing_side: bool) -> bool
 {
     let attacked_by_white =
@@ -250,9 +674,9 @@ pub fn is_square_attacked(sq: usize, board: &Board, by_playing_side: bool) -> bo
         return true;
     }
 
-    let enemy_king =
-        if attacked_by_white { 1u64 << board.white_king } else { 1u64 << board.black_king };
-    if enemy_king & king_mask(sq) != 0
+    // Kings can't capture in Atomic.
+    if board.variant != Variant::Atomic
+        && board.king_bitboard(attacked_by_white) & king_mask(sq) != 0
     {
         return true;
     }
@@ -268,7 +692,261 @@ pub fn is_king_attacked(board: &Board, by_playing_side: bool) -> bool
 
     let sq = if attacked_by_white { board.black_king } else { board.white_king };
 
-    return is_square_attacked(sq, board, by_playing_side);
+    match board.variant
+    {
+        Variant::Antichess => return false,
+        // An exploded king counts as attacked, so that a move exploding its own king is illegal.
+        // Once the enemy king exploded, or when the kings touch, the king is safe, since the enemy
+        // would explode its own king by capturing it.
+        Variant::Atomic =>
+        {
+            let enemy_king = board.king_bitboard(attacked_by_white);
+            if sq == NO_SQUARE
+            {
+                return true;
+            }
+            if enemy_king == 0 || enemy_king & king_mask(sq) != 0
+            {
+                return false;
+            }
+        },
+        _ => (),
+    }
+
+    // A side without a king can't be in check.
+    return sq != NO_SQUARE && is_square_attacked(sq, board, by_playing_side);
+}
+
+impl Board
+{
+    // Tell whether a legal move puts the enemy king in check, without making it. The enemy king
+    // must not be in check already, as in the positions reached in a game. The piece landing on
+    // the destination square can attack the king, and a slider of the side to move can attack
+    // it through a square left by the move, when that square is on a line going through the king.
+    // The variants changing the check rules make the move on a copy of the board instead.
+    pub fn gives_check(&self, mv: Move) -> bool
+    {
+        let white = self.white_to_play;
+        let king = if white { self.black_king } else { self.white_king };
+        if king == NO_SQUARE
+        {
+            return false;
+        }
+        if self.variant.changes_check_rules()
+        {
+            let mut after = *self;
+            after.make_move(mv);
+            return is_king_attacked(&after, false);
+        }
+
+        // The squares left by the move, and the piece attacking from the destination square, or
+        // the rook for a castle.
+        let (from_mask, to_mask) = (1u64 << mv.start, 1u64 << mv.end);
+        let mut vacated = from_mask;
+        let (mut piece, mut sq) = (mv.piece, mv.end);
+        match mv.context
+        {
+            MoveContext::EnPassant =>
+            {
+                vacated |= 1u64 << if white { mv.end - 8 } else { mv.end + 8 }
+            },
+            MoveContext::Promotion(promoted) => piece = promoted,
+            MoveContext::QueenSideCastle | MoveContext::KingSideCastle =>
+            {
+                let castle = CastlingInfo::of_move(&mv, white).expect("The move is a castle.");
+                vacated |= 1u64 << castle.rook_from;
+                (piece, sq) = (ROOK, castle.rook_to);
+            },
+            _ => (),
+        }
+        let occupancy = (self.pieces & !vacated) | to_mask | (1u64 << sq);
+
+        let king_mask = 1u64 << king;
+        let direct = match piece
+        {
+            PAWN =>
+            {
+                let attackers =
+                    if white { black_king_pawn_mask(king) } else { white_king_pawn_mask(king) };
+                attackers & (1u64 << sq) != 0
+            },
+            KNIGHT => knight_mask(sq) & king_mask != 0,
+            BISHOP => bishop_attacks_hq(sq, occupancy) & king_mask != 0,
+            ROOK => rook_attacks_hq(sq, occupancy) & king_mask != 0,
+            QUEEN => queen_attacks_hq(sq, occupancy) & king_mask != 0,
+            _ => false,
+        };
+        if direct
+        {
+            return true;
+        }
+
+        // A discovered check needs a left square on a line through the king.
+        let mut aligned = false;
+        let mut bits = vacated;
+        while bits != 0
+        {
+            aligned |= line(king, bits.trailing_zeros() as usize) != 0;
+            bits &= bits - 1;
+        }
+        if !aligned
+        {
+            return false;
+        }
+        // The moved piece was counted above, and a captured piece doesn't attack anymore.
+        let (rooks, bishops, queens) = if white
+        {
+            (self.white_rooks, self.white_bishops, self.white_queens)
+        }
+        else
+        {
+            (self.black_rooks, self.black_bishops, self.black_queens)
+        };
+        let remaining = !vacated & !to_mask;
+        return (rooks | queens) & remaining & rook_attacks_hq(king, occupancy) != 0
+            || (bishops | queens) & remaining & bishop_attacks_hq(king, occupancy) != 0;
+    }
+
+    // Tell whether a move follows the way its piece moves in the position, without generating the
+    // other moves, and without checking whether it leaves the king in check. The moves from other
+    // positions, like those of the transposition table, can be checked before being played: the
+    // state saved in the move must be the one of the board, so that unmaking it restores the board.
+    // The castles are checked completely, since they can't go through check.
+    pub fn is_pseudo_legal(&self, mv: Move) -> bool
+    {
+        if mv.start >= 64 || mv.end >= 64 || self.variant_loss().is_some()
+        {
+            return false;
+        }
+        let (friendly, enemy) = if self.white_to_play
+        {
+            (self.white_pieces, self.black_pieces)
+        }
+        else
+        {
+            (self.black_pieces, self.white_pieces)
+        };
+        let (from_mask, to_mask) = (1u64 << mv.start, 1u64 << mv.end);
+        if friendly & from_mask == 0
+            || friendly & to_mask != 0
+            || get_piece_type_on_square(self, mv.start) != mv.piece
+        {
+            return false;
+        }
+        let capture = (enemy & to_mask != 0).then(|| get_piece_type_on_square(self, mv.end));
+        let captures = capture.is_some() || mv.context == MoveContext::EnPassant;
+        if mv.capture != capture
+            || mv.previous_ep_target != self.en_passant_target
+            || mv.previous_castling_rights != self.castling_rights
+            || mv.previous_halfmove_clock != self.halfmove_clock
+            || mv.exploded != self.explosion(mv.start, mv.end, captures)
+        {
+            return false;
+        }
+
+        return match mv.piece
+        {
+            PAWN => self.is_pawn_move(mv),
+            KING if mv.context != MoveContext::None =>
+            {
+                let mut board = *self;
+                generate_castling_moves(&mut board).contains(&mv)
+            },
+            // A king capturing in Atomic would explode with the captured piece.
+            KING =>
+            {
+                king_mask(mv.start) & to_mask != 0 && !(self.variant == Variant::Atomic && captures)
+            },
+            piece =>
+            {
+                let attacks = match piece
+                {
+                    KNIGHT => knight_mask(mv.start),
+                    BISHOP => bishop_attacks_hq(mv.start, self.pieces),
+                    ROOK => rook_attacks_hq(mv.start, self.pieces),
+                    _ => queen_attacks_hq(mv.start, self.pieces),
+                };
+                mv.context == MoveContext::None && attacks & to_mask != 0
+            },
+        };
+    }
+
+    // Tell whether a move can be played in the position, without generating the other moves.
+    pub fn is_legal(&self, mv: Move) -> bool
+    {
+        if !self.is_pseudo_legal(mv)
+        {
+            return false;
+        }
+        // Captures are compulsory in Antichess, where there is no check.
+        if self.variant == Variant::Antichess
+        {
+            let captures = mv.capture.is_some() || mv.context == MoveContext::EnPassant;
+            let mut board = *self;
+            return captures || generate_captures(&mut board).is_empty();
+        }
+        // The castles were checked completely, and the en passant captures have their own check,
+        // like in the move generation.
+        match mv.context
+        {
+            MoveContext::QueenSideCastle | MoveContext::KingSideCastle => return true,
+            MoveContext::EnPassant if !self.variant.changes_check_rules() =>
+            {
+                return is_en_passant_legal(self, mv.start, mv.end);
+            },
+            _ => (),
+        }
+        let mut after = *self;
+        after.make_move(mv);
+        return !is_king_attacked(&after, true);
+    }
+
+    // Tell whether a pawn move goes one or two squares forward to empty squares, or diagonally
+    // forward to capture, with the context telling the double steps, the en passant captures and
+    // the promotions.
+    fn is_pawn_move(&self, mv: Move) -> bool
+    {
+        let white = self.white_to_play;
+        let forward: isize = if white { 8 } else { -8 };
+        let step = mv.end as isize - mv.start as isize;
+        let file_change = (mv.end % 8).abs_diff(mv.start % 8);
+        let empty = |sq: isize| self.pieces & (1u64 << sq) == 0;
+        let (start_rank, last_rank) = if white { (1, 7) } else { (6, 0) };
+        let rank = mv.start / 8;
+
+        let single = step == forward && empty(mv.end as isize);
+        let capture = step.abs_diff(forward) == 1 && file_change == 1;
+        let promotes = mv.end / 8 == last_rank;
+        return match mv.context
+        {
+            MoveContext::None if !promotes =>
+            {
+                // The pawns of a side without a king, in Horde, can also move two squares from the
+                // first rank, without allowing en passant.
+                let first_rank = if white { 0 } else { 7 };
+                let double = step == 2 * forward
+                    && rank == first_rank
+                    && self.king_bitboard(white) == 0
+                    && empty(mv.start as isize + forward)
+                    && empty(mv.end as isize);
+                single || double || (capture && mv.capture.is_some())
+            },
+            MoveContext::DoubleStep =>
+            {
+                step == 2 * forward
+                    && rank == start_rank
+                    && empty(mv.start as isize + forward)
+                    && empty(mv.end as isize)
+            },
+            MoveContext::EnPassant => capture && self.en_passant_target == Some(mv.end),
+            MoveContext::Promotion(piece) if promotes =>
+            {
+                matches!(piece, KNIGHT | BISHOP | ROOK | QUEEN)
+                    && (single || (capture && mv.capture.is_some()))
+            },
+            _ => false,
+        };
+    }
 }
 
 pub fn get_attacked_squares(board: &Board) -> Bitboard
@@ -284,3 +962,72 @@ pub fn get_attacked_squares(board: &Board) -> Bitboard
 
     return m;
 }
+
+#[cfg(test)]
+mod tests
+{
+    use crate::*;
+
+    // Positions with pieces attacking along the a and h files and the first and last ranks, and
+    // knights and kings in the corners.
+    const EDGE_POSITIONS: [&str; 5] = [
+        "r3k2r/8/8/8/8/8/8/R3K2R w KQkq -",
+        "N6n/8/8/8/8/8/8/n3K1kN w - -",
+        "7k/p6p/8/8/8/8/P6P/K7 b - -",
+        "q6Q/8/1k6/8/8/6K1/8/b6B w - -",
+        "1r4r1/8/8/R6k/8/8/8/K6R b - -",
+    ];
+
+    // Compare 'is_square_attacked' with the slow mailbox generator on every square, for both sides.
+    fn assert_attacks_match(board: &Board)
+    {
+        let mailbox = Mailbox::from_board(board);
+        for sq in 0 .. 64
+        {
+            for by_playing_side in [true, false]
+            {
+                let white = by_playing_side == board.white_to_play;
+                assert_eq!(
+                    is_square_attacked(sq, board, by_playing_side),
+                    mailbox.is_square_attacked(sq, white),
+                    "square {} attacked by {} ({})",
+                    square_name(sq),
+                    if white { "white" } else { "black" },
+                    board.to_fen()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn square_attacks_match_mailbox_on_edges()
+    {
+        for fen in EDGE_POSITIONS
+        {
+            assert_attacks_match(&Board::from_fen(fen).unwrap());
+        }
+    }
+
+    #[test]
+    fn square_attacks_match_mailbox_on_random_positions()
+    {
+        let mut rng = default_randomness().rng(0);
+        for plies in 0 .. 200
+        {
+            assert_attacks_match(&Board::random_position(&mut rng, plies % 80));
+        }
+    }
+
+    #[test]
+    fn square_attacks_follow_side_to_move()
+    {
+        // With white to play, f3 is attacked by the playing side, and f6 by its opponent.
+        let mut board = Board::new().unwrap();
+        assert!(is_square_attacked(21, &board, true));
+        assert!(!is_square_attacked(21, &board, false));
+        assert!(is_square_attacked(45, &board, false));
+        board.make_move(Move::bare(12, 28, None));
+        assert!(is_square_attacked(45, &board, true));
+        assert!(is_square_attacked(21, &board, false));
+    }
+}
@@ -0,0 +1,323 @@
+use crate::*;
+
+// The changes of file and of rank of the leaps of a knight.
+const KNIGHT_LEAPS: [(isize, isize); 8] =
+    [(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+
+// The pieces a pawn can promote to, with their letter in UCI.
+const PROMOTIONS: [(Piece, char); 4] = [(QUEEN, 'q'), (ROOK, 'r'), (BISHOP, 'b'), (KNIGHT, 'n')];
+
+// A slow but straightforward move generator working on an array of 64 squares, to cross-check the
+// bitboard move generation in the self-test. It only knows the rules of standard chess.
+#[derive(Debug, Clone, Copy)]
+pub struct Mailbox
+{
+    // The piece on each square with its color, or EMPTY.
+    pub squares: [Piece; 64],
+    pub white_to_play: bool,
+    pub castling_rights: CastlingRights,
+    pub en_passant_target: Option<Index>,
+}
+
+// A move of the mailbox generator. The castling moves go from the king's square to its
+// destination, like in UCI.
+#[derive(Debug, Clone, Copy)]
+struct NaiveMove
+{
+    from: Index,
+    to: Index,
+    promotion: Option<(Piece, char)>,
+}
+
+impl Mailbox
+{
+    pub fn from_board(board: &Board) -> Self
+    {
+        let mut squares = [EMPTY; 64];
+        for (sq, square) in squares.iter_mut().enumerate()
+        {
+            let piece_type = get_piece_type_on_square(board, sq);
+            if piece_type != EMPTY
+            {
+                let white = board.white_pieces & (1u64 << sq) != 0;
+                *square = piece_type | if white { WHITE } else { BLACK };
+            }
+        }
+        return Mailbox {
+            squares,
+            white_to_play: board.white_to_play,
+            castling_rights: board.castling_rights,
+            en_passant_target: board.en_passant_target,
+        };
+    }
+
+    // Get the piece on a square given by its file and rank, or None outside of the board.
+    fn at(&self, file: isize, rank: isize) -> Option<Piece>
+    {
+        if !(0 .. 8).contains(&file) || !(0 .. 8).contains(&rank)
+        {
+            return None;
+        }
+        return Some(self.squares[(rank * 8 + file) as usize]);
+    }
+
+    fn color(white: bool) -> Color
+    {
+        return if white { WHITE } else { BLACK };
+    }
+
+    // Tell whether a side attacks a square, by looking from the square in every direction for a
+    // piece of that side able to reach it.
+    pub fn is_square_attacked(&self, sq: Index, white: bool) -> bool
+    {
+        let color = Self::color(white);
+        let (file, rank) = ((sq % 8) as isize, (sq / 8) as isize);
+        let is = |df: isize, dr: isize, piece_type: Piece| -> bool {
+            return self.at(file + df, rank + dr) == Some(piece_type | color);
+        };
+
+        // A pawn attacks the squares diagonally in front of it, so it stands diagonally behind.
+        let behind = if white { -1 } else { 1 };
+        if is(-1, behind, PAWN) || is(1, behind, PAWN)
+        {
+            return true;
+        }
+        if KNIGHT_LEAPS.iter().any(|(df, dr)| is(*df, *dr, KNIGHT))
+        {
+            return true;
+        }
+        for direction in Direction::ALL
+        {
+            let (df, dr) = direction.steps();
+            if is(df, dr, KING)
+            {
+                return true;
+            }
+            let slider = if direction.is_straight() { ROOK } else { BISHOP };
+            let (mut f, mut r) = (file + df, rank + dr);
+            while let Some(piece) = self.at(f, r)
+            {
+                if piece != EMPTY
+                {
+                    if piece == slider | color || piece == QUEEN | color
+                    {
+                        return true;
+                    }
+                    break;
+                }
+                f += df;
+                r += dr;
+            }
+        }
+        return false;
+    }
+
+    // Get the legal moves of the side to move in UCI notation, sorted.
+    pub fn legal_moves(&self) -> Vec<String>
+    {
+        let mut moves: Vec<String> = self
+            .pseudo_legal_moves()
+            .into_iter()
+            .filter(|mv| {
+                let after = self.play(*mv);
+                let king = after
+                    .squares
+                    .iter()
+                    .position(|piece| *piece == KING | Self::color(self.white_to_play));
+                return king
+                    .is_some_and(|king| !after.is_square_attacked(king, !self.white_to_play));
+            })
+            .map(|mv| {
+                let mut uci = format!("{}{}", square_name(mv.from), square_name(mv.to));
+                if let Some((_, letter)) = mv.promotion
+                {
+                    uci.push(letter);
+                }
+                return uci;
+            })
+            .collect();
+        moves.sort();
+        return moves;
+    }
+
+    // Generate the moves following the way each piece moves, without checking whether they leave
+    // the king in check. Castling is checked completely, since it can't go through check.
+    fn pseudo_legal_moves(&self) -> Vec<NaiveMove>
+    {
+        let white = self.white_to_play;
+        let color = Self::color(white);
+        let mut moves = vec![];
+        let is_enemy = |piece: Piece| piece != EMPTY && get_piece_color(piece) != color;
+        for from in 0 .. 64
+        {
+            let piece = self.squares[from];
+            if piece == EMPTY || get_piece_color(piece) != color
+            {
+                continue;
+            }
+            let (file, rank) = ((from % 8) as isize, (from / 8) as isize);
+            let square = |f: isize, r: isize| (r * 8 + f) as Index;
+            match get_piece_type(piece)
+            {
+                PAWN =>
+                {
+                    let forward = if white { 1 } else { -1 };
+                    let (start_rank, last_rank) = if white { (1, 7) } else { (6, 0) };
+                    let mut targets = vec![];
+                    if self.at(file, rank + forward) == Some(EMPTY)
+                    {
+                        targets.push(square(file, rank + forward));
+                        if rank == start_rank && self.at(file, rank + 2 * forward) == Some(EMPTY)
+                        {
+                            targets.push(square(file, rank + 2 * forward));
+                        }
+                    }
+                    for df in [-1, 1]
+                    {
+                        let Some(target) = self.at(file + df, rank + forward)
+                        else
+                        {
+                            continue;
+                        };
+                        let to = square(file + df, rank + forward);
+                        if is_enemy(target) || self.en_passant_target == Some(to)
+                        {
+                            targets.push(to);
+                        }
+                    }
+                    for to in targets
+                    {
+                        if (to / 8) as isize == last_rank
+                        {
+                            for promotion in PROMOTIONS
+                            {
+                                moves.push(NaiveMove { from, to, promotion: Some(promotion) });
+                            }
+                        }
+                        else
+                        {
+                            moves.push(NaiveMove { from, to, promotion: None });
+                        }
+                    }
+                },
+                KNIGHT | KING =>
+                {
+                    let steps: Vec<(isize, isize)> = if get_piece_type(piece) == KNIGHT
+                    {
+                        KNIGHT_LEAPS.to_vec()
+                    }
+                    else
+                    {
+                        Direction::ALL.iter().map(|direction| direction.steps()).collect()
+                    };
+                    for (df, dr) in steps
+                    {
+                        if let Some(target) = self.at(file + df, rank + dr)
+                            && (target == EMPTY || is_enemy(target))
+                        {
+                            moves.push(NaiveMove {
+                                from,
+                                to: square(file + df, rank + dr),
+                                promotion: None,
+                            });
+                        }
+                    }
+                },
+                slider =>
+                {
+                    for direction in Direction::ALL
+                    {
+                        if (slider == ROOK && !direction.is_straight())
+                            || (slider == BISHOP && direction.is_straight())
+                        {
+                            continue;
+                        }
+                        let (df, dr) = direction.steps();
+                        let (mut f, mut r) = (file + df, rank + dr);
+                        while let Some(target) = self.at(f, r)
+                        {
+                            if target == EMPTY || is_enemy(target)
+                            {
+                                moves.push(NaiveMove { from, to: square(f, r), promotion: None });
+                            }
+                            if target != EMPTY
+                            {
+                                break;
+                            }
+                            f += df;
+                            r += dr;
+                        }
+                    }
+                },
+            }
+        }
+
+        // Castling: the king and the rook are on their squares, the squares between them are
+        // empty, and the king doesn't start on, cross or land on an attacked square.
+        let (king_home, rights) = if white
+        {
+            (4, [(WHITE_KING_SIDE, 7), (WHITE_QUEEN_SIDE, 0)])
+        }
+        else
+        {
+            (60, [(BLACK_KING_SIDE, 63), (BLACK_QUEEN_SIDE, 56)])
+        };
+        for (right, rook) in rights
+        {
+            if !self.castling_rights.contains(right)
+                || self.squares[king_home] != KING | color
+                || self.squares[rook] != ROOK | color
+            {
+                continue;
+            }
+            let (low, high) = if rook > king_home { (king_home, rook) } else { (rook, king_home) };
+            let to = if rook > king_home { king_home + 2 } else { king_home - 2 };
+            let path_empty = (low + 1 .. high).all(|sq| self.squares[sq] == EMPTY);
+            let (first, last) = if to > king_home { (king_home, to) } else { (to, king_home) };
+            let path_safe = (first ..= last).all(|sq| !self.is_square_attacked(sq, !white));
+            if path_empty && path_safe
+            {
+                moves.push(NaiveMove { from: king_home, to, promotion: None });
+            }
+        }
+        return moves;
+    }
+
+    // Get the position after a move, the castling rights and the en passant target aside.
+    fn play(&self, mv: NaiveMove) -> Mailbox
+    {
+        let mut after = *self;
+        let piece = self.squares[mv.from];
+        after.squares[mv.from] = EMPTY;
+        after.squares[mv.to] = match mv.promotion
+        {
+            Some((promoted, _)) => promoted | get_piece_color(piece),
+            None => piece,
+        };
+        match get_piece_type(piece)
+        {
+            // A pawn moving diagonally to an empty square takes en passant the pawn beside it.
+            PAWN if mv.from % 8 != mv.to % 8 && self.squares[mv.to] == EMPTY =>
+            {
+                after.squares[(mv.from / 8) * 8 + mv.to % 8] = EMPTY;
+            },
+            // The rook jumps over the castling king.
+            KING if mv.from.abs_diff(mv.to) == 2 =>
+            {
+                let (rook_from, rook_to) = if mv.to > mv.from
+                {
+                    (mv.from + 3, mv.from + 1)
+                }
+                else
+                {
+                    (mv.from - 4, mv.from - 1)
+                };
+                after.squares[rook_to] = after.squares[rook_from];
+                after.squares[rook_from] = EMPTY;
+            },
+            _ => (),
+        }
+        after.white_to_play = !self.white_to_play;
+        return after;
+    }
+}
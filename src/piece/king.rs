@@ -1,6 +1,9 @@
+use alloc::{vec, vec::Vec};
+
 use crate::{
-    Bitboard, Board, Move, MoveContext, get_piece_type_on_square, is_king_attacked,
-    is_square_attacked, masks::*,
+    BLACK_KING_SIDE, BLACK_QUEEN_SIDE, Bitboard, Board, CastlingRights, Explosion, Index, KING,
+    Move, MoveContext, NO_SQUARE, Variant, WHITE_KING_SIDE, WHITE_QUEEN_SIDE,
+    get_piece_type_on_square, is_king_attacked, is_square_attacked, masks::*,
 };
 
 // Generate a bitboard representing squares attacked by the king of the player that just played.
@@ -11,6 +14,10 @@ pub fn generate_king_attacks(board: &Board) -> Bitboard
 
     // Get the starting position of the king.
     let from = if board.white_to_play { board.white_king } else { board.black_king };
+    if from == NO_SQUARE
+    {
+        return 0;
+    }
 
     // Get legal moves.
     let moves_bb = king_mask(from);
@@ -29,8 +36,9 @@ pub fn generate_king_attacks(board: &Board) -> Bitboard
     return m;
 }
 
-// Create a vector containing moves that the king can make.
-pub fn generate_king_moves(board: &mut Board) -> Vec<Move>
+// Create a vector containing moves that the king can make, castling excluded.
+// Only moves ending on a square of 'target_mask' are generated.
+pub fn generate_king_moves(board: &mut Board, target_mask: Bitboard) -> Vec<Move>
 {
     let mut moves = vec![];
 
@@ -40,12 +48,22 @@ pub fn generate_king_moves(board: &mut Board) -> Vec<Move>
 
     // Get the starting position of the king.
     let from = if board.white_to_play { board.white_king } else { board.black_king };
+    if from == NO_SQUARE
+    {
+        return vec![];
+    }
 
     // Get pseudo-legal moves.
     let pl_moves_bb = king_mask(from);
 
-    // Forbid capture of friendly pieces.
-    let moves_bb = pl_moves_bb & !friendly;
+    // Forbid capture of friendly pieces, and keep only the requested target squares.
+    let mut moves_bb = pl_moves_bb & !friendly & target_mask;
+
+    // A king capturing in Atomic would explode with the captured piece.
+    if board.variant == Variant::Atomic
+    {
+        moves_bb &= !enemy;
+    }
 
     // Add a move for each target square.
     let mut t = moves_bb;
@@ -59,12 +77,11 @@ pub fn generate_king_moves(board: &mut Board) -> Vec<Move>
         let mv = Move {
             start: from,
             end: to,
+            piece: KING,
             context: MoveContext::None,
             previous_ep_target: board.en_passant_target,
-            previous_wqs: board.white_queen_side_castling_right,
-            previous_wks: board.white_king_side_castling_right,
-            previous_bqs: board.black_queen_side_castling_right,
-            previous_bks: board.black_king_side_castling_right,
+            previous_castling_rights: board.castling_rights,
+            previous_halfmove_clock: board.halfmove_clock,
             capture: if enemy & to_mask != 0
             {
                 Some(get_piece_type_on_square(board, to))
@@ -73,11 +90,13 @@ pub fn generate_king_moves(board: &mut Board) -> Vec<Move>
             {
                 None
             },
+            // Kings can't capture in Atomic, so they never explode.
+            exploded: Explosion::NONE,
         };
 
         board.make_move(mv);
         // Add the move only if the king is not in check.
-        if !is_king_attacked(&board, true)
+        if !is_king_attacked(board, true)
         {
             moves.push(mv);
         }
@@ -85,103 +104,172 @@ pub fn generate_king_moves(board: &mut Board) -> Vec<Move>
         t &= t - 1;
     }
 
-    // Masks representing the squares that must be free for a castle to be legal.
-    const WHITE_QUEENSIDE_FREE_PATH_MASK: u64 = 0x00_00_00_00_00_00_00_0e;
-    const WHITE_KINGSIDE_FREE_PATH_MASK: u64 = 0x00_00_00_00_00_00_00_60;
-    const BLACK_QUEENSIDE_FREE_PATH_MASK: u64 = 0x0e_00_00_00_00_00_00_00;
-    const BLACK_KINGSIDE_FREE_PATH_MASK: u64 = 0x60_00_00_00_00_00_00_00;
+    return moves;
+}
+
+// The squares and the right of a castle of one side to one wing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CastlingInfo
+{
+    // The castling right needed to castle.
+    pub right: CastlingRights,
+    // The context of the castling move, which tells the wing.
+    pub context: MoveContext,
+    pub king_from: Index,
+    pub king_to: Index,
+    pub rook_from: Index,
+    pub rook_to: Index,
+    // The squares crossed by the king and the rook, which must be empty, apart from the king and
+    // the rook themselves.
+    pub free_path: Bitboard,
+    // The squares the king starts on, crosses and lands on, which must not be attacked.
+    pub safe_squares: Bitboard,
+}
+
+// The castles of each side, white first, to the queen side then to the king side.
+pub const CASTLING_INFO: [[CastlingInfo; 2]; 2] = [
+    [
+        CastlingInfo::new(WHITE_QUEEN_SIDE, MoveContext::QueenSideCastle, 4, 2, 0, 3),
+        CastlingInfo::new(WHITE_KING_SIDE, MoveContext::KingSideCastle, 4, 6, 7, 5),
+    ],
+    [
+        CastlingInfo::new(BLACK_QUEEN_SIDE, MoveContext::QueenSideCastle, 60, 58, 56, 59),
+        CastlingInfo::new(BLACK_KING_SIDE, MoveContext::KingSideCastle, 60, 62, 63, 61),
+    ],
+];
 
-    // Add the castle moves.
-    if board.white_to_play
+impl CastlingInfo
+{
+    const fn new(
+        right: CastlingRights,
+        context: MoveContext,
+        king_from: Index,
+        king_to: Index,
+        rook_from: Index,
+        rook_to: Index,
+    ) -> Self
     {
-        // White queen side.
-        if board.white_queen_side_castling_right
-            && board.pieces & WHITE_QUEENSIDE_FREE_PATH_MASK == 0
-            && !is_square_attacked(2, board, false)
-            && !is_square_attacked(3, board, false)
-            && !is_square_attacked(4, board, false)
-        {
-            let mv = Move {
-                start: from,
-                end: from - 2,
-                context: MoveContext::QueenSideCastle,
-                previous_ep_target: board.en_passant_target,
-                previous_wqs: board.white_queen_side_castling_right,
-                previous_wks: board.white_king_side_castling_right,
-                previous_bqs: board.black_queen_side_castling_right,
-                previous_bks: board.black_king_side_castling_right,
-                capture: None,
-            };
+        let pieces = (1u64 << king_from) | (1u64 << rook_from);
+        let free_path = (span(king_from, king_to) | span(rook_from, rook_to)) & !pieces;
+        return CastlingInfo {
+            right,
+            context,
+            king_from,
+            king_to,
+            rook_from,
+            rook_to,
+            free_path,
+            safe_squares: span(king_from, king_to),
+        };
+    }
 
-            moves.push(mv);
-        }
-        // White king side.
-        if board.white_king_side_castling_right
-            && board.pieces & WHITE_KINGSIDE_FREE_PATH_MASK == 0
-            && !is_square_attacked(4, board, false)
-            && !is_square_attacked(5, board, false)
-            && !is_square_attacked(6, board, false)
+    // Get the castles of a side, to the queen side then to the king side.
+    pub fn of_side(white: bool) -> &'static [CastlingInfo; 2]
+    {
+        return &CASTLING_INFO[if white { 0 } else { 1 }];
+    }
+
+    // Get the castle played by a move of the given side, or None if it is not a castling move.
+    pub fn of_move(mv: &Move, white: bool) -> Option<&'static CastlingInfo>
+    {
+        let wing = match mv.context
         {
-            let mv = Move {
-                start: from,
-                end: from + 2,
-                context: MoveContext::KingSideCastle,
-                previous_ep_target: board.en_passant_target,
-                previous_wqs: board.white_queen_side_castling_right,
-                previous_wks: board.white_king_side_castling_right,
-                previous_bqs: board.black_queen_side_castling_right,
-                previous_bks: board.black_king_side_castling_right,
-                capture: None,
-            };
+            MoveContext::QueenSideCastle => 0,
+            MoveContext::KingSideCastle => 1,
+            _ => return None,
+        };
+        return Some(&Self::of_side(white)[wing]);
+    }
 
-            moves.push(mv);
-        }
+    // Get the squares left and reached by the rook, to move it with a XOR, or back when unmaking.
+    pub fn rook_squares(&self) -> Bitboard
+    {
+        return (1u64 << self.rook_from) | (1u64 << self.rook_to);
+    }
+}
+
+// Get the squares from one square to another on the same rank, both included.
+const fn span(a: Index, b: Index) -> Bitboard
+{
+    let (low, high) = if a < b { (a, b) } else { (b, a) };
+    let mut squares = 0;
+    let mut sq = low;
+    while sq <= high
+    {
+        squares |= 1u64 << sq;
+        sq += 1;
     }
-    else
+    return squares;
+}
+
+// Create a vector containing the castling moves that the king can make.
+pub fn generate_castling_moves(board: &mut Board) -> Vec<Move>
+{
+    let mut moves = vec![];
+
+    // Get the starting position of the king.
+    // There is no castling in Antichess.
+    let from = if board.white_to_play { board.white_king } else { board.black_king };
+    if from == NO_SQUARE || board.variant == Variant::Antichess
     {
-        // Black queen side.
-        if board.black_queen_side_castling_right
-            && board.pieces & BLACK_QUEENSIDE_FREE_PATH_MASK == 0
-            && !is_square_attacked(58, board, false)
-            && !is_square_attacked(59, board, false)
-            && !is_square_attacked(60, board, false)
-        {
-            let mv = Move {
-                start: from,
-                end: from - 2,
-                context: MoveContext::QueenSideCastle,
-                previous_ep_target: board.en_passant_target,
-                previous_wqs: board.white_queen_side_castling_right,
-                previous_wks: board.white_king_side_castling_right,
-                previous_bqs: board.black_queen_side_castling_right,
-                previous_bks: board.black_king_side_castling_right,
-                capture: None,
-            };
+        return vec![];
+    }
 
-            moves.push(mv);
-        }
-        // Black king side.
-        if board.black_king_side_castling_right
-            && board.pieces & BLACK_KINGSIDE_FREE_PATH_MASK == 0
-            && !is_square_attacked(60, board, false)
-            && !is_square_attacked(61, board, false)
-            && !is_square_attacked(62, board, false)
+    for castle in CastlingInfo::of_side(board.white_to_play)
+    {
+        if board.castling_rights.contains(castle.right)
+            && has_castling_pieces(board, from, castle)
+            && board.pieces & castle.free_path == 0
+            && is_castling_path_safe(board, castle.safe_squares)
         {
-            let mv = Move {
+            moves.push(Move {
                 start: from,
-                end: from + 2,
-                context: MoveContext::KingSideCastle,
+                end: castle.king_to,
+                piece: KING,
+                context: castle.context,
                 previous_ep_target: board.en_passant_target,
-                previous_wqs: board.white_queen_side_castling_right,
-                previous_wks: board.white_king_side_castling_right,
-                previous_bqs: board.black_queen_side_castling_right,
-                previous_bks: board.black_king_side_castling_right,
+                previous_castling_rights: board.castling_rights,
+                previous_halfmove_clock: board.halfmove_clock,
                 capture: None,
-            };
-
-            moves.push(mv);
+                exploded: Explosion::NONE,
+            });
         }
     }
 
     return moves;
 }
+
+// Tell whether the king and the rook of the side to move stand on their initial squares. A FEN can
+// keep the castling rights without them, so the rights are not enough to castle.
+fn has_castling_pieces(board: &Board, king: Index, castle: &CastlingInfo) -> bool
+{
+    let rooks = if board.white_to_play { board.white_rooks } else { board.black_rooks };
+    return king == castle.king_from && rooks & (1u64 << castle.rook_from) != 0;
+}
+
+// Tell whether none of the squares the king goes through while castling is attacked.
+fn is_castling_path_safe(board: &Board, mut squares: Bitboard) -> bool
+{
+    while squares != 0
+    {
+        if !is_castling_square_safe(board, squares.trailing_zeros() as Index)
+        {
+            return false;
+        }
+        squares &= squares - 1;
+    }
+    return true;
+}
+
+// Tell whether the king can stand on a square while castling, without being attacked by an enemy
+// piece. In Atomic, the enemy king doesn't attack, and a square next to it is safe, since capturing
+// the king there would explode the enemy king too.
+fn is_castling_square_safe(board: &Board, sq: Index) -> bool
+{
+    if board.variant == Variant::Atomic
+        && board.king_bitboard(!board.white_to_play) & king_mask(sq) != 0
+    {
+        return true;
+    }
+    return !is_square_attacked(sq, board, false);
+}
@@ -1,5 +1,8 @@
+use alloc::{vec, vec::Vec};
+
 use crate::{
-    Bitboard, Board, Move, MoveContext, get_piece_type_on_square, is_king_attacked, masks::*,
+    Bitboard, Board, KNIGHT, Move, MoveContext, get_piece_type_on_square, is_king_attacked,
+    masks::*,
 };
 
 // Generate a bitboard representing squares attacked by the knights of the player that just played.
@@ -41,7 +44,8 @@ pub fn generate_knight_attacks(board: &Board) -> Bitboard
 }
 
 // Create a vector containing moves that knights can make.
-pub fn generate_knight_moves(board: &mut Board) -> Vec<Move>
+// Only moves ending on a square of 'target_mask' are generated.
+pub fn generate_knight_moves(board: &mut Board, target_mask: Bitboard) -> Vec<Move>
 {
     let mut moves = vec![];
 
@@ -61,8 +65,8 @@ pub fn generate_knight_moves(board: &mut Board) -> Vec<Move>
         // Get pseudo-legal moves.
         let pl_moves_bb = knight_mask(from);
 
-        // Forbid capture of friendly pieces.
-        let moves_bb = pl_moves_bb & !friendly;
+        // Forbid capture of friendly pieces, and keep only the requested target squares.
+        let moves_bb = pl_moves_bb & !friendly & target_mask;
 
         // Add a move for each target square.
         let mut t = moves_bb;
@@ -76,12 +80,11 @@ pub fn generate_knight_moves(board: &mut Board) -> Vec<Move>
             let mv = Move {
                 start: from,
                 end: to,
+                piece: KNIGHT,
                 context: MoveContext::None,
                 previous_ep_target: board.en_passant_target,
-                previous_wqs: board.white_queen_side_castling_right,
-                previous_wks: board.white_king_side_castling_right,
-                previous_bqs: board.black_queen_side_castling_right,
-                previous_bks: board.black_king_side_castling_right,
+                previous_castling_rights: board.castling_rights,
+                previous_halfmove_clock: board.halfmove_clock,
                 capture: if enemy & to_mask != 0
                 {
                     Some(get_piece_type_on_square(board, to))
@@ -90,11 +93,12 @@ pub fn generate_knight_moves(board: &mut Board) -> Vec<Move>
                 {
                     None
                 },
+                exploded: board.explosion(from, to, enemy & to_mask != 0),
             };
-            
+
             board.make_move(mv);
             // Add the move only if the king is not in check.
-            if !is_king_attacked(&board, true)
+            if !is_king_attacked(board, true)
             {
                 moves.push(mv);
             }
@@ -1,6 +1,9 @@
+use alloc::vec::Vec;
+
 use crate::{
-    BISHOP, Bitboard, Board, KNIGHT, Move, MoveContext, QUEEN, ROOK, get_piece_type_on_square,
-    is_king_attacked,
+    BISHOP, Bitboard, Board, Index, KNIGHT, Move, MoveContext, NO_SQUARE, PAWN, QUEEN, ROOK,
+    bishop_attacks_hq, black_king_pawn_mask, get_piece_type_on_square, is_king_attacked,
+    knight_mask, rook_attacks_hq, white_king_pawn_mask,
 };
 
 // Generate legal moves for pawns.
@@ -84,7 +87,9 @@ pub fn generate_pawn_attacks(board: &Board) -> Bitboard
 }
 
 // Generate legal moves for pawns.
-pub fn generate_pawn_moves(board: &mut Board) -> Vec<Move>
+// Only moves ending on a square of 'target_mask' are generated. En passant captures are also
+// kept when the captured pawn is on a square of 'target_mask'.
+pub fn generate_pawn_moves(board: &mut Board, target_mask: Bitboard) -> Vec<Move>
 {
     // Create a vector representing legal moves for pawns.
     let mut moves = Vec::new();
@@ -100,6 +105,9 @@ pub fn generate_pawn_moves(board: &mut Board) -> Vec<Move>
     // The following rank masks allow us to verify that a pawn is allowed to move 2 squares forward.
     const RANK_2: u64 = 0x00_00_00_00_00_00_FF_00;
     const RANK_7: u64 = 0x00_FF_00_00_00_00_00_00;
+    // The pawns of a side without a king, in Horde, can also move 2 squares from the first rank.
+    const RANK_1: u64 = 0x00_00_00_00_00_00_00_FF;
+    const RANK_8: u64 = 0xFF_00_00_00_00_00_00_00;
     // The following rank masks allow us to verify that a pawn is in a situation where an en passant
     // capture may be possible.
     const RANK_5: u64 = 0x00_00_00_FF_00_00_00_00;
@@ -112,19 +120,20 @@ pub fn generate_pawn_moves(board: &mut Board) -> Vec<Move>
 
         // Create a bitboard representing squares that pawns can go to by moving one square forward.
         // The square ahead must be free.
-        let singles = (wp << 8) & empty;
+        let singles = (wp << 8) & empty & target_mask;
         bitboard_to_moves(board, singles, 8, &mut moves, false);
 
         // Create a bitboard representing squares that pawns can go to by moving two squares
         // forward. The two squares ahead must be free, and the pawn must be on rank 2.
-        let doubles = ((wp & RANK_2) << 16) & empty & (empty << 8);
+        let double_ranks = if board.white_king == NO_SQUARE { RANK_1 | RANK_2 } else { RANK_2 };
+        let doubles = ((wp & double_ranks) << 16) & empty & (empty << 8) & target_mask;
         bitboard_to_moves(board, doubles, 16, &mut moves, false);
 
         // Create two bitboards representing squares that pawns can go to by capturing a black
         // piece. For each bitboard, the corresponding diagonal square must contain a black
         // piece.
-        let cap_nw = ((wp & !FILE_A) << 7) & board.black_pieces;
-        let cap_ne = ((wp & !FILE_H) << 9) & board.black_pieces;
+        let cap_nw = ((wp & !FILE_A) << 7) & board.black_pieces & target_mask;
+        let cap_ne = ((wp & !FILE_H) << 9) & board.black_pieces & target_mask;
         bitboard_to_moves(board, cap_nw, 7, &mut moves, false);
         bitboard_to_moves(board, cap_ne, 9, &mut moves, false);
 
@@ -132,6 +141,7 @@ pub fn generate_pawn_moves(board: &mut Board) -> Vec<Move>
         // this ply.
         if let Some(ep_sq) = board.en_passant_target
             && ep_sq > 39
+            && target_mask & ((1u64 << ep_sq) | (1u64 << (ep_sq - 8))) != 0
         {
             // Get a bitboard representing the position of the target tile.
             let ep_bb = 1u64 << ep_sq;
@@ -154,19 +164,20 @@ pub fn generate_pawn_moves(board: &mut Board) -> Vec<Move>
 
         // Create a bitboard representing squares that pawns can go to by moving one square forward.
         // The square ahead must be free.
-        let singles = (bp >> 8) & empty;
+        let singles = (bp >> 8) & empty & target_mask;
         bitboard_to_moves(board, singles, -8, &mut moves, false);
 
         // Create a bitboard representing squares that pawns can go to by moving two squares
         // forward. The two squares ahead must be free, and the pawn must be on rank 7.
-        let doubles = ((bp & RANK_7) >> 16) & empty & (empty >> 8);
+        let double_ranks = if board.black_king == NO_SQUARE { RANK_7 | RANK_8 } else { RANK_7 };
+        let doubles = ((bp & double_ranks) >> 16) & empty & (empty >> 8) & target_mask;
         bitboard_to_moves(board, doubles, -16, &mut moves, false);
 
         // Create two bitboards representing squares that pawns can go to by capturing a white
         // piece. For each bitboard, the corresponding diagonal square must contain a white
         // piece.
-        let cap_sw = ((bp & !FILE_A) >> 9) & board.white_pieces;
-        let cap_se = ((bp & !FILE_H) >> 7) & board.white_pieces;
+        let cap_sw = ((bp & !FILE_A) >> 9) & board.white_pieces & target_mask;
+        let cap_se = ((bp & !FILE_H) >> 7) & board.white_pieces & target_mask;
         bitboard_to_moves(board, cap_sw, -9, &mut moves, false);
         bitboard_to_moves(board, cap_se, -7, &mut moves, false);
 
@@ -174,6 +185,7 @@ pub fn generate_pawn_moves(board: &mut Board) -> Vec<Move>
         // this ply.
         if let Some(ep_sq) = board.en_passant_target
             && ep_sq < 24
+            && target_mask & ((1u64 << ep_sq) | (1u64 << (ep_sq + 8))) != 0
         {
             // Get a bitboard representing the position of the target tile.
             let ep_bb = 1u64 << ep_sq;
@@ -193,6 +205,55 @@ pub fn generate_pawn_moves(board: &mut Board) -> Vec<Move>
     return moves;
 }
 
+// Tell whether the en passant capture of the side to move from 'from' to 'to' leaves its king
+// safe. The capturing pawn and the captured pawn leave the same rank at once, so the capture can
+// expose the king to a rook or a queen along that rank even though neither pawn is pinned alone.
+// The attacks on the king are computed with the occupancy after the capture, and the captured pawn
+// doesn't attack anymore.
+pub fn is_en_passant_legal(board: &Board, from: Index, to: Index) -> bool
+{
+    let captured = if board.white_to_play { to - 8 } else { to + 8 };
+    if board.king_bitboard(board.white_to_play) == 0
+    {
+        return true;
+    }
+    let occupancy = board.pieces ^ (1u64 << from) ^ (1u64 << captured) ^ (1u64 << to);
+
+    let (king, pawn_attackers) = if board.white_to_play
+    {
+        (board.white_king, white_king_pawn_mask(board.white_king))
+    }
+    else
+    {
+        (board.black_king, black_king_pawn_mask(board.black_king))
+    };
+    let (pawns, knights, bishops, rooks, queens) = if board.white_to_play
+    {
+        (
+            board.black_pawns,
+            board.black_knights,
+            board.black_bishops,
+            board.black_rooks,
+            board.black_queens,
+        )
+    }
+    else
+    {
+        (
+            board.white_pawns,
+            board.white_knights,
+            board.white_bishops,
+            board.white_rooks,
+            board.white_queens,
+        )
+    };
+
+    return pawns & !(1u64 << captured) & pawn_attackers == 0
+        && knights & knight_mask(king) == 0
+        && (rooks | queens) & rook_attacks_hq(king, occupancy) == 0
+        && (bishops | queens) & bishop_attacks_hq(king, occupancy) == 0;
+}
+
 // For a given move type (represented by a shift value), and a given destination bitboard,
 // this helper creates a move and adds it to a vector.
 fn bitboard_to_moves(
@@ -224,27 +285,27 @@ fn bitboard_to_moves(
             if enemy & to_mask != 0 { Some(get_piece_type_on_square(board, to)) } else { None };
 
         // Add the promoting moves if necessary.
-        if to > 55 || to < 8
+        if !(8 ..= 55).contains(&to)
         {
             // For each piece the pawn can promote to:
-            for p in vec![BISHOP, ROOK, KNIGHT, QUEEN].iter()
+            for p in [BISHOP, ROOK, KNIGHT, QUEEN].iter()
             {
                 // Create a promoting move.
                 let mv = Move {
                     start: from,
                     end: to,
+                    piece: PAWN,
                     context: MoveContext::Promotion(*p),
                     previous_ep_target: board.en_passant_target,
-                    previous_wqs: board.white_queen_side_castling_right,
-                    previous_wks: board.white_king_side_castling_right,
-                    previous_bqs: board.black_queen_side_castling_right,
-                    previous_bks: board.black_king_side_castling_right,
+                    previous_castling_rights: board.castling_rights,
+                    previous_halfmove_clock: board.halfmove_clock,
                     capture,
+                    exploded: board.explosion(from, to, capture.is_some()),
                 };
 
                 board.make_move(mv);
                 // Add the move only if the king is not in check.
-                if !is_king_attacked(&board, true)
+                if !is_king_attacked(board, true)
                 {
                     out.push(mv);
                 }
@@ -257,11 +318,14 @@ fn bitboard_to_moves(
             let mv = Move {
                 start: from,
                 end: to,
+                piece: PAWN,
                 context: if ep
                 {
                     MoveContext::EnPassant
                 }
-                else if shift == -16 || shift == 16
+                // Only the double steps from the second rank allow en passant captures, not those
+                // from the first rank in Horde.
+                else if (shift == 16 && from / 8 == 1) || (shift == -16 && from / 8 == 6)
                 {
                     MoveContext::DoubleStep
                 }
@@ -270,20 +334,31 @@ fn bitboard_to_moves(
                     MoveContext::None
                 },
                 previous_ep_target: board.en_passant_target,
-                previous_wqs: board.white_queen_side_castling_right,
-                previous_wks: board.white_king_side_castling_right,
-                previous_bqs: board.black_queen_side_castling_right,
-                previous_bks: board.black_king_side_castling_right,
+                previous_castling_rights: board.castling_rights,
+                previous_halfmove_clock: board.halfmove_clock,
                 capture,
+                exploded: board.explosion(from, to, ep || capture.is_some()),
             };
-            
-            board.make_move(mv);
-            // Add the move only if the king is not in check.
-            if !is_king_attacked(&board, true)
+
+            // En passant captures have their own legality check with the standard check rules,
+            // since they remove two pieces from the same rank.
+            if ep && !board.variant.changes_check_rules()
+            {
+                if is_en_passant_legal(board, from, to)
+                {
+                    out.push(mv);
+                }
+            }
+            else
             {
-                out.push(mv);
+                board.make_move(mv);
+                // Add the move only if the king is not in check.
+                if !is_king_attacked(board, true)
+                {
+                    out.push(mv);
+                }
+                board.unmake_move(mv);
             }
-            board.unmake_move(mv);
         }
         // Remove the last bit of the bitboard.
         bits &= bits - 1;
@@ -1,5 +1,7 @@
+use alloc::vec::Vec;
+
 use crate::{
-    Bitboard, Board, Move, MoveContext, get_piece_type_on_square, is_king_attacked, masks::*,
+    Bitboard, Board, Move, MoveContext, ROOK, get_piece_type_on_square, is_king_attacked, masks::*,
     piece::slider::*,
 };
 
@@ -40,7 +42,8 @@ pub fn generate_rook_attacks(board: &Board) -> Bitboard
 }
 
 // Create a vector containing moves that rooks on the board can make.
-pub fn generate_rook_moves_hq(board: &mut Board) -> Vec<Move>
+// Only moves ending on a square of 'target_mask' are generated.
+pub fn generate_rook_moves_hq(board: &mut Board, target_mask: Bitboard) -> Vec<Move>
 {
     let mut moves = Vec::new();
 
@@ -61,8 +64,8 @@ pub fn generate_rook_moves_hq(board: &mut Board) -> Vec<Move>
         // Get all pseudo-legals moves the rook can make.
         let attacks = rook_attacks_hq(from, occ);
 
-        // Forbid capturing friendly pieces.
-        let targets = attacks & !friendly;
+        // Forbid capturing friendly pieces, and keep only the requested target squares.
+        let targets = attacks & !friendly & target_mask;
 
         // Add a move for each target square.
         let mut t = targets;
@@ -76,12 +79,11 @@ pub fn generate_rook_moves_hq(board: &mut Board) -> Vec<Move>
             let mv = Move {
                 start: from,
                 end: to,
+                piece: ROOK,
                 context: MoveContext::None,
                 previous_ep_target: board.en_passant_target,
-                previous_wqs: board.white_queen_side_castling_right,
-                previous_wks: board.white_king_side_castling_right,
-                previous_bqs: board.black_queen_side_castling_right,
-                previous_bks: board.black_king_side_castling_right,
+                previous_castling_rights: board.castling_rights,
+                previous_halfmove_clock: board.halfmove_clock,
                 capture: if enemy & to_mask != 0
                 {
                     Some(get_piece_type_on_square(board, to))
@@ -90,11 +92,12 @@ pub fn generate_rook_moves_hq(board: &mut Board) -> Vec<Move>
                 {
                     None
                 },
+                exploded: board.explosion(from, to, enemy & to_mask != 0),
             };
-            
+
             board.make_move(mv);
             // Add the move only if the king is not in check.
-            if !is_king_attacked(&board, true)
+            if !is_king_attacked(board, true)
             {
                 moves.push(mv);
             }
@@ -114,3 +117,13 @@ pub fn rook_attacks_hq(sq: usize, occ: u64) -> u64
     let fm = file_mask(sq);
     return slider_attacks_hq(sq, occ, rm) | slider_attacks_hq(sq, occ, fm);
 }
+
+// Create a bitboard representing the squares a rook attacks through the pieces of 'blockers' it
+// attacks directly, up to the next piece behind them, which is included. The squares attacked
+// directly are not part of it.
+pub fn xray_rook_attacks(sq: usize, occ: u64, blockers: u64) -> u64
+{
+    let attacks = rook_attacks_hq(sq, occ);
+    let blockers = blockers & attacks;
+    return attacks ^ rook_attacks_hq(sq, occ ^ blockers);
+}
@@ -1,143 +1,592 @@
-use std::{collections::HashMap, io};
+use std::{
+    io,
+    time::{Duration, Instant},
+};
 
+use log::{debug, info};
 use rand::seq::IndexedRandom;
 
 use super::*;
 
-pub enum GameResult
+// How a game ended.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Termination
 {
-    White,
-    Black,
+    Checkmate,
     Stalemate,
+    // The same position occurred three times.
+    Repetition,
+    // Fifty moves of each side were played without a capture or a pawn move.
+    FiftyMove,
+    InsufficientMaterial,
+    Resignation,
+    // A side ran out of time.
+    Timeout,
+    // The scores reported by the strategies decided the result.
+    Adjudication,
+    // The game reached the maximum number of moves.
+    MoveLimit,
+    // A rule of the variant decided the game, described from the winner's side.
+    Variant(&'static str),
 }
 
-pub fn play(white_strategy_choice: &str, black_strategy_choice: &str)
--> Result<GameResult, String>
+// The result of a game: the winning color, or None for a draw, and the way it ended.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Outcome
 {
-    let white_strategy = match white_strategy_choice
+    pub winner: Option<Color>,
+    pub reason: Termination,
+}
+
+impl Outcome
+{
+    pub fn win(winner: Color, reason: Termination) -> Self
     {
-        "player" => player_strategy,
-        "random" => random_strategy,
-        "negamax" => negamax_strategy,
-        "alphabeta" => alpha_beta_strategy,
-        "alphabetaq" => alpha_beta_quiesce_strategy,
-        _ => return Err("The chosen white strategy is not valid.".into()),
-    };
-    let black_strategy = match black_strategy_choice
-    {
-        "player" => player_strategy,
-        "random" => random_strategy,
-        "negamax" => negamax_strategy,
-        "alphabeta" => alpha_beta_strategy,
-        "alphabetaq" => alpha_beta_quiesce_strategy,
-        _ => return Err("The chosen black strategy is not valid.".into()),
+        return Outcome { winner: Some(winner), reason };
+    }
+
+    pub fn draw(reason: Termination) -> Self
+    {
+        return Outcome { winner: None, reason };
+    }
+
+    // Get the result as written in PGN: "1-0", "0-1" or "1/2-1/2".
+    pub fn to_pgn(&self) -> &'static str
+    {
+        return match self.winner
+        {
+            Some(WHITE) => "1-0",
+            Some(_) => "0-1",
+            None => "1/2-1/2",
+        };
+    }
+
+    // Describe the outcome in a sentence, e.g. "White wins by checkmate."
+    pub fn description(&self) -> String
+    {
+        let reason = match self.reason
+        {
+            Termination::Checkmate => "by checkmate",
+            Termination::Stalemate => "by stalemate",
+            Termination::Repetition => "by threefold repetition",
+            Termination::FiftyMove => "by the fifty-move rule",
+            Termination::InsufficientMaterial => "by insufficient material",
+            Termination::Resignation => "by resignation",
+            Termination::Timeout => "on time",
+            Termination::Adjudication => "by adjudication",
+            Termination::MoveLimit => "by reaching the move limit",
+            Termination::Variant(reason) => reason,
+        };
+        return match self.winner
+        {
+            Some(WHITE) => format!("White wins {}.", reason),
+            Some(_) => format!("Black wins {}.", reason),
+            None => format!("The game is drawn {}.", reason),
+        };
+    }
+}
+
+// What a strategy decides to do when it is its turn.
+pub enum Decision
+{
+    // Play a move, with the score of the position for the side to move if the strategy has one.
+    Play(Move, Option<Score>),
+    // Take back the last move of each side.
+    TakeBack,
+    Resign,
+}
+
+// Time given to each side for the whole game, and added to its clock after each of its moves.
+#[derive(Debug, Clone, Copy)]
+pub struct TimeControl
+{
+    pub base: Duration,
+    pub increment: Duration,
+}
+
+// The time left to a side when it has to move.
+#[derive(Debug, Clone, Copy)]
+pub struct Clock
+{
+    pub remaining: Duration,
+    pub increment: Duration,
+}
+
+// A strategy chooses what to do in a position, given its clock if the game has a time control.
+// It returns None when there are no legal moves.
+pub type Strategy = fn(&mut Board, Option<Clock>) -> Option<Decision>;
+
+// Maximum depth of the searches limited by time.
+const TIMED_SEARCH_DEPTH: u8 = 64;
+
+// Depth of the search suggesting a move to a human player.
+const HINT_DEPTH: u8 = 4;
+
+// Number of full moves after which a game is stopped and counted as a draw.
+pub const MAX_FULL_MOVES: usize = 500;
+
+// A rule ending the game when the scores reported by both strategies stayed beyond a threshold
+// during their last 'moves' moves.
+#[derive(Debug, Clone, Copy)]
+pub struct ScoreRule
+{
+    pub score: Score,
+    pub moves: usize,
+}
+
+// Rules ending a game before it is over on the board.
+#[derive(Debug, Clone, Copy)]
+pub struct Adjudication
+{
+    // A side resigns when both strategies agree that its score is at most '-score'.
+    pub resign: Option<ScoreRule>,
+    // The game is drawn when both strategies agree that the score is within 'score' of 0.
+    pub draw: Option<ScoreRule>,
+    // Number of full moves after which the game is drawn.
+    pub max_moves: usize,
+}
+
+impl Default for Adjudication
+{
+    fn default() -> Self
+    {
+        return Adjudication { resign: None, draw: None, max_moves: MAX_FULL_MOVES };
+    }
+}
+
+impl Adjudication
+{
+    // Decide whether the game ends, given the scores reported for each move from white's
+    // perspective. Moves played without a score never end the game.
+    fn judge(&self, scores: &[Option<Score>]) -> Option<Outcome>
+    {
+        // Get the last scores, if every one of them is known.
+        let last_scores = |moves: usize| -> Option<Vec<Score>> {
+            if moves == 0 || scores.len() < 2 * moves
+            {
+                return None;
+            }
+            return scores[scores.len() - 2 * moves ..].iter().copied().collect();
+        };
+
+        if let Some(rule) = self.resign
+            && let Some(last) = last_scores(rule.moves)
+        {
+            if last.iter().all(|score| *score <= -rule.score)
+            {
+                return Some(Outcome::win(BLACK, Termination::Adjudication));
+            }
+            if last.iter().all(|score| *score >= rule.score)
+            {
+                return Some(Outcome::win(WHITE, Termination::Adjudication));
+            }
+        }
+        if let Some(rule) = self.draw
+            && let Some(last) = last_scores(rule.moves)
+            && last.iter().all(|score| score.abs() <= rule.score)
+        {
+            return Some(Outcome::draw(Termination::Adjudication));
+        }
+        return None;
+    }
+}
+
+// Settings of a game between two strategies.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GameOptions
+{
+    pub time_control: Option<TimeControl>,
+    pub adjudication: Adjudication,
+    // Print the board after each move.
+    pub verbose: bool,
+    // How the board is printed in verbose mode. In blindfold mode, the moves of the game are
+    // printed under the empty board.
+    pub display: DisplayOptions,
+}
+
+// Get a strategy from its name.
+pub fn get_strategy(name: &str) -> Option<Strategy>
+{
+    return match name
+    {
+        "player" => Some(player_strategy),
+        "random" => Some(random_strategy),
+        "negamax" => Some(negamax_strategy),
+        "alphabeta" => Some(alpha_beta_strategy),
+        "alphabetaq" => Some(alpha_beta_quiesce_strategy),
+        // Variants without a pruning technique, to measure its effect in tournaments.
+        "alphabetaq-norfp" => Some(no_reverse_futility_strategy),
+        "alphabetaq-noprobcut" => Some(no_probcut_strategy),
+        _ => None,
     };
-    match Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -")
+}
+
+// Play a game between two strategies from the initial position of a variant.
+// Return the outcome and the record of the game, with the strategies as players.
+pub fn play(
+    white_strategy_choice: &str,
+    black_strategy_choice: &str,
+    variant: Variant,
+    options: &GameOptions,
+) -> Result<(Outcome, GameRecord), String>
+{
+    let white_strategy =
+        get_strategy(white_strategy_choice).ok_or("The chosen white strategy is not valid.")?;
+    let black_strategy =
+        get_strategy(black_strategy_choice).ok_or("The chosen black strategy is not valid.")?;
+    let mut board = Board::from_fen_variant(variant.start_fen(), variant)?;
+    let (outcome, mut record) = play_game(&mut board, white_strategy, black_strategy, options);
+    record.set_tag("White", white_strategy_choice);
+    record.set_tag("Black", black_strategy_choice);
+    return Ok((outcome, record));
+}
+
+// Play a game from the given position until it ends or is adjudicated. With a time control, a
+// side that runs out of time loses.
+// Return the outcome and the record of the game, where each move has the score reported by the
+// strategy from white's perspective, the time the strategy took to choose it, and the clock of its
+// side if the game has a time control.
+pub fn play_game(
+    board: &mut Board,
+    white_strategy: Strategy,
+    black_strategy: Strategy,
+    options: &GameOptions,
+) -> (Outcome, GameRecord)
+{
+    let GameOptions { time_control, adjudication, verbose, display } = *options;
+    let mut outcome = Outcome::draw(Termination::MoveLimit);
+    let mut record = GameRecord::new(&board.to_fen(), board.variant);
+    // The score reported for each move of the record, from white's perspective.
+    let mut scores: Vec<Option<Score>> = vec![];
+    // The hash of each position of the game, to apply the repetition rule.
+    let mut hashes = vec![board.hash];
+    // The time left to white and to black.
+    let mut remaining = time_control.map(|tc| [tc.base, tc.base]);
+    if verbose
     {
-        Ok(mut board) =>
+        show_position(board, &record, &display);
+    }
+    let mut n = 0;
+    while n < adjudication.max_moves
+    {
+        let (strategy, side) =
+            if board.white_to_play { (white_strategy, "White") } else { (black_strategy, "Black") };
+        let side_index = if board.white_to_play { 0 } else { 1 };
+        let opponent = if board.white_to_play { BLACK } else { WHITE };
+        if verbose
         {
-            let mut game_result = GameResult::Stalemate;
-            board.display();
-            let mut n = 0;
-            while n < 500
+            println!("============================");
+        }
+
+        let clock = time_control.zip(remaining).map(|(tc, remaining)| Clock {
+            remaining: remaining[side_index],
+            increment: tc.increment,
+        });
+        let start = Instant::now();
+        let decision = strategy(board, clock);
+        let elapsed = start.elapsed();
+
+        // Charge the thinking time to the clock of the side to move.
+        if let (Some(tc), Some(remaining)) = (time_control, remaining.as_mut())
+        {
+            if elapsed > remaining[side_index]
             {
-                if board.white_to_play
+                if verbose
+                {
+                    println!("{} lost on time.", side);
+                }
+                outcome = Outcome::win(opponent, Termination::Timeout);
+                break;
+            }
+            remaining[side_index] -= elapsed;
+            if let Some(Decision::Play(..)) = decision
+            {
+                remaining[side_index] += tc.increment;
+            }
+        }
+
+        match decision
+        {
+            Some(Decision::Play(mv, score)) =>
+            {
+                let mv_name = mv.to_uci();
+                debug!(
+                    "{} plays {} in {:.3}s, score {}",
+                    side,
+                    mv_name,
+                    elapsed.as_secs_f64(),
+                    score.map(|score| score.to_string()).unwrap_or("-".into())
+                );
+                let white_score = if board.white_to_play { score } else { score.map(|s| -s) };
+                board.make_move(mv);
+                scores.push(white_score);
+                record.push(
+                    mv,
+                    Annotation {
+                        clock: remaining.map(|remaining| remaining[side_index]),
+                        elapsed: Some(elapsed),
+                        eval: white_score,
+                        ..Annotation::default()
+                    },
+                );
+                hashes.push(board.hash);
+                if verbose
                 {
-                    println!("============================");
-                    match white_strategy(&mut board)
+                    show_position(board, &record, &display);
+                    println!("{} played: {}", side, mv_name);
+                    if let Some(remaining) = remaining
                     {
-                        Some(mv) =>
-                        {
-                            let mv_name = mv.to_uci();
-                            board.make_move(mv);
-                            board.display();
-                            println!("White played: {}", mv_name);
-                        },
-                        None =>
-                        {
-                            if is_king_attacked(&board, false)
-                            {
-                                game_result = GameResult::Black;
-                            }
-                            break;
-                        },
+                        println!(
+                            "Clocks: white {:.1}s, black {:.1}s",
+                            remaining[0].as_secs_f64(),
+                            remaining[1].as_secs_f64()
+                        );
                     }
                 }
+                if board.white_to_play
+                {
+                    n += 1;
+                }
+
+                // Apply the draw rules, then the adjudication.
+                let draw_reason = if hashes.iter().filter(|hash| **hash == board.hash).count() >= 3
+                {
+                    Some(Termination::Repetition)
+                }
+                else if board.halfmove_clock >= FIFTY_MOVE_PLIES
+                {
+                    Some(Termination::FiftyMove)
+                }
+                else if board.has_insufficient_material()
+                {
+                    Some(Termination::InsufficientMaterial)
+                }
                 else
                 {
-                    println!("============================");
-                    match black_strategy(&mut board)
-                    {
-                        Some(mv) =>
-                        {
-                            let mv_name = mv.to_uci();
-                            board.make_move(mv);
-                            board.display();
-                            println!("Black played: {}", mv_name);
-                            n += 1;
-                        },
-                        None =>
-                        {
-                            if is_king_attacked(&board, false)
-                            {
-                                game_result = GameResult::White;
-                            }
-                            break;
-                        },
-                    }
+                    None
+                };
+                if let Some(reason) = draw_reason
+                {
+                    outcome = Outcome::draw(reason);
+                    break;
                 }
-            }
-            println!("The game ends after {} full moves.", n);
-            return Ok(game_result);
-        },
-        Err(err) => return Err(err),
+                if let Some(result) = adjudication.judge(&scores)
+                {
+                    outcome = result;
+                    break;
+                }
+            },
+            Some(Decision::TakeBack) =>
+            {
+                // Take back the last move of each side, so that the same side plays again.
+                if record.moves.len() < 2
+                {
+                    println!("There is no full move to take back.");
+                    continue;
+                }
+                for _ in 0 .. 2
+                {
+                    board.unmake_move(record.moves.pop().unwrap().mv);
+                    scores.pop();
+                    hashes.pop();
+                }
+                n -= 1;
+                debug!("{} takes back the last full move", side);
+                if verbose
+                {
+                    show_position(board, &record, &display);
+                    println!("{} took back the last full move.", side);
+                }
+            },
+            Some(Decision::Resign) =>
+            {
+                outcome = Outcome::win(opponent, Termination::Resignation);
+                break;
+            },
+            None =>
+            {
+                outcome = if let Some(reason) = board.variant_loss()
+                {
+                    Outcome::win(opponent, Termination::Variant(reason))
+                }
+                else if let Some(reason) = board.variant_win()
+                {
+                    let player = if board.white_to_play { WHITE } else { BLACK };
+                    Outcome::win(player, Termination::Variant(reason))
+                }
+                else if is_king_attacked(board, false)
+                {
+                    Outcome::win(opponent, Termination::Checkmate)
+                }
+                else
+                {
+                    Outcome::draw(Termination::Stalemate)
+                };
+                break;
+            },
+        }
     }
+    if verbose
+    {
+        println!("The game ends after {} full moves.", n);
+    }
+    info!("Game over after {} full moves: {}", n, outcome.description());
+    record.result = outcome.to_pgn().into();
+    return (outcome, record);
 }
 
-fn player_strategy(board: &mut Board) -> Option<Move>
+// Print the board of a game, with its moves in blindfold mode.
+fn show_position(board: &Board, record: &GameRecord, options: &DisplayOptions)
+{
+    let last_move = record.moves.last().map(|recorded| recorded.mv);
+    board.display_with(&DisplayOptions { last_move, ..*options });
+    if options.blindfold
+        && let Ok(start) = record.start_board()
+    {
+        let moves: Vec<Move> = record.moves.iter().map(|recorded| recorded.mv).collect();
+        println!("Moves: {}", format_movetext(&start, &moves, Notation::San, None));
+    }
+}
+
+const PLAYER_HELP: &str = "Enter a move in SAN (e.g. Nf3, exd5, O-O, e8=Q) or UCI (e.g. g1f3), or:
+  <square>  show where the piece on this square can go (e.g. e2)
+  moves     list the legal moves
+  hint      show the move recommended by the engine
+  takeback  take back the last full move (also 'undo')
+  resign    resign the game
+  help      print this message";
+
+fn player_strategy(board: &mut Board, clock: Option<Clock>) -> Option<Decision>
 {
     let moves = board.get_legal_moves();
-    if moves.len() == 0
+    if moves.is_empty()
     {
         return None;
     }
-    let mut dict = HashMap::new();
-    for m in moves.iter()
+    if let Some(clock) = clock
     {
-        dict.insert(m.to_uci(), m);
+        println!("You have {:.1}s left.", clock.remaining.as_secs_f64());
     }
-    let mut choice = String::new();
-    while !dict.contains_key(choice.as_str())
-    {
-        println!("Write a valid move name:");
-        choice.clear();
-        io::stdin()
-            .read_line(&mut choice)
-            .expect("Failed to read line");
-        choice = choice.trim().to_string();
+    println!("Your move (type 'help' for the commands):");
+    loop
+    {
+        let mut choice = String::new();
+        io::stdin().read_line(&mut choice).expect("Failed to read line");
+        let choice = choice.trim();
+
+        match choice
+        {
+            "help" => println!("{}", PLAYER_HELP),
+            "moves" =>
+            {
+                let names: Vec<String> =
+                    moves.iter().map(|mv| mv.to_san_with(board, &moves)).collect();
+                println!("Legal moves: {}", names.join(", "));
+            },
+            "hint" => show_hint(board),
+            "takeback" | "undo" => return Some(Decision::TakeBack),
+            "resign" => return Some(Decision::Resign),
+            _ =>
+            {
+                if let Some(mv) = parse_move(board, choice)
+                {
+                    return Some(Decision::Play(mv, None));
+                }
+                // Show the legal destinations of the piece on the given square.
+                else if let Some(sq) = parse_square(choice)
+                {
+                    if board.legal_destinations(sq) == 0
+                    {
+                        println!("There is no piece that can move on {}.", choice);
+                    }
+                    else
+                    {
+                        board.display_destinations(sq, &DisplayOptions::default());
+                    }
+                }
+                else if choice.is_empty()
+                {
+                    println!("Please enter a move, or 'help' for the commands.");
+                }
+                else
+                {
+                    println!(
+                        "'{}' is not a legal move in this position. Type 'moves' to list them.",
+                        choice
+                    );
+                }
+            },
+        }
+    }
+}
+
+// Search the position at a low depth and print the best move found, with its score.
+fn show_hint(board: &mut Board)
+{
+    let (score, result) = Search::new(SearchOptions::default()).search(board, HINT_DEPTH);
+    if let Some(mv) = result
+    {
+        println!("Hint: {} ({})", mv.to_san(board), score);
     }
-    return Some(**dict.get(&choice).unwrap());
 }
 
-fn random_strategy(board: &mut Board) -> Option<Move>
+fn random_strategy(board: &mut Board, _: Option<Clock>) -> Option<Decision>
 {
-    return board.get_legal_moves().choose(&mut rand::rng()).cloned();
+    // With a seed, the move chosen only depends on the position.
+    let mut rng = default_randomness().rng(board.hash);
+    let mv = board.get_legal_moves().choose(&mut rng).cloned();
+    return mv.map(|mv| Decision::Play(mv, None));
 }
 
-fn negamax_strategy(board: &mut Board) -> Option<Move>
+fn negamax_strategy(board: &mut Board, _: Option<Clock>) -> Option<Decision>
 {
-    let (_, result) = negamax(board, 4);
-    return result;
+    let (score, result) = negamax(board, 4);
+    return result.map(|mv| Decision::Play(mv, Some(score)));
 }
 
-fn alpha_beta_strategy(board: &mut Board) -> Option<Move>
+fn alpha_beta_strategy(board: &mut Board, _: Option<Clock>) -> Option<Decision>
 {
-    let (_, result) = launch_alpha_beta(board, 4);
-    return result;
+    let (score, result) = launch_alpha_beta(board, 4);
+    return result.map(|mv| Decision::Play(mv, Some(score)));
 }
 
-fn alpha_beta_quiesce_strategy(board: &mut Board) -> Option<Move>
+fn alpha_beta_quiesce_strategy(board: &mut Board, clock: Option<Clock>) -> Option<Decision>
 {
-    let (_, result) = launch_alpha_beta_quiesce(board, 4);
-    return result;
+    return search_strategy(board, clock, SearchOptions::default());
+}
+
+fn no_reverse_futility_strategy(board: &mut Board, clock: Option<Clock>) -> Option<Decision>
+{
+    let options = SearchOptions { reverse_futility: false, ..SearchOptions::default() };
+    return search_strategy(board, clock, options);
+}
+
+fn no_probcut_strategy(board: &mut Board, clock: Option<Clock>) -> Option<Decision>
+{
+    let options = SearchOptions { probcut: false, ..SearchOptions::default() };
+    return search_strategy(board, clock, options);
+}
+
+// Search at depth 4 with the given options.
+// With a clock, the search deepens until the time allocated to the move is used.
+fn search_strategy(
+    board: &mut Board,
+    clock: Option<Clock>,
+    mut options: SearchOptions,
+) -> Option<Decision>
+{
+    let mut depth = 4;
+    if let Some(clock) = clock
+    {
+        depth = TIMED_SEARCH_DEPTH;
+        let overhead = engine_config().move_overhead();
+        options.set_time_limits(TimeLimits::for_clock(
+            clock.remaining,
+            clock.increment,
+            None,
+            overhead,
+        ));
+    }
+    let (score, result) = Search::new(options).search(board, depth);
+    return result.map(|mv| Decision::Play(mv, Some(score)));
 }
@@ -0,0 +1,50 @@
+use std::sync::Mutex;
+
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+// Where the random choices of the engine come from: the random strategy, the book moves, the
+// openings of the data generation, the positions of the self-test and the opening variety of the
+// searches.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Randomness
+{
+    Random,
+    // Seed the generators with this number, so that the choices can be reproduced.
+    Seeded(u64),
+}
+
+static DEFAULT_RANDOMNESS: Mutex<Randomness> = Mutex::new(Randomness::Random);
+
+pub fn set_default_randomness(randomness: Randomness)
+{
+    *DEFAULT_RANDOMNESS.lock().unwrap() = randomness;
+}
+
+pub fn default_randomness() -> Randomness
+{
+    return *DEFAULT_RANDOMNESS.lock().unwrap();
+}
+
+impl Randomness
+{
+    // Get a generator for random choices. With a seed, the choices of each 'stream' are always the
+    // same, and different from the other streams, e.g. the games of a data generation.
+    pub fn rng(self, stream: u64) -> StdRng
+    {
+        return match self
+        {
+            Randomness::Seeded(seed) => StdRng::seed_from_u64(seed.wrapping_add(stream)),
+            Randomness::Random => StdRng::from_rng(&mut rand::rng()),
+        };
+    }
+
+    // Get the seed, or a random one without it.
+    pub fn seed(self) -> u64
+    {
+        return match self
+        {
+            Randomness::Seeded(seed) => seed,
+            Randomness::Random => rand::rng().random(),
+        };
+    }
+}
@@ -0,0 +1,643 @@
+use std::{collections::BTreeMap, fs, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Board, Move, Notation, Score, ScoreKind, Variant, number_moves, parse_move};
+
+// The tags every PGN game starts with, written in this order, with their value when unknown.
+// The result is written after them.
+const SEVEN_TAG_ROSTER: [(&str, &str); 6] = [
+    ("Event", "?"),
+    ("Site", "?"),
+    ("Date", "????.??.??"),
+    ("Round", "?"),
+    ("White", "?"),
+    ("Black", "?"),
+];
+
+// Tags computed from the other fields of a record, so they are not kept with the other tags.
+const COMPUTED_TAGS: [&str; 4] = ["Result", "Variant", "SetUp", "FEN"];
+
+// Suffixes of the moves in PGN, with the numeric annotation glyph they stand for.
+const SUFFIX_NAGS: [(&str, u8); 6] =
+    [("!!", 3), ("??", 4), ("!?", 5), ("?!", 6), ("!", 1), ("?", 2)];
+
+// A game with the annotations of its moves, read from and written to PGN or JSON.
+#[derive(Debug, PartialEq, Clone)]
+pub struct GameRecord
+{
+    // The PGN tags, such as "White" or "Event", without the ones computed from the other fields.
+    pub tags: BTreeMap<String, String>,
+    pub variant: Variant,
+    // The position the game starts from.
+    pub fen: String,
+    pub moves: Vec<RecordedMove>,
+    // The result as written in PGN: "1-0", "0-1", "1/2-1/2", or "*" while the game goes on.
+    pub result: String,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RecordedMove
+{
+    pub mv: Move,
+    pub annotation: Annotation,
+}
+
+// What is known about a move besides the move itself. Every field is optional.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub struct Annotation
+{
+    // Time left to the side that played the move, after it.
+    pub clock: Option<Duration>,
+    // Time the side that played the move spent thinking about it.
+    pub elapsed: Option<Duration>,
+    // Score of the position after the move, from white's perspective.
+    pub eval: Option<Score>,
+    pub comment: Option<String>,
+    // Numeric annotation glyphs, e.g. 1 for a good move ("!") and 4 for a blunder ("??").
+    pub nags: Vec<u8>,
+}
+
+// The JSON form of a record. The moves are written in SAN and in UCI, and read from UCI when it
+// is given, or from SAN otherwise.
+#[derive(Serialize, Deserialize)]
+struct JsonRecord
+{
+    #[serde(default)]
+    tags: BTreeMap<String, String>,
+    #[serde(default = "default_variant_name")]
+    variant: String,
+    fen: Option<String>,
+    #[serde(default)]
+    moves: Vec<JsonMove>,
+    #[serde(default = "default_result")]
+    result: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct JsonMove
+{
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    san: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    uci: Option<String>,
+    // Seconds left on the clock.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    clock: Option<f64>,
+    // Seconds spent thinking about the move.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    elapsed: Option<f64>,
+    // Written like in PGN: "0.35" in pawns, or "#-3" when black mates in 3 moves.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    eval: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    nags: Vec<u8>,
+}
+
+fn default_variant_name() -> String
+{
+    return Variant::Standard.name().into();
+}
+
+fn default_result() -> String
+{
+    return "*".into();
+}
+
+impl GameRecord
+{
+    // Create a record of a game starting from a position, without moves.
+    pub fn new(fen: &str, variant: Variant) -> Self
+    {
+        return GameRecord {
+            tags: BTreeMap::new(),
+            variant,
+            fen: fen.into(),
+            moves: vec![],
+            result: default_result(),
+        };
+    }
+
+    pub fn set_tag(&mut self, name: &str, value: &str)
+    {
+        self.tags.insert(name.into(), value.into());
+    }
+
+    pub fn push(&mut self, mv: Move, annotation: Annotation)
+    {
+        self.moves.push(RecordedMove { mv, annotation });
+    }
+
+    // Get the position the game starts from.
+    pub fn start_board(&self) -> Result<Board, String>
+    {
+        return Board::from_fen_variant(&self.fen, self.variant);
+    }
+
+    // Get the position after the moves of the game.
+    pub fn end_board(&self) -> Result<Board, String>
+    {
+        let mut board = self.start_board()?;
+        for recorded in self.moves.iter()
+        {
+            board.make_move(recorded.mv);
+        }
+        return Ok(board);
+    }
+
+    // Get the total time white and black spent thinking about their moves, as far as it is known.
+    pub fn think_times(&self) -> Result<[Duration; 2], String>
+    {
+        let mut white_to_play = self.start_board()?.white_to_play;
+        let mut times = [Duration::ZERO; 2];
+        for recorded in self.moves.iter()
+        {
+            times[if white_to_play { 0 } else { 1 }] +=
+                recorded.annotation.elapsed.unwrap_or_default();
+            white_to_play = !white_to_play;
+        }
+        return Ok(times);
+    }
+
+    // Read a record from a file, in JSON if its name ends with ".json" and in PGN otherwise.
+    // Only the first game of a PGN file is read.
+    pub fn load(path: &str) -> Result<GameRecord, String>
+    {
+        let text =
+            fs::read_to_string(path).map_err(|err| format!("Can't read '{}': {}", path, err))?;
+        return if path.ends_with(".json")
+        {
+            GameRecord::from_json(&text)
+        }
+        else
+        {
+            GameRecord::from_pgn(&text)
+        };
+    }
+
+    // Write the record to a file, in JSON if its name ends with ".json" and in PGN otherwise.
+    pub fn save(&self, path: &str) -> Result<(), String>
+    {
+        return self.save_with(path, Notation::San);
+    }
+
+    // Write the record to a file like 'save', with the moves of the PGN in the given notation.
+    // The JSON always has the moves in SAN and in UCI.
+    pub fn save_with(&self, path: &str, notation: Notation) -> Result<(), String>
+    {
+        let text =
+            if path.ends_with(".json") { self.to_json()? } else { self.to_pgn_with(notation)? };
+        return fs::write(path, text).map_err(|err| format!("Can't write '{}': {}", path, err));
+    }
+
+    // Write the game in PGN, with its moves in SAN. The clocks, the times spent on the moves and
+    // the evaluations are written in the comments as "[%clk 0:01:30]", "[%emt 0:00:04.2]" and
+    // "[%eval 0.35]", like the usual chess servers do.
+    pub fn to_pgn(&self) -> Result<String, String>
+    {
+        return self.to_pgn_with(Notation::San);
+    }
+
+    // Write the game in PGN like 'to_pgn', with its moves in the given notation. The PGN standard
+    // only allows SAN, but some correspondence and GUI tools expect the other notations.
+    pub fn to_pgn_with(&self, notation: Notation) -> Result<String, String>
+    {
+        let mut pgn = String::new();
+        for (name, unknown) in SEVEN_TAG_ROSTER
+        {
+            let value = self.tags.get(name).map(String::as_str).unwrap_or(unknown);
+            pgn.push_str(&format!("[{} \"{}\"]\n", name, escape_tag(value)));
+        }
+        pgn.push_str(&format!("[Result \"{}\"]\n", self.result));
+        for (name, value) in self.tags.iter()
+        {
+            if !SEVEN_TAG_ROSTER.iter().any(|(roster_name, _)| roster_name == name)
+            {
+                pgn.push_str(&format!("[{} \"{}\"]\n", name, escape_tag(value)));
+            }
+        }
+        if self.variant != Variant::Standard
+        {
+            pgn.push_str(&format!("[Variant \"{}\"]\n", self.variant.name()));
+        }
+        // The FEN is written unless the game starts from the initial position of the variant.
+        let board = self.start_board()?;
+        if board.to_fen()
+            != Board::from_fen_variant(self.variant.start_fen(), self.variant)?.to_fen()
+        {
+            pgn.push_str(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", self.fen));
+        }
+        pgn.push('\n');
+
+        // Number the moves from 1, starting with "1..." when black plays first. Black's move number
+        // is repeated after a comment.
+        let moves: Vec<Move> = self.moves.iter().map(|recorded| recorded.mv).collect();
+        let mut movetext = vec![];
+        let mut needs_number = true;
+        for (numbered, recorded) in
+            number_moves(&board, &moves, notation).into_iter().zip(&self.moves)
+        {
+            if numbered.white || needs_number
+            {
+                movetext.push(numbered.number_text());
+            }
+            movetext.push(numbered.text);
+
+            let annotation = &recorded.annotation;
+            movetext.extend(annotation.nags.iter().map(|nag| format!("${}", nag)));
+            let comment = annotation.to_pgn_comment();
+            needs_number = comment.is_some();
+            movetext.extend(comment);
+        }
+        movetext.push(self.result.clone());
+
+        // Wrap the movetext at 80 columns.
+        let mut line = String::new();
+        for token in movetext
+        {
+            if !line.is_empty() && line.len() + token.len() + 1 > 80
+            {
+                pgn.push_str(&line);
+                pgn.push('\n');
+                line.clear();
+            }
+            if !line.is_empty()
+            {
+                line.push(' ');
+            }
+            line.push_str(&token);
+        }
+        pgn.push_str(&line);
+        pgn.push_str("\n\n");
+        return Ok(pgn);
+    }
+
+    // Read the first game of a PGN text. The variations are skipped, and the suffixes of the moves
+    // such as "!?" are read as numeric annotation glyphs. The moves can also be written in LAN, in
+    // UCI or in ICCF numeric notation.
+    pub fn from_pgn(text: &str) -> Result<GameRecord, String>
+    {
+        let mut tags = BTreeMap::new();
+        let mut lines = text.lines().map(str::trim).skip_while(|line| line.is_empty()).peekable();
+        while let Some(line) = lines.next_if(|line| line.starts_with('[') || line.is_empty())
+        {
+            if line.is_empty()
+            {
+                continue;
+            }
+            let (name, value) = parse_tag(line).ok_or(format!("Invalid PGN tag '{}'.", line))?;
+            tags.insert(name, value);
+        }
+
+        let variant = match tags.get("Variant").map(String::as_str)
+        {
+            None | Some("Standard") | Some("Chess") | Some("From Position") => Variant::Standard,
+            Some(name) => Variant::from_name(&name.replace([' ', '-'], ""))?,
+        };
+        let fen = tags.get("FEN").cloned().unwrap_or(variant.start_fen().into());
+        let mut record = GameRecord::new(&fen, variant);
+        let mut board = record.start_board()?;
+
+        // Only the text until the tags of the next game is read.
+        let mut movetext = String::new();
+        for line in lines
+        {
+            if parse_tag(line).is_some()
+            {
+                break;
+            }
+            movetext.push_str(line);
+            movetext.push('\n');
+        }
+
+        let mut chars = movetext.chars().peekable();
+        while let Some(c) = chars.next()
+        {
+            match c
+            {
+                '{' | ';' =>
+                {
+                    let end = if c == '{' { '}' } else { '\n' };
+                    let comment: String = chars.by_ref().take_while(|c| *c != end).collect();
+                    if let Some(last) = record.moves.last_mut()
+                    {
+                        last.annotation.add_pgn_comment(&comment);
+                    }
+                },
+                '(' =>
+                {
+                    // Skip the variation, with the variations it contains.
+                    let mut depth = 1;
+                    while depth > 0
+                    {
+                        match chars.next()
+                        {
+                            Some('(') => depth += 1,
+                            Some(')') => depth -= 1,
+                            Some(_) => (),
+                            None => return Err("Unclosed variation in the PGN.".into()),
+                        }
+                    }
+                },
+                _ if c.is_whitespace() => (),
+                _ =>
+                {
+                    let mut token = String::from(c);
+                    while let Some(c) =
+                        chars.next_if(|c| !c.is_whitespace() && !"{}();".contains(*c))
+                    {
+                        token.push(c);
+                    }
+                    if let Some(nag) = token.strip_prefix('$')
+                    {
+                        let nag = nag.parse().map_err(|_| format!("Invalid NAG '{}'.", token))?;
+                        if let Some(last) = record.moves.last_mut()
+                        {
+                            last.annotation.nags.push(nag);
+                        }
+                        continue;
+                    }
+                    if ["1-0", "0-1", "1/2-1/2", "*"].contains(&token.as_str())
+                    {
+                        record.result = token;
+                        break;
+                    }
+
+                    // Remove the move number, e.g. "12." or "12...", which can precede the move.
+                    // The digits are only a move number when dots follow them, since the moves in
+                    // ICCF numeric notation are digits too.
+                    let after_digits = token.trim_start_matches(|c: char| c.is_ascii_digit());
+                    let san = if after_digits.starts_with('.')
+                    {
+                        after_digits.trim_start_matches('.')
+                    }
+                    else
+                    {
+                        token.as_str()
+                    };
+                    if san.is_empty()
+                    {
+                        continue;
+                    }
+                    let (san, suffix_nag) = split_suffix(san);
+                    let mv = parse_move(&mut board, san)
+                        .ok_or(format!("Illegal move '{}' in the PGN.", token))?;
+                    board.make_move(mv);
+                    let mut annotation = Annotation::default();
+                    annotation.nags.extend(suffix_nag);
+                    record.push(mv, annotation);
+                },
+            }
+        }
+
+        // The unknown values of the seven tag roster are written back by default.
+        for (name, value) in tags
+        {
+            let unknown = SEVEN_TAG_ROSTER.contains(&(name.as_str(), value.as_str()));
+            if !COMPUTED_TAGS.contains(&name.as_str()) && !unknown
+            {
+                record.tags.insert(name, value);
+            }
+        }
+        return Ok(record);
+    }
+
+    pub fn to_json(&self) -> Result<String, String>
+    {
+        let mut board = self.start_board()?;
+        let mut moves = vec![];
+        for recorded in self.moves.iter()
+        {
+            let annotation = &recorded.annotation;
+            moves.push(JsonMove {
+                san: Some(recorded.mv.to_san(&mut board)),
+                uci: Some(recorded.mv.to_uci()),
+                clock: annotation.clock.map(|clock| clock.as_secs_f64()),
+                elapsed: annotation.elapsed.map(|elapsed| elapsed.as_secs_f64()),
+                eval: annotation.eval.map(format_eval),
+                comment: annotation.comment.clone(),
+                nags: annotation.nags.clone(),
+            });
+            board.make_move(recorded.mv);
+        }
+        let json = JsonRecord {
+            tags: self.tags.clone(),
+            variant: self.variant.name().into(),
+            fen: Some(self.fen.clone()),
+            moves,
+            result: self.result.clone(),
+        };
+        return serde_json::to_string_pretty(&json).map_err(|err| err.to_string());
+    }
+
+    pub fn from_json(text: &str) -> Result<GameRecord, String>
+    {
+        let json: JsonRecord =
+            serde_json::from_str(text).map_err(|err| format!("Invalid game record: {}", err))?;
+        let variant = Variant::from_name(&json.variant)?;
+        let fen = json.fen.unwrap_or(variant.start_fen().into());
+        let mut record = GameRecord::new(&fen, variant);
+        record.tags = json.tags;
+        record.result = json.result;
+
+        let mut board = record.start_board()?;
+        for json_move in json.moves
+        {
+            let text = json_move
+                .uci
+                .or(json_move.san)
+                .ok_or("A move of the game record has neither SAN nor UCI.")?;
+            let mv = parse_move(&mut board, &text)
+                .ok_or(format!("Illegal move '{}' in the game record.", text))?;
+            board.make_move(mv);
+            let eval = match json_move.eval
+            {
+                Some(eval) => Some(parse_eval(&eval).ok_or(format!("Invalid eval '{}'.", eval))?),
+                None => None,
+            };
+            let [clock, elapsed] = [json_move.clock, json_move.elapsed].map(|seconds| {
+                return seconds
+                    .map(|seconds| {
+                        Duration::try_from_secs_f64(seconds)
+                            .map_err(|_| format!("Invalid time '{}'.", seconds))
+                    })
+                    .transpose();
+            });
+            record.push(
+                mv,
+                Annotation {
+                    clock: clock?,
+                    elapsed: elapsed?,
+                    eval,
+                    comment: json_move.comment,
+                    nags: json_move.nags,
+                },
+            );
+        }
+        return Ok(record);
+    }
+}
+
+impl Annotation
+{
+    // Write the comment following the move in PGN, with the evaluation, the clock and the time
+    // spent on the move first.
+    fn to_pgn_comment(&self) -> Option<String>
+    {
+        let mut parts = vec![];
+        if let Some(eval) = self.eval
+        {
+            parts.push(format!("[%eval {}]", format_eval(eval)));
+        }
+        if let Some(clock) = self.clock
+        {
+            parts.push(format!("[%clk {}]", format_clock(clock)));
+        }
+        if let Some(elapsed) = self.elapsed
+        {
+            parts.push(format!("[%emt {}]", format_clock(elapsed)));
+        }
+        if let Some(comment) = &self.comment
+        {
+            // A comment ends at the first closing brace.
+            parts.push(comment.replace('}', ")"));
+        }
+        if parts.is_empty()
+        {
+            return None;
+        }
+        return Some(format!("{{{}}}", parts.join(" ")));
+    }
+
+    // Read a PGN comment of the move, taking the evaluation, the clock and the time spent on the
+    // move out of the text. The commands other than "%eval", "%clk" and "%emt" are kept in the
+    // comment.
+    fn add_pgn_comment(&mut self, comment: &str)
+    {
+        let mut text = String::new();
+        let mut rest = comment;
+        while let Some(start) = rest.find("[%")
+        {
+            let Some(length) = rest[start ..].find(']')
+            else
+            {
+                break;
+            };
+            let command = &rest[start + 2 .. start + length];
+            let (name, value) = command.split_once(' ').unwrap_or((command, ""));
+            text.push_str(&rest[.. start]);
+            match name
+            {
+                "eval" if parse_eval(value.trim()).is_some() =>
+                {
+                    self.eval = parse_eval(value.trim())
+                },
+                "clk" if parse_clock(value.trim()).is_some() =>
+                {
+                    self.clock = parse_clock(value.trim())
+                },
+                "emt" if parse_clock(value.trim()).is_some() =>
+                {
+                    self.elapsed = parse_clock(value.trim())
+                },
+                _ => text.push_str(&rest[start .. start + length + 1]),
+            }
+            rest = &rest[start + length + 1 ..];
+        }
+        text.push_str(rest);
+
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !text.is_empty()
+        {
+            self.comment = Some(match self.comment.take()
+            {
+                Some(comment) => format!("{} {}", comment, text),
+                None => text,
+            });
+        }
+    }
+}
+
+// Write an evaluation like in PGN: in pawns with 2 decimals, or "#n" when white mates in 'n'
+// moves and "#-n" when black does.
+pub fn format_eval(eval: Score) -> String
+{
+    return match eval.kind()
+    {
+        ScoreKind::Mate(moves) => format!("#{}", moves),
+        ScoreKind::Centipawns(centipawns) => format!("{:.2}", centipawns as f64 / 100.0),
+    };
+}
+
+// Read an evaluation written by 'format_eval'.
+pub fn parse_eval(text: &str) -> Option<Score>
+{
+    if let Some(moves) = text.strip_prefix('#')
+    {
+        let moves: i32 = moves.parse().ok()?;
+        let plies = u8::try_from(2 * moves.unsigned_abs()).ok()?;
+        return match moves
+        {
+            1 .. => Some(Score::mate_in(plies - 1)),
+            .. 0 => Some(Score::mated_in(plies)),
+            0 => None,
+        };
+    }
+    let pawns: f64 = text.parse().ok()?;
+    return pawns.is_finite().then(|| Score((pawns * 100.0).round() as i32));
+}
+
+// Write a clock as "h:mm:ss", with the fraction of seconds when there is one, up to milliseconds so
+// that the time spent on fast moves is kept.
+fn format_clock(clock: Duration) -> String
+{
+    let millis = clock.as_millis();
+    let seconds = millis / 1000;
+    let mut text = format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60);
+    if !millis.is_multiple_of(1000)
+    {
+        text.push_str(format!(".{:03}", millis % 1000).trim_end_matches('0'));
+    }
+    return text;
+}
+
+// Read a clock written as "h:mm:ss", "mm:ss" or "ss", with an optional fraction of seconds.
+fn parse_clock(text: &str) -> Option<Duration>
+{
+    let mut seconds = 0.0;
+    for part in text.split(':')
+    {
+        let value: f64 = part.parse().ok()?;
+        seconds = seconds * 60.0 + value;
+    }
+    return Duration::try_from_secs_f64(seconds).ok();
+}
+
+// Read a tag pair, e.g. '[White "Carlsen, Magnus"]'.
+fn parse_tag(line: &str) -> Option<(String, String)>
+{
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (name, value) = inner.split_once(' ')?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    return Some((name.into(), value.replace("\\\"", "\"").replace("\\\\", "\\")));
+}
+
+fn escape_tag(value: &str) -> String
+{
+    return value.replace('\\', "\\\\").replace('"', "\\\"");
+}
+
+// Split the suffix annotating a move, e.g. "!?", from its SAN.
+fn split_suffix(san: &str) -> (&str, Option<u8>)
+{
+    for (suffix, nag) in SUFFIX_NAGS
+    {
+        if let Some(san) = san.strip_suffix(suffix)
+        {
+            return (san, Some(nag));
+        }
+    }
+    return (san, None);
+}
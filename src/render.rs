@@ -0,0 +1,180 @@
+use crate::{
+    BISHOP, Board, DisplayOptions, KING, KNIGHT, PAWN, QUEEN, ROOK, get_piece_type_on_square,
+    is_king_attacked,
+};
+
+// Size of a square of the image, in pixels.
+const SQUARE_SIZE: usize = 60;
+// Size of the border holding the coordinates.
+const BORDER_SIZE: usize = 20;
+
+const LIGHT_SQUARE_COLOR: &str = "#f0d9b5";
+const DARK_SQUARE_COLOR: &str = "#b58863";
+const LAST_MOVE_COLOR: &str = "#cdd26a";
+const CHECK_COLOR: &str = "#e05050";
+const MARKED_COLOR: &str = "#3a7d44";
+const BORDER_COLOR: &str = "#404040";
+
+impl Board
+{
+    // Draw the board as an SVG image. The squares of the last move, the king in check and the
+    // marked squares are highlighted, the coordinates are drawn around the board if requested,
+    // and the board is seen from black's side if flipped.
+    // The pieces are drawn with the chess glyphs of the font, so the 'unicode' and 'colors'
+    // options are not used.
+    pub fn to_svg(&self, options: &DisplayOptions) -> String
+    {
+        let border = if options.coordinates { BORDER_SIZE } else { 0 };
+        let size = 8 * SQUARE_SIZE + 2 * border;
+        let checked_king = if !options.blindfold && is_king_attacked(self, false)
+        {
+            Some(if self.white_to_play { self.white_king } else { self.black_king })
+        }
+        else
+        {
+            None
+        };
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{0}\" height=\"{0}\" \
+             viewBox=\"0 0 {0} {0}\">\n",
+            size
+        );
+        if options.coordinates
+        {
+            svg.push_str(&format!(
+                "<rect width=\"{0}\" height=\"{0}\" fill=\"{1}\"/>\n",
+                size, BORDER_COLOR
+            ));
+        }
+
+        for sq in 0 .. 64
+        {
+            let (rank, file) = (sq / 8, sq % 8);
+            // Position of the square in the image, from the top left corner.
+            let (column, row) = if options.flipped { (7 - file, rank) } else { (file, 7 - rank) };
+            let x = border + column * SQUARE_SIZE;
+            let y = border + row * SQUARE_SIZE;
+
+            let color = if checked_king == Some(sq)
+            {
+                CHECK_COLOR
+            }
+            else if options.last_move.is_some_and(|mv| mv.start == sq || mv.end == sq)
+            {
+                LAST_MOVE_COLOR
+            }
+            else if (rank + file) % 2 == 0
+            {
+                DARK_SQUARE_COLOR
+            }
+            else
+            {
+                LIGHT_SQUARE_COLOR
+            };
+            svg.push_str(&format!(
+                "<rect x=\"{}\" y=\"{}\" width=\"{2}\" height=\"{2}\" fill=\"{3}\"/>\n",
+                x, y, SQUARE_SIZE, color
+            ));
+
+            if let Some(glyph) = self.svg_glyph(sq).filter(|_| !options.blindfold)
+            {
+                svg.push_str(&glyph_element(glyph, x, y, self.white_pieces & (1u64 << sq) != 0));
+            }
+
+            // Marked squares get a dot in their center.
+            if options.marked & (1u64 << sq) != 0
+            {
+                svg.push_str(&format!(
+                    "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"{}\" fill-opacity=\"0.6\"/>\n",
+                    x + SQUARE_SIZE / 2,
+                    y + SQUARE_SIZE / 2,
+                    SQUARE_SIZE / 6,
+                    MARKED_COLOR
+                ));
+            }
+        }
+
+        if options.coordinates
+        {
+            for i in 0 .. 8
+            {
+                let file = if options.flipped { 7 - i } else { i };
+                let rank = if options.flipped { i } else { 7 - i };
+                let center = border + i * SQUARE_SIZE + SQUARE_SIZE / 2;
+                svg.push_str(&coordinate_element(
+                    (b'a' + file as u8) as char,
+                    center,
+                    size - border / 2,
+                ));
+                svg.push_str(&coordinate_element((b'1' + rank as u8) as char, border / 2, center));
+            }
+        }
+
+        svg.push_str("</svg>\n");
+        return svg;
+    }
+
+    // Get the glyph of the piece on a square, if there is one.
+    // The same filled glyph is used for both colors, and white pieces are filled in white.
+    fn svg_glyph(&self, sq: usize) -> Option<char>
+    {
+        return match get_piece_type_on_square(self, sq)
+        {
+            PAWN => Some('♟'),
+            ROOK => Some('♜'),
+            KNIGHT => Some('♞'),
+            BISHOP => Some('♝'),
+            QUEEN => Some('♛'),
+            KING => Some('♚'),
+            _ => None,
+        };
+    }
+}
+
+// Create the text element of a piece glyph, in the square whose top left corner is (x, y).
+fn glyph_element(glyph: char, x: usize, y: usize, white: bool) -> String
+{
+    let (fill, stroke) = if white { ("#ffffff", "#000000") } else { ("#000000", "#000000") };
+    return format!(
+        "<text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"middle\" \
+         dominant-baseline=\"central\" font-family=\"DejaVu Sans, sans-serif\" fill=\"{}\" \
+         stroke=\"{}\" stroke-width=\"1.5\">{}</text>\n",
+        x + SQUARE_SIZE / 2,
+        y + SQUARE_SIZE / 2,
+        SQUARE_SIZE * 4 / 5,
+        fill,
+        stroke,
+        glyph
+    );
+}
+
+// Create the text element of a file letter or a rank number, centered on (x, y).
+fn coordinate_element(label: char, x: usize, y: usize) -> String
+{
+    return format!(
+        "<text x=\"{}\" y=\"{}\" font-size=\"{}\" text-anchor=\"middle\" \
+         dominant-baseline=\"central\" font-family=\"DejaVu Sans, sans-serif\" \
+         fill=\"#ffffff\">{}</text>\n",
+        x,
+        y,
+        BORDER_SIZE * 3 / 5,
+        label
+    );
+}
+
+// Convert an SVG image to PNG, using the fonts installed on the system for the glyphs.
+#[cfg(feature = "png")]
+pub fn svg_to_png(svg: &str) -> Result<Vec<u8>, String>
+{
+    use resvg::{tiny_skia, usvg};
+
+    let mut options = usvg::Options::default();
+    options.fontdb_mut().load_system_fonts();
+    let tree = usvg::Tree::from_str(svg, &options).map_err(|err| err.to_string())?;
+    let size = tree.size().to_int_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or("The image has an invalid size.")?;
+    resvg::render(&tree, tiny_skia::Transform::default(), &mut pixmap.as_mut());
+    return pixmap.encode_png().map_err(|err| err.to_string());
+}
@@ -0,0 +1,211 @@
+use std::io::{self, BufRead, Write};
+
+use crate::*;
+
+// Depth searched when the 'go' command doesn't give one.
+const DEFAULT_DEPTH: u8 = 5;
+
+const HELP: &str = "Commands:
+  position startpos | position <fen>   set up a position
+  display                              print the board
+  moves [<square>]                     list the legal moves, or show those of a piece
+  move <uci>                           play a move (e.g. e2e4, e7e8q)
+  undo                                 take back the last move
+  history                              list the moves played since the position was set up
+  flip                                 mirror the position and swap the colors
+  toggle <option>                      switch a display option on or off: unicode, coordinates,
+                                       flipped (black's perspective), colors, blindfold (hide
+                                       the pieces and print the moves instead)
+  eval                                 print the static evaluation, term by term
+  go [depth <n>]                       search the position
+  perft <n>                            count the positions after n plies, per move
+  help                                 print this message
+  quit                                 leave";
+
+// State kept between the commands of a REPL session.
+struct ReplSession
+{
+    board: Board,
+    history: Vec<Move>,
+    search: Search,
+    display_options: DisplayOptions,
+}
+
+impl ReplSession
+{
+    // Print the board with the display options of the session, highlighting the last move.
+    fn display(&self)
+    {
+        let options =
+            DisplayOptions { last_move: self.history.last().copied(), ..self.display_options };
+        self.board.display_with(&options);
+        if options.blindfold
+        {
+            println!("Moves: {}", self.movetext());
+        }
+    }
+
+    // Get the moves played since the position was set up, numbered like in PGN.
+    fn movetext(&self) -> String
+    {
+        // Replay the moves from the position they were played from.
+        let mut start = self.board;
+        for mv in self.history.iter().rev()
+        {
+            start.unmake_move(*mv);
+        }
+        return format_movetext(&start, &self.history, Notation::San, None);
+    }
+}
+
+// Read commands from the standard input to set up positions, play and take back moves, and run
+// searches or perft on the current position, until 'quit' is received.
+pub fn repl()
+{
+    let mut session = ReplSession {
+        board: Board::new().expect("The starting position is valid."),
+        history: vec![],
+        search: Search::default(),
+        display_options: DisplayOptions::default(),
+    };
+    session.display();
+
+    let mut lines = io::stdin().lock().lines();
+    loop
+    {
+        print!("> ");
+        io::stdout().flush().expect("Failed to flush stdout");
+        let Some(Ok(line)) = lines.next()
+        else
+        {
+            break;
+        };
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.first()
+        {
+            Some(&"quit") | Some(&"exit") => break,
+            Some(command) =>
+            {
+                if let Err(err) = run_command(&mut session, command, &tokens[1 ..])
+                {
+                    println!("{}", err);
+                }
+            },
+            None => (),
+        }
+    }
+}
+
+fn run_command(session: &mut ReplSession, command: &str, args: &[&str]) -> Result<(), String>
+{
+    match command
+    {
+        "help" => println!("{}", HELP),
+        "position" =>
+        {
+            session.board = match args
+            {
+                [] | ["startpos"] => Board::new()?,
+                _ => Board::from_fen_strict(&args.join(" "))?,
+            };
+            session.history.clear();
+            session.display();
+        },
+        "display" => session.display(),
+        "history" if session.history.is_empty() => println!("No move played yet."),
+        "history" => println!("{}", session.movetext()),
+        "moves" if !args.is_empty() =>
+        {
+            let sq = parse_square(args[0]).ok_or(format!("Invalid square '{}'.", args[0]))?;
+            let options = DisplayOptions { last_move: None, ..session.display_options };
+            session.board.display_destinations(sq, &options);
+        },
+        "moves" =>
+        {
+            let moves = session.board.get_legal_moves();
+            let names: Vec<String> = moves
+                .iter()
+                .map(|mv| {
+                    format!("{} ({})", mv.to_san_with(&mut session.board, &moves), mv.to_uci())
+                })
+                .collect();
+            println!("{} legal moves: {}", moves.len(), names.join(", "));
+        },
+        "move" =>
+        {
+            let name = args.first().ok_or("Usage: move <uci>")?;
+            let mv = parse_uci_move(&mut session.board, name)
+                .ok_or(format!("'{}' is not a legal move.", name))?;
+            session.board.make_move(mv);
+            session.history.push(mv);
+            session.display();
+        },
+        "undo" =>
+        {
+            let mv = session.history.pop().ok_or("There is no move to take back.")?;
+            session.board.unmake_move(mv);
+            session.display();
+        },
+        "flip" =>
+        {
+            // The moves of the history can't be unmade on the mirrored board.
+            session.board = session.board.flipped();
+            session.history.clear();
+            session.display();
+        },
+        "toggle" =>
+        {
+            let options = &mut session.display_options;
+            let option = match args.first()
+            {
+                Some(&"unicode") => &mut options.unicode,
+                Some(&"coordinates") => &mut options.coordinates,
+                Some(&"flipped") => &mut options.flipped,
+                Some(&"colors") => &mut options.colors,
+                Some(&"blindfold") => &mut options.blindfold,
+                _ =>
+                {
+                    return Err(
+                        "Usage: toggle <unicode | coordinates | flipped | colors | blindfold>"
+                            .into(),
+                    );
+                },
+            };
+            *option = !*option;
+            session.display();
+        },
+        "eval" =>
+        {
+            print!("{}", evaluate_trace(&session.board).to_table());
+            println!("evaluation (side to move): {}", evaluate(&session.board));
+        },
+        "go" =>
+        {
+            let depth = match args
+            {
+                [] => DEFAULT_DEPTH,
+                ["depth", n] => n.parse::<u8>().map_err(|_| format!("Invalid depth '{}'.", n))?,
+                _ => return Err("Usage: go [depth <n>]".into()),
+            };
+            print_analysis(&mut session.board, depth, 1, &[], &mut session.search);
+        },
+        "perft" =>
+        {
+            let depth = match args
+            {
+                [n] => n.parse::<usize>().map_err(|_| format!("Invalid depth '{}'.", n))?,
+                _ => return Err("Usage: perft <n>".into()),
+            };
+            let count = launch_explore(&mut session.board, depth, false, &mut PerftTable::new(0));
+            println!("total: {}", count);
+        },
+        _ =>
+        {
+            return Err(format!(
+                "Unknown command '{}'. Type 'help' for the list of commands.",
+                command
+            ));
+        },
+    }
+    return Ok(());
+}
@@ -0,0 +1,491 @@
+use std::sync::atomic::AtomicBool;
+
+use log::trace;
+
+use crate::{
+    Board, Bound, CUTOFF_MOVE_SLOTS, EvalCache, History, Move, NodeEnd, PlayedMove, Score,
+    SearchInfo, SearchOptions, SearchTree, SharedDeadlines, TranspositionTable, evaluate,
+    generate_check_evasions, is_king_attacked, is_quiet,
+};
+
+// Minimum remaining depth for a node to try a singular extension.
+const SINGULAR_MIN_DEPTH: u8 = 4;
+// Margin below the transposition table score that the other moves must stay under for the
+// transposition table move to be singular.
+const SINGULAR_MARGIN: Score = Score(100);
+// Maximum remaining depth for reverse futility pruning, and margin for each ply of depth.
+const REVERSE_FUTILITY_MAX_DEPTH: u8 = 3;
+const REVERSE_FUTILITY_MARGIN: Score = Score(120);
+// Minimum remaining depth for ProbCut, margin above beta, and depth reduction of the verification
+// search.
+const PROBCUT_MIN_DEPTH: u8 = 5;
+const PROBCUT_MARGIN: Score = Score(200);
+const PROBCUT_REDUCTION: u8 = 4;
+// Number of plies without capture or pawn move after which the game is drawn.
+pub const FIFTY_MOVE_PLIES: u32 = 100;
+// Maximum depth of an iteration. The extensions take the search at most 3 times as deep, and
+// quiescence 'MAX_QUIESCENCE_PLIES' deeper, so the plies stay within a u8.
+pub const MAX_DEPTH: u8 = 64;
+
+// Holds the state shared by all the nodes of an iteration of a search, borrowed from 'Search'.
+pub(crate) struct Searcher<'a>
+{
+    pub(super) options: &'a SearchOptions,
+    pub(super) tt: &'a TranspositionTable,
+    pub(super) eval_cache: &'a mut EvalCache,
+    pub(super) history: &'a mut History,
+    pub(super) info: &'a mut SearchInfo,
+    pub(super) stop: &'a AtomicBool,
+    pub(super) deadlines: &'a SharedDeadlines,
+    pub(super) root_depth: u8,
+    // Moves played from the root to the current node.
+    pub(super) stack: Vec<PlayedMove>,
+    // Hashes of the positions from which the moves of 'stack' were played.
+    pub(super) path: Vec<u64>,
+    // Set when the deadline is reached, after which the results of the search are meaningless.
+    pub(super) stopped: bool,
+    // The tree of the first plies, when it is recorded.
+    pub(super) tree: Option<&'a mut SearchTree>,
+}
+
+impl<'a> Searcher<'a>
+{
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn new(
+        options: &'a SearchOptions,
+        tt: &'a TranspositionTable,
+        eval_cache: &'a mut EvalCache,
+        history: &'a mut History,
+        info: &'a mut SearchInfo,
+        stop: &'a AtomicBool,
+        deadlines: &'a SharedDeadlines,
+        root_depth: u8,
+    ) -> Self
+    {
+        return Searcher {
+            options,
+            tt,
+            eval_cache,
+            history,
+            info,
+            stop,
+            deadlines,
+            root_depth,
+            stack: vec![],
+            path: vec![],
+            stopped: false,
+            tree: None,
+        };
+    }
+
+    // Evaluate the position through the evaluation cache.
+    pub(super) fn evaluate(&mut self, board: &Board) -> Score
+    {
+        let (score, cached) = self.eval_cache.evaluate(board, &self.options.eval_params);
+        self.info.eval_cache_probes += 1;
+        if cached
+        {
+            self.info.eval_cache_hits += 1;
+        }
+        return score;
+    }
+
+    // Get the score of a draw for the side to move at 'ply' plies from the root.
+    fn draw_score(&self, ply: u8) -> Score
+    {
+        return if ply.is_multiple_of(2) { -self.options.contempt } else { self.options.contempt };
+    }
+
+    pub(super) fn alpha_beta_quiesce(
+        &mut self,
+        board: &mut Board,
+        alpha: Score,
+        beta: Score,
+        depth: u8,
+        ply: u8,
+        excluded: &[Move],
+    ) -> (Score, Option<Move>)
+    {
+        if self.tree.is_none()
+        {
+            return self.search_node(board, alpha, beta, depth, ply, excluded);
+        }
+        self.enter_tree_node(alpha, beta, depth, ply, ply > 0 && !excluded.is_empty());
+        let result = self.search_node(board, alpha, beta, depth, ply, excluded);
+        self.leave_tree_node(ply, result);
+        return result;
+    }
+
+    fn search_node(
+        &mut self,
+        board: &mut Board,
+        mut alpha: Score,
+        beta: Score,
+        depth: u8,
+        ply: u8,
+        excluded: &[Move],
+    ) -> (Score, Option<Move>)
+    {
+        // A position already met on the path from the root is a draw: the side that repeated it can
+        // repeat it again, so no side can hope for more than a draw from the second occurrence.
+        if ply > 0 && self.path.contains(&board.hash)
+        {
+            self.note_tree_node(ply, |node| node.end = NodeEnd::Draw);
+            return (self.draw_score(ply), None);
+        }
+        // The fifty-move rule draws the game, unless the last move gave checkmate.
+        if ply > 0 && board.halfmove_clock >= FIFTY_MOVE_PLIES
+        {
+            if is_king_attacked(board, false) && board.get_legal_moves().is_empty()
+            {
+                self.note_tree_node(ply, |node| node.end = NodeEnd::GameOver);
+                return (Score::mated_in(ply), None);
+            }
+            self.note_tree_node(ply, |node| node.end = NodeEnd::Draw);
+            return (self.draw_score(ply), None);
+        }
+        if depth == 0
+        {
+            self.note_tree_node(ply, |node| node.end = NodeEnd::Quiescence);
+            let check_plies = self.options.quiescence_check_plies;
+            let in_check = is_king_attacked(board, false);
+            return (self.quiesce(board, alpha, beta, in_check, check_plies, ply), None);
+        }
+        self.info.nodes += 1;
+        self.info.seldepth = self.info.seldepth.max(ply);
+        if self.should_stop()
+        {
+            return (Score::DRAW, None);
+        }
+
+        // Reuse a previous search of this position if it was deep enough.
+        // The root always searches, so that a move is returned.
+        let tt_entry = self.probe_tt(board);
+        self.note_tree_node(ply, |node| node.tt_hit = tt_entry.is_some());
+        let tt_move = tt_entry.and_then(|entry| entry.best_move_on(board));
+        if ply > 0
+            && excluded.is_empty()
+            && let Some(entry) = tt_entry
+            && entry.depth >= depth
+        {
+            let score = entry.score.from_tt(ply);
+            let cutoff = match entry.bound
+            {
+                Bound::Exact => true,
+                Bound::Lower => score >= beta,
+                Bound::Upper => score <= alpha,
+            };
+            if cutoff
+            {
+                self.info.tt_cutoffs += 1;
+                self.note_tree_node(ply, |node| node.end = NodeEnd::TtCutoff);
+                return (score, tt_move);
+            }
+        }
+        let original_alpha = alpha;
+
+        let mut max = -Score::INFINITE;
+        let mut best = None;
+
+        // When in check, only evasions need to be generated.
+        let in_check = is_king_attacked(board, false);
+        let mut moves =
+            if in_check { generate_check_evasions(board) } else { board.get_legal_moves() };
+        if moves.is_empty()
+        {
+            self.note_tree_node(ply, |node| node.end = NodeEnd::GameOver);
+            if board.variant_win().is_some()
+            {
+                return (Score::mate_in(ply), None);
+            }
+            if in_check || board.variant_loss().is_some()
+            {
+                return (Score::mated_in(ply), None);
+            }
+            return (self.draw_score(ply), None);
+        }
+        let previous = self.stack.last().copied();
+        self.order_moves(board, &mut moves, tt_move);
+
+        // Skip the excluded moves, used by the singular extension search and by MultiPV at the
+        // root.
+        if !excluded.is_empty()
+        {
+            moves.retain(|mv| !excluded.contains(mv));
+            if moves.is_empty()
+            {
+                return (-Score::INFINITE, None);
+            }
+        }
+
+        // Pruning is only tried far from mate scores, and never at the root, in check, or when
+        // moves are excluded.
+        let can_prune = ply > 0
+            && !in_check
+            && excluded.is_empty()
+            && beta.abs() < Score::INFINITE
+            && !beta.is_mate();
+
+        // Reverse futility pruning: near the leaves, a static evaluation well above beta is
+        // unlikely to drop below it.
+        if can_prune && self.options.reverse_futility && depth <= REVERSE_FUTILITY_MAX_DEPTH
+        {
+            let eval = self.evaluate(board);
+            if eval - Score(REVERSE_FUTILITY_MARGIN.0 * depth as i32) >= beta
+            {
+                self.info.reverse_futility_prunes += 1;
+                self.note_tree_node(ply, |node| node.end = NodeEnd::ReverseFutility);
+                trace!("ply {} depth {}: reverse futility prune, eval {}", ply, depth, eval);
+                return (eval, None);
+            }
+        }
+
+        // ProbCut: if a capture keeps the score above a raised beta in a quiescence search, then
+        // in a reduced search, the full search would very probably fail high.
+        if can_prune && self.options.probcut && depth >= PROBCUT_MIN_DEPTH
+        {
+            let probcut_beta = beta + PROBCUT_MARGIN;
+            for mv in moves.iter().filter(|mv| !is_quiet(**mv))
+            {
+                self.stack.push(PlayedMove::new(board, *mv));
+                self.path.push(board.hash);
+                let checked = board.gives_check(*mv);
+                board.make_move(*mv);
+                let mut score = -self.quiesce(
+                    board,
+                    -probcut_beta,
+                    -probcut_beta + Score(1),
+                    checked,
+                    0,
+                    ply + 1,
+                );
+                if score >= probcut_beta
+                {
+                    let (reduced, _) = self.alpha_beta_quiesce(
+                        board,
+                        -probcut_beta,
+                        -probcut_beta + Score(1),
+                        depth - PROBCUT_REDUCTION,
+                        ply + 1,
+                        &[],
+                    );
+                    score = -reduced;
+                }
+                board.unmake_move(*mv);
+                self.path.pop();
+                self.stack.pop();
+                if self.stopped
+                {
+                    return (Score::DRAW, None);
+                }
+                if score >= probcut_beta
+                {
+                    self.info.probcuts += 1;
+                    self.note_tree_node(ply, |node| node.end = NodeEnd::ProbCut);
+                    trace!(
+                        "ply {} depth {}: ProbCut by {}, score {}",
+                        ply,
+                        depth,
+                        mv.to_uci(),
+                        score
+                    );
+                    return (score, Some(*mv));
+                }
+            }
+        }
+
+        // Extensions are only allowed until twice the nominal depth, so that they can't make the
+        // search endless.
+        let can_extend = u16::from(ply) < 2 * u16::from(self.root_depth);
+
+        // Check extension: all the moves of a node in check are searched one ply deeper.
+        let mut extension = 0;
+        if can_extend && in_check && self.options.check_extension
+        {
+            extension = 1;
+            self.info.check_extensions += 1;
+        }
+
+        // Singular extension: if the transposition table move failed high before, and a reduced
+        // search without it stays well below its score, it is the only good move, and it is
+        // searched one ply deeper.
+        let mut singular_move = None;
+        if can_extend
+            && extension == 0
+            && self.options.singular_extension
+            && ply > 0
+            && excluded.is_empty()
+            && depth >= SINGULAR_MIN_DEPTH
+            && let Some(entry) = tt_entry
+            && let Some(tt_mv) = tt_move
+            && entry.bound != Bound::Upper
+            && entry.depth + 3 >= depth
+            && !entry.score.is_mate()
+        {
+            let singular_beta = entry.score - SINGULAR_MARGIN;
+            let (score, _) = self.alpha_beta_quiesce(
+                board,
+                singular_beta - Score(1),
+                singular_beta,
+                (depth - 1) / 2,
+                ply,
+                &[tt_mv],
+            );
+            if score < singular_beta
+            {
+                singular_move = Some(tt_mv);
+                self.info.singular_extensions += 1;
+                trace!("ply {} depth {}: {} is singular", ply, depth, tt_mv.to_uci());
+            }
+        }
+
+        // Quiet moves searched without causing a cutoff, whose history is lowered when a later
+        // quiet move causes one.
+        let mut quiets_tried = vec![];
+        for (index, mv) in moves.iter().enumerate()
+        {
+            let child_extension = if singular_move == Some(*mv) { 1 } else { extension };
+            // The root moves get their random bonus by searching them in a window lowered by it.
+            let noise = if ply == 0 { self.root_noise(board, *mv) } else { Score(0) };
+            let played = PlayedMove::new(board, *mv);
+            self.stack.push(played);
+            self.path.push(board.hash);
+            board.make_move(*mv);
+            let (mut score, _) = self.alpha_beta_quiesce(
+                board,
+                noise - beta,
+                noise - alpha,
+                depth - 1 + child_extension,
+                ply + 1,
+                &[],
+            );
+            score = -score;
+            if !score.is_mate()
+            {
+                score = score + noise;
+            }
+            board.unmake_move(*mv);
+            self.path.pop();
+            self.stack.pop();
+            if self.stopped
+            {
+                return (Score::DRAW, None);
+            }
+            if score > max
+            {
+                max = score;
+                best = Some(*mv);
+                if score > alpha
+                {
+                    alpha = score;
+                }
+            }
+            if score >= beta
+            {
+                self.info.beta_cutoffs[index.min(CUTOFF_MOVE_SLOTS - 1)] += 1;
+                if is_quiet(*mv)
+                    && let Some(previous) = previous
+                {
+                    self.history.update(&previous, &played, &quiets_tried, depth);
+                }
+                break;
+            }
+            if is_quiet(*mv)
+            {
+                quiets_tried.push(played);
+            }
+        }
+
+        // Store the result, unless moves were excluded, since the search was incomplete.
+        if excluded.is_empty()
+        {
+            self.store_tt(board, depth, ply, max, original_alpha, beta, best);
+        }
+
+        return (max, best);
+    }
+}
+
+pub fn negamax(board: &mut Board, depth: u8) -> (Score, Option<Move>)
+{
+    if depth == 0
+    {
+        return (evaluate(board), None);
+    }
+    let mut max = -Score::INFINITE;
+    let mut best = None;
+
+    let moves = board.get_legal_moves();
+    if moves.is_empty()
+    {
+        return (-Score::MATE, None);
+    }
+
+    for mv in moves.iter()
+    {
+        board.make_move(*mv);
+        let (mut score, _) = negamax(board, depth - 1);
+        score = -score;
+        board.unmake_move(*mv);
+        if score > max
+        {
+            max = score;
+            best = Some(*mv);
+        }
+    }
+    return (max, best);
+}
+
+pub fn launch_alpha_beta(board: &mut Board, depth: u8) -> (Score, Option<Move>)
+{
+    return alpha_beta(board, -Score::INFINITE, Score::INFINITE, depth);
+}
+
+fn alpha_beta(board: &mut Board, mut alpha: Score, beta: Score, depth: u8)
+-> (Score, Option<Move>)
+{
+    if depth == 0
+    {
+        return (evaluate(board), None);
+    }
+    let mut max = -Score::INFINITE;
+    let mut best = None;
+
+    // When in check, only evasions need to be generated.
+    let in_check = is_king_attacked(board, false);
+    let moves = if in_check { generate_check_evasions(board) } else { board.get_legal_moves() };
+    if moves.is_empty()
+    {
+        if board.variant_win().is_some()
+        {
+            return (Score::MATE, None);
+        }
+        if in_check || board.variant_loss().is_some()
+        {
+            return (-Score::MATE, None);
+        }
+        return (Score::DRAW, None);
+    }
+
+    for mv in moves.iter()
+    {
+        board.make_move(*mv);
+        let (mut score, _) = alpha_beta(board, -beta, -alpha, depth - 1);
+        score = -score;
+        board.unmake_move(*mv);
+        if score > max
+        {
+            max = score;
+            best = Some(*mv);
+            if score > alpha
+            {
+                alpha = score;
+            }
+        }
+        if score >= beta
+        {
+            return (max, best);
+        }
+    }
+    return (max, best);
+}
@@ -0,0 +1,196 @@
+use std::{
+    sync::{Mutex, atomic::Ordering},
+    time::{Duration, Instant},
+};
+
+use super::{SearchOptions, alphabeta::Searcher};
+use crate::{Move, Score};
+
+// Number of nodes between two checks of the deadline and of the stop flag.
+const DEADLINE_CHECK_NODES: u64 = 1024;
+
+// Number of iterations with the same best move and score after which a timed search stops, when
+// more than half of its time is used.
+pub const DEFAULT_STABLE_ITERATIONS: u8 = 3;
+
+// Largest change of the score between two iterations for which the score is considered stable.
+const STABLE_SCORE_MARGIN: Score = Score(20);
+
+// The results of the iterations of an iterative deepening, to stop it before its maximum depth
+// when deeper iterations are unlikely to change the move.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Stability
+{
+    start: Instant,
+    best_move: Option<Move>,
+    score: Score,
+    // Whether the last iteration found another best move than the previous one.
+    best_move_changed: bool,
+    // Number of iterations in a row, after the first one, that kept the best move and a close
+    // score.
+    stable_iterations: u8,
+}
+
+impl Stability
+{
+    pub(crate) fn new() -> Self
+    {
+        return Stability {
+            start: Instant::now(),
+            best_move: None,
+            score: Score::DRAW,
+            best_move_changed: false,
+            stable_iterations: 0,
+        };
+    }
+
+    // Record the result of a complete iteration.
+    pub(crate) fn update(&mut self, score: Score, best_move: Option<Move>)
+    {
+        let stable = self.best_move.is_some()
+            && best_move == self.best_move
+            && (score - self.score).abs() <= STABLE_SCORE_MARGIN;
+        self.stable_iterations = if stable { self.stable_iterations.saturating_add(1) } else { 0 };
+        self.best_move_changed = self.best_move.is_some() && best_move != self.best_move;
+        self.best_move = best_move;
+        self.score = score;
+    }
+
+    // Tell whether the iterative deepening can stop after the last iteration, searched to 'depth':
+    // - with 'stop_on_mate', when its score is a mate within 'depth' plies, which the deeper
+    //   iterations would only find again;
+    // - after the soft deadline, which is twice as far when the best move just changed;
+    // - with 'stable_iterations', when the best move and the score stayed the same during that
+    //   many iterations, and the time left before the deadline is shorter than the time spent so
+    //   far, so that the next iteration would probably not complete anyway.
+    pub(crate) fn can_stop(&self, options: &SearchOptions, depth: u8) -> bool
+    {
+        let mate_plies = Score::MATE.0 - self.score.abs().0;
+        if options.stop_on_mate && self.score.is_mate() && mate_plies <= depth as i32
+        {
+            return true;
+        }
+        if let Some(soft_deadline) = options.soft_deadline
+        {
+            let soft_limit = soft_deadline.saturating_duration_since(self.start);
+            let factor = if self.best_move_changed { BEST_MOVE_CHANGE_FACTOR } else { 1 };
+            if self.start.elapsed() >= soft_limit * factor
+            {
+                return true;
+            }
+        }
+        let Some(deadline) = options.deadline
+        else
+        {
+            return false;
+        };
+        return options.stable_iterations > 0
+            && self.stable_iterations >= options.stable_iterations
+            && deadline.saturating_duration_since(Instant::now()) < self.start.elapsed();
+    }
+}
+
+// Time kept by default on each move for the communication with the GUI, which the clock of the
+// engine is charged for too.
+pub const DEFAULT_MOVE_OVERHEAD_MS: u64 = 30;
+// Number of moves the time left is planned for when the moves until the next time control are not
+// known.
+const PLANNED_MOVES: u32 = 30;
+// The hard limit of a move is this many times its soft limit, within the time left.
+const HARD_LIMIT_FACTOR: u32 = 3;
+// Factor of the soft limit when the best move changed in the last iteration, to resolve the doubt.
+const BEST_MOVE_CHANGE_FACTOR: u32 = 2;
+
+// How long to think about a move: no iteration of the iterative deepening starts after the soft
+// limit, and the search stops at the hard limit.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TimeLimits
+{
+    pub soft: Duration,
+    pub hard: Duration,
+}
+
+impl TimeLimits
+{
+    // Choose the limits of a move, given the time left on the clock, the increment received after
+    // each move, and the number of moves until the next time control if there is one. The move
+    // overhead is taken from the time left first, so that the clock never runs out.
+    pub fn for_clock(
+        remaining: Duration,
+        increment: Duration,
+        moves_to_go: Option<u32>,
+        overhead: Duration,
+    ) -> Self
+    {
+        let available = remaining.saturating_sub(overhead);
+        let moves = moves_to_go.unwrap_or(PLANNED_MOVES).clamp(1, PLANNED_MOVES);
+        // With the last move before the time control, most of the time can be used.
+        let max = if moves == 1 { available * 3 / 4 } else { available / 2 };
+        let hard = ((available / moves + increment * 3 / 4) * HARD_LIMIT_FACTOR).min(max);
+        let soft = (available / moves + increment * 3 / 4).min(hard);
+        return TimeLimits { soft, hard };
+    }
+
+    // Use a fixed time for the move, like 'go movetime' in UCI, less the move overhead.
+    pub fn fixed(time: Duration, overhead: Duration) -> Self
+    {
+        let time = time.saturating_sub(overhead);
+        return TimeLimits { soft: time, hard: time };
+    }
+}
+
+// Deadlines given to a search from another thread while it runs, e.g. when the opponent plays the
+// move a ponder search expected, and the search must end in time for the clock. They replace the
+// deadlines of the options from the next check on.
+#[derive(Debug, Default)]
+pub struct SharedDeadlines
+{
+    // The soft and the hard deadlines.
+    deadlines: Mutex<Option<(Instant, Instant)>>,
+}
+
+impl SharedDeadlines
+{
+    // Set the deadlines of a search continuing from now with the given limits.
+    pub fn set(&self, limits: TimeLimits)
+    {
+        let now = Instant::now();
+        *self.deadlines.lock().expect("The deadlines are never poisoned.") =
+            Some((now + limits.soft, now + limits.hard));
+    }
+
+    // Remove the deadlines, so that the search keeps the ones of its options.
+    pub fn clear(&self)
+    {
+        *self.deadlines.lock().expect("The deadlines are never poisoned.") = None;
+    }
+
+    // Get the soft and the hard deadlines, if they are set.
+    pub fn get(&self) -> Option<(Instant, Instant)>
+    {
+        return *self.deadlines.lock().expect("The deadlines are never poisoned.");
+    }
+}
+
+impl Searcher<'_>
+{
+    // Check regularly whether the deadline is reached or a stop is requested. The first iteration
+    // is never stopped.
+    pub(super) fn should_stop(&mut self) -> bool
+    {
+        if !self.stopped
+            && self.root_depth > 1
+            && self.info.nodes.is_multiple_of(DEADLINE_CHECK_NODES)
+        {
+            let deadline = match self.deadlines.get()
+            {
+                Some((_, hard)) => Some(hard),
+                None => self.options.deadline,
+            };
+            let deadline_reached = deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            let stop_requested = self.stop.load(Ordering::Relaxed);
+            self.stopped = deadline_reached || stop_requested;
+        }
+        return self.stopped;
+    }
+}
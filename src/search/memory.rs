@@ -0,0 +1,41 @@
+use crate::{EVAL_CACHE_ENTRY_BYTES, PAWN_TABLE_ENTRY_BYTES, TT_ENTRY_BYTES};
+
+// Parts of a memory budget given to the evaluation cache, one eighth, and to the pawn table, one
+// thirty-second, since there are much fewer pawn structures than positions. The rest goes to the
+// transposition table, whose entries save whole searches instead of evaluations.
+const EVAL_CACHE_DIVISOR: usize = 8;
+const PAWN_TABLE_DIVISOR: usize = 32;
+
+// The number of entries of each table of the searches.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct TableSizes
+{
+    pub tt_entries: usize,
+    pub eval_cache_entries: usize,
+    pub pawn_table_entries: usize,
+}
+
+impl TableSizes
+{
+    // Split a budget in megabytes between the tables, so that they use about this much memory
+    // together. The transposition table gets at least one entry.
+    pub fn for_megabytes(megabytes: usize) -> Self
+    {
+        let bytes = megabytes * (1 << 20);
+        let eval_cache_bytes = bytes / EVAL_CACHE_DIVISOR;
+        let pawn_table_bytes = bytes / PAWN_TABLE_DIVISOR;
+        return TableSizes {
+            tt_entries: ((bytes - eval_cache_bytes - pawn_table_bytes) / TT_ENTRY_BYTES).max(1),
+            eval_cache_entries: eval_cache_bytes / EVAL_CACHE_ENTRY_BYTES,
+            pawn_table_entries: pawn_table_bytes / PAWN_TABLE_ENTRY_BYTES,
+        };
+    }
+
+    // Get the memory allocated by tables of these sizes, in bytes.
+    pub fn bytes(&self) -> usize
+    {
+        return self.tt_entries * TT_ENTRY_BYTES
+            + self.eval_cache_entries * EVAL_CACHE_ENTRY_BYTES
+            + self.pawn_table_entries * PAWN_TABLE_ENTRY_BYTES;
+    }
+}
@@ -0,0 +1,627 @@
+pub mod alphabeta;
+pub mod limits;
+pub mod memory;
+pub mod ordering;
+pub mod quiescence;
+pub mod skill;
+pub mod tree;
+pub mod tt;
+
+use std::{
+    sync::{Arc, atomic::AtomicBool},
+    time::Instant,
+};
+
+pub use alphabeta::*;
+pub use limits::*;
+pub use memory::*;
+pub use quiescence::*;
+pub use skill::*;
+pub use tree::*;
+pub use tt::*;
+
+use crate::{Board, ChessError, EvalCache, EvalParams, History, Move, Score, engine_config};
+
+// Options changing the behavior of the search.
+#[derive(Debug, Clone)]
+pub struct SearchOptions
+{
+    // Number of quiescence plies in which quiet checking moves are searched along with captures.
+    // With 0, quiescence only searches captures.
+    pub quiescence_check_plies: u8,
+    // Search one ply deeper when the side to move is in check.
+    pub check_extension: bool,
+    // Search the transposition table move one ply deeper when a reduced search shows that all the
+    // other moves are clearly worse.
+    pub singular_extension: bool,
+    // At low depth, return the static evaluation when it is above beta by a margin growing with
+    // the depth (reverse futility pruning).
+    pub reverse_futility: bool,
+    // At high depth, return early when a capture followed by a reduced search beats beta by a
+    // margin, since the full search would very probably fail high too (ProbCut).
+    pub probcut: bool,
+    // Order the quiet moves with the countermove of the previous move first.
+    pub countermoves: bool,
+    // Order the quiet moves by their continuation history with the previous move.
+    pub continuation_history: bool,
+    // Stop searching when this instant is reached, and return the result of the last complete
+    // iteration of the iterative deepening. The first iteration is always completed.
+    pub deadline: Option<Instant>,
+    // Don't start a new iteration of the iterative deepening after this instant, or after twice as
+    // long when the best move changed in the last iteration. The deadline is the hard limit.
+    pub soft_deadline: Option<Instant>,
+    // Stop the iterative deepening once it proves a forced mate, for either side.
+    pub stop_on_mate: bool,
+    // With a deadline, stop the iterative deepening when the best move and the score stayed the
+    // same during this many iterations and less than half of the time is left. 0 never stops it
+    // early.
+    pub stable_iterations: u8,
+    // Score that the side to move at the root gives up by drawing, when it considers itself
+    // stronger than its opponent. Draws are scored '-contempt' for it, and 'contempt' for its
+    // opponent, so a negative contempt makes it seek draws.
+    pub contempt: Score,
+    // Number of entries of the evaluation cache, which is created for each search. With 0, the
+    // evaluations are not cached.
+    pub eval_cache_entries: usize,
+    // Number of entries of the pawn table of the evaluation cache. With 0, the pawn structures
+    // are not cached.
+    pub pawn_table_entries: usize,
+    // The weights of the static evaluation.
+    pub eval_params: EvalParams,
+    // Probability, between 0 and 1, that the root moves are shuffled in the opening, so that the
+    // moves scoring the same are chosen at random instead of in the order of the generation, and
+    // the self-play games differ. 0 keeps the searches reproducible, e.g. for analysis.
+    pub opening_variety: f32,
+    // Maximum random bonus added to the score of each root move, so that the engine plays one of
+    // the moves close to the best one instead of always the same. 0 by default, for analysis.
+    pub root_score_noise: Score,
+    // Strength of the play, from 0 to 'MAX_SKILL_LEVEL', the full strength. Below it, 'search'
+    // limits the depth and sometimes plays worse moves, for humans to play against.
+    pub skill_level: u8,
+    // Seed of the shuffle of the opening variety and of the root score noise, mixed with the hash
+    // of the position.
+    pub seed: u64,
+}
+
+impl SearchOptions
+{
+    // Set the soft and the hard deadlines of a search starting now.
+    pub fn set_time_limits(&mut self, limits: TimeLimits)
+    {
+        let now = Instant::now();
+        self.soft_deadline = Some(now + limits.soft);
+        self.deadline = Some(now + limits.hard);
+    }
+
+    // Remove the deadlines, for searches limited by depth only.
+    pub fn clear_time_limits(&mut self)
+    {
+        self.soft_deadline = None;
+        self.deadline = None;
+    }
+}
+
+// The default options come from the engine configuration.
+impl Default for SearchOptions
+{
+    fn default() -> Self
+    {
+        return engine_config().search_options();
+    }
+}
+
+// Statistics gathered during a search.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SearchInfo
+{
+    // Number of nodes visited, quiescence nodes included.
+    pub nodes: u64,
+    // Number of nodes searched one ply deeper because the side to move was in check.
+    pub check_extensions: u64,
+    // Number of singular moves searched one ply deeper.
+    pub singular_extensions: u64,
+    // Number of static evaluations requested, and number of them read from the evaluation cache.
+    pub eval_cache_probes: u64,
+    pub eval_cache_hits: u64,
+    // Number of nodes pruned by reverse futility pruning and by ProbCut.
+    pub reverse_futility_prunes: u64,
+    pub probcuts: u64,
+    // Number of quiescence nodes, which are also counted in 'nodes'.
+    pub quiescence_nodes: u64,
+    // Number of quiescence nodes evaluated without searching their captures, because they were
+    // 'MAX_QUIESCENCE_PLIES' beyond the depth of the iteration.
+    pub quiescence_depth_cuts: u64,
+    // Selective depth: the largest number of plies from the root reached by the search,
+    // quiescence included.
+    pub seldepth: u8,
+    // Number of transposition table probes of the main search, of them finding an entry of the
+    // position, and of them ending the node with the stored score.
+    pub tt_probes: u64,
+    pub tt_hits: u64,
+    pub tt_cutoffs: u64,
+    // Number of beta cutoffs of the main search by the first move searched, the second, and so
+    // on, the last slot counting the cutoffs by any later move.
+    pub beta_cutoffs: [u64; CUTOFF_MOVE_SLOTS],
+}
+
+// Number of slots of 'SearchInfo::beta_cutoffs'.
+pub const CUTOFF_MOVE_SLOTS: usize = 8;
+
+impl SearchInfo
+{
+    // Add the statistics of another search to these ones.
+    pub fn add(&mut self, other: &SearchInfo)
+    {
+        self.nodes += other.nodes;
+        self.check_extensions += other.check_extensions;
+        self.singular_extensions += other.singular_extensions;
+        self.eval_cache_probes += other.eval_cache_probes;
+        self.eval_cache_hits += other.eval_cache_hits;
+        self.reverse_futility_prunes += other.reverse_futility_prunes;
+        self.probcuts += other.probcuts;
+        self.quiescence_nodes += other.quiescence_nodes;
+        self.quiescence_depth_cuts += other.quiescence_depth_cuts;
+        self.seldepth = self.seldepth.max(other.seldepth);
+        self.tt_probes += other.tt_probes;
+        self.tt_hits += other.tt_hits;
+        self.tt_cutoffs += other.tt_cutoffs;
+        for (total, count) in self.beta_cutoffs.iter_mut().zip(other.beta_cutoffs.iter())
+        {
+            *total += count;
+        }
+    }
+
+    // Get the fraction of the transposition table probes finding an entry of the position.
+    pub fn tt_hit_rate(&self) -> f64
+    {
+        if self.tt_probes == 0
+        {
+            return 0.0;
+        }
+        return self.tt_hits as f64 / self.tt_probes as f64;
+    }
+
+    // Get the fraction of the beta cutoffs made by the first move searched, which tells how good
+    // the move ordering is.
+    pub fn first_move_cutoff_rate(&self) -> f64
+    {
+        let total: u64 = self.beta_cutoffs.iter().sum();
+        if total == 0
+        {
+            return 0.0;
+        }
+        return self.beta_cutoffs[0] as f64 / total as f64;
+    }
+
+    // Get the fraction of the static evaluations read from the evaluation cache.
+    pub fn eval_cache_hit_rate(&self) -> f64
+    {
+        if self.eval_cache_probes == 0
+        {
+            return 0.0;
+        }
+        return self.eval_cache_hits as f64 / self.eval_cache_probes as f64;
+    }
+}
+
+// A line of play found by the search, starting from the root position.
+#[derive(Debug, Clone)]
+pub struct PvLine
+{
+    pub score: Score,
+    pub moves: Vec<Move>,
+}
+
+// A root move and the best alternative to it, with their lines.
+#[derive(Debug, Clone)]
+pub struct Alternative
+{
+    pub excluded: PvLine,
+    // The best line starting with another move, if there is another legal move.
+    pub alternative: Option<PvLine>,
+}
+
+impl Alternative
+{
+    // Get how much worse the alternative is than the excluded move, for the side to move.
+    pub fn loss(&self) -> Option<Score>
+    {
+        return self.alternative.as_ref().map(|line| self.excluded.score - line.score);
+    }
+}
+
+// The state of the searches of an engine, kept from one search to the next: the transposition
+// table, the history of the quiet moves, and the flag stopping the search from another thread.
+// A new game should start with 'clear', so that the results of the previous game don't lead the
+// searches astray.
+pub struct Search
+{
+    pub options: SearchOptions,
+    // The transposition table, which can be shared with other searches.
+    pub tt: Arc<TranspositionTable>,
+    // Statistics of the last search.
+    pub info: SearchInfo,
+    // Number of plies of the search tree recorded for debugging, 0 to record nothing, and the tree
+    // of the last complete iteration of the last search.
+    pub tree_plies: u8,
+    pub tree: Option<SearchTree>,
+    history: History,
+    // Stops the search like the deadline when it is set. It stays set until it is cleared.
+    stop: Arc<AtomicBool>,
+    // Deadlines given while the search runs, which replace the ones of the options.
+    deadlines: Arc<SharedDeadlines>,
+}
+
+impl Search
+{
+    // Create a search with a transposition table of the configured size.
+    pub fn new(options: SearchOptions) -> Self
+    {
+        return Search::with_tt(options, Arc::new(TranspositionTable::default()));
+    }
+
+    // Create a search using a transposition table, which may be shared with other searches running
+    // at the same time.
+    pub fn with_tt(options: SearchOptions, tt: Arc<TranspositionTable>) -> Self
+    {
+        return Search {
+            options,
+            tt,
+            info: SearchInfo::default(),
+            tree_plies: 0,
+            tree: None,
+            history: History::new(),
+            stop: Arc::new(AtomicBool::new(false)),
+            deadlines: Arc::new(SharedDeadlines::default()),
+        };
+    }
+
+    // Replace the tables with empty ones of the given sizes.
+    pub fn resize_tables(&mut self, sizes: TableSizes)
+    {
+        self.tt = Arc::new(TranspositionTable::new(sizes.tt_entries));
+        self.options.eval_cache_entries = sizes.eval_cache_entries;
+        self.options.pawn_table_entries = sizes.pawn_table_entries;
+    }
+
+    // Get the sizes of the tables, the evaluation cache and its pawn table being created by each
+    // search.
+    pub fn table_sizes(&self) -> TableSizes
+    {
+        return TableSizes {
+            tt_entries: self.tt.size(),
+            eval_cache_entries: self.options.eval_cache_entries,
+            pawn_table_entries: self.options.pawn_table_entries,
+        };
+    }
+
+    // Forget everything learned by the previous searches. A shared transposition table is cleared
+    // for all the searches using it.
+    pub fn clear(&mut self)
+    {
+        self.tt.clear();
+        self.history.clear();
+    }
+
+    // Get the flag stopping the search when it is set, to stop it from another thread or from
+    // a signal handler.
+    pub fn stop_flag(&self) -> Arc<AtomicBool>
+    {
+        return self.stop.clone();
+    }
+
+    // Get the deadlines that can be given to the search from another thread while it runs. They
+    // stay set until they are cleared.
+    pub fn shared_deadlines(&self) -> Arc<SharedDeadlines>
+    {
+        return self.deadlines.clone();
+    }
+
+    // Take the deadlines given while the search runs, if there are, in place of the ones of the
+    // options.
+    fn update_deadlines(&mut self)
+    {
+        if let Some((soft, hard)) = self.deadlines.get()
+        {
+            self.options.soft_deadline = Some(soft);
+            self.options.deadline = Some(hard);
+        }
+    }
+
+    // Search the position with alpha-beta and quiescence, using iterative deepening up to 'depth',
+    // at most 'MAX_DEPTH'. The transposition table is kept between the iterations, and between the
+    // searches. Below the full strength, the search is weakened by 'skill_search'.
+    pub fn search(&mut self, board: &mut Board, depth: u8) -> (Score, Option<Move>)
+    {
+        if self.options.skill_level < MAX_SKILL_LEVEL
+        {
+            return self.skill_search(board, depth);
+        }
+        return self.search_excluding(board, depth, &[]);
+    }
+
+    // Same as 'search', but the root moves in 'excluded' are not searched.
+    // If every legal move is excluded, no move is returned.
+    pub fn search_excluding(
+        &mut self,
+        board: &mut Board,
+        depth: u8,
+        excluded: &[Move],
+    ) -> (Score, Option<Move>)
+    {
+        self.info = SearchInfo::default();
+        let mut result = (-Score::INFINITE, None);
+        let mut eval_cache =
+            EvalCache::new(self.options.eval_cache_entries, self.options.pawn_table_entries);
+        let mut stability = Stability::new();
+        for d in 1 ..= depth.clamp(1, MAX_DEPTH)
+        {
+            let mut tree = self.new_tree();
+            let mut searcher = Searcher::new(
+                &self.options,
+                &self.tt,
+                &mut eval_cache,
+                &mut self.history,
+                &mut self.info,
+                &self.stop,
+                &self.deadlines,
+                d,
+            );
+            searcher.tree = tree.as_mut();
+            let iteration_result = searcher.alpha_beta_quiesce(
+                board,
+                -Score::INFINITE,
+                Score::INFINITE,
+                d,
+                0,
+                excluded,
+            );
+            if searcher.stopped
+            {
+                log::debug!("depth {}: stopped, keeping the previous iteration", d);
+                break;
+            }
+            result = iteration_result;
+            self.tree = tree;
+            log::debug!(
+                "depth {}: score {}, best move {}, {} nodes",
+                d,
+                result.0,
+                result.1.map(|mv| mv.to_uci()).unwrap_or("none".into()),
+                self.info.nodes
+            );
+            stability.update(result.0, result.1);
+            self.update_deadlines();
+            if stability.can_stop(&self.options, d)
+            {
+                log::debug!("depth {}: the result can't change anymore, stopping", d);
+                break;
+            }
+        }
+        return result;
+    }
+
+    // Search the 'count' best root moves, each with its score and principal variation, best first.
+    // Each line is found by searching again while excluding the root moves of the previous lines.
+    // Fewer lines are returned if there are not enough legal moves.
+    pub fn multipv(&mut self, board: &mut Board, depth: u8, count: usize) -> Vec<PvLine>
+    {
+        return self.analyze(board, depth, count, &[], |_, _, _, _| ());
+    }
+
+    // Same as 'multipv', but 'report' is called after each iteration of the iterative deepening
+    // with the iteration depth, the root position, the lines found and the statistics so far.
+    // Unless 'search_moves' is empty, only the root moves it contains are searched.
+    pub fn analyze(
+        &mut self,
+        board: &mut Board,
+        depth: u8,
+        count: usize,
+        search_moves: &[Move],
+        report: impl FnMut(u8, &mut Board, &[PvLine], &SearchInfo),
+    ) -> Vec<PvLine>
+    {
+        self.info = SearchInfo::default();
+        return self.run_analysis(board, depth, count, search_moves, report);
+    }
+
+    // Search the given root move, or the best move if none is given, and then the best move other
+    // than it, to tell what the alternative is and how much worse it is.
+    // Return None if there is no legal move, or if the given move is not legal.
+    pub fn alternative(
+        &mut self,
+        board: &mut Board,
+        depth: u8,
+        excluded: Option<Move>,
+    ) -> Option<Alternative>
+    {
+        self.info = SearchInfo::default();
+        let search_moves: Vec<Move> = excluded.into_iter().collect();
+        let excluded = self
+            .run_analysis(board, depth, 1, &search_moves, |_, _, _, _| ())
+            .into_iter()
+            .next()?;
+        let others: Vec<Move> =
+            board.get_legal_moves().into_iter().filter(|mv| *mv != excluded.moves[0]).collect();
+        let alternative = if others.is_empty()
+        {
+            None
+        }
+        else
+        {
+            self.run_analysis(board, depth, 1, &others, |_, _, _, _| ()).into_iter().next()
+        };
+        return Some(Alternative { excluded, alternative });
+    }
+
+    // Create the tree recording an iteration, if it is recorded.
+    fn new_tree(&self) -> Option<SearchTree>
+    {
+        return (self.tree_plies > 0).then(|| SearchTree::new(self.tree_plies));
+    }
+
+    // Run an analysis like 'analyze', adding its statistics to the ones of the search.
+    fn run_analysis(
+        &mut self,
+        board: &mut Board,
+        depth: u8,
+        count: usize,
+        search_moves: &[Move],
+        mut report: impl FnMut(u8, &mut Board, &[PvLine], &SearchInfo),
+    ) -> Vec<PvLine>
+    {
+        // Restricting the search to some root moves is the same as excluding all the others.
+        let filtered: Vec<Move> = if search_moves.is_empty()
+        {
+            vec![]
+        }
+        else
+        {
+            board.get_legal_moves().into_iter().filter(|mv| !search_moves.contains(mv)).collect()
+        };
+
+        let mut lines = vec![];
+        let mut eval_cache =
+            EvalCache::new(self.options.eval_cache_entries, self.options.pawn_table_entries);
+        let mut stability = Stability::new();
+        for d in 1 ..= depth.clamp(1, MAX_DEPTH)
+        {
+            // Search each line at this depth, excluding the first moves of the better lines.
+            let mut iteration_lines = vec![];
+            let mut excluded = filtered.clone();
+            let mut stopped = false;
+            let mut tree = self.new_tree();
+            for _ in 0 .. count
+            {
+                let mut searcher = Searcher::new(
+                    &self.options,
+                    &self.tt,
+                    &mut eval_cache,
+                    &mut self.history,
+                    &mut self.info,
+                    &self.stop,
+                    &self.deadlines,
+                    d,
+                );
+                searcher.tree = tree.as_mut();
+                let (score, best) = searcher.alpha_beta_quiesce(
+                    board,
+                    -Score::INFINITE,
+                    Score::INFINITE,
+                    d,
+                    0,
+                    &excluded,
+                );
+                if searcher.stopped
+                {
+                    stopped = true;
+                    break;
+                }
+                let Some(mv) = best
+                else
+                {
+                    break;
+                };
+                excluded.push(mv);
+                iteration_lines
+                    .push(PvLine { score, moves: extract_pv(board, mv, &self.tt, d as usize) });
+            }
+            // An interrupted iteration is incomplete, so the lines of the previous one are kept.
+            if stopped
+            {
+                log::debug!("depth {}: stopped, keeping the previous iteration", d);
+                break;
+            }
+            lines = iteration_lines;
+            self.tree = tree;
+            for (i, line) in lines.iter().enumerate()
+            {
+                let pv: Vec<String> = line.moves.iter().map(|mv| mv.to_uci()).collect();
+                log::debug!(
+                    "depth {} line {}: score {}, pv {}, {} nodes",
+                    d,
+                    i + 1,
+                    line.score,
+                    pv.join(" "),
+                    self.info.nodes
+                );
+            }
+            report(d, board, &lines, &self.info);
+            // The best line decides whether to go deeper.
+            if let Some(best) = lines.first()
+            {
+                stability.update(best.score, best.moves.first().copied());
+                self.update_deadlines();
+                if stability.can_stop(&self.options, d)
+                {
+                    log::debug!("depth {}: the result can't change anymore, stopping", d);
+                    break;
+                }
+            }
+        }
+        return lines;
+    }
+}
+
+// A search with the default options and a transposition table of the configured size.
+impl Default for Search
+{
+    fn default() -> Self
+    {
+        return Search::new(SearchOptions::default());
+    }
+}
+
+// Search the position to 'depth' with a new search using the default options.
+pub fn launch_alpha_beta_quiesce(board: &mut Board, depth: u8) -> (Score, Option<Move>)
+{
+    return Search::default().search(board, depth);
+}
+
+// Search the position of a FEN to 'depth' with the default options, and return the best move in
+// UCI notation with its score in centipawns from the side to move's perspective, so that a script
+// can use the engine in one call. A position that can't be reached in a game is an invalid FEN.
+pub fn best_move(fen: &str, depth: u8) -> Result<(String, i32), ChessError>
+{
+    let mut board = Board::from_fen_strict(fen).map_err(ChessError::InvalidFen)?;
+    return match launch_alpha_beta_quiesce(&mut board, depth)
+    {
+        (score, Some(mv)) => Ok((mv.to_uci(), score.0)),
+        (_, None) => Err(ChessError::NoLegalMove),
+    };
+}
+
+// Build the principal variation starting with 'first', by following the best moves stored in the
+// transposition table, up to 'max_length' moves.
+pub fn extract_pv(
+    board: &mut Board,
+    first: Move,
+    tt: &TranspositionTable,
+    max_length: usize,
+) -> Vec<Move>
+{
+    let mut pv = vec![first];
+    let mut seen = vec![board.hash];
+    board.make_move(first);
+    while pv.len() < max_length
+    {
+        // Stop when the table has no move for the position, or when the line repeats itself.
+        let Some(mv) = tt.probe(board.hash).and_then(|entry| entry.best_move_on(board))
+        else
+        {
+            break;
+        };
+        if seen.contains(&board.hash) || !board.is_legal(mv)
+        {
+            break;
+        }
+        seen.push(board.hash);
+        pv.push(mv);
+        board.make_move(mv);
+    }
+    for mv in pv.iter().rev()
+    {
+        board.unmake_move(*mv);
+    }
+    return pv;
+}
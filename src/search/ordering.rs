@@ -0,0 +1,84 @@
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+use super::alphabeta::Searcher;
+use crate::{
+    Board, MAX_PHASE, Move, PlayedMove, Score, Threats, creates_threat, game_phase, is_quiet,
+};
+
+impl Searcher<'_>
+{
+    // Try the transposition table move first, then the captures and promotions, then the
+    // countermove of the previous move, then the quiet moves saving a threatened piece or
+    // threatening an enemy piece, then the other quiet moves. The quiet moves are sorted by
+    // continuation history.
+    // The sort is stable, so the moves ordered the same stay in the order of the generation, and
+    // the first one searched is kept when they score the same.
+    pub(super) fn order_moves(&mut self, board: &Board, moves: &mut [Move], tt_move: Option<Move>)
+    {
+        if self.stack.is_empty()
+        {
+            self.vary_opening(board, moves);
+        }
+        let previous = self.stack.last().copied();
+        let countermove = previous
+            .filter(|_| self.options.countermoves)
+            .and_then(|previous| self.history.countermove(&previous));
+        let threatened = Threats::new(board).threatened(board.white_to_play);
+        moves.sort_by_cached_key(|mv| {
+            if Some(*mv) == tt_move
+            {
+                return (0, 0);
+            }
+            if !is_quiet(*mv)
+            {
+                return (1, 0);
+            }
+            if Some(*mv) == countermove
+            {
+                return (2, 0);
+            }
+            let continuation = match previous
+            {
+                Some(previous) if self.options.continuation_history =>
+                {
+                    self.history.continuation_score(&previous, &PlayedMove::new(board, *mv))
+                },
+                _ => 0,
+            };
+            let threat = threatened & (1u64 << mv.start) != 0 || creates_threat(board, *mv);
+            return (if threat { 3 } else { 4 }, -continuation);
+        });
+    }
+
+    // Get the random bonus of a root move, up to the score noise of the options. It only depends on
+    // the seed, the position and the move, so that every iteration gives the same bonus.
+    pub(super) fn root_noise(&self, board: &Board, mv: Move) -> Score
+    {
+        let max = self.options.root_score_noise.0;
+        if max <= 0
+        {
+            return Score(0);
+        }
+        let square_pair = (mv.start * 64 + mv.end) as u64;
+        let key = self.options.seed ^ board.hash ^ square_pair.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        return Score(StdRng::seed_from_u64(key).random_range(0 ..= max));
+    }
+
+    // Shuffle the root moves with the probability of the opening variety, as long as no piece was
+    // traded, so that another of the moves scoring the same is played and the self-play games
+    // differ. The generator only depends on the seed and the position, so that every iteration
+    // makes the same choice.
+    fn vary_opening(&self, board: &Board, moves: &mut [Move])
+    {
+        let variety = self.options.opening_variety;
+        if variety <= 0.0 || game_phase(board) < MAX_PHASE
+        {
+            return;
+        }
+        let mut rng = StdRng::seed_from_u64(self.options.seed ^ board.hash);
+        if rng.random::<f32>() < variety
+        {
+            moves.shuffle(&mut rng);
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use super::alphabeta::Searcher;
+use crate::{
+    Board, Move, MoveContext, Score, generate_captures, generate_check_evasions, is_quiet,
+};
+
+// Number of quiescence plies in which quiet checking moves are searched along with captures.
+pub const QUIESCENCE_CHECK_PLIES: u8 = 1;
+// Maximum number of plies from the root beyond the depth of the iteration, after which quiescence
+// returns the static evaluation, so that a long chain of captures can't take the time of the whole
+// search.
+pub const MAX_QUIESCENCE_PLIES: u8 = 16;
+
+impl Searcher<'_>
+{
+    // Search the captures until the position is quiet, so that the static evaluation is never
+    // taken in the middle of an exchange. 'in_check' tells whether the side to move is in check:
+    // it can't stand pat then, since the evaluation ignores the threat on the king, so all its
+    // evasions are searched instead, and it is mated without any.
+    pub(super) fn quiesce(
+        &mut self,
+        board: &mut Board,
+        mut alpha: Score,
+        beta: Score,
+        in_check: bool,
+        check_plies: u8,
+        ply: u8,
+    ) -> Score
+    {
+        self.info.nodes += 1;
+        self.info.quiescence_nodes += 1;
+        self.info.seldepth = self.info.seldepth.max(ply);
+        if self.should_stop()
+        {
+            return Score::DRAW;
+        }
+        // A capture can end the game in the variants, e.g. by taking the last piece of the horde.
+        if board.variant_loss().is_some()
+        {
+            return Score::mated_in(ply);
+        }
+        if ply >= self.root_depth.saturating_add(MAX_QUIESCENCE_PLIES)
+        {
+            self.info.quiescence_depth_cuts += 1;
+            return self.evaluate(board);
+        }
+        let (mut best_value, moves) = if in_check
+        {
+            let mut evasions = generate_check_evasions(board);
+            if evasions.is_empty()
+            {
+                return Score::mated_in(ply);
+            }
+            // The captures come first, since they are the evasions most likely to keep the score.
+            evasions.sort_by_key(|mv| is_quiet(*mv));
+            (-Score::INFINITE, evasions)
+        }
+        else
+        {
+            let stand_pat = self.evaluate(board);
+            if stand_pat >= beta
+            {
+                return stand_pat;
+            }
+            if stand_pat > alpha
+            {
+                alpha = stand_pat;
+            }
+
+            // Captures are searched until the position is quiet.
+            let mut moves = generate_captures(board);
+
+            // During the first plies, quiet moves giving check are searched too, so that mating
+            // nets just behind the horizon are not missed.
+            if check_plies > 0
+            {
+                let mut quiet_checks: Vec<Move> = board
+                    .get_legal_moves()
+                    .into_iter()
+                    .filter(|mv| {
+                        mv.capture.is_none()
+                            && mv.context != MoveContext::EnPassant
+                            && board.gives_check(*mv)
+                    })
+                    .collect();
+                moves.append(&mut quiet_checks);
+            }
+
+            (stand_pat, moves)
+        };
+
+        for mv in moves.iter()
+        {
+            let checked = board.gives_check(*mv);
+            board.make_move(*mv);
+            let score = -self.quiesce(
+                board,
+                -beta,
+                -alpha,
+                checked,
+                check_plies.saturating_sub(1),
+                ply + 1,
+            );
+            board.unmake_move(*mv);
+            if self.stopped
+            {
+                return Score::DRAW;
+            }
+            if score >= beta
+            {
+                return score;
+            }
+            if score > best_value
+            {
+                best_value = score;
+            }
+            if score > alpha
+            {
+                alpha = score;
+            }
+        }
+        return best_value;
+    }
+}
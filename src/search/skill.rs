@@ -0,0 +1,73 @@
+use rand::{Rng, SeedableRng, rngs::StdRng};
+
+use crate::{Board, Move, PvLine, Score, Search};
+
+// Skill level of the full strength, the default, and of the weakest play.
+pub const MAX_SKILL_LEVEL: u8 = 20;
+// Number of root lines searched below the full strength, among which the move is chosen.
+pub const SKILL_LINES: usize = 4;
+// Scores beyond this many centipawns, mates included, weigh like it when a line is chosen.
+const SKILL_SCORE_CAP: i32 = 1000;
+
+// Get the maximum depth searched at a skill level: 1 ply at level 0, up to 5 plies at level 19, and
+// no limit at the full strength.
+pub fn skill_max_depth(level: u8) -> u8
+{
+    return if level >= MAX_SKILL_LEVEL { u8::MAX } else { 1 + level / 4 };
+}
+
+// Choose one of the lines found by a search at a skill level, each one with a weight falling
+// exponentially with how much worse than the best line it scores. The temperature of the weights
+// goes from 2 pawns at level 0 down to 10 centipawns at level 19, so that the strong levels
+// rarely miss the best move and the weak ones often blunder.
+pub fn choose_skill_line<'a>(
+    lines: &'a [PvLine],
+    level: u8,
+    rng: &mut impl Rng,
+) -> Option<&'a PvLine>
+{
+    if level >= MAX_SKILL_LEVEL || lines.len() < 2
+    {
+        return lines.first();
+    }
+    let temperature = 10.0 * (MAX_SKILL_LEVEL - level) as f64;
+    let capped = |score: Score| score.0.clamp(-SKILL_SCORE_CAP, SKILL_SCORE_CAP) as f64;
+    let best = lines.iter().map(|line| capped(line.score)).fold(f64::MIN, f64::max);
+    let weights: Vec<f64> =
+        lines.iter().map(|line| ((capped(line.score) - best) / temperature).exp()).collect();
+    let mut pick = rng.random::<f64>() * weights.iter().sum::<f64>();
+    for (line, weight) in lines.iter().zip(weights.iter())
+    {
+        if pick < *weight
+        {
+            return Some(line);
+        }
+        pick -= weight;
+    }
+    return lines.last();
+}
+
+impl Search
+{
+    // Choose the line to play among the lines found for a position, at the skill level of the
+    // options. The choice only depends on the seed of the options and on the position.
+    pub fn choose_line<'a>(&self, board: &Board, lines: &'a [PvLine]) -> Option<&'a PvLine>
+    {
+        let mut rng = StdRng::seed_from_u64(self.options.seed ^ board.hash);
+        return choose_skill_line(lines, self.options.skill_level, &mut rng);
+    }
+
+    // Search like 'search' below the full strength: the depth is limited by the skill level, and
+    // the move is chosen among the best lines by 'choose_line'.
+    pub(super) fn skill_search(&mut self, board: &mut Board, depth: u8) -> (Score, Option<Move>)
+    {
+        let max_depth = skill_max_depth(self.options.skill_level);
+        let lines = self.multipv(board, depth.min(max_depth), SKILL_LINES);
+        return match self.choose_line(board, &lines)
+        {
+            Some(line) => (line.score, line.moves.first().copied()),
+            // Without legal moves, the normal search scores the end of the game.
+            None => self.search_excluding(board, 1, &[]),
+        };
+    }
+}
@@ -0,0 +1,242 @@
+use serde::Serialize;
+
+use super::alphabeta::Searcher;
+use crate::{Move, Score};
+
+// Number of plies of the search tree recorded by default in the debug mode. The tree grows
+// exponentially with it, so it should stay small.
+pub const DEFAULT_TREE_PLIES: u8 = 3;
+
+// How a node of the search tree ended.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NodeEnd
+{
+    // All its moves were searched without a cutoff.
+    Searched,
+    // A move scored at least beta.
+    BetaCutoff,
+    // The transposition table had a result deep enough.
+    TtCutoff,
+    ReverseFutility,
+    ProbCut,
+    // Drawn by repetition or by the fifty-move rule.
+    Draw,
+    // Checkmate, stalemate or the end of a variant game.
+    GameOver,
+    // The remaining depth was 0, so the quiescence search took over.
+    Quiescence,
+    // The deadline was reached or the search was stopped, so the score is meaningless.
+    Stopped,
+}
+
+// A node of the main search, with the window it was searched with and its result. The scores are
+// in centipawns from the side to move's perspective.
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeNode
+{
+    // The move leading to the node in UCI, None for the root.
+    #[serde(rename = "move")]
+    pub mv: Option<String>,
+    pub ply: u8,
+    pub depth: u8,
+    pub alpha: i32,
+    pub beta: i32,
+    pub score: i32,
+    pub best_move: Option<String>,
+    // Whether the transposition table had an entry for the position.
+    pub tt_hit: bool,
+    pub end: NodeEnd,
+    // Set for the searches excluding some moves of the position, like the reduced search of the
+    // singular extension, which are children of the node they exclude moves of.
+    pub excluding: bool,
+    pub children: Vec<TreeNode>,
+}
+
+// The nodes of the first plies of an iteration of a search, recorded to debug the search. With
+// MultiPV, there is one root for each line.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchTree
+{
+    pub max_plies: u8,
+    pub roots: Vec<TreeNode>,
+    // The nodes being searched, from the root to the current node.
+    #[serde(skip)]
+    open: Vec<TreeNode>,
+}
+
+impl SearchTree
+{
+    // Record the nodes up to 'max_plies' plies from the root.
+    pub fn new(max_plies: u8) -> Self
+    {
+        return SearchTree { max_plies, roots: vec![], open: vec![] };
+    }
+
+    pub fn node_count(&self) -> usize
+    {
+        fn count(node: &TreeNode) -> usize
+        {
+            return 1 + node.children.iter().map(count).sum::<usize>();
+        }
+        return self.roots.iter().map(count).sum();
+    }
+
+    pub fn to_json(&self) -> Result<String, String>
+    {
+        return serde_json::to_string_pretty(self).map_err(|err| err.to_string());
+    }
+
+    // Write the tree in the DOT language of GraphViz, e.g. for 'dot -Tsvg tree.dot > tree.svg'.
+    // Each node shows its window, score and best move, and the nodes cut off are colored.
+    pub fn to_dot(&self) -> String
+    {
+        let mut dot = String::from("digraph search {\n    node [shape=box, fontname=monospace];\n");
+        let mut next_id = 0;
+        for root in self.roots.iter()
+        {
+            write_dot_node(&mut dot, root, None, &mut next_id);
+        }
+        dot.push_str("}\n");
+        return dot;
+    }
+
+    // Start recording a node, if it is close enough to the root.
+    fn enter(&mut self, node: TreeNode)
+    {
+        if node.ply <= self.max_plies
+        {
+            self.open.push(node);
+        }
+    }
+
+    // Get the node being searched at 'ply', if it is recorded.
+    fn current(&mut self, ply: u8) -> Option<&mut TreeNode>
+    {
+        return self.open.last_mut().filter(|node| node.ply == ply);
+    }
+
+    // Finish recording the node being searched at 'ply', and add it to its parent.
+    fn leave(&mut self, ply: u8, score: Score, best_move: Option<Move>, stopped: bool)
+    {
+        let Some(mut node) = self.current(ply).is_some().then(|| self.open.pop()).flatten()
+        else
+        {
+            return;
+        };
+        node.score = score.0;
+        node.best_move = best_move.map(|mv| mv.to_uci());
+        if stopped
+        {
+            node.end = NodeEnd::Stopped;
+        }
+        else if node.end == NodeEnd::Searched && node.score >= node.beta
+        {
+            node.end = NodeEnd::BetaCutoff;
+        }
+        match self.open.last_mut()
+        {
+            Some(parent) => parent.children.push(node),
+            None => self.roots.push(node),
+        }
+    }
+}
+
+// Write a node and its children, with an edge from its parent labeled with the move.
+fn write_dot_node(dot: &mut String, node: &TreeNode, parent: Option<usize>, next_id: &mut usize)
+{
+    let id = *next_id;
+    *next_id += 1;
+    let color = match node.end
+    {
+        NodeEnd::BetaCutoff | NodeEnd::ReverseFutility | NodeEnd::ProbCut => "salmon",
+        NodeEnd::TtCutoff => "lightblue",
+        NodeEnd::Draw | NodeEnd::GameOver => "lightgrey",
+        NodeEnd::Stopped => "yellow",
+        NodeEnd::Searched | NodeEnd::Quiescence => "white",
+    };
+    dot.push_str(&format!(
+        "    n{} [label=\"depth {}{}\\n[{}, {}]\\n{} {}\\n{:?}{}\", style=filled, fillcolor={}];\n",
+        id,
+        node.depth,
+        if node.excluding { " excluding" } else { "" },
+        window_bound(node.alpha),
+        window_bound(node.beta),
+        Score(node.score),
+        node.best_move.as_deref().unwrap_or("-"),
+        node.end,
+        if node.tt_hit { " tt" } else { "" },
+        color
+    ));
+    if let Some(parent) = parent
+    {
+        let label = node.mv.as_deref().unwrap_or("");
+        dot.push_str(&format!("    n{} -> n{} [label=\"{}\"];\n", parent, id, label));
+    }
+    for child in node.children.iter()
+    {
+        write_dot_node(dot, child, Some(id), next_id);
+    }
+}
+
+// Write a bound of the window of a node, the infinite ones included.
+fn window_bound(bound: i32) -> String
+{
+    if bound.abs() >= Score::INFINITE.0
+    {
+        return if bound < 0 { "-inf".into() } else { "inf".into() };
+    }
+    return Score(bound).to_string();
+}
+
+impl Searcher<'_>
+{
+    // Start recording a node of the main search, if the tree is recorded.
+    pub(super) fn enter_tree_node(
+        &mut self,
+        alpha: Score,
+        beta: Score,
+        depth: u8,
+        ply: u8,
+        excluding: bool,
+    )
+    {
+        let mv =
+            if ply > 0 && !excluding { self.stack.last().map(|played| played.mv) } else { None };
+        if let Some(tree) = self.tree.as_mut()
+        {
+            tree.enter(TreeNode {
+                mv: mv.map(|mv| mv.to_uci()),
+                ply,
+                depth,
+                alpha: alpha.0,
+                beta: beta.0,
+                score: 0,
+                best_move: None,
+                tt_hit: false,
+                end: NodeEnd::Searched,
+                excluding,
+                children: vec![],
+            });
+        }
+    }
+
+    // Update the node being searched at 'ply', if it is recorded.
+    pub(super) fn note_tree_node(&mut self, ply: u8, note: impl FnOnce(&mut TreeNode))
+    {
+        if let Some(node) = self.tree.as_mut().and_then(|tree| tree.current(ply))
+        {
+            note(node);
+        }
+    }
+
+    // Finish recording the node being searched at 'ply'.
+    pub(super) fn leave_tree_node(&mut self, ply: u8, result: (Score, Option<Move>))
+    {
+        let stopped = self.stopped;
+        if let Some(tree) = self.tree.as_mut()
+        {
+            tree.leave(ply, result.0, result.1, stopped);
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use super::alphabeta::Searcher;
+use crate::{Board, Move, Score, engine_config, is_promotion_piece};
+
+// Default number of entries of a transposition table.
+pub const DEFAULT_TT_ENTRIES: usize = 1 << 16;
+
+// Binary format of the saved tables: the magic bytes, the version (u16), the number of slots of the
+// table (u64), the number of entries (u64), and the entries, all little-endian. Only the filled
+// slots are written, so the table can be loaded with another number of slots.
+const TT_FILE_MAGIC: &[u8; 4] = b"BNTT";
+// Version 2 only stores the squares and the promotion piece of the best moves.
+const TT_FILE_VERSION: u16 = 2;
+const TT_HEADER_SIZE: usize = 4 + 2 + 8 + 8;
+// Key (8), depth (1), score (4), bound (1) and best move (4).
+const TT_ENTRY_SIZE: usize = 18;
+
+// Layout of the data of an entry packed in a u64: the depth, the bound, the score, the best move
+// (whether there is one, start, end and promotion piece), and a bit set in the filled slots.
+const DEPTH_SHIFT: u32 = 0;
+const BOUND_SHIFT: u32 = 8;
+const SCORE_SHIFT: u32 = 10;
+const MOVE_SHIFT: u32 = 42;
+const FILLED_BIT: u64 = 1 << 63;
+
+// Tells how a stored score relates to the real score of the position.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Bound
+{
+    // The score is exact.
+    Exact,
+    // The real score is at least the stored score (the search failed high).
+    Lower,
+    // The real score is at most the stored score (the search failed low).
+    Upper,
+}
+
+// The result of a search on a position, stored to be reused when the position is met again.
+// Only the squares and the promotion piece of the best move are stored: the rest of the move is
+// found again on the board by 'best_move_on'.
+#[derive(Debug, Clone, Copy)]
+pub struct TTEntry
+{
+    pub key: u64,
+    pub depth: u8,
+    // Mate scores are relative to the position, not to the root of the search.
+    pub score: Score,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+}
+
+impl TTEntry
+{
+    // Get the best move, ready to be played on the position of the entry. The state saved in the
+    // move, the moving and the captured pieces and the pieces exploded in Atomic are taken from the
+    // board. None is returned if the move can't be completed on the board, e.g. after a collision
+    // of the keys.
+    pub fn best_move_on(&self, board: &Board) -> Option<Move>
+    {
+        return self.best_move.and_then(|mv| board.complete_move(mv));
+    }
+
+    // Pack the entry, without its key, in the layout described at 'DEPTH_SHIFT'.
+    fn pack(&self) -> u64
+    {
+        let bound = match self.bound
+        {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+        let mv = match self.best_move
+        {
+            Some(mv) =>
+            {
+                let promotion = mv.promotion().unwrap_or(0) as u64;
+                1 | (mv.start as u64) << 1 | (mv.end as u64) << 7 | promotion << 13
+            },
+            None => 0,
+        };
+        return FILLED_BIT
+            | (self.depth as u64) << DEPTH_SHIFT
+            | bound << BOUND_SHIFT
+            | (self.score.0 as u32 as u64) << SCORE_SHIFT
+            | mv << MOVE_SHIFT;
+    }
+
+    // Unpack an entry packed by 'pack'.
+    fn unpack(key: u64, data: u64) -> Self
+    {
+        let bound = match (data >> BOUND_SHIFT) & 3
+        {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+        let mv = data >> MOVE_SHIFT;
+        let promotion = ((mv >> 13) & 7) as u8;
+        let best_move = (mv & 1 != 0).then_some(Move::bare(
+            ((mv >> 1) & 63) as usize,
+            ((mv >> 7) & 63) as usize,
+            (promotion != 0).then_some(promotion),
+        ));
+        return TTEntry {
+            key,
+            depth: (data >> DEPTH_SHIFT) as u8,
+            score: Score((data >> SCORE_SHIFT) as u32 as i32),
+            bound,
+            best_move,
+        };
+    }
+}
+
+// A slot of the table. The key is stored XORed with the data, so that a slot written by two threads
+// at once, with the key of one entry and the data of another, is seen as empty instead of giving a
+// wrong entry: the slots can be written without locks.
+#[derive(Default)]
+struct TTSlot
+{
+    check: AtomicU64,
+    data: AtomicU64,
+}
+
+impl TTSlot
+{
+    fn load(&self) -> Option<TTEntry>
+    {
+        let data = self.data.load(Ordering::Relaxed);
+        if data & FILLED_BIT == 0
+        {
+            return None;
+        }
+        return Some(TTEntry::unpack(self.check.load(Ordering::Relaxed) ^ data, data));
+    }
+
+    fn store(&self, entry: &TTEntry)
+    {
+        let data = entry.pack();
+        self.check.store(entry.key ^ data, Ordering::Relaxed);
+        self.data.store(data, Ordering::Relaxed);
+    }
+
+    fn clear(&self)
+    {
+        self.data.store(0, Ordering::Relaxed);
+        self.check.store(0, Ordering::Relaxed);
+    }
+}
+
+// Memory used by each entry of a transposition table, in bytes.
+pub const TT_ENTRY_BYTES: usize = size_of::<TTSlot>();
+
+// A hash table indexed by the Zobrist hash of the positions.
+// Each index holds a bucket of two slots: the first one keeps the deepest search met at the index,
+// and the second one takes every other entry. A deep entry replaced by a deeper one moves to the
+// second slot, so the entries of the principal variation, searched deeper than the rest of the
+// tree, are not evicted by the many shallow nodes sharing their index.
+// The table is shared between threads without locks, e.g. by searches of the same position in
+// parallel or by the analyses of several clients, see 'TTSlot'.
+pub struct TranspositionTable
+{
+    buckets: Vec<[TTSlot; 2]>,
+}
+
+impl TranspositionTable
+{
+    // Create an empty table with the given number of entries, rounded down to a whole number of
+    // buckets.
+    pub fn new(size: usize) -> Self
+    {
+        return TranspositionTable {
+            buckets: (0 .. (size / 2).max(1)).map(|_| Default::default()).collect(),
+        };
+    }
+
+    // Get the entry stored for a position, if there is one.
+    pub fn probe(&self, key: u64) -> Option<TTEntry>
+    {
+        return self.buckets[self.index(key)]
+            .iter()
+            .find_map(|slot| slot.load().filter(|entry| entry.key == key));
+    }
+
+    // Store an entry. An entry of the same position is replaced unless it holds a deeper search;
+    // otherwise the entry goes to the depth slot when it is at least as deep as the one there, and
+    // to the other slot when it isn't.
+    pub fn store(&self, entry: TTEntry)
+    {
+        let [deep, recent] = &self.buckets[self.index(entry.key)];
+        let old_deep = deep.load();
+        if let Some(old) = old_deep.filter(|old| old.key == entry.key)
+        {
+            if old.depth <= entry.depth
+            {
+                deep.store(&entry);
+            }
+            return;
+        }
+        match old_deep
+        {
+            Some(old) if old.depth > entry.depth => recent.store(&entry),
+            Some(old) =>
+            {
+                deep.store(&entry);
+                recent.store(&old);
+            },
+            None => deep.store(&entry),
+        }
+    }
+
+    // Remove all entries.
+    pub fn clear(&self)
+    {
+        self.slots().for_each(TTSlot::clear);
+    }
+
+    // Get the number of slots of the table.
+    pub fn size(&self) -> usize
+    {
+        return self.buckets.len() * 2;
+    }
+
+    // Get the memory used by the slots, in bytes.
+    pub fn bytes(&self) -> usize
+    {
+        return self.size() * TT_ENTRY_BYTES;
+    }
+
+    // Get the number of filled slots.
+    pub fn used(&self) -> usize
+    {
+        return self.slots().filter(|slot| slot.load().is_some()).count();
+    }
+
+    // Write the table in the binary format described at 'TT_FILE_MAGIC'.
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut data = Vec::with_capacity(TT_HEADER_SIZE + self.used() * TT_ENTRY_SIZE);
+        data.extend_from_slice(TT_FILE_MAGIC);
+        data.extend_from_slice(&TT_FILE_VERSION.to_le_bytes());
+        data.extend_from_slice(&(self.size() as u64).to_le_bytes());
+        data.extend_from_slice(&(self.used() as u64).to_le_bytes());
+        for entry in self.slots().filter_map(TTSlot::load)
+        {
+            data.extend_from_slice(&entry.key.to_le_bytes());
+            data.push(entry.depth);
+            data.extend_from_slice(&entry.score.0.to_le_bytes());
+            data.push(match entry.bound
+            {
+                Bound::Exact => 0,
+                Bound::Lower => 1,
+                Bound::Upper => 2,
+            });
+            data.extend_from_slice(&encode_move(entry.best_move));
+        }
+        return data;
+    }
+
+    // Read a table written by 'to_bytes'. The table has the given number of slots, or the number
+    // of slots of the saved table if none is given. When the sizes differ, the entries are stored
+    // again in the new slots, and the deeper searches are kept on collisions.
+    pub fn from_bytes(data: &[u8], size: Option<usize>) -> Result<Self, String>
+    {
+        if data.len() < TT_HEADER_SIZE || &data[0 .. 4] != TT_FILE_MAGIC
+        {
+            return Err("Not a transposition table file.".into());
+        }
+        let version = u16::from_le_bytes([data[4], data[5]]);
+        if version != TT_FILE_VERSION
+        {
+            return Err(format!(
+                "Unsupported transposition table version {} (expected {}).",
+                version, TT_FILE_VERSION
+            ));
+        }
+        let read_u64 = |offset: usize| {
+            u64::from_le_bytes(data[offset .. offset + 8].try_into().unwrap_or_default())
+        };
+        let saved_size = read_u64(6) as usize;
+        let count = read_u64(14) as usize;
+        if count.checked_mul(TT_ENTRY_SIZE).and_then(|n| n.checked_add(TT_HEADER_SIZE))
+            != Some(data.len())
+        {
+            return Err("Truncated or corrupted transposition table file.".into());
+        }
+
+        let tt = TranspositionTable::new(size.unwrap_or(saved_size));
+        for chunk in data[TT_HEADER_SIZE ..].chunks_exact(TT_ENTRY_SIZE)
+        {
+            let bound = match chunk[13]
+            {
+                0 => Bound::Exact,
+                1 => Bound::Lower,
+                2 => Bound::Upper,
+                _ => return Err("Invalid bound in transposition table file.".into()),
+            };
+            tt.store(TTEntry {
+                key: u64::from_le_bytes(chunk[0 .. 8].try_into().unwrap_or_default()),
+                depth: chunk[8],
+                score: Score(i32::from_le_bytes(chunk[9 .. 13].try_into().unwrap_or_default())),
+                bound,
+                best_move: decode_move(&chunk[14 .. 18])?,
+            });
+        }
+        return Ok(tt);
+    }
+
+    // Save the table to a file.
+    pub fn save(&self, path: &str) -> Result<(), String>
+    {
+        return std::fs::write(path, self.to_bytes())
+            .map_err(|err| format!("Can't write '{}': {}", path, err));
+    }
+
+    // Load a table saved to a file, see 'from_bytes'.
+    pub fn load(path: &str, size: Option<usize>) -> Result<Self, String>
+    {
+        let data = std::fs::read(path).map_err(|err| format!("Can't read '{}': {}", path, err))?;
+        return Self::from_bytes(&data, size);
+    }
+
+    fn slots(&self) -> impl Iterator<Item = &TTSlot>
+    {
+        return self.buckets.iter().flatten();
+    }
+
+    fn index(&self, key: u64) -> usize
+    {
+        return (key % self.buckets.len() as u64) as usize;
+    }
+}
+
+// The default size comes from the engine configuration.
+impl Default for TranspositionTable
+{
+    fn default() -> Self
+    {
+        return Self::new(engine_config().table_sizes().tt_entries);
+    }
+}
+
+impl Searcher<'_>
+{
+    // Probe the table for the position, counting the probes and the hits.
+    pub(super) fn probe_tt(&mut self, board: &Board) -> Option<TTEntry>
+    {
+        let entry = self.tt.probe(board.hash);
+        self.info.tt_probes += 1;
+        if entry.is_some()
+        {
+            self.info.tt_hits += 1;
+        }
+        return entry;
+    }
+
+    // Store the result of a node searched in the window from 'alpha' to 'beta', with the bound
+    // telling how 'max' relates to the real score.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn store_tt(
+        &mut self,
+        board: &Board,
+        depth: u8,
+        ply: u8,
+        max: Score,
+        alpha: Score,
+        beta: Score,
+        best: Option<Move>,
+    )
+    {
+        let bound = if max >= beta
+        {
+            Bound::Lower
+        }
+        else if max <= alpha
+        {
+            Bound::Upper
+        }
+        else
+        {
+            Bound::Exact
+        };
+        self.tt.store(TTEntry {
+            key: board.hash,
+            depth,
+            score: max.to_tt(ply),
+            bound,
+            best_move: best,
+        });
+    }
+}
+
+// Write a move on 4 bytes: whether there is one, start, end and promotion piece (0 for none).
+fn encode_move(mv: Option<Move>) -> [u8; 4]
+{
+    return match mv
+    {
+        Some(mv) => [1, mv.start as u8, mv.end as u8, mv.promotion().unwrap_or(0)],
+        None => [0; 4],
+    };
+}
+
+fn decode_move(bytes: &[u8]) -> Result<Option<Move>, String>
+{
+    if bytes[0] == 0
+    {
+        return Ok(None);
+    }
+    if bytes[1] >= 64 || bytes[2] >= 64 || (bytes[3] != 0 && !is_promotion_piece(bytes[3]))
+    {
+        return Err("Invalid move in transposition table file.".into());
+    }
+    let promotion = (bytes[3] != 0).then_some(bytes[3]);
+    return Ok(Some(Move::bare(bytes[1] as usize, bytes[2] as usize, promotion)));
+}
@@ -0,0 +1,1407 @@
+use std::{
+    sync::Arc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use rand::Rng;
+
+use crate::*;
+
+// Positions from which the self-test explores the game tree: the initial position, the usual
+// perft positions, and a few endgames.
+const SELFTEST_POSITIONS: [&str; 8] = [
+    START_FEN,
+    "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -",
+    "8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -",
+    "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq -",
+    "rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ -",
+    "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - -",
+    "8/8/4k3/3p4/3P4/4K3/8/8 w - -",
+    "8/1k6/8/8/8/8/6Q1/4K2R w K -",
+];
+
+// Positions of the variants explored by the self-test, with pawns of the horde on the first rank,
+// kings close to the center, explosions near the kings and rooks, and sides without kings.
+const VARIANT_SELFTEST_POSITIONS: [(Variant, &str); 4] = [
+    (Variant::Horde, "4k3/pp6/8/8/8/1P6/P1P5/PPPP4 w - -"),
+    (Variant::KingOfTheHill, "r3k2r/8/8/3p4/8/4K3/8/R6R w kq -"),
+    (Variant::Atomic, "r3k2r/p1p2p1p/2n5/1b1pP3/4Q3/2N5/PPP2PPP/R3K2R w KQkq d6"),
+    (Variant::Antichess, "8/1p3k2/2n5/8/3q4/1P6/P4R2/8 b - -"),
+];
+
+// Positions with their known number of leaf nodes at a given depth, to catch move generation
+// regressions. Most of them are chosen for en passant captures exposing the king along a rank or
+// a diagonal, castling through or out of check, castling rights kept without the rook or the king
+// on its square, and promotions giving or escaping check.
+const PERFT_POSITIONS: [(&str, usize, u64); 23] = [
+    (START_FEN, 4, 197281),
+    ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq -", 3, 97862),
+    ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -", 5, 674624),
+    ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq -", 4, 422333),
+    ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ -", 3, 62379),
+    ("r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - -", 3, 89890),
+    ("3k4/3p4/8/K1P4r/8/8/8/8 b - -", 6, 1134888),
+    ("8/8/4k3/8/2p5/8/B2P2K1/8 w - -", 6, 1015133),
+    ("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3", 6, 1440467),
+    ("5k2/8/8/8/8/8/8/4K2R w K -", 6, 661072),
+    ("3k4/8/8/8/8/8/8/R3K3 w Q -", 6, 803711),
+    ("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq -", 4, 1274206),
+    ("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq -", 4, 1720476),
+    ("2K2r2/4P3/8/8/8/8/8/3k4 w - -", 6, 3821001),
+    ("8/8/1P2K3/8/2n5/1q6/8/5k2 b - -", 5, 1004658),
+    ("4k3/1P6/8/8/8/8/K7/8 w - -", 6, 217342),
+    ("8/P1k5/K7/8/8/8/8/8 w - -", 6, 92683),
+    ("K1k5/8/P7/8/8/8/8/8 w - -", 6, 2217),
+    ("8/k1P5/8/1K6/8/8/8/8 w - -", 7, 567584),
+    ("8/8/2k5/5q2/5n2/8/5K2/8 b - -", 4, 23527),
+    ("8/8/8/8/k2Pp2Q/8/8/3K4 b - d3", 4, 20471),
+    ("r3k3/8/8/8/8/8/8/4K2R w Kkq -", 5, 961399),
+    ("r3k2r/8/8/8/8/8/8/R4K1R w KQkq -", 4, 261945),
+];
+
+// Perft positions of the variants. In King of the Hill, the positions where a king reached the
+// center have no moves.
+const VARIANT_PERFT_POSITIONS: [(Variant, &str, usize, u64); 6] = [
+    (Variant::Horde, HORDE_START_FEN, 5, 265223),
+    (Variant::KingOfTheHill, "8/8/8/8/8/4K3/8/k7 w - -", 2, 18),
+    (Variant::Atomic, START_FEN, 4, 197326),
+    (Variant::Atomic, "rn2kb1r/1pp1p2p/p2q1pp1/3P4/2P3b1/4PN2/PP3PPP/R2QKB1R b KQkq -", 3, 45237),
+    (Variant::Atomic, "rn1qkb1r/p5pp/2p5/3p4/N3P3/5P2/PPP4P/R1BQK3 w Qkq -", 3, 23353),
+    (Variant::Antichess, ANTICHESS_START_FEN, 4, 153299),
+];
+
+// Entries of the tables of the hashed perft, few enough for their counts to replace each other.
+const PERFT_TABLE_ENTRIES: usize = 1 << 12;
+
+// Positions of the mate search, with the moves tried by the mating side, the maximum number of
+// its moves, and the number of moves of the mate expected to be found, if any.
+const MATE_POSITIONS: [(&str, bool, u8, Option<usize>); 4] = [
+    ("6k1/5ppp/8/8/8/8/8/R5K1 w - -", false, 2, Some(1)),
+    ("r1b1kb1r/pppp1ppp/5q2/4n3/3KP3/2N3PN/PPP4P/R1BQ1B1R b kq -", false, 3, Some(3)),
+    // The ladder mate starts with a quiet move.
+    ("3k4/8/8/8/8/8/R7/1R4K1 w - -", false, 3, None),
+    ("3k4/8/8/8/8/8/R7/1R4K1 w - -", true, 3, Some(3)),
+];
+
+// Positions of the distance-to-mate tables with their distance in plies, including the longest
+// mates of each ending.
+const DTM_POSITIONS: [(&str, Option<i32>); 6] = [
+    ("8/8/8/5k2/8/8/1Q6/K7 w - -", Some(19)),
+    ("k7/1q6/8/8/5K2/8/8/8 b - -", Some(19)),
+    ("8/8/8/8/8/2k5/1R6/K7 w - -", Some(31)),
+    ("8/8/8/1k6/8/8/K5P1/8 w - -", Some(55)),
+    ("4k3/8/4K3/4P3/8/8/8/8 b - -", Some(-24)),
+    ("4k3/4P3/4K3/8/8/8/8/8 b - -", None),
+];
+
+// A game read from PGN, then written back and read again in PGN, with its moves in each notation,
+// and in JSON, with the number of its moves. It has a move number glued to its move, evaluations,
+// clocks, a time spent on a move, a mate score, comments, suffixes, numeric annotation glyphs and a
+// variation.
+const RECORD_PGN: (&str, usize) = (
+    "[Event \"Self-test\"]\n[White \"Alice \\\"A\\\"\"]\n[Black \"Bob\"]\n[Result \"1-0\"]\n\n\
+     1.e4 {[%eval 0.30] [%clk 0:05:00]} e5 {[%eval 0.25] [%clk 0:04:58.5] [%emt 0:00:01.5] Open game} 2. Bc4 $1 \
+     Nc6 3. Qh5 Nf6?? (3... g6 4. Qf3) 4. Qxf7# {[%eval #0] [%cal Gf7e8] Mate} 1-0",
+    7,
+);
+
+// Depth of the search whose principal variation is read back from the transposition table.
+const TT_PV_DEPTH: u8 = 5;
+
+// Depth of the searches annotating the game of 'RECORD_PGN'.
+const ANNOTATION_DEPTH: u8 = 3;
+
+// Statistics of a self-test.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SelftestReport
+{
+    pub positions: usize,
+    pub failures: usize,
+}
+
+// Maximum number of random moves played to create the random positions of the self-test.
+const RANDOM_POSITION_MAX_PLIES: usize = 80;
+
+// Check the consistency of the board code on every position reached in 'depth' plies from the
+// self-test positions, and from 'random' positions created by random games:
+// - the evaluation is symmetric: the evaluation of the flipped position is the same from the side
+//   to move's perspective, and the opposite from white's perspective;
+// - the evaluation agrees with the total of its trace;
+// - making then unmaking a move gives back the same board;
+// - the hashes updated by 'make_move' are the same as the hashes computed from scratch;
+// - the SAN of each move is parsed back to the same move;
+// - checking the legality of a single move agrees with the move generation, and checking whether
+//   it gives check agrees with making it.
+// The moves are written in SAN, LAN and ICCF numeric notation like their standards, the move
+// counters are written to FEN, and the lists of UCI moves are played. The positions set up piece
+// by piece are the same as those read from FEN.
+// The iterative deepening stops early on a proven mate, and on a stable best move when its time
+// runs out. The time limits of a move stay within its clock. Quiescence stops at its limit, and
+// doesn't stand pat in check. The pawn structures and the space they gain are found and cached, and
+// the threats on the pieces are found. The initial position is worth the tempo to the side to move.
+// The drawish endings are scaled down. The mate search finds the expected mates, and its lines end
+// with a checkmate. The transposition table keeps the whole principal variation. A game record is
+// the same once written and read back in PGN and in JSON, and its annotation only flags the
+// blunder.
+// The chances of a win, a draw and a loss given by a score are consistent, and the mate scores are
+// told apart from the centipawns. The evaluation weights and piece-square tables read from a file
+// are applied and validated.
+// With 'perft', the node counts of the perft regression positions, counted with and without a
+// table of the counts, and the distances to mate of the tables are checked too.
+// Each failure is printed with the FEN of the position.
+pub fn run_selftest(depth: usize, random: usize, perft: bool) -> SelftestReport
+{
+    let mut report = SelftestReport::default();
+    if perft
+    {
+        check_perft(&mut report);
+        check_dtm(&mut report);
+        check_kpk_bitbase(&mut report);
+    }
+    check_mates(&mut report);
+    check_validation(&mut report);
+    check_builder(&mut report);
+    check_en_passant_fen(&mut report);
+    check_fen_counters(&mut report);
+    check_notations(&mut report);
+    check_uci_moves(&mut report);
+    check_best_move(&mut report);
+    check_basic_search(&mut report);
+    check_stop_conditions(&mut report);
+    check_time_limits(&mut report);
+    check_quiescence_limit(&mut report);
+    check_quiescence_checks(&mut report);
+    check_pawn_structure(&mut report);
+    check_space(&mut report);
+    check_threats(&mut report);
+    check_side_to_move(&mut report);
+    check_endgame_scale(&mut report);
+    check_skill(&mut report);
+    check_shared_tt(&mut report);
+    check_tt_pv(&mut report);
+    check_record(&mut report);
+    check_guess(&mut report);
+    check_movetext(&mut report);
+    check_annotation(&mut report);
+    check_win_probability(&mut report);
+    check_score_kinds(&mut report);
+    check_config(&mut report);
+    check_masks(&mut report);
+    for fen in SELFTEST_POSITIONS
+    {
+        let mut board = Board::from_fen(fen).expect("The self-test positions are valid.");
+        check_tree(&mut board, depth, &mut report);
+    }
+    for (variant, fen) in VARIANT_SELFTEST_POSITIONS
+    {
+        let mut board =
+            Board::from_fen_variant(fen, variant).expect("The self-test positions are valid.");
+        check_tree(&mut board, depth, &mut report);
+    }
+    let mut rng = default_randomness().rng(0);
+    for _ in 0 .. random
+    {
+        let plies = rng.random_range(0 ..= RANDOM_POSITION_MAX_PLIES);
+        let mut board = Board::random_position(&mut rng, plies);
+        check_tree(&mut board, depth, &mut report);
+    }
+    return report;
+}
+
+fn check_perft(report: &mut SelftestReport)
+{
+    let positions =
+        PERFT_POSITIONS.map(|(fen, depth, expected)| (Variant::Standard, fen, depth, expected));
+    for (variant, fen, depth, expected) in positions.into_iter().chain(VARIANT_PERFT_POSITIONS)
+    {
+        report.positions += 1;
+        let mut board =
+            Board::from_fen_variant(fen, variant).expect("The perft positions are valid.");
+        let nodes = crate::perft(&mut board, depth);
+        if nodes != expected
+        {
+            println!(
+                "perft {}: {} nodes instead of {} ({}, {})",
+                depth,
+                nodes,
+                expected,
+                fen,
+                variant.name()
+            );
+            report.failures += 1;
+        }
+        // The hash doesn't tell the variants apart, so each position gets its own table.
+        let hashed = perft_hashed(&mut board, depth, &mut PerftTable::new(PERFT_TABLE_ENTRIES));
+        if hashed != nodes
+        {
+            println!(
+                "hashed perft {}: {} nodes instead of {} ({}, {})",
+                depth,
+                hashed,
+                nodes,
+                fen,
+                variant.name()
+            );
+            report.failures += 1;
+        }
+    }
+}
+
+fn check_dtm(report: &mut SelftestReport)
+{
+    for (fen, expected) in DTM_POSITIONS
+    {
+        report.positions += 1;
+        let board = Board::from_fen(fen).expect("The distance-to-mate positions are valid.");
+        if dtm(&board) != expected
+        {
+            println!("distance to mate: {:?} instead of {:?} ({})", dtm(&board), expected, fen);
+            report.failures += 1;
+        }
+    }
+}
+
+// The bitbase embedded by the build script must be the one generated by the crate.
+fn check_kpk_bitbase(report: &mut SelftestReport)
+{
+    report.positions += 1;
+    if generate_kpk() != kpk_bitbase()
+    {
+        println!("KPK bitbase: the embedded bitbase differs from a new generation");
+        report.failures += 1;
+    }
+}
+
+fn check_mates(report: &mut SelftestReport)
+{
+    for (fen, all_moves, max_moves, expected) in MATE_POSITIONS
+    {
+        report.positions += 1;
+        let mut board = Board::from_fen(fen).expect("The mate positions are valid.");
+        let line = MateSearch::new(all_moves).find(&mut board, max_moves);
+        let found = line.as_ref().map(|line| line.len().div_ceil(2));
+        let mut mated = true;
+        if let Some(line) = &line
+        {
+            let mut after = board;
+            for mv in line.iter()
+            {
+                after.make_move(*mv);
+            }
+            mated = is_king_attacked(&after, false) && after.get_legal_moves().is_empty();
+        }
+        if found != expected || !mated
+        {
+            println!("mate search: {:?} moves instead of {:?} ({})", found, expected, fen);
+            report.failures += 1;
+        }
+    }
+}
+
+// Check that the initial positions of the variants are legal, that each illegal position is
+// rejected for the expected reason, and that the malformed en passant fields are errors.
+fn check_validation(report: &mut SelftestReport)
+{
+    for variant in Variant::ALL
+    {
+        report.positions += 1;
+        let fen = variant.start_fen();
+        let board =
+            Board::from_fen_variant(fen, variant).expect("The initial positions are valid.");
+        let violations = board.validate();
+        if !violations.is_empty()
+        {
+            println!("validation: {:?} in a legal position ({})", violations, fen);
+            report.failures += 1;
+        }
+    }
+    for (fen, expected) in [
+        (
+            "4k3/pppppppp/p7/8/8/8/PPPPPPPP/4K3 w - -",
+            Violation::TooManyPawns { white: false, count: 9 },
+        ),
+        ("4k3/8/8/8/8/8/QQQQQQQQ/QQQQK3 w - -", Violation::TooManyPromotedPieces { white: true }),
+        ("4k3/8/8/8/8/8/8/P3K3 w - -", Violation::PawnOnBackRank(0)),
+        ("8/8/8/3kK3/8/8/8/8 w - -", Violation::KingsAdjacent),
+        ("4k3/8/8/8/8/8/4R3/4K3 w - -", Violation::OpponentInCheck),
+        ("4k3/8/8/8/8/8/8/4K3 w K -", Violation::InvalidCastlingRight('K')),
+    ]
+    {
+        report.positions += 1;
+        let board = Board::from_fen(fen).expect("The illegal positions can be parsed.");
+        if !board.validate().contains(&expected) || Board::from_fen_strict(fen).is_ok()
+        {
+            println!("validation: {:?} not found ({})", expected, fen);
+            report.failures += 1;
+        }
+    }
+    // The malformed en passant fields are rejected before any validation.
+    for en_passant in ["e", "E3", "e9", "e33", "\u{e9}3"]
+    {
+        report.positions += 1;
+        let fen = format!("4k3/8/8/8/8/8/8/4K3 w - {}", en_passant);
+        if Board::from_fen(&fen).is_ok() || Board::from_fen_strict(&fen).is_ok()
+        {
+            println!("validation: malformed en passant field accepted ({})", fen);
+            report.failures += 1;
+        }
+    }
+}
+
+// Check that a position set up piece by piece is the same as when it is read from FEN, that a board
+// is the same once turned into a builder and built again, and that the positions that can't be
+// reached are rejected.
+fn check_builder(report: &mut SelftestReport)
+{
+    report.positions += 1;
+    let mut builder = BoardBuilder::new().castling(ALL_CASTLING_RIGHTS);
+    for (file, piece_type) in
+        [ROOK, KNIGHT, BISHOP, QUEEN, KING, BISHOP, KNIGHT, ROOK].into_iter().enumerate()
+    {
+        builder = builder
+            .piece(file, WHITE, piece_type)
+            .piece(8 + file, WHITE, PAWN)
+            .piece(48 + file, BLACK, PAWN)
+            .piece(56 + file, BLACK, piece_type);
+    }
+    let expected = Board::new().expect("The initial position is valid.");
+    if builder.build() != Ok(expected)
+    {
+        println!("builder: the initial position is built as {:?}", builder.build());
+        report.failures += 1;
+    }
+
+    for fen in PERFT_POSITIONS.map(|(fen, _, _)| fen)
+    {
+        // Some of the positions keep castling rights without the rook, which the builder rejects.
+        let board = Board::from_fen(fen).expect("The perft positions are valid.");
+        if !board.validate().is_empty()
+        {
+            continue;
+        }
+        report.positions += 1;
+        if board.to_builder().build() != Ok(board)
+        {
+            println!(
+                "builder: the position is built as {:?} ({})",
+                board.to_builder().build(),
+                fen
+            );
+            report.failures += 1;
+        }
+    }
+
+    report.positions += 1;
+    let kings = BoardBuilder::new().piece(4, WHITE, KING);
+    let errors = [
+        (kings.clone().build(), ValidationError::MissingKing { white: false }),
+        (
+            kings.clone().piece(12, BLACK, KING).build(),
+            ValidationError::Unreachable(vec![
+                Violation::KingsAdjacent,
+                Violation::OpponentInCheck,
+            ]),
+        ),
+        (kings.clone().piece(60, WHITE, KING).build(), ValidationError::ExtraKing { white: true }),
+    ];
+    for (built, expected) in errors
+    {
+        if built != Err(expected.clone())
+        {
+            println!("builder: {:?} instead of the error {:?}", built, expected);
+            report.failures += 1;
+        }
+    }
+}
+
+// Check that the en passant targets are only kept in FEN when a pawn can legally capture on them,
+// and that an impossible one set on a board is reported.
+fn check_en_passant_fen(report: &mut SelftestReport)
+{
+    for (fen, expected) in [
+        // No black pawn next to the pawn that moved.
+        ("rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3", "-"),
+        ("rnbqkbnr/ppp1pppp/8/8/3pP3/8/PPPP1PPP/RNBQKBNR b KQkq e3", "e3"),
+        // The pawn next to it is pinned against its king.
+        ("8/8/8/8/k2pP2R/8/8/4K3 b - e3", "-"),
+        // No pawn in front of the target.
+        ("4k3/8/8/8/8/8/8/R3K3 w - e6", "-"),
+    ]
+    {
+        report.positions += 1;
+        let board = Board::from_fen(fen).expect("The en passant positions are valid.");
+        let written = board.to_fen();
+        if written.split_whitespace().nth(3) != Some(expected)
+        {
+            println!("en passant: '{}' instead of '{}' ({})", written, expected, fen);
+            report.failures += 1;
+        }
+    }
+
+    report.positions += 1;
+    let mut board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - -").expect("The position is valid.");
+    board.en_passant_target = Some(44);
+    if !board.validate().contains(&Violation::InvalidEnPassant(44))
+    {
+        println!("validation: the impossible en passant target e6 is not reported");
+        report.failures += 1;
+    }
+}
+
+// Check that the move counters are written back to FEN, and that they follow the moves played.
+fn check_fen_counters(report: &mut SelftestReport)
+{
+    report.positions += 1;
+    let fen = "4k3/8/8/8/8/8/4P3/4K3 b - - 12 40";
+    let mut board = Board::from_fen(fen).expect("The position is valid.");
+    let written = board.to_fen();
+    let mv = parse_uci_move(&mut board, "e8d8").expect("The move is legal.");
+    let mv = board.make_move(mv);
+    let after_black = board.to_fen();
+    let pawn_move = parse_uci_move(&mut board, "e2e4").expect("The move is legal.");
+    let pawn_move = board.make_move(pawn_move);
+    let after_white = board.to_fen();
+    board.unmake_move(pawn_move);
+    board.unmake_move(mv);
+    if written != fen
+        || !after_black.ends_with(" w - - 13 41")
+        || !after_white.ends_with(" b - - 0 41")
+        || board.to_fen() != fen
+    {
+        println!("move counters: {}, {}, {} ({})", written, after_black, after_white, fen);
+        report.failures += 1;
+    }
+}
+
+// Check that a list of UCI moves reaches the expected position, and that a list with a move that
+// can't be played leaves the board unchanged.
+fn check_uci_moves(report: &mut SelftestReport)
+{
+    report.positions += 1;
+    let start = Board::new().expect("The initial position is valid.");
+    let expected =
+        Board::from_fen("rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2")
+            .expect("The position after the moves is valid.");
+    let mut board = start;
+    if board.apply_uci_moves(&["e2e4 e7e5", "g1f3"]) != Ok(()) || board != expected
+    {
+        println!("uci moves: {} reached instead of {}", board.to_fen(), expected.to_fen());
+        report.failures += 1;
+    }
+    for (moves, expected) in [
+        (["e2e4", "e2e4"], MoveError::Illegal { index: 1, text: "e2e4".into() }),
+        (["e2e4", "e7e9"], MoveError::InvalidNotation { index: 1, text: "e7e9".into() }),
+    ]
+    {
+        let mut board = start;
+        let result = board.apply_uci_moves(&moves);
+        if result != Err(expected.clone()) || board != start
+        {
+            println!("uci moves: {:?} instead of the error {:?} ({:?})", result, expected, moves);
+            report.failures += 1;
+        }
+    }
+}
+
+// Check the moves written in SAN, in LAN and in ICCF numeric notation: '#' for a checkmate, '+'
+// for a check, including by castling, the promotions written with '=' and a capital letter or
+// with a digit, and each notation parsed back to the same move.
+fn check_notations(report: &mut SelftestReport)
+{
+    let cases = [
+        ("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - -", "a1a8", "Ra8#", "Ra1-a8#", "1118"),
+        ("4k3/P7/8/8/8/8/8/4K3 w - -", "a7a8q", "a8=Q+", "a7-a8=Q+", "17181"),
+        ("4k3/P7/8/8/8/8/8/4K3 w - -", "a7a8n", "a8=N", "a7-a8=N", "17184"),
+        ("5k2/8/8/8/8/8/8/4K2R w K -", "e1g1", "O-O+", "O-O+", "5171"),
+        ("4k3/8/8/8/8/8/8/R3K3 w Q -", "e1c1", "O-O-O", "O-O-O", "5131"),
+        ("4k3/8/8/3pP3/8/8/8/4K3 w - d6", "e5d6", "exd6", "e5xd6", "5546"),
+    ];
+    for (fen, uci, san, lan, iccf) in cases
+    {
+        report.positions += 1;
+        let mut board = Board::from_fen(fen).expect("The notation positions are valid.");
+        let Some(mv) = parse_uci_move(&mut board, uci)
+        else
+        {
+            println!("notations: {} is not legal ({})", uci, fen);
+            report.failures += 1;
+            continue;
+        };
+        let written = (mv.to_san(&mut board), mv.to_lan(&mut board), mv.to_iccf());
+        let parsed = [
+            parse_san_move(&mut board, san),
+            parse_lan_move(&mut board, lan),
+            parse_iccf_move(&mut board, iccf),
+        ];
+        if written != (san.into(), lan.into(), iccf.into()) || parsed.iter().any(|p| *p != Some(mv))
+        {
+            println!(
+                "notations: {:?} instead of {} {} {} for {} ({})",
+                written, san, lan, iccf, uci, fen
+            );
+            report.failures += 1;
+        }
+    }
+}
+
+// Check the one-call search: a back rank mate is found, and the errors are told apart.
+fn check_best_move(report: &mut SelftestReport)
+{
+    let cases = [
+        ("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - -", Ok("a1a8".to_string())),
+        ("R5k1/5ppp/8/8/8/8/5PPP/6K1 b - -", Err(ChessError::NoLegalMove)),
+    ];
+    for (fen, expected) in cases
+    {
+        report.positions += 1;
+        let found = best_move(fen, 2).map(|(mv, _)| mv);
+        if found != expected
+        {
+            println!("best move: {:?} instead of {:?} ({})", found, expected, fen);
+            report.failures += 1;
+        }
+    }
+    if !matches!(best_move("8/8/8 w - -", 2), Err(ChessError::InvalidFen(_)))
+    {
+        println!("best move: an invalid FEN is not reported");
+        report.failures += 1;
+    }
+}
+
+// Check the early stops of the iterative deepening: a proven mate ends it sooner with the same
+// move, and a stable best move ends it only after enough iterations with a close score, when
+// little time is left before the deadline.
+fn check_stop_conditions(report: &mut SelftestReport)
+{
+    report.positions += 1;
+    let mut board = Board::from_fen("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - -")
+        .expect("The stop condition position is valid.");
+    let results = [true, false].map(|stop_on_mate| {
+        let mut search = Search::new(SearchOptions { stop_on_mate, ..SearchOptions::default() });
+        let (_, best) = search.search(&mut board, 5);
+        return (best.map(|mv| mv.to_uci()), search.info.nodes);
+    });
+    let [(stopped_move, stopped_nodes), (full_move, full_nodes)] = results.clone();
+    if stopped_move.as_deref() != Some("a1a8")
+        || full_move != stopped_move
+        || stopped_nodes >= full_nodes
+    {
+        println!("stop on mate: {:?} instead of a1a8 in fewer nodes", results);
+        report.failures += 1;
+    }
+
+    report.positions += 1;
+    let mut board = Board::new().expect("The starting position is valid.");
+    let moves = board.get_legal_moves();
+    let options = SearchOptions {
+        deadline: Some(Instant::now()),
+        stop_on_mate: false,
+        stable_iterations: 2,
+        ..SearchOptions::default()
+    };
+    let later = SearchOptions {
+        deadline: Some(Instant::now() + Duration::from_secs(3600)),
+        ..options.clone()
+    };
+    let mut stability = Stability::new();
+    let mut stops = vec![];
+    for (score, mv) in [
+        (10, moves[0]),
+        (15, moves[1]),
+        (20, moves[1]),
+        (60, moves[1]),
+        (50, moves[1]),
+        (45, moves[1]),
+    ]
+    {
+        stability.update(Score(score), Some(mv));
+        stops.push((stability.can_stop(&options, 4), stability.can_stop(&later, 4)));
+    }
+    let expected = [false, false, false, false, false, true].map(|stop| (stop, false));
+    if stops != expected
+    {
+        println!("stable iterations: {:?} instead of {:?}", stops, expected);
+        report.failures += 1;
+    }
+}
+
+// Check the time limits of a move: within the time left less the move overhead, the hard limit
+// beyond the soft one, more time with an increment or fewer moves until the time control, and
+// nothing once the overhead uses the whole clock.
+fn check_time_limits(report: &mut SelftestReport)
+{
+    report.positions += 1;
+    let seconds = Duration::from_secs;
+    let overhead = Duration::from_millis(DEFAULT_MOVE_OVERHEAD_MS);
+    let base = TimeLimits::for_clock(seconds(60), Duration::ZERO, None, overhead);
+    let increment = TimeLimits::for_clock(seconds(60), seconds(1), None, overhead);
+    let last_move = TimeLimits::for_clock(seconds(60), Duration::ZERO, Some(1), overhead);
+    let out_of_time = TimeLimits::for_clock(overhead / 2, seconds(1), None, overhead);
+    let fixed = TimeLimits::fixed(seconds(2), overhead);
+    let all = [base, increment, last_move, out_of_time, fixed];
+    if all.iter().any(|limits| limits.soft > limits.hard || limits.hard > seconds(60) - overhead)
+        || base.soft >= base.hard
+        || increment.soft <= base.soft
+        || last_move.hard <= base.hard
+        || out_of_time.hard != Duration::ZERO
+        || fixed.hard != seconds(2) - overhead
+    {
+        println!("time limits: {:?}", all);
+        report.failures += 1;
+    }
+}
+
+// The long chains of captures of a tactical middlegame reach the quiescence limit, which the
+// selective depth never goes beyond.
+fn check_quiescence_limit(report: &mut SelftestReport)
+{
+    report.positions += 1;
+    let fen = "r4rk1/1pp1qppp/p1np1n2/2b1p1B1/2B1P1b1/P1NP1N2/1PP1QPPP/R4RK1 w - -";
+    let mut board = Board::from_fen(fen).expect("The quiescence limit position is valid.");
+    let depth = 4;
+    let mut search = Search::default();
+    search.search(&mut board, depth);
+    let info = search.info;
+    if info.seldepth < depth
+        || info.seldepth > depth + MAX_QUIESCENCE_PLIES
+        || info.quiescence_depth_cuts == 0
+    {
+        println!(
+            "quiescence limit: selective depth {}, {} cuts ({})",
+            info.seldepth, info.quiescence_depth_cuts, fen
+        );
+        report.failures += 1;
+    }
+}
+
+// Check the pawn structure of a position with doubled, isolated and passed pawns, and that the
+// pawn table gives back the structure it stored.
+fn check_pawn_structure(report: &mut SelftestReport)
+{
+    report.positions += 1;
+    let fen = "6k1/5ppp/8/3P4/8/1P6/1P3PPP/R5K1 w - -";
+    let board = Board::from_fen(fen).expect("The pawn structure position is valid.");
+    let structure = PawnStructure::new(&board);
+    let squares = |names: &[&str]| -> Bitboard {
+        return names
+            .iter()
+            .filter_map(|name| parse_square(name))
+            .fold(0, |bits, sq| bits | 1u64 << sq);
+    };
+    let expected = structure.passed == [squares(&["b3", "d5"]), 0]
+        && structure.isolated == [squares(&["b2", "b3", "d5"]), 0]
+        && structure.doubled == [squares(&["b2"]), 0]
+        && structure.open_files == 0b0001_0101
+        && structure.shield[0][6] == 3
+        && structure.shield[1][6] == 3;
+    let mut table = PawnTable::new(16);
+    let cached = table.get(&board) == structure && table.get(&board) == structure;
+    if !expected || !cached
+    {
+        println!("pawn structure: {:?} ({})", structure, fen);
+        report.failures += 1;
+    }
+}
+
+// Check the space of the French advance variation: behind its pawns on d4 and e5, white gains d2,
+// d3, e2 and e3, e4 being attacked by the pawn on d5, while black only gains d7 and e7.
+fn check_space(report: &mut SelftestReport)
+{
+    report.positions += 1;
+    let fen = "rnbqkbnr/ppp2ppp/4p3/3pP3/3P4/8/PPP2PPP/RNBQKBNR b KQkq -";
+    let board = Board::from_fen(fen).expect("The space position is valid.");
+    let space = PawnStructure::new(&board).space;
+    if space != [4, 2]
+    {
+        println!("space: {:?} safe squares instead of [4, 2] ({})", space, fen);
+        report.failures += 1;
+    }
+}
+
+// Check that a knight attacked by a pawn is threatened, and that only the pawn move attacking it
+// creates a threat: the knight move attacks a piece of the same value.
+fn check_threats(report: &mut SelftestReport)
+{
+    report.positions += 1;
+    let fen = "r1bqkbnr/pppp1ppp/8/4p3/3n4/2P5/PP1PPPPP/RNBQKBNR w KQkq -";
+    let board = Board::from_fen(fen).expect("The threat position is valid.");
+    let threats = Threats::new(&board);
+    let knight = 1u64 << parse_square("d4").unwrap_or(0);
+    let mut before = Board::from_fen("r1bqkbnr/pppp1ppp/8/4p3/3n4/8/PPPPPPPP/RNBQKBNR w KQkq -")
+        .expect("The threat position is valid.");
+    let creates = |board: &mut Board, uci: &str| {
+        return parse_uci_move(board, uci).is_some_and(|mv| creates_threat(board, mv));
+    };
+    if threats.attacked_by_lesser != [0, knight]
+        || threats.hanging != [0, 0]
+        || !creates(&mut before, "c2c3")
+        || creates(&mut before, "g1f3")
+    {
+        println!("threats: {:?} ({})", threats, fen);
+        report.failures += 1;
+    }
+}
+
+// Check the perspective of the evaluation: the initial position is worth the tempo to the side to
+// move, whichever it is, and the score of a white advantage changes sign with the side to move.
+fn check_side_to_move(report: &mut SelftestReport)
+{
+    let tempo = Score(EvalParams::DEFAULT.tempo.taper(MAX_PHASE));
+    for (fen, to_move_better) in [
+        (START_FEN, None),
+        ("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq -", None),
+        ("rnbqkb1r/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq -", Some(true)),
+        ("rnbqkb1r/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR b KQkq -", Some(false)),
+    ]
+    {
+        report.positions += 1;
+        let board = Board::from_fen(fen).expect("The side to move positions are valid.");
+        let score = evaluate(&board);
+        let expected = match to_move_better
+        {
+            None => score == tempo,
+            Some(better) => (score > Score::DRAW) == better,
+        };
+        if !expected
+        {
+            println!("side to move: evaluation {} ({})", score, fen);
+            report.failures += 1;
+        }
+    }
+}
+
+// Check that the drawish endings are scaled down, and only them: the bishops of opposite colors,
+// and a rook ending with one extra pawn, but not with two.
+fn check_endgame_scale(report: &mut SelftestReport)
+{
+    for (fen, expected) in [
+        ("8/5k2/2b3p1/5p2/5P2/4B1PK/6P1/8 w - -", Some("opposite bishops")),
+        ("8/5k2/3b2p1/5p2/5P2/4B1PK/6P1/8 w - -", None),
+        ("8/5k2/6p1/r4p2/5P2/R5PK/6P1/8 w - -", Some("rook ending")),
+        ("8/5k2/8/r4p2/5P2/R5PK/6P1/8 w - -", None),
+    ]
+    {
+        report.positions += 1;
+        let board = Board::from_fen(fen).expect("The scaled endings are valid.");
+        let scale = evaluate_trace(&board).scale.map(|scale| scale.name);
+        if scale != expected
+        {
+            println!("endgame scale: {:?} instead of {:?} ({})", scale, expected, fen);
+            report.failures += 1;
+        }
+    }
+}
+
+// Check that quiescence doesn't stand pat in check: at depth 1, the capture mating on the back rank
+// is only seen as a mate because the position after it is searched for evasions.
+fn check_quiescence_checks(report: &mut SelftestReport)
+{
+    report.positions += 1;
+    let fen = "3r2k1/5ppp/8/8/8/8/5PPP/3R2K1 w - -";
+    let mut board = Board::from_fen(fen).expect("The quiescence check position is valid.");
+    let (score, best) = Search::default().search(&mut board, 1);
+    let found = best.map(|mv| mv.to_uci());
+    if score != Score::mate_in(1) || found.as_deref() != Some("d1d8")
+    {
+        println!("quiescence checks: {:?} scored {} instead of mate ({})", found, score, fen);
+        report.failures += 1;
+    }
+}
+
+// Check that the basic search of the core finds a back rank mate, and a winning capture.
+fn check_basic_search(report: &mut SelftestReport)
+{
+    for (fen, expected) in [
+        ("6k1/5ppp/8/8/8/8/5PPP/R5K1 w - -", "a1a8"),
+        ("6k1/5ppp/8/3q4/8/8/5PPP/3R2K1 w - -", "d1d5"),
+    ]
+    {
+        report.positions += 1;
+        let mut board = Board::from_fen(fen).expect("The basic search positions are valid.");
+        let found = basic_search(&mut board, 3).1.map(|mv| mv.to_uci());
+        if found.as_deref() != Some(expected)
+        {
+            println!("basic search: {:?} instead of {} ({})", found, expected, fen);
+            report.failures += 1;
+        }
+    }
+}
+
+// Check that the full strength always plays the best line, and that a strong level never plays a
+// line losing a queen, while the weakest level sometimes plays worse lines.
+fn check_skill(report: &mut SelftestReport)
+{
+    report.positions += 1;
+    let lines: Vec<PvLine> =
+        [0, -30, -900].map(|score| PvLine { score: Score(score), moves: vec![] }).to_vec();
+    let mut rng = default_randomness().rng(0);
+    let mut chosen = |level: u8| -> Vec<i32> {
+        return (0 .. 200)
+            .map(|_| choose_skill_line(&lines, level, &mut rng).map_or(0, |line| line.score.0))
+            .collect();
+    };
+    let (full, strong, weak) = (chosen(MAX_SKILL_LEVEL), chosen(19), chosen(0));
+    if full.iter().any(|score| *score != 0)
+        || strong.contains(&-900)
+        || weak.iter().all(|score| *score == 0)
+    {
+        println!("skill level: unexpected choice of the lines");
+        report.failures += 1;
+    }
+}
+
+// Check that guessing the move of the game gets all the points and that a guess leaving a queen
+// hanging gets none.
+fn check_guess(report: &mut SelftestReport)
+{
+    report.positions += 1;
+    let fen = "6k1/5ppp/8/3q4/8/8/5PPP/3R2K1 w - -";
+    let mut board = Board::from_fen(fen).expect("The guess position is valid.");
+    let played = parse_uci_move(&mut board, "d1d5").expect("The move of the game is legal.");
+    let blunder = parse_uci_move(&mut board, "h2h3").expect("The blunder is legal.");
+    let mut search = Search::default();
+    let right = score_guess(&mut board, played, played, 3, &mut search).points;
+    let wrong = score_guess(&mut board, blunder, played, 3, &mut search).points;
+    if right != MAX_GUESS_POINTS || wrong != 0
+    {
+        println!(
+            "guess the move: {} and {} points instead of {} and 0",
+            right, wrong, MAX_GUESS_POINTS
+        );
+        report.failures += 1;
+    }
+}
+
+// Check the numbered movetext, from white's and from black's first move, with and without a
+// result.
+fn check_movetext(report: &mut SelftestReport)
+{
+    let cases = [
+        (START_FEN, &["e2e4", "e7e5", "g1f3"][..], Notation::San, None, "1. e4 e5 2. Nf3"),
+        (
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq -",
+            &["e7e5", "g1f3", "b8c6"][..],
+            Notation::Lan,
+            Some("*"),
+            "1... e7-e5 2. Ng1-f3 Nb8-c6 *",
+        ),
+    ];
+    for (fen, names, notation, result, expected) in cases
+    {
+        report.positions += 1;
+        let mut board = Board::from_fen(fen).expect("The movetext positions are valid.");
+        let start = board;
+        let mut moves = vec![];
+        for name in names
+        {
+            let mv = parse_uci_move(&mut board, name).expect("The listed moves are legal.");
+            board.make_move(mv);
+            moves.push(mv);
+        }
+        let movetext = format_movetext(&start, &moves, notation, result);
+        if movetext != expected
+        {
+            println!("movetext: '{}' instead of '{}'", movetext, expected);
+            report.failures += 1;
+        }
+    }
+}
+
+// Search the self-test positions in parallel with a shared transposition table, then check that
+// each entry left for them holds a legal move: the slots written by several threads at once must
+// never give an entry mixing two positions.
+fn check_shared_tt(report: &mut SelftestReport)
+{
+    let tt = Arc::new(TranspositionTable::new(1 << 12));
+    thread::scope(|scope| {
+        for fen in SELFTEST_POSITIONS
+        {
+            let tt = tt.clone();
+            scope.spawn(move || {
+                let mut board = Board::from_fen(fen).expect("The self-test positions are valid.");
+                Search::with_tt(SearchOptions::default(), tt).search(&mut board, 4);
+            });
+        }
+    });
+    for fen in SELFTEST_POSITIONS
+    {
+        report.positions += 1;
+        let mut board = Board::from_fen(fen).expect("The self-test positions are valid.");
+        let mv = tt.probe(board.hash).and_then(|entry| entry.best_move_on(&board));
+        if mv.is_some_and(|mv| !board.get_legal_moves().contains(&mv))
+        {
+            println!("shared transposition table: illegal move {:?} ({})", mv, fen);
+            report.failures += 1;
+        }
+    }
+}
+
+// Search the initial position with a table of the default size, then check that the principal
+// variation read back from the table is as long as the search: the shallow nodes sharing the slots
+// of the line must not evict it.
+fn check_tt_pv(report: &mut SelftestReport)
+{
+    report.positions += 1;
+    let mut board = Board::new().expect("The initial position is valid.");
+    let mut search = Search::with_tt(
+        SearchOptions::default(),
+        Arc::new(TranspositionTable::new(DEFAULT_TT_ENTRIES)),
+    );
+    let lines = search.multipv(&mut board, TT_PV_DEPTH, 1);
+    if lines.first().is_none_or(|line| line.moves.len() != TT_PV_DEPTH as usize)
+    {
+        println!("transposition table: principal variation cut short: {:?}", lines);
+        report.failures += 1;
+    }
+}
+
+fn check_record(report: &mut SelftestReport)
+{
+    report.positions += 1;
+    let (pgn, expected_moves) = RECORD_PGN;
+    let record = GameRecord::from_pgn(pgn).expect("The self-test game is valid.");
+    let annotated = record.moves.iter().filter(|recorded| recorded.annotation.eval.is_some());
+    let from_pgn = [Notation::San, Notation::Lan, Notation::Iccf]
+        .map(|notation| record.to_pgn_with(notation).and_then(|pgn| GameRecord::from_pgn(&pgn)));
+    let from_json = record.to_json().and_then(|json| GameRecord::from_json(&json));
+    if record.moves.len() != expected_moves
+        || annotated.count() != 2
+        || record.moves[5].annotation.nags != [4]
+        || record.think_times() != Ok([Duration::ZERO, Duration::from_millis(1500)])
+        || from_pgn.iter().any(|from_pgn| from_pgn.as_ref() != Ok(&record))
+        || from_json.as_ref() != Ok(&record)
+    {
+        println!("game record: the game changed when written and read back");
+        println!("{:?}\n{:?}\n{:?}", record, from_pgn, from_json);
+        report.failures += 1;
+    }
+}
+
+// Annotate the game of 'RECORD_PGN', where the only bad move is black's blunder allowing mate.
+fn check_annotation(report: &mut SelftestReport)
+{
+    report.positions += 1;
+    let mut record = GameRecord::from_pgn(RECORD_PGN.0).expect("The self-test game is valid.");
+    let reviews = annotate_game(
+        &mut record,
+        ANNOTATION_DEPTH,
+        &LossThresholds::default(),
+        &mut Search::default(),
+    );
+    let flagged: Vec<usize> = reviews
+        .iter()
+        .flatten()
+        .enumerate()
+        .filter(|(_, review)| review.class.is_some())
+        .map(|(i, _)| i)
+        .collect();
+    if flagged != [5]
+    {
+        println!("annotation: moves {:?} flagged instead of [5]", flagged);
+        report.failures += 1;
+    }
+    // The report of each player counts the blunder against black only.
+    let reviews = reviews.unwrap_or_default();
+    let white = PlayerReport::from_reviews(&reviews, true);
+    let black = PlayerReport::from_reviews(&reviews, false);
+    if white.blunders != 0 || black.blunders != 1 || white.accuracy <= black.accuracy
+    {
+        println!("annotation: wrong player reports {:?} and {:?}", white, black);
+        report.failures += 1;
+    }
+}
+
+// The win probability grows with the score, and the chances of a win, a draw and a loss are the
+// same as the chances of a loss, a draw and a win of the opposite score, in every game phase.
+fn check_win_probability(report: &mut SelftestReport)
+{
+    for phase in 0 ..= MAX_PHASE
+    {
+        report.positions += 1;
+        let scores = (-20 .. 20).map(|pawns| Score(pawns * 100));
+        let increasing = scores.clone().all(|score| {
+            score.win_probability(phase) < (score + Score(100)).win_probability(phase)
+        });
+        let symmetric = scores.clone().all(|score| {
+            let (win, draw, loss) = score.wdl(phase);
+            return (-score).wdl(phase) == (loss, draw, win) && win + draw + loss == 1000;
+        });
+        if !increasing || !symmetric
+        {
+            println!("win probability: inconsistent at phase {}", phase);
+            report.failures += 1;
+        }
+    }
+}
+
+// The mates are told apart from the centipawns and written in moves for UCI, and a mate score
+// stored in the transposition table at a ply before the mate is read back the same at that ply.
+fn check_score_kinds(report: &mut SelftestReport)
+{
+    for (score, kind, uci) in [
+        (Score(35), ScoreKind::Centipawns(35), "cp 35"),
+        (Score(-1200), ScoreKind::Centipawns(-1200), "cp -1200"),
+        (Score::mate_in(7), ScoreKind::Mate(4), "mate 4"),
+        (Score::mated_in(4), ScoreKind::Mate(-2), "mate -2"),
+    ]
+    {
+        report.positions += 1;
+        if score.kind() != kind || score.to_uci() != uci || score.to_tt(3).from_tt(3) != score
+        {
+            println!(
+                "score kinds: {:?} is {:?}, written '{}'",
+                score,
+                score.kind(),
+                score.to_uci()
+            );
+            report.failures += 1;
+        }
+    }
+}
+
+// The configuration must survive its TOML output, an override must only change its key, and the
+// evaluation weights and piece-square tables can come from their own file.
+fn check_config(report: &mut SelftestReport)
+{
+    report.positions += 1;
+    let config = engine_config();
+    let round_trip = config.to_toml().and_then(|text| EngineConfig::from_toml(&text));
+    if round_trip.as_ref() != Ok(&config)
+    {
+        println!("config: TOML round trip failed: {:?}", round_trip.err());
+        report.failures += 1;
+    }
+
+    let overrides = ["search.probcut=false".to_string(), "eval.knight.mg=300".to_string()];
+    let mut expected = EngineConfig::default();
+    expected.search.probcut = false;
+    expected.eval.knight.mg = 300;
+    let overridden = EngineConfig::load(None, &overrides);
+    if overridden.as_ref() != Ok(&expected)
+    {
+        println!("config: overrides not applied: {:?}", overridden);
+        report.failures += 1;
+    }
+
+    // The weights of an evaluation file replace the configured ones, and a weight out of its
+    // bounds is refused.
+    let path = std::env::temp_dir().join("barnarok-selftest-eval.json");
+    let path = path.to_string_lossy().to_string();
+    let written = std::fs::write(&path, r#"{ "knight": { "mg": 310, "eg": 290 } }"#);
+    let loaded = EngineConfig::load(None, &[format!("eval_file={}", path)]);
+    let _ = std::fs::remove_file(&path);
+    if written.is_err() || loaded.map(|config| config.eval.knight) != Ok(Tapered::new(310, 290))
+    {
+        println!("config: evaluation file '{}' not applied", path);
+        report.failures += 1;
+    }
+    if EngineConfig::load(None, &["eval.pawn.mg=0".to_string()]).is_ok()
+    {
+        println!("config: a pawn worth nothing is accepted");
+        report.failures += 1;
+    }
+
+    // The piece-square tables can come from the file too, with 8 rows of 8 values.
+    let rows = |count: usize| format!("[{}]", vec!["[0, 0, 0, 0, 0, 0, 0, 5]"; count].join(", "));
+    let path = std::env::temp_dir().join("barnarok-selftest-pst.toml");
+    let path = path.to_string_lossy().to_string();
+    for (count, valid) in [(8, true), (7, false)]
+    {
+        let text = format!("[rook_pst]\nmg = {}\neg = {}\n", rows(count), rows(8));
+        let written = std::fs::write(&path, text);
+        let loaded = EvalParams::from_file(&path);
+        let expected = SquareTable::uniform([[0, 0, 0, 0, 0, 0, 0, 5]; 8]);
+        if written.is_err() || loaded.is_ok_and(|params| params.rook_pst == expected) != valid
+        {
+            println!("config: piece-square table of {} rows not handled", count);
+            report.failures += 1;
+        }
+    }
+    let _ = std::fs::remove_file(&path);
+}
+
+// The masks of the pawns of black are the ones of white seen from the other side of the board, the
+// front span is part of the passed pawn mask, the squares between two squares are on their line and
+// the rays of a square cover its lines, and the knights and kings reach the squares at the right
+// distance.
+fn check_masks(report: &mut SelftestReport)
+{
+    report.positions += 1;
+    let pawns_mirrored = (0 .. 64).all(|sq| {
+        return passed_pawn_mask(false, sq) == passed_pawn_mask(true, sq ^ 56).swap_bytes()
+            && front_span(false, sq) == front_span(true, sq ^ 56).swap_bytes()
+            && front_span(true, sq) & !passed_pawn_mask(true, sq) == 0
+            && passed_pawn_mask(true, sq) & !(front_span(true, sq) | adjacent_files_mask(sq)) == 0;
+    });
+    let between_on_line = (0 .. 64).all(|a| {
+        return (0 .. 64).all(|b| {
+            return between(a, b) == between(b, a)
+                && between(a, b) & !line(a, b) == 0
+                && between(a, b) & (1u64 << a | 1u64 << b) == 0;
+        });
+    });
+    // The rays of a square and of the opposite direction make up the lines going through it.
+    let rays_on_lines = (0 .. 64).all(|sq| {
+        let straight = rank_mask(sq) | file_mask(sq);
+        let diagonal = diagonal_mask(sq) | antidiagonal_mask(sq);
+        let mut rays = [0u64; 2];
+        for direction in Direction::ALL
+        {
+            let forward = ray(sq, direction);
+            if forward & ray(sq, direction.opposite()) != 0
+            {
+                return false;
+            }
+            rays[direction.is_straight() as usize] |= forward;
+        }
+        return rays == [diagonal & !(1u64 << sq), straight & !(1u64 << sq)];
+    });
+    // The leaps of the knights and the steps of the kings, from the distances between the squares.
+    let leaps_match = (0 .. 64).all(|sq: usize| {
+        let (mut knight, mut king) = (0u64, 0u64);
+        for target in 0 .. 64
+        {
+            let df = (sq % 8).abs_diff(target % 8);
+            let dr = (sq / 8).abs_diff(target / 8);
+            knight |= (((df, dr) == (1, 2) || (df, dr) == (2, 1)) as u64) << target;
+            king |= ((df.max(dr) == 1) as u64) << target;
+        }
+        return knight_mask(sq) == knight && king_mask(sq) == king;
+    });
+    // A white pawn on e4 is stopped by pawns from d5 to f8, but not by a pawn on e3 or c5.
+    let e4 = passed_pawn_mask(true, 28);
+    let expected = (4 .. 8).fold(0u64, |mask, rank| mask | 0b111 << (rank * 8 + 3));
+    if !pawns_mirrored || !between_on_line || !rays_on_lines || !leaps_match || e4 != expected
+    {
+        println!("masks: inconsistent pawn, line or leap masks");
+        report.failures += 1;
+    }
+}
+
+// Every square is attacked by each side according to 'is_square_attacked' exactly when the
+// mailbox generator finds an attacker, and in standard chess both generators find the same legal
+// moves. The kings don't attack in Atomic, unlike in the mailbox.
+fn check_naive_generator(board: &mut Board, report: &mut SelftestReport)
+{
+    let mailbox = Mailbox::from_board(board);
+    if board.variant != Variant::Atomic
+    {
+        for sq in 0 .. 64
+        {
+            for by_playing_side in [true, false]
+            {
+                let white = board.white_to_play == by_playing_side;
+                if is_square_attacked(sq, board, by_playing_side)
+                    != mailbox.is_square_attacked(sq, white)
+                {
+                    println!(
+                        "attacked squares: {} by {} ({})",
+                        square_name(sq),
+                        if white { "white" } else { "black" },
+                        board.to_fen()
+                    );
+                    report.failures += 1;
+                    return;
+                }
+            }
+        }
+    }
+    if board.variant == Variant::Standard
+    {
+        let mut moves: Vec<String> = board.get_legal_moves().iter().map(Move::to_uci).collect();
+        moves.sort();
+        let expected = mailbox.legal_moves();
+        if moves != expected
+        {
+            println!("move generation: {:?} instead of {:?} ({})", moves, expected, board.to_fen());
+            report.failures += 1;
+        }
+    }
+}
+
+// A piece is pinned when it is the only piece between its king and an enemy slider going along
+// their line.
+fn check_pinned_pieces(board: &Board, report: &mut SelftestReport)
+{
+    let white = board.white_to_play;
+    let king = board.king_bitboard(white);
+    let own_pieces = if white { board.white_pieces } else { board.black_pieces };
+    let (rooks, bishops) = if white
+    {
+        (board.black_rooks | board.black_queens, board.black_bishops | board.black_queens)
+    }
+    else
+    {
+        (board.white_rooks | board.white_queens, board.white_bishops | board.white_queens)
+    };
+    let mut expected = 0u64;
+    if king != 0
+    {
+        let sq = king.trailing_zeros() as usize;
+        for slider in 0 .. 64
+        {
+            let straight = rank_mask(sq) | file_mask(sq);
+            let slides = if straight & 1u64 << slider != 0 { rooks } else { bishops };
+            let blockers = between(sq, slider) & board.pieces;
+            if line(sq, slider) != 0
+                && slides & 1u64 << slider != 0
+                && blockers.count_ones() == 1
+                && blockers & own_pieces != 0
+            {
+                expected |= blockers;
+            }
+        }
+    }
+    if get_pinned_pieces(board) != expected
+    {
+        println!("pinned pieces: {}", board.to_fen());
+        report.failures += 1;
+    }
+}
+
+// Check that the legality of single moves agrees with the move generation, that the board gives
+// back the moves from their squares alone, and that the checks found without making the moves are
+// those found after making them: on the legal moves of the position, on the moves of the other side
+// after the first of them, and on the moves of the position played again after a move of each side,
+// like the moves of the transposition table from other positions.
+fn check_move_legality(board: &mut Board, report: &mut SelftestReport)
+{
+    let moves = board.get_legal_moves();
+    for mv in moves.iter()
+    {
+        if !board.is_pseudo_legal(*mv) || !board.is_legal(*mv)
+        {
+            println!("move legality: {} rejected ({})", mv.to_uci(), board.to_fen());
+            report.failures += 1;
+        }
+        // A move keeping only its squares and its promotion is completed by the board.
+        // Without its promotion piece, or with one it can't have, it is rejected.
+        let bare = Move::bare(mv.start, mv.end, mv.promotion());
+        let wrong_promotion =
+            Move::bare(mv.start, mv.end, if mv.promotion().is_some() { None } else { Some(QUEEN) });
+        if board.complete_move(bare) != Some(*mv) || board.complete_move(wrong_promotion).is_some()
+        {
+            println!("complete move: {} ({})", mv.to_uci(), board.to_fen());
+            report.failures += 1;
+        }
+        // The self-test positions include one where the side not to move is already in check.
+        let mut after = *board;
+        after.make_move(*mv);
+        if !is_king_attacked(board, true)
+            && board.gives_check(*mv) != is_king_attacked(&after, false)
+        {
+            println!("gives check: {} ({})", mv.to_uci(), board.to_fen());
+            report.failures += 1;
+        }
+    }
+    let Some(first) = moves.first()
+    else
+    {
+        return;
+    };
+    let mut later = *board;
+    later.make_move(*first);
+    let replies = later.get_legal_moves();
+    if let Some(reply) = replies.iter().find(|reply| board.is_pseudo_legal(**reply))
+    {
+        println!(
+            "move legality: {} of the other side accepted ({})",
+            reply.to_uci(),
+            board.to_fen()
+        );
+        report.failures += 1;
+    }
+    let Some(reply) = replies.first()
+    else
+    {
+        return;
+    };
+    later.make_move(*reply);
+    let later_moves = later.get_legal_moves();
+    for mv in moves.iter()
+    {
+        // Take the state saved in the move from the board, like the transposition table does.
+        let enemy = if later.white_to_play { later.black_pieces } else { later.white_pieces };
+        let capture = (enemy & (1u64 << mv.end) != 0).then(|| later.piece_at(mv.end));
+        let captures = capture.is_some() || mv.context == MoveContext::EnPassant;
+        let candidate = Move {
+            previous_ep_target: later.en_passant_target,
+            previous_castling_rights: later.castling_rights,
+            previous_halfmove_clock: later.halfmove_clock,
+            capture,
+            exploded: later.explosion(mv.start, mv.end, captures),
+            ..*mv
+        };
+        let legal = later.is_legal(candidate);
+        if legal != later_moves.contains(&candidate)
+        {
+            println!(
+                "move legality: {} {} ({})",
+                candidate.to_uci(),
+                if legal { "accepted" } else { "rejected" },
+                later.to_fen()
+            );
+            report.failures += 1;
+        }
+    }
+}
+
+fn check_tree(board: &mut Board, depth: usize, report: &mut SelftestReport)
+{
+    report.positions += 1;
+
+    let flipped = board.flipped();
+    let (zones, flipped_zones) = (ZoneAttacks::new(board), ZoneAttacks::new(&flipped));
+    if evaluate(board) != evaluate(&flipped)
+        || evaluate_trace(board).total != -evaluate_trace(&flipped).total
+        || zones.white != flipped_zones.black
+        || zones.black != flipped_zones.white
+    {
+        println!("evaluation symmetry: {}", board.to_fen());
+        report.failures += 1;
+    }
+
+    // The evaluation is from the side to move's perspective, and the trace from white's.
+    let white_score = if board.white_to_play { evaluate(board) } else { -evaluate(board) };
+    if white_score != evaluate_trace(board).total
+    {
+        println!("evaluation trace: {}", board.to_fen());
+        report.failures += 1;
+    }
+
+    check_pinned_pieces(board, report);
+    check_naive_generator(board, report);
+    check_move_legality(board, report);
+
+    if depth == 0
+    {
+        return;
+    }
+    for mv in board.get_legal_moves()
+    {
+        let san = mv.to_san(board);
+        if parse_san_move(board, &san) != Some(mv)
+        {
+            println!("SAN round-trip: {} with {} ({})", board.to_fen(), san, mv.to_uci());
+            report.failures += 1;
+        }
+
+        let before = *board;
+        board.make_move(mv);
+        if board.hash != board.compute_hash() || board.pawn_hash != board.compute_pawn_hash()
+        {
+            println!("incremental hash: {} after {}", before.to_fen(), mv.to_uci());
+            report.failures += 1;
+        }
+        check_tree(board, depth - 1, report);
+        board.unmake_move(mv);
+        if *board != before
+        {
+            println!("make/unmake: {} with {}", before.to_fen(), mv.to_uci());
+            report.failures += 1;
+            // Continue from the right position.
+            *board = before;
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use std::sync::OnceLock;
+
+use crate::{
+    Bitboard, Board, Index, Move, PAWN, Piece, QUEEN, ROOK, Variant, bishop_attacks_hq, king_mask,
+    rook_attacks_hq, white_king_pawn_mask,
+};
+
+// Distance-to-mate tables of the endings with a king and a queen, a rook or a pawn against a lone
+// king. They are generated in memory by retrograde analysis the first time they are probed.
+// Positions are seen from the side with the piece, called strong, as if it was white. Each entry
+// holds the number of plies until the weak king is mated with the best play of both sides.
+static KQK_TABLE: OnceLock<Vec<u8>> = OnceLock::new();
+static KRK_TABLE: OnceLock<Vec<u8>> = OnceLock::new();
+static KPK_TABLE: OnceLock<Vec<u8>> = OnceLock::new();
+
+// Side to move, square of the piece, strong king and weak king.
+const TABLE_SIZE: usize = 2 * 64 * 64 * 64;
+
+// Values of the entries other than distances to mate.
+const UNKNOWN: u8 = u8::MAX;
+const INVALID: u8 = u8::MAX - 1;
+const DRAW: u8 = u8::MAX - 2;
+
+// Tell whether the position belongs to an ending with a table.
+pub fn has_dtm_table(board: &Board) -> bool
+{
+    return table_position(board).is_some();
+}
+
+// Get the distance to mate of a position, in plies, from the side to move's perspective: positive
+// when it mates, negative when it is mated, and 0 when it is already checkmated. Return None when
+// the position is drawn or has no table. Castling rights are ignored.
+pub fn dtm(board: &Board) -> Option<i32>
+{
+    let (piece, strong_to_move, strong_king, weak_king, sq) = table_position(board)?;
+    let plies = match table(piece)[table_index(strong_to_move, strong_king, weak_king, sq)]
+    {
+        DRAW | INVALID | UNKNOWN => return None,
+        plies => plies as i32,
+    };
+    return Some(if strong_to_move { plies } else { -plies });
+}
+
+// Get a move keeping the result of a position with a table: the fastest mate for the winning side,
+// the slowest one for the losing side, and a move keeping the draw otherwise.
+// Return None when there is no legal move or no table.
+pub fn dtm_best_move(board: &mut Board) -> Option<Move>
+{
+    if !has_dtm_table(board)
+    {
+        return None;
+    }
+    // Value of a move for the side playing it, from the distance to mate of the position reached.
+    let value = |board: &Board| -> i32 {
+        return match dtm(board)
+        {
+            None => 0,
+            Some(plies) if plies > 0 => plies - i32::from(u8::MAX),
+            Some(plies) => i32::from(u8::MAX) + plies,
+        };
+    };
+    return board.get_legal_moves().into_iter().max_by_key(|mv| {
+        board.make_move(*mv);
+        let move_value = value(board);
+        board.unmake_move(*mv);
+        return move_value;
+    });
+}
+
+// Get the piece of the ending, the side to move, and the squares of the strong king, of the weak
+// king and of the piece, seen from the strong side. Return None if the position has no table.
+fn table_position(board: &Board) -> Option<(Piece, bool, Index, Index, Index)>
+{
+    if board.variant != Variant::Standard
+    {
+        return None;
+    }
+    let white_pieces = board.white_pieces & !board.king_bitboard(true);
+    let black_pieces = board.black_pieces & !board.king_bitboard(false);
+    // The board is flipped when black has the piece.
+    let board = match (white_pieces.count_ones(), black_pieces.count_ones())
+    {
+        (1, 0) => *board,
+        (0, 1) => board.flipped(),
+        _ => return None,
+    };
+    let sq = (board.white_pieces & !board.king_bitboard(true)).trailing_zeros() as usize;
+    let piece = board.piece_at(sq);
+    if piece != QUEEN && piece != ROOK && piece != PAWN
+    {
+        return None;
+    }
+    return Some((piece, board.white_to_play, board.white_king, board.black_king, sq));
+}
+
+fn table(piece: Piece) -> &'static [u8]
+{
+    return match piece
+    {
+        QUEEN => KQK_TABLE.get_or_init(|| generate(QUEEN)),
+        ROOK => KRK_TABLE.get_or_init(|| generate(ROOK)),
+        _ => KPK_TABLE.get_or_init(|| generate(PAWN)),
+    };
+}
+
+fn table_index(strong_to_move: bool, strong_king: Index, weak_king: Index, sq: Index) -> usize
+{
+    let side = if strong_to_move { 0 } else { 1 };
+    return ((side * 64 + sq) * 64 + strong_king) * 64 + weak_king;
+}
+
+// Get the position of an index: side to move, strong king, weak king and piece.
+fn table_position_of(index: usize) -> (bool, Index, Index, Index)
+{
+    let weak_king = index % 64;
+    let strong_king = index / 64 % 64;
+    let sq = index / (64 * 64) % 64;
+    let strong_to_move = index / (64 * 64 * 64) == 0;
+    return (strong_to_move, strong_king, weak_king, sq);
+}
+
+// Generate the table of an ending. The checkmates are found first, then the positions mated in
+// one more ply each round: the strong side mates in n plies if one of its moves reaches a mate in
+// n - 1 plies, and the weak side is mated in n plies once all its moves reach mates, the longest
+// being in n - 1 plies. The positions left are draws.
+fn generate(piece: Piece) -> Vec<u8>
+{
+    // A pawn promotes to a queen or a rook, so their tables are needed first.
+    let promotions = if piece == PAWN { Some((table(QUEEN), table(ROOK))) } else { None };
+    let mut values: Vec<u8> =
+        (0 .. TABLE_SIZE).map(|index| classify_initial(piece, index)).collect();
+
+    let mut plies = 1;
+    loop
+    {
+        let found: Vec<usize> = (0 .. TABLE_SIZE)
+            .filter(|index| {
+                values[*index] == UNKNOWN && classify(piece, *index, plies, &values, promotions)
+            })
+            .collect();
+        if found.is_empty()
+        {
+            break;
+        }
+        for index in found
+        {
+            values[index] = plies;
+        }
+        plies += 1;
+    }
+
+    for value in values.iter_mut()
+    {
+        if *value == UNKNOWN
+        {
+            *value = DRAW;
+        }
+    }
+    return values;
+}
+
+// Get the squares attacked by the piece, through the given occupancy.
+fn piece_attacks(piece: Piece, sq: Index, occupancy: Bitboard) -> Bitboard
+{
+    return match piece
+    {
+        QUEEN => rook_attacks_hq(sq, occupancy) | bishop_attacks_hq(sq, occupancy),
+        ROOK => rook_attacks_hq(sq, occupancy),
+        _ => white_king_pawn_mask(sq),
+    };
+}
+
+// Get the squares where the weak king can go, the undefended piece included. The piece attacks
+// through the square of the weak king, which can't step back along the line of a check.
+fn weak_king_moves(piece: Piece, strong_king: Index, weak_king: Index, sq: Index) -> Bitboard
+{
+    let occupancy = (1u64 << strong_king) | (1u64 << sq);
+    return king_mask(weak_king) & !king_mask(strong_king) & !piece_attacks(piece, sq, occupancy);
+}
+
+// Classify the invalid positions, the checkmates and the stalemates.
+fn classify_initial(piece: Piece, index: usize) -> u8
+{
+    let (strong_to_move, strong_king, weak_king, sq) = table_position_of(index);
+    let occupancy = (1u64 << strong_king) | (1u64 << weak_king) | (1u64 << sq);
+    let in_check = piece_attacks(piece, sq, occupancy) & (1u64 << weak_king) != 0;
+    if strong_king == weak_king
+        || strong_king == sq
+        || weak_king == sq
+        || king_mask(strong_king) & (1u64 << weak_king) != 0
+        || (piece == PAWN && !(8 .. 56).contains(&sq))
+        || (strong_to_move && in_check)
+    {
+        return INVALID;
+    }
+    if !strong_to_move && weak_king_moves(piece, strong_king, weak_king, sq) == 0
+    {
+        return if in_check { 0 } else { DRAW };
+    }
+    return UNKNOWN;
+}
+
+// Tell whether a position is mated in 'plies' plies, from the values of the positions mated in
+// fewer plies. The promotions of a pawn reach the tables of the queen and of the rook.
+fn classify(
+    piece: Piece,
+    index: usize,
+    plies: u8,
+    values: &[u8],
+    promotions: Option<(&[u8], &[u8])>,
+) -> bool
+{
+    let (strong_to_move, strong_king, weak_king, sq) = table_position_of(index);
+    // The strong side mates in an odd number of plies.
+    if strong_to_move != (plies % 2 == 1)
+    {
+        return false;
+    }
+
+    if !strong_to_move
+    {
+        // Taking the piece draws, and so does a move to a position not mated yet.
+        let moves = weak_king_moves(piece, strong_king, weak_king, sq);
+        if moves & (1u64 << sq) != 0
+        {
+            return false;
+        }
+        let mut bits = moves;
+        while bits != 0
+        {
+            let to = bits.trailing_zeros() as usize;
+            bits &= bits - 1;
+            if values[table_index(true, strong_king, to, sq)] >= DRAW
+            {
+                return false;
+            }
+        }
+        return true;
+    }
+
+    let mates_next = |value: u8| value == plies - 1;
+    let occupancy = (1u64 << strong_king) | (1u64 << weak_king) | (1u64 << sq);
+
+    let mut king_moves = king_mask(strong_king) & !king_mask(weak_king) & !(1u64 << sq);
+    while king_moves != 0
+    {
+        let to = king_moves.trailing_zeros() as usize;
+        king_moves &= king_moves - 1;
+        if mates_next(values[table_index(false, to, weak_king, sq)])
+        {
+            return true;
+        }
+    }
+
+    if let Some((queen_table, rook_table)) = promotions
+    {
+        let push = sq + 8;
+        if occupancy & (1u64 << push) != 0
+        {
+            return false;
+        }
+        if push >= 56
+        {
+            let promoted = table_index(false, strong_king, weak_king, push);
+            return mates_next(queen_table[promoted]) || mates_next(rook_table[promoted]);
+        }
+        let double_push = sq + 16;
+        return mates_next(values[table_index(false, strong_king, weak_king, push)])
+            || (sq / 8 == 1
+                && occupancy & (1u64 << double_push) == 0
+                && mates_next(values[table_index(false, strong_king, weak_king, double_push)]));
+    }
+
+    let mut piece_moves = piece_attacks(piece, sq, occupancy) & !occupancy;
+    while piece_moves != 0
+    {
+        let to = piece_moves.trailing_zeros() as usize;
+        piece_moves &= piece_moves - 1;
+        if mates_next(values[table_index(false, strong_king, weak_king, to)])
+        {
+            return true;
+        }
+    }
+    return false;
+}
@@ -0,0 +1,418 @@
+use std::{
+    fs,
+    sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread,
+};
+
+use crate::*;
+
+// Settings of a tournament between two strategies.
+pub struct TournamentOptions
+{
+    // Number of games to play. The strategies swap colors after each game.
+    pub games: usize,
+    // Starting positions, each one played twice so that both strategies get each color.
+    // The games start from the initial position when it is empty.
+    pub openings: Vec<Opening>,
+    // File where the games are written in PGN, if any, with the scores of the strategies and their
+    // clocks in the comments of the moves.
+    pub pgn_path: Option<String>,
+    // Stop the tournament as soon as a sequential probability ratio test concludes, in which case
+    // 'games' is the maximum number of games.
+    pub sprt: Option<SprtOptions>,
+    // Number of games played at the same time, each one on its own thread.
+    pub concurrency: usize,
+    // Time control and adjudication of the games.
+    pub game_options: GameOptions,
+}
+
+// Results gathered by the threads of a tournament.
+#[derive(Default)]
+struct TournamentProgress
+{
+    score: TournamentScore,
+    // The PGN of each finished game, with the index of the game.
+    pgn_games: Vec<(usize, String)>,
+    // Set when the SPRT concludes, so that no other game is started or counted.
+    stopped: bool,
+}
+
+// Settings of a sequential probability ratio test.
+// It decides between the hypotheses "the first strategy is 'elo0' stronger than the second" (H0)
+// and "it is 'elo1' stronger" (H1), with error rates 'alpha' (accepting H1 when H0 is true) and
+// 'beta' (accepting H0 when H1 is true).
+#[derive(Debug, Clone, Copy)]
+pub struct SprtOptions
+{
+    pub elo0: f64,
+    pub elo1: f64,
+    pub alpha: f64,
+    pub beta: f64,
+}
+
+impl Default for SprtOptions
+{
+    fn default() -> Self
+    {
+        return SprtOptions { elo0: 0.0, elo1: 10.0, alpha: 0.05, beta: 0.05 };
+    }
+}
+
+impl SprtOptions
+{
+    // Get the bounds of the log-likelihood ratio: H0 is accepted below the lower one, and H1
+    // above the upper one.
+    pub fn bounds(&self) -> (f64, f64)
+    {
+        return ((self.beta / (1.0 - self.alpha)).ln(), ((1.0 - self.beta) / self.alpha).ln());
+    }
+}
+
+// Conclusion of a sequential probability ratio test.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SprtVerdict
+{
+    // The results are more likely if the first strategy is 'elo0' stronger.
+    H0,
+    // The results are more likely if the first strategy is 'elo1' stronger.
+    H1,
+    // More games are needed.
+    Continue,
+}
+
+// A starting position: a FEN and the moves played from it before the strategies take over.
+pub struct Opening
+{
+    pub fen: String,
+    pub moves: Vec<Move>,
+}
+
+// Wins, draws and losses of the first strategy of a tournament.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TournamentScore
+{
+    pub wins: usize,
+    pub draws: usize,
+    pub losses: usize,
+}
+
+impl TournamentScore
+{
+    pub fn games(&self) -> usize
+    {
+        return self.wins + self.draws + self.losses;
+    }
+
+    // Get the fraction of the points scored, between 0 and 1.
+    pub fn ratio(&self) -> f64
+    {
+        if self.games() == 0
+        {
+            return 0.5;
+        }
+        return (self.wins as f64 + self.draws as f64 / 2.0) / self.games() as f64;
+    }
+
+    // Estimate the Elo difference between the two strategies, with the margin of its 95%
+    // confidence interval. The difference is infinite when a strategy scored every point.
+    pub fn elo_difference(&self) -> (f64, f64)
+    {
+        let n = self.games() as f64;
+        let ratio = self.ratio();
+        let elo = ratio_to_elo(ratio);
+        if n == 0.0 || !elo.is_finite()
+        {
+            return (elo, f64::INFINITY);
+        }
+
+        // Standard deviation of the score of a single game.
+        let deviation = ((self.wins as f64 * (1.0 - ratio).powi(2)
+            + self.draws as f64 * (0.5 - ratio).powi(2)
+            + self.losses as f64 * ratio.powi(2))
+            / n)
+            .sqrt();
+        let margin = 1.96 * deviation / n.sqrt();
+
+        let low = ratio_to_elo(ratio - margin);
+        let high = ratio_to_elo(ratio + margin);
+        return (elo, (high - low) / 2.0);
+    }
+
+    // Compute the log-likelihood ratio of the hypotheses of a test, using the normal
+    // approximation of the distribution of the score. Half a game is added to each kind of
+    // result, so that the variance isn't 0 while every game ends the same way.
+    pub fn log_likelihood_ratio(&self, sprt: &SprtOptions) -> f64
+    {
+        if self.games() == 0
+        {
+            return 0.0;
+        }
+        let (wins, draws, losses) =
+            (self.wins as f64 + 0.5, self.draws as f64 + 0.5, self.losses as f64 + 0.5);
+        let n = wins + draws + losses;
+        let ratio = (wins + draws / 2.0) / n;
+        let variance =
+            (wins * (1.0 - ratio).powi(2) + draws * (0.5 - ratio).powi(2) + losses * ratio.powi(2))
+                / n;
+
+        let s0 = elo_to_ratio(sprt.elo0);
+        let s1 = elo_to_ratio(sprt.elo1);
+        return n * (s1 - s0) * (2.0 * ratio - s0 - s1) / (2.0 * variance);
+    }
+
+    pub fn sprt_verdict(&self, sprt: &SprtOptions) -> SprtVerdict
+    {
+        let llr = self.log_likelihood_ratio(sprt);
+        let (lower, upper) = sprt.bounds();
+        if llr <= lower
+        {
+            return SprtVerdict::H0;
+        }
+        if llr >= upper
+        {
+            return SprtVerdict::H1;
+        }
+        return SprtVerdict::Continue;
+    }
+}
+
+// Convert an Elo difference to an expected score.
+fn elo_to_ratio(elo: f64) -> f64
+{
+    return 1.0 / (1.0 + 10f64.powf(-elo / 400.0));
+}
+
+// Convert an expected score to an Elo difference.
+fn ratio_to_elo(ratio: f64) -> f64
+{
+    if ratio <= 0.0
+    {
+        return f64::NEG_INFINITY;
+    }
+    if ratio >= 1.0
+    {
+        return f64::INFINITY;
+    }
+    return -400.0 * (1.0 / ratio - 1.0).log10();
+}
+
+// Read a file of openings, one per line: either a FEN, or moves in SAN or UCI played from the
+// initial position (move numbers are ignored). Empty lines and lines starting with '#' are skipped.
+pub fn read_openings(path: &str) -> Result<Vec<Opening>, String>
+{
+    let text = fs::read_to_string(path).map_err(|err| format!("Can't read '{}': {}", path, err))?;
+    let mut openings = vec![];
+    for line in text.lines().map(str::trim)
+    {
+        if line.is_empty() || line.starts_with('#')
+        {
+            continue;
+        }
+        if Board::from_fen(line).is_ok()
+        {
+            openings.push(Opening { fen: line.into(), moves: vec![] });
+            continue;
+        }
+
+        let mut board = Board::from_fen(START_FEN)?;
+        let mut moves = vec![];
+        for token in line.split_whitespace().filter(|token| !token.ends_with('.'))
+        {
+            let mv = parse_move(&mut board, token)
+                .ok_or(format!("Illegal move '{}' in opening '{}'.", token, line))?;
+            board.make_move(mv);
+            moves.push(mv);
+        }
+        openings.push(Opening { fen: START_FEN.into(), moves });
+    }
+    return Ok(openings);
+}
+
+// Get the moves played by the openings from a position, each one once, to use them as a book. The
+// positions are compared by their hash, so an opening reaching the position with another move
+// order still counts.
+pub fn book_moves(openings: &[Opening], board: &mut Board) -> Vec<Move>
+{
+    let mut names = vec![];
+    for opening in openings.iter()
+    {
+        let Ok(mut line_board) = Board::from_fen(&opening.fen)
+        else
+        {
+            continue;
+        };
+        for mv in opening.moves.iter()
+        {
+            let name = mv.to_uci();
+            if line_board.hash == board.hash && !names.contains(&name)
+            {
+                names.push(name);
+            }
+            line_board.make_move(*mv);
+        }
+    }
+    return names.iter().filter_map(|name| parse_uci_move(board, name)).collect();
+}
+
+// Play games between two strategies, alternating colors, printing the result of each game and the
+// final score. Return the score of the first strategy.
+pub fn run_tournament(
+    first: &str,
+    second: &str,
+    options: &TournamentOptions,
+) -> Result<TournamentScore, String>
+{
+    let first_strategy = get_strategy(first).ok_or(format!("Invalid strategy '{}'.", first))?;
+    let second_strategy = get_strategy(second).ok_or(format!("Invalid strategy '{}'.", second))?;
+    if first == "player" || second == "player"
+    {
+        return Err("The player strategy can't take part in a tournament.".into());
+    }
+
+    let default_openings = [Opening { fen: START_FEN.into(), moves: vec![] }];
+    let openings =
+        if options.openings.is_empty() { &default_openings[..] } else { &options.openings[..] };
+
+    // Each thread takes the next game to play until they are all played, or the SPRT concludes.
+    let next_game = AtomicUsize::new(0);
+    let progress = Mutex::new(TournamentProgress::default());
+    let worker = || -> Result<(), String> {
+        loop
+        {
+            let game = next_game.fetch_add(1, Ordering::Relaxed);
+            if game >= options.games || progress.lock().unwrap().stopped
+            {
+                return Ok(());
+            }
+
+            // Each opening is played twice in a row, once with each color.
+            let opening = &openings[(game / 2) % openings.len()];
+            let first_is_white = game.is_multiple_of(2);
+            let (white, black) = if first_is_white { (first, second) } else { (second, first) };
+
+            let mut board = Board::from_fen(&opening.fen)?;
+            for mv in opening.moves.iter()
+            {
+                board.make_move(*mv);
+            }
+            let (outcome, game_record) = if first_is_white
+            {
+                play_game(&mut board, first_strategy, second_strategy, &options.game_options)
+            }
+            else
+            {
+                play_game(&mut board, second_strategy, first_strategy, &options.game_options)
+            };
+
+            let mut progress = progress.lock().unwrap();
+            if progress.stopped
+            {
+                return Ok(());
+            }
+            let score = &mut progress.score;
+            let first_color = if first_is_white { WHITE } else { BLACK };
+            match outcome.winner
+            {
+                None => score.draws += 1,
+                Some(winner) if winner == first_color => score.wins += 1,
+                Some(_) => score.losses += 1,
+            }
+            println!(
+                "Game {}/{}: {} - {}: {} {:?}   (+{} ={} -{})",
+                game + 1,
+                options.games,
+                white,
+                black,
+                outcome.to_pgn(),
+                outcome.reason,
+                score.wins,
+                score.draws,
+                score.losses
+            );
+
+            if options.pgn_path.is_some()
+            {
+                // The record starts before the moves of the opening, which have no annotation.
+                let mut record = GameRecord::new(&opening.fen, Variant::Standard);
+                for mv in opening.moves.iter()
+                {
+                    record.push(*mv, Annotation::default());
+                }
+                record.moves.extend(game_record.moves);
+                record.result = game_record.result;
+                record.set_tag("Event", "barnarok tournament");
+                record.set_tag("Round", &(game + 1).to_string());
+                record.set_tag("White", white);
+                record.set_tag("Black", black);
+                progress.pgn_games.push((game, record.to_pgn()?));
+            }
+
+            if let Some(sprt) = &options.sprt
+            {
+                let score = progress.score;
+                let (lower, upper) = sprt.bounds();
+                println!(
+                    "LLR: {:.2} ({:.2}, {:.2}) [{}, {}]",
+                    score.log_likelihood_ratio(sprt),
+                    lower,
+                    upper,
+                    sprt.elo0,
+                    sprt.elo1
+                );
+                match score.sprt_verdict(sprt)
+                {
+                    SprtVerdict::H0 =>
+                    {
+                        println!(
+                            "H0 accepted: {} is {} Elo stronger, not {}.",
+                            first, sprt.elo0, sprt.elo1
+                        );
+                        progress.stopped = true;
+                    },
+                    SprtVerdict::H1 =>
+                    {
+                        println!(
+                            "H1 accepted: {} is {} Elo stronger, not {}.",
+                            first, sprt.elo1, sprt.elo0
+                        );
+                        progress.stopped = true;
+                    },
+                    SprtVerdict::Continue => (),
+                }
+            }
+        }
+    };
+    thread::scope(|scope| {
+        let handles: Vec<_> =
+            (0 .. options.concurrency.max(1)).map(|_| scope.spawn(worker)).collect();
+        return handles.into_iter().try_for_each(|handle| handle.join().unwrap());
+    })?;
+
+    let progress = progress.into_inner().unwrap();
+    let score = progress.score;
+    let (elo, margin) = score.elo_difference();
+    println!(
+        "Score of {} vs {}: {} - {} - {} [{:.3}] {}",
+        first,
+        second,
+        score.wins,
+        score.losses,
+        score.draws,
+        score.ratio(),
+        score.games()
+    );
+    println!("Elo difference: {:+.1} +/- {:.1}", elo, margin);
+
+    if let Some(path) = &options.pgn_path
+    {
+        // The games are written in the order they were started, not finished.
+        let mut pgn_games = progress.pgn_games;
+        pgn_games.sort_by_key(|(game, _)| *game);
+        let pgn: String = pgn_games.into_iter().map(|(_, pgn)| pgn).collect();
+        fs::write(path, pgn).map_err(|err| format!("Can't write '{}': {}", path, err))?;
+    }
+    return Ok(score);
+}
@@ -0,0 +1,142 @@
+use std::io::{self, BufRead, Write};
+
+use crate::*;
+
+// Points given for a guess: all of them for the move of the game, and one less for each
+// 'GUESS_LOSS_PER_POINT' centipawns that the guess loses compared to it.
+pub const MAX_GUESS_POINTS: u32 = 5;
+const GUESS_LOSS_PER_POINT: i32 = 50;
+
+// Settings of a "guess the move" session.
+#[derive(Debug, Clone, Copy)]
+pub struct GuessOptions
+{
+    // The side whose moves are guessed, or None to guess the moves of both sides.
+    pub side: Option<Color>,
+    // Depth of the searches scoring the guesses.
+    pub depth: u8,
+    pub display: DisplayOptions,
+}
+
+// A guess compared to the move of the game. The scores are from the perspective of the side that
+// played the move.
+#[derive(Debug, Clone, Copy)]
+pub struct Guess
+{
+    pub guessed: Move,
+    pub played: Move,
+    pub guessed_score: Score,
+    pub played_score: Score,
+    pub points: u32,
+}
+
+// Score a guess against the move played in the game, by searching the position after each of
+// them. A guess the engine likes at least as much as the move of the game gets all the points.
+pub fn score_guess(
+    board: &mut Board,
+    guessed: Move,
+    played: Move,
+    depth: u8,
+    search: &mut Search,
+) -> Guess
+{
+    let mut score_move = |mv: Move| -> Score {
+        board.make_move(mv);
+        let (score, _) = search.search(board, depth.saturating_sub(1).max(1));
+        board.unmake_move(mv);
+        return -score;
+    };
+    let played_score = score_move(played);
+    let guessed_score = if guessed == played { played_score } else { score_move(guessed) };
+
+    let loss = review_centipawns(played_score) - review_centipawns(guessed_score);
+    let points = if guessed == played || loss <= 0
+    {
+        MAX_GUESS_POINTS
+    }
+    else
+    {
+        let lost_points = (loss / GUESS_LOSS_PER_POINT) as u32 + 1;
+        MAX_GUESS_POINTS.saturating_sub(lost_points)
+    };
+    return Guess { guessed, played, guessed_score, played_score, points };
+}
+
+// Show the positions of a game one after the other, and ask for the move played in each of them by
+// the chosen side. Each guess is scored against the move of the game, which is then played.
+// Return the points scored and the maximum number of points of the guesses made.
+pub fn guess_the_move(record: &GameRecord, options: &GuessOptions) -> Result<(u32, u32), String>
+{
+    let mut board = record.start_board()?;
+    let mut search = Search::default();
+    let mut lines = io::stdin().lock().lines();
+    let (mut points, mut max_points) = (0, 0);
+    for (i, recorded) in record.moves.iter().enumerate()
+    {
+        let side = if board.white_to_play { WHITE } else { BLACK };
+        if options.side.is_some_and(|chosen| chosen != side)
+        {
+            board.make_move(recorded.mv);
+            continue;
+        }
+
+        let last_move = i.checked_sub(1).map(|previous| record.moves[previous].mv);
+        board.display_with(&DisplayOptions { last_move, ..options.display });
+        if options.display.blindfold
+        {
+            let moves: Vec<Move> = record.moves[.. i].iter().map(|recorded| recorded.mv).collect();
+            println!(
+                "Moves: {}",
+                format_movetext(&record.start_board()?, &moves, Notation::San, None)
+            );
+        }
+        let side_name = if side == WHITE { "White" } else { "Black" };
+        println!("Guess the move of {} ('skip' to see it, 'quit' to stop):", side_name);
+
+        let guessed = loop
+        {
+            print!("> ");
+            io::stdout().flush().map_err(|err| err.to_string())?;
+            let Some(Ok(line)) = lines.next()
+            else
+            {
+                return Ok((points, max_points));
+            };
+            match line.trim()
+            {
+                "quit" => return Ok((points, max_points)),
+                "skip" => break None,
+                text => match parse_move(&mut board, text)
+                {
+                    Some(mv) => break Some(mv),
+                    None => println!("'{}' is not a legal move in this position.", text),
+                },
+            }
+        };
+
+        let played_name = recorded.mv.to_san(&mut board);
+        match guessed
+        {
+            Some(guessed) =>
+            {
+                let guess =
+                    score_guess(&mut board, guessed, recorded.mv, options.depth, &mut search);
+                points += guess.points;
+                max_points += MAX_GUESS_POINTS;
+                println!(
+                    "The game went {} ({}), you chose {} ({}): {} points, {}/{} in total.",
+                    played_name,
+                    guess.played_score,
+                    guessed.to_san(&mut board),
+                    guess.guessed_score,
+                    guess.points,
+                    points,
+                    max_points
+                );
+            },
+            None => println!("The game went {}.", played_name),
+        }
+        board.make_move(recorded.mv);
+    }
+    return Ok((points, max_points));
+}
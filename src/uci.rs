@@ -0,0 +1,624 @@
+use std::{
+    io::{self, BufRead},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use log::debug;
+use rand::seq::IndexedRandom;
+
+use crate::*;
+
+// Depth searched when the 'go' command doesn't give one.
+const DEFAULT_DEPTH: u8 = 5;
+// Maximum depth of the searches limited by time.
+const TIMED_DEPTH: u8 = 64;
+// Bound of the Move Overhead option, in milliseconds.
+const MAX_MOVE_OVERHEAD_MS: u64 = 5000;
+// Maximum number of lines that can be requested with the MultiPV option.
+const MAX_MULTIPV: usize = 64;
+// Bound of the Contempt option, in centipawns.
+const MAX_CONTEMPT: i32 = 1000;
+// Bounds of the Hash option, in megabytes, and of the Threads option.
+const MAX_HASH_MEGABYTES: usize = 1 << 16;
+const MAX_THREADS: usize = 1024;
+// Parameters of the 'go' command, which end the list of moves of 'searchmoves'.
+const GO_PARAMETERS: [&str; 12] = [
+    "searchmoves",
+    "ponder",
+    "wtime",
+    "btime",
+    "winc",
+    "binc",
+    "movestogo",
+    "depth",
+    "nodes",
+    "mate",
+    "movetime",
+    "infinite",
+];
+
+// Print a line of the protocol to the GUI, and log it.
+macro_rules! send {
+    ($($arg:tt)*) => {{
+        let line = format!($($arg)*);
+        debug!(">> {}", line);
+        println!("{}", line);
+    }};
+}
+
+// State kept between the commands of a UCI session.
+struct UciSession
+{
+    board: Board,
+    search: Search,
+    multipv: usize,
+    // Add the chances of a win, a draw and a loss to the info lines.
+    show_wdl: bool,
+    // Number of threads searching the position: the threads beyond the first one run helper
+    // searches sharing the transposition table with the search of the session.
+    threads: usize,
+    // File of the openings from which the moves are played without searching, and its openings.
+    book_path: Option<String>,
+    book: Vec<Opening>,
+    // File of the evaluation weights used instead of the configured ones.
+    eval_file: Option<String>,
+    // Give the expected reply of the opponent with the best move, to ponder on it.
+    ponder: bool,
+    // Time kept on each timed move for the communication with the GUI.
+    move_overhead: Duration,
+    // Best move of the book found by 'go ponder', printed at 'ponderhit' or 'stop': the GUI doesn't
+    // expect it before.
+    pending_bestmove: Option<String>,
+    // Search of 'go infinite' or 'go ponder' running in the background until 'stop' or
+    // 'ponderhit'. The search of the session is moved to it meanwhile.
+    analysis: Option<InfiniteAnalysis>,
+    // Helper searches of the background search, stopped with it.
+    helpers: Vec<InfiniteAnalysis>,
+    // Error of the last 'position' command, reported by 'go' instead of searching the previous
+    // position.
+    position_error: Option<String>,
+    // Time limits of the move given to 'go ponder', which apply to its search from 'ponderhit'.
+    ponder_limits: Option<TimeLimits>,
+    // Search of 'go ponder' after 'ponderhit', ending by itself with its time limits and printing
+    // the best move, with its stop flag. It gives the search of the session back.
+    timed_analysis: Option<(Arc<AtomicBool>, JoinHandle<Search>)>,
+}
+
+// Read UCI commands from the standard input and answer them, until 'quit' is received.
+pub fn uci_loop()
+{
+    let config = engine_config();
+    let mut session = UciSession {
+        board: Board::new().expect("The starting position is valid."),
+        search: Search::default(),
+        multipv: 1,
+        show_wdl: false,
+        threads: config.threads,
+        book_path: None,
+        book: vec![],
+        eval_file: config.eval_file.clone(),
+        ponder: false,
+        move_overhead: config.move_overhead(),
+        pending_bestmove: None,
+        analysis: None,
+        helpers: vec![],
+        position_error: None,
+        ponder_limits: None,
+        timed_analysis: None,
+    };
+    if let Some(path) = config.book
+    {
+        set_book(&mut session, &path);
+    }
+
+    for line in io::stdin().lock().lines()
+    {
+        let Ok(line) = line
+        else
+        {
+            break;
+        };
+        debug!("<< {}", line);
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        // The GUI stops the background search before changing the position or the options, but it
+        // is stopped here too if it didn't.
+        if matches!(
+            tokens.first(),
+            Some(&("stop" | "quit" | "ucinewgame" | "position" | "setoption" | "go"))
+        )
+        {
+            finish_analysis(&mut session);
+        }
+        match tokens.first()
+        {
+            Some(&"uci") =>
+            {
+                send!("id name barnarok {}", env!("CARGO_PKG_VERSION"));
+                send!("id author the barnarok developers");
+                print_options(&session);
+                send!("uciok");
+            },
+            Some(&"isready") => send!("readyok"),
+            Some(&"ucinewgame") =>
+            {
+                session.board = Board::new().expect("The starting position is valid.");
+                session.position_error = None;
+                session.search.clear();
+            },
+            Some(&"position") =>
+            {
+                session.position_error = set_position(&mut session, &tokens[1 ..]).err();
+                if let Some(err) = &session.position_error
+                {
+                    send!("info string {}", err);
+                }
+            },
+            Some(&"setoption") => set_option(&mut session, &tokens[1 ..]),
+            Some(&"go") => go(&mut session, &tokens[1 ..]),
+            Some(&"ponderhit") => ponderhit(&mut session),
+            Some(&"stop") =>
+            {
+                if let Some(bestmove) = session.pending_bestmove.take()
+                {
+                    send!("{}", bestmove);
+                }
+            },
+            Some(&"quit") => break,
+            _ => (),
+        }
+    }
+}
+
+// Handle 'position [startpos | fen <fen>] [moves <move>...]'. The positions that can't be reached
+// in a game are refused, since the search relies on their legality.
+fn set_position(session: &mut UciSession, args: &[&str]) -> Result<(), String>
+{
+    let moves_index = args.iter().position(|arg| *arg == "moves").unwrap_or(args.len());
+    let mut board = match args.first()
+    {
+        Some(&"startpos") => Board::new()?,
+        Some(&"fen") => Board::from_fen_strict(&args[1 .. moves_index].join(" "))?,
+        _ => return Err("Expected 'startpos' or 'fen'.".into()),
+    };
+
+    // Play the moves that follow the position.
+    let moves = args.get(moves_index + 1 ..).unwrap_or_default();
+    board.apply_uci_moves(moves).map_err(|err| err.to_string())?;
+
+    session.board = board;
+    return Ok(());
+}
+
+// Print the options that can be set, with their current values as defaults, since the defaults
+// come from the engine configuration.
+fn print_options(session: &UciSession)
+{
+    let string = |value: &Option<String>| value.clone().unwrap_or("<empty>".into());
+    send!(
+        "option name Hash type spin default {} min 1 max {}",
+        session.search.table_sizes().bytes().div_ceil(1 << 20),
+        MAX_HASH_MEGABYTES
+    );
+    send!("option name Threads type spin default {} min 1 max {}", session.threads, MAX_THREADS);
+    send!("option name MultiPV type spin default {} min 1 max {}", session.multipv, MAX_MULTIPV);
+    send!(
+        "option name Contempt type spin default {} min {} max {}",
+        session.search.options.contempt.0,
+        -MAX_CONTEMPT,
+        MAX_CONTEMPT
+    );
+    send!(
+        "option name Skill Level type spin default {} min 0 max {}",
+        session.search.options.skill_level,
+        MAX_SKILL_LEVEL
+    );
+    send!("option name Book type string default {}", string(&session.book_path));
+    send!("option name EvalFile type string default {}", string(&session.eval_file));
+    send!("option name Ponder type check default {}", session.ponder);
+    send!(
+        "option name Move Overhead type spin default {} min 0 max {}",
+        session.move_overhead.as_millis(),
+        MAX_MOVE_OVERHEAD_MS
+    );
+    send!("option name UCI_ShowWDL type check default {}", session.show_wdl);
+}
+
+// Handle 'setoption name <name> value <value>'.
+fn set_option(session: &mut UciSession, args: &[&str])
+{
+    let value_index = args.iter().position(|arg| *arg == "value").unwrap_or(args.len());
+    let name = args.get(1 .. value_index).unwrap_or_default().join(" ");
+    let value = args.get(value_index + 1 ..).unwrap_or_default().join(" ");
+    // An empty string option is sent as "<empty>" by some GUIs.
+    let text = if value.is_empty() || value == "<empty>" { None } else { Some(value.clone()) };
+
+    let invalid = || send!("info string Invalid {} value '{}'.", name, value);
+    match name.to_ascii_lowercase().as_str()
+    {
+        "hash" => match value.parse::<usize>()
+        {
+            Ok(megabytes) =>
+            {
+                let sizes = TableSizes::for_megabytes(megabytes.clamp(1, MAX_HASH_MEGABYTES));
+                session.search.resize_tables(sizes);
+                send!(
+                    "info string Allocated {} bytes: {} transposition table entries, {} \
+                     evaluation cache entries, {} pawn table entries.",
+                    sizes.bytes(),
+                    sizes.tt_entries,
+                    sizes.eval_cache_entries,
+                    sizes.pawn_table_entries
+                );
+            },
+            Err(_) => invalid(),
+        },
+        "threads" => match value.parse::<usize>()
+        {
+            Ok(threads) => session.threads = threads.clamp(1, MAX_THREADS),
+            Err(_) => invalid(),
+        },
+        "multipv" => match value.parse::<usize>()
+        {
+            Ok(n) => session.multipv = n.clamp(1, MAX_MULTIPV),
+            Err(_) => invalid(),
+        },
+        "contempt" => match value.parse::<i32>()
+        {
+            Ok(contempt) =>
+            {
+                session.search.options.contempt = Score(contempt.clamp(-MAX_CONTEMPT, MAX_CONTEMPT))
+            },
+            Err(_) => invalid(),
+        },
+        "skill level" => match value.parse::<u8>()
+        {
+            Ok(level) => session.search.options.skill_level = level.min(MAX_SKILL_LEVEL),
+            Err(_) => invalid(),
+        },
+        "book" => match text
+        {
+            Some(path) => set_book(session, &path),
+            None =>
+            {
+                session.book_path = None;
+                session.book = vec![];
+            },
+        },
+        "evalfile" => set_eval_file(session, text),
+        "ponder" => match value.parse::<bool>()
+        {
+            Ok(ponder) => session.ponder = ponder,
+            Err(_) => invalid(),
+        },
+        "move overhead" => match value.parse::<u64>()
+        {
+            Ok(millis) =>
+            {
+                session.move_overhead = Duration::from_millis(millis.min(MAX_MOVE_OVERHEAD_MS))
+            },
+            Err(_) => invalid(),
+        },
+        "uci_showwdl" => match value.parse::<bool>()
+        {
+            Ok(show_wdl) => session.show_wdl = show_wdl,
+            Err(_) => invalid(),
+        },
+        _ => send!("info string Unknown option '{}'.", name),
+    }
+}
+
+// Read the evaluation weights of a file, or go back to the configured ones without a file. The
+// previous weights are kept if the file can't be read or its weights are invalid. The tables are
+// cleared, since their scores come from the previous weights.
+fn set_eval_file(session: &mut UciSession, path: Option<String>)
+{
+    let params = match &path
+    {
+        Some(path) => EvalParams::from_file(path),
+        None => Ok(engine_config().eval),
+    };
+    match params
+    {
+        Ok(params) =>
+        {
+            session.search.options.eval_params = params;
+            session.search.clear();
+            session.eval_file = path;
+        },
+        Err(err) => send!("info string {}", err),
+    }
+}
+
+// Read the openings of a book file. The previous book is kept if the file can't be read.
+fn set_book(session: &mut UciSession, path: &str)
+{
+    match read_openings(path)
+    {
+        Ok(openings) =>
+        {
+            session.book_path = Some(path.into());
+            session.book = openings;
+        },
+        Err(err) => send!("info string {}", err),
+    }
+}
+
+// Print the best move, or keep it until 'ponderhit' or 'stop' when pondering.
+fn send_bestmove(session: &mut UciSession, bestmove: String, pondering: bool)
+{
+    if pondering
+    {
+        session.pending_bestmove = Some(bestmove);
+    }
+    else
+    {
+        send!("{}", bestmove);
+    }
+}
+
+// Stop the background search of 'go infinite' or 'go ponder', if there is one, take the search of
+// the session back, and print the best move. After 'ponderhit', the search prints the best move
+// itself.
+fn finish_analysis(session: &mut UciSession)
+{
+    if let Some((stop, handle)) = session.timed_analysis.take()
+    {
+        stop.store(true, Ordering::Relaxed);
+        let search = match handle.join()
+        {
+            Ok(search) => search,
+            Err(payload) => std::panic::resume_unwind(payload),
+        };
+        restore_search(session, search);
+    }
+    let Some(analysis) = session.analysis.take()
+    else
+    {
+        return;
+    };
+    stop_helpers(session.helpers.drain(..));
+    let (lines, search) = analysis.stop();
+    restore_search(session, search);
+    let bestmove = bestmove_command(&session.search, &session.board, session.ponder, &lines);
+    send!("{}", bestmove);
+}
+
+// Give the search of a background search back to the session.
+fn restore_search(session: &mut UciSession, search: Search)
+{
+    // The flag and the deadlines stay set after the search ended, and would stop the next one at
+    // once.
+    search.stop_flag().store(false, Ordering::Relaxed);
+    search.shared_deadlines().clear();
+    session.search = search;
+}
+
+// Handle 'ponderhit': the opponent played the expected move, so the search of 'go ponder' goes on
+// as a normal search, with the time limits given to 'go ponder' from now on. Without them, it ends
+// at once.
+fn ponderhit(session: &mut UciSession)
+{
+    if let Some(bestmove) = session.pending_bestmove.take()
+    {
+        send!("{}", bestmove);
+        return;
+    }
+    let (Some(analysis), Some(limits)) = (session.analysis.take(), session.ponder_limits.take())
+    else
+    {
+        finish_analysis(session);
+        return;
+    };
+    analysis.set_time_limits(limits);
+    let stop = analysis.stop_flag();
+    let helpers = std::mem::take(&mut session.helpers);
+    let board = session.board;
+    let ponder = session.ponder;
+    let handle = thread::spawn(move || {
+        let (lines, search) = analysis.wait();
+        stop_helpers(helpers.into_iter());
+        send!("{}", bestmove_command(&search, &board, ponder, &lines));
+        return search;
+    });
+    session.timed_analysis = Some((stop, handle));
+}
+
+// Start a helper search for each thread beyond the first one. The helpers search the position
+// without time limits, sharing the transposition table with the search of the session, whose
+// iterations find the entries they stored.
+fn start_helpers(session: &UciSession, count: usize, search_moves: &[Move])
+-> Vec<InfiniteAnalysis>
+{
+    let mut options = session.search.options.clone();
+    options.clear_time_limits();
+    return (1 .. session.threads)
+        .map(|_| {
+            let search = Search::with_tt(options.clone(), session.search.tt.clone());
+            InfiniteAnalysis::start(&session.board, count, search_moves, search, |_, _, _, _| ())
+        })
+        .collect();
+}
+
+fn stop_helpers(helpers: impl Iterator<Item = InfiniteAnalysis>)
+{
+    for helper in helpers
+    {
+        helper.stop();
+    }
+}
+
+// Get the 'bestmove' command for the lines found by a search, with the expected reply to ponder on
+// when pondering is enabled.
+fn bestmove_command(search: &Search, board: &Board, ponder: bool, lines: &[PvLine]) -> String
+{
+    return match search.choose_line(board, lines)
+    {
+        Some(line) if ponder && line.moves.len() >= 2 =>
+        {
+            format!("bestmove {} ponder {}", line.moves[0].to_uci(), line.moves[1].to_uci())
+        },
+        Some(line) => format!("bestmove {}", line.moves[0].to_uci()),
+        None => "bestmove 0000".into(),
+    };
+}
+
+// Get the function printing one info line per PV after each iteration of a search started at
+// 'start', with the chances of a win, a draw and a loss when 'show_wdl' is set.
+fn info_reporter(
+    show_wdl: bool,
+    phase: i32,
+    start: Instant,
+) -> impl FnMut(u8, &mut Board, &[PvLine], &SearchInfo) + Send + 'static
+{
+    return move |depth, _, lines, info| {
+        for (i, line) in lines.iter().enumerate()
+        {
+            let pv: Vec<String> = line.moves.iter().map(|mv| mv.to_uci()).collect();
+            let mut score = line.score.to_uci();
+            if show_wdl
+            {
+                let (win, draw, loss) = line.score.wdl(phase);
+                score.push_str(&format!(" wdl {} {} {}", win, draw, loss));
+            }
+            send!(
+                "info multipv {} depth {} seldepth {} score {} nodes {} time {} pv {}",
+                i + 1,
+                depth,
+                info.seldepth,
+                score,
+                info.nodes,
+                start.elapsed().as_millis(),
+                pv.join(" ")
+            );
+        }
+    };
+}
+
+// Get the time limits of the move from the parameters of 'go': 'movetime', or the clock of the side
+// to move with 'wtime', 'winc', 'btime', 'binc' and 'movestogo'. Return None without them.
+fn go_time_limits(session: &UciSession, args: &[&str]) -> Option<TimeLimits>
+{
+    // Some GUIs send a negative time when the clock is about to run out.
+    let millis = |name: &str| -> Option<Duration> {
+        let value = args.iter().position(|arg| *arg == name).and_then(|i| args.get(i + 1))?;
+        return value.parse::<i64>().ok().map(|ms| Duration::from_millis(ms.max(0) as u64));
+    };
+    if let Some(time) = millis("movetime")
+    {
+        return Some(TimeLimits::fixed(time, session.move_overhead));
+    }
+    let (time, increment) =
+        if session.board.white_to_play { ("wtime", "winc") } else { ("btime", "binc") };
+    let moves_to_go = args
+        .iter()
+        .position(|arg| *arg == "movestogo")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|moves| moves.parse::<u32>().ok());
+    return millis(time).map(|remaining| {
+        TimeLimits::for_clock(
+            remaining,
+            millis(increment).unwrap_or_default(),
+            moves_to_go,
+            session.move_overhead,
+        )
+    });
+}
+
+// Handle 'go [depth <n>] [wtime <ms>] [btime <ms>] [winc <ms>] [binc <ms>] [movestogo <n>]
+// [movetime <ms>] [searchmoves <move> ...] [ponder] [infinite]', printing one info line per PV
+// after each iteration, and the best move. With a clock or a move time, the search deepens until
+// its time limits, or until the depth if one is given. With 'infinite' or 'ponder', the search
+// runs in the background until 'stop', while the commands are still read. After 'ponderhit', the
+// search of 'ponder' goes on with the time limits of the move.
+fn go(session: &mut UciSession, args: &[&str])
+{
+    if let Some(err) = &session.position_error
+    {
+        send!("info string {}", err);
+        send!("bestmove 0000");
+        return;
+    }
+    let time_limits = go_time_limits(session, args);
+    let depth = args
+        .iter()
+        .position(|arg| *arg == "depth")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|d| d.parse::<u32>().ok())
+        .map_or(if time_limits.is_some() { TIMED_DEPTH } else { DEFAULT_DEPTH }, |d| {
+            d.min(u32::from(MAX_DEPTH)) as u8
+        });
+
+    // The moves of 'searchmoves' go until the next parameter of 'go'.
+    let mut search_moves = vec![];
+    if let Some(start) = args.iter().position(|arg| *arg == "searchmoves")
+    {
+        for text in args[start + 1 ..].iter().take_while(|arg| !GO_PARAMETERS.contains(*arg))
+        {
+            match parse_uci_move(&mut session.board, text)
+            {
+                Some(mv) => search_moves.push(mv),
+                None => send!("info string Ignoring illegal move '{}'.", text),
+            }
+        }
+    }
+
+    let pondering = args.contains(&"ponder");
+    let infinite = args.contains(&"infinite");
+    session.pending_bestmove = None;
+    session.ponder_limits = if pondering { time_limits } else { None };
+
+    // A move of the book is played without searching, unless the moves are restricted or the
+    // position is analyzed.
+    if search_moves.is_empty() && !infinite
+    {
+        let moves = book_moves(&session.book, &mut session.board);
+        let mut rng = default_randomness().rng(session.board.hash);
+        if let Some(mv) = moves.choose(&mut rng)
+        {
+            send!("info string book move");
+            send_bestmove(session, format!("bestmove {}", mv.to_uci()), pondering);
+            return;
+        }
+    }
+
+    // Below the full strength, the depth is limited, and the best move is chosen among more lines.
+    let level = session.search.options.skill_level;
+    let depth = depth.min(skill_max_depth(level));
+    let count =
+        if level < MAX_SKILL_LEVEL { session.multipv.max(SKILL_LINES) } else { session.multipv };
+
+    // Print the lines found after each iteration.
+    let start = Instant::now();
+    let report = info_reporter(session.show_wdl, game_phase(&session.board), start);
+
+    let helpers = start_helpers(session, count, &search_moves);
+
+    // The background search has no time limits: it ends when it is told to.
+    if infinite || pondering
+    {
+        session.search.options.clear_time_limits();
+        // The search left to the session meanwhile shares the transposition table.
+        let placeholder =
+            Search::with_tt(session.search.options.clone(), session.search.tt.clone());
+        let search = std::mem::replace(&mut session.search, placeholder);
+        session.analysis =
+            Some(InfiniteAnalysis::start(&session.board, count, &search_moves, search, report));
+        session.helpers = helpers;
+        return;
+    }
+
+    match time_limits
+    {
+        Some(limits) => session.search.options.set_time_limits(limits),
+        None => session.search.options.clear_time_limits(),
+    }
+    let lines = session.search.analyze(&mut session.board, depth, count, &search_moves, report);
+    stop_helpers(helpers.into_iter());
+    let bestmove = bestmove_command(&session.search, &session.board, session.ponder, &lines);
+    send!("{}", bestmove);
+}
@@ -1,4 +1,6 @@
-use crate::{Board, defines::*};
+use alloc::{format, string::String, vec, vec::Vec};
+
+use crate::{Board, Move, Notation, defines::*};
 
 // Get the piece type on a certain square.
 pub fn get_piece_type_on_square(board: &Board, sq: usize) -> Piece
@@ -64,7 +66,27 @@ pub fn get_piece_type_on_square(board: &Board, sq: usize) -> Piece
     }
 }
 
+// Get the index of a square from its name (e.g. "e4"), if it is valid.
+pub fn parse_square(name: &str) -> Option<Index>
+{
+    let bytes = name.as_bytes();
+    if bytes.len() != 2
+        || !(b'a' ..= b'h').contains(&bytes[0])
+        || !(b'1' ..= b'8').contains(&bytes[1])
+    {
+        return None;
+    }
+    return Some((bytes[1] - b'1') as usize * 8 + (bytes[0] - b'a') as usize);
+}
+
+// Get the name of a square from its index, e.g. "e4".
+pub fn square_name(sq: Index) -> String
+{
+    return format!("{}{}", (b'a' + (sq % 8) as u8) as char, sq / 8 + 1);
+}
+
 // Print a bitboard as an 8x8 board (white perspective).
+#[cfg(feature = "std")]
 pub fn print_bb(bb: u64)
 {
     for rank in (0 .. 8).rev()
@@ -79,55 +101,122 @@ pub fn print_bb(bb: u64)
     println!();
 }
 
-pub fn launch_explore(board: &mut Board, max_depth: usize, verbose: bool) -> usize
+// Count the positions reached after 'max_depth' plies, printing the count after each root move,
+// or the whole tree of moves if 'verbose'. The counts of the subtrees are cached in 'table'.
+#[cfg(feature = "std")]
+pub fn launch_explore(
+    board: &mut Board,
+    max_depth: usize,
+    verbose: bool,
+    table: &mut PerftTable,
+) -> u64
 {
     if verbose
     {
-        return explore_verbose(board, max_depth, String::new());
+        return explore_verbose(board, max_depth, String::new(), table);
     }
-    else
+    if max_depth == 0
     {
-        return explore(board, max_depth, true);
+        return 1;
     }
+    let mut n = 0;
+    for mv in board.get_legal_moves().iter()
+    {
+        board.make_move(*mv);
+        let m = perft_hashed(board, max_depth - 1, table);
+        board.unmake_move(*mv);
+        println!("{}: {}", mv.to_uci(), m);
+        n += m;
+    }
+    return n;
 }
 
-// Explore every possible position after a certain amount of plies.
-// 1: 20; 2: 400; 3: 8902; etc.
-fn explore(board: &mut Board, max_depth: usize, root: bool) -> usize
+// Count the positions reached after 'depth' plies, without printing anything.
+// 1: 20; 2: 400; 3: 8902; etc. from the initial position.
+pub fn perft(board: &mut Board, depth: usize) -> u64
 {
-    if max_depth == 0
+    if depth == 0
     {
         return 1;
     }
+    // The generated moves are all legal, so the positions one ply away are counted without
+    // playing the moves.
+    if depth == 1
+    {
+        return board.get_legal_moves().len() as u64;
+    }
 
     let mut n = 0;
-    let mut uci: String;
-    let moves = board.get_legal_moves();
-    for mv in moves.iter()
+    for mv in board.get_legal_moves().iter()
     {
-        if root
-        {
-            uci = mv.to_uci();
-        }
-        else
-        {
-            uci = "".into();
-        }
         board.make_move(*mv);
-        let m = explore(board, max_depth - 1, false);
-        if root
-        {
-            println!("{}: {}", uci, m)
-        };
-        n += m;
+        n += perft(board, depth - 1);
         board.unmake_move(*mv);
     }
+    return n;
+}
+
+// A hash table of perft node counts, indexed by the hash of the positions and the remaining depth.
+// The same positions are reached by many move orders, so a deep perft counts most subtrees only
+// once. Each slot holds the last count stored in it, with the hash and the depth it is for.
+pub struct PerftTable
+{
+    entries: Vec<Option<(u64, usize, u64)>>,
+}
+
+impl PerftTable
+{
+    // Create an empty table with the given number of entries. With 0 entries, nothing is cached.
+    pub fn new(size: usize) -> Self
+    {
+        return PerftTable { entries: vec![None; size] };
+    }
 
+    fn index(&self, hash: u64, depth: usize) -> usize
+    {
+        // Spread the depths over the table, so that the counts of a position at different depths
+        // don't replace each other.
+        let key = hash ^ (depth as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+        return (key % self.entries.len() as u64) as usize;
+    }
+}
+
+// Count the positions reached after 'depth' plies like 'perft', caching the counts of the
+// subtrees by position and depth. The counts at depth 1 are not cached, they are as fast to find
+// again.
+pub fn perft_hashed(board: &mut Board, depth: usize, table: &mut PerftTable) -> u64
+{
+    if table.entries.is_empty() || depth <= 1
+    {
+        return perft(board, depth);
+    }
+    let index = table.index(board.hash, depth);
+    if let Some((hash, entry_depth, count)) = table.entries[index]
+        && hash == board.hash
+        && entry_depth == depth
+    {
+        return count;
+    }
+
+    let mut n = 0;
+    for mv in board.get_legal_moves().iter()
+    {
+        board.make_move(*mv);
+        n += perft_hashed(board, depth - 1, table);
+        board.unmake_move(*mv);
+    }
+    table.entries[index] = Some((board.hash, depth, n));
     return n;
 }
 
 // Explore every possible position after a certain amount of plies, and print the tree of moves.
-fn explore_verbose(board: &mut Board, max_depth: usize, prefix: String) -> usize
+#[cfg(feature = "std")]
+fn explore_verbose(
+    board: &mut Board,
+    max_depth: usize,
+    prefix: String,
+    table: &mut PerftTable,
+) -> u64
 {
     if max_depth == 0
     {
@@ -146,7 +235,7 @@ fn explore_verbose(board: &mut Board, max_depth: usize, prefix: String) -> usize
         let child_prefix = if is_last { "    " } else { "│   " };
 
         board.make_move(*mv);
-        let nb = explore(board, max_depth - 1, false);
+        let nb = perft_hashed(board, max_depth - 1, table);
         board.unmake_move(*mv);
 
         println!(
@@ -158,7 +247,7 @@ fn explore_verbose(board: &mut Board, max_depth: usize, prefix: String) -> usize
         );
 
         board.make_move(*mv);
-        explore_verbose(board, max_depth - 1, prefix.clone() + child_prefix);
+        explore_verbose(board, max_depth - 1, prefix.clone() + child_prefix, table);
         board.unmake_move(*mv);
 
         n += nb;
@@ -166,3 +255,135 @@ fn explore_verbose(board: &mut Board, max_depth: usize, prefix: String) -> usize
 
     return n;
 }
+
+// Write a sequence of moves played from the given position in SAN, separated by spaces.
+// The board is left unchanged.
+pub fn moves_to_san(board: &mut Board, moves: &[Move]) -> String
+{
+    let mut names = vec![];
+    for mv in moves.iter()
+    {
+        names.push(mv.to_san(board));
+        board.make_move(*mv);
+    }
+    for mv in moves.iter().rev()
+    {
+        board.unmake_move(*mv);
+    }
+    return names.join(" ");
+}
+
+// A move of a game with its move number, to write it in numbered movetext.
+#[derive(Debug, PartialEq, Clone)]
+pub struct NumberedMove
+{
+    // The number of the move, from 1, shared by a move of white and the reply of black.
+    pub number: usize,
+    pub white: bool,
+    // The move in the chosen notation.
+    pub text: String,
+}
+
+impl NumberedMove
+{
+    // Get the number written before the move: "12." for white, "12..." for black.
+    pub fn number_text(&self) -> String
+    {
+        return format!("{}{}", self.number, if self.white { "." } else { "..." });
+    }
+}
+
+// Write each move of a sequence played from the given position in a notation, with its number.
+pub fn number_moves(board: &Board, moves: &[Move], notation: Notation) -> Vec<NumberedMove>
+{
+    let mut board = *board;
+    let mut numbered = vec![];
+    let mut number = 1;
+    for mv in moves.iter()
+    {
+        let white = board.white_to_play;
+        numbered.push(NumberedMove { number, white, text: mv.to_notation(&mut board, notation) });
+        board.make_move(*mv);
+        if board.white_to_play
+        {
+            number += 1;
+        }
+    }
+    return numbered;
+}
+
+// Write a sequence of moves played from the given position as numbered movetext, like in PGN:
+// "1. e4 e5 2. Nf3", or "1... e5 2. Nf3" when black plays first, then the result if there is one.
+pub fn format_movetext(
+    board: &Board,
+    moves: &[Move],
+    notation: Notation,
+    result: Option<&str>,
+) -> String
+{
+    let mut tokens = vec![];
+    for (i, numbered) in number_moves(board, moves, notation).into_iter().enumerate()
+    {
+        if numbered.white || i == 0
+        {
+            tokens.push(numbered.number_text());
+        }
+        tokens.push(numbered.text);
+    }
+    tokens.extend(result.map(String::from));
+    return tokens.join(" ");
+}
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::START_FEN;
+
+    // Positions with their number of leaf nodes at a given depth: perft position 3, en passant
+    // captures exposing the king along a rank or a diagonal, and castling rights kept without the
+    // rook on its square, the king off its square, or through attacked squares.
+    const REGRESSION_POSITIONS: [(&str, usize, u64); 10] = [
+        (START_FEN, 3, 8902),
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - -", 4, 43238),
+        ("3k4/3p4/8/K1P4r/8/8/8/8 b - -", 4, 10138),
+        ("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3", 4, 13931),
+        ("8/8/8/8/k2Pp2Q/8/8/3K4 b - d3", 4, 20471),
+        ("5k2/8/8/8/8/8/8/4K2R w K -", 4, 6399),
+        ("3k4/8/8/8/8/8/8/R3K3 w Q -", 4, 7418),
+        ("r3k3/8/8/8/8/8/8/4K2R w Kkq -", 3, 3616),
+        ("r3k2r/8/8/8/8/8/8/R4K1R w KQkq -", 3, 11463),
+        ("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq -", 3, 27826),
+    ];
+
+    #[test]
+    fn perft_regressions()
+    {
+        for (fen, depth, expected) in REGRESSION_POSITIONS
+        {
+            let mut board = Board::from_fen(fen).unwrap();
+            assert_eq!(perft(&mut board, depth), expected, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn hashed_perft_matches_unhashed()
+    {
+        for (fen, depth, expected) in REGRESSION_POSITIONS
+        {
+            let mut board = Board::from_fen(fen).unwrap();
+            // Few entries, so that the counts replace each other.
+            let mut table = PerftTable::new(1 << 10);
+            assert_eq!(perft_hashed(&mut board, depth, &mut table), expected, "{}", fen);
+            // The counts cached by the first run are found again.
+            assert_eq!(perft_hashed(&mut board, depth, &mut table), expected, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn hashed_perft_without_entries_is_perft()
+    {
+        let mut board = Board::new().unwrap();
+        assert_eq!(perft_hashed(&mut board, 3, &mut PerftTable::new(0)), 8902);
+    }
+}
@@ -0,0 +1,249 @@
+use alloc::{format, string::String, vec::Vec};
+use core::fmt;
+
+use crate::{
+    BLACK_KING_SIDE, BLACK_QUEEN_SIDE, Bitboard, Board, Index, MoveContext, NO_SQUARE, Variant,
+    WHITE_KING_SIDE, WHITE_QUEEN_SIDE, is_king_attacked, king_mask, square_name,
+};
+
+// The first and the last ranks, where no pawn can stand.
+const BACK_RANKS: Bitboard = 0xff00_0000_0000_00ff;
+
+// A reason why a position can't be reached in a game. The search assumes that none of them
+// happens, and can panic or play nonsense on such positions.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Violation
+{
+    // A side has more than 8 pawns.
+    TooManyPawns
+    {
+        white: bool,
+        count: u32,
+    },
+    // A side has more than 16 pieces.
+    TooManyPieces
+    {
+        white: bool,
+        count: u32,
+    },
+    // A side has more queens, rooks, bishops and knights than its missing pawns could have
+    // promoted to.
+    TooManyPromotedPieces
+    {
+        white: bool,
+    },
+    PawnOnBackRank(Index),
+    KingsAdjacent,
+    // The side that just moved left its king in check.
+    OpponentInCheck,
+    // The en passant target doesn't follow a double pawn push of the side that just moved.
+    InvalidEnPassant(Index),
+    // A castling right is kept while the king or the rook left its initial square.
+    InvalidCastlingRight(char),
+}
+
+impl fmt::Display for Violation
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let side = |white: bool| if white { "White" } else { "Black" };
+        return match self
+        {
+            Violation::TooManyPawns { white, count } =>
+            {
+                write!(f, "{} has {} pawns.", side(*white), count)
+            },
+            Violation::TooManyPieces { white, count } =>
+            {
+                write!(f, "{} has {} pieces.", side(*white), count)
+            },
+            Violation::TooManyPromotedPieces { white } =>
+            {
+                write!(f, "{} has more promoted pieces than missing pawns.", side(*white))
+            },
+            Violation::PawnOnBackRank(sq) =>
+            {
+                write!(f, "Pawn on the back rank on {}.", square_name(*sq))
+            },
+            Violation::KingsAdjacent => write!(f, "The kings are adjacent."),
+            Violation::OpponentInCheck => write!(f, "The side not to move is in check."),
+            Violation::InvalidEnPassant(sq) =>
+            {
+                write!(f, "Impossible en passant square {}.", square_name(*sq))
+            },
+            Violation::InvalidCastlingRight(right) =>
+            {
+                write!(
+                    f,
+                    "Castling right '{}' without the king and the rook on their squares.",
+                    right
+                )
+            },
+        };
+    }
+}
+
+impl Board
+{
+    // Find the reasons why the position can't be reached in a game of its variant, if any.
+    // The Horde side is allowed its 36 pawns, including those on the first rank, and the kings can
+    // touch each other in Atomic and Antichess.
+    pub fn validate(&self) -> Vec<Violation>
+    {
+        let mut violations = Vec::new();
+        for white in [true, false]
+        {
+            // The pawns of the Horde side don't follow the usual counts.
+            if white && self.variant == Variant::Horde
+            {
+                continue;
+            }
+            let (pawns, pieces, minors_and_majors) = if white
+            {
+                (
+                    self.white_pawns,
+                    self.white_pieces,
+                    [self.white_knights, self.white_bishops, self.white_rooks, self.white_queens],
+                )
+            }
+            else
+            {
+                (
+                    self.black_pawns,
+                    self.black_pieces,
+                    [self.black_knights, self.black_bishops, self.black_rooks, self.black_queens],
+                )
+            };
+            let pawn_count = pawns.count_ones();
+            if pawn_count > 8
+            {
+                violations.push(Violation::TooManyPawns { white, count: pawn_count });
+            }
+            if pieces.count_ones() > 16
+            {
+                violations.push(Violation::TooManyPieces { white, count: pieces.count_ones() });
+            }
+            // Each piece beyond the initial ones (2 knights, 2 bishops, 2 rooks and a queen) was
+            // promoted from a pawn.
+            let promoted: u32 = minors_and_majors
+                .iter()
+                .zip([2, 2, 2, 1])
+                .map(|(bitboard, initial)| bitboard.count_ones().saturating_sub(initial))
+                .sum();
+            if promoted > 8u32.saturating_sub(pawn_count)
+            {
+                violations.push(Violation::TooManyPromotedPieces { white });
+            }
+        }
+
+        // The Horde pawns can stand on the first rank, where they can still move two squares.
+        let back_rank_pawns = if self.variant == Variant::Horde
+        {
+            (self.white_pawns & 0xff00_0000_0000_0000) | (self.black_pawns & BACK_RANKS)
+        }
+        else
+        {
+            (self.white_pawns | self.black_pawns) & BACK_RANKS
+        };
+        let mut remaining = back_rank_pawns;
+        while remaining != 0
+        {
+            let sq = remaining.trailing_zeros() as Index;
+            violations.push(Violation::PawnOnBackRank(sq));
+            remaining &= remaining - 1;
+        }
+
+        let both_kings = self.white_king != NO_SQUARE && self.black_king != NO_SQUARE;
+        if both_kings
+            && !matches!(self.variant, Variant::Atomic | Variant::Antichess)
+            && king_mask(self.white_king) & (1u64 << self.black_king) != 0
+        {
+            violations.push(Violation::KingsAdjacent);
+        }
+        let opponent_king = if self.white_to_play { self.black_king } else { self.white_king };
+        if opponent_king != NO_SQUARE && is_king_attacked(self, true)
+        {
+            violations.push(Violation::OpponentInCheck);
+        }
+
+        if let Some(target) = self.en_passant_target
+            && !self.en_passant_follows_push(target)
+        {
+            violations.push(Violation::InvalidEnPassant(target));
+        }
+
+        for (right, letter, king, rook) in [
+            (WHITE_KING_SIDE, 'K', 4, 7),
+            (WHITE_QUEEN_SIDE, 'Q', 4, 0),
+            (BLACK_KING_SIDE, 'k', 60, 63),
+            (BLACK_QUEEN_SIDE, 'q', 60, 56),
+        ]
+        {
+            let white = (WHITE_KING_SIDE | WHITE_QUEEN_SIDE).contains(right);
+            let (king_square, rooks) = if white
+            {
+                (self.white_king, self.white_rooks)
+            }
+            else
+            {
+                (self.black_king, self.black_rooks)
+            };
+            if self.castling_rights.contains(right)
+                && (king_square != king || rooks & (1u64 << rook) == 0)
+            {
+                violations.push(Violation::InvalidCastlingRight(letter));
+            }
+        }
+        return violations;
+    }
+
+    // Create a Board from a FEN string like 'from_fen', and reject it if it can't be reached in a
+    // game, with the list of the violations found.
+    pub fn from_fen_strict(fen: &str) -> Result<Self, String>
+    {
+        let board = Self::from_fen(fen)?;
+        let violations = board.validate();
+        if !violations.is_empty()
+        {
+            let reasons: Vec<String> =
+                violations.iter().map(|violation| format!("{}", violation)).collect();
+            return Err(format!("Illegal position: {}", reasons.join(" ")));
+        }
+        return Ok(board);
+    }
+
+    // Get the en passant target if a pawn of the side to move can legally capture on it. Only such
+    // targets are written in FEN, like in X-FEN and in the Polyglot books, so that a double step
+    // that no pawn can take doesn't make the position look different.
+    pub fn legal_en_passant_target(&self) -> Option<Index>
+    {
+        let target =
+            self.en_passant_target.filter(|target| self.en_passant_follows_push(*target))?;
+        // The boards being created don't have their hashes yet, which the moves tried update.
+        let mut board = *self;
+        board.hash = board.compute_hash();
+        board.pawn_hash = board.compute_pawn_hash();
+        return board
+            .get_legal_moves()
+            .iter()
+            .any(|mv| mv.context == MoveContext::EnPassant && mv.end == target)
+            .then_some(target);
+    }
+
+    // Check whether the en passant target is behind a pawn that just moved two squares: the target
+    // and the square the pawn came from are empty, and the pawn of the side that just moved is in
+    // front of the target.
+    fn en_passant_follows_push(&self, target: Index) -> bool
+    {
+        let (rank, pusher_pawns) =
+            if self.white_to_play { (5, self.black_pawns) } else { (2, self.white_pawns) };
+        if target / 8 != rank
+        {
+            return false;
+        }
+        let (from, to) =
+            if self.white_to_play { (target + 8, target - 8) } else { (target - 8, target + 8) };
+        return self.pieces & ((1u64 << target) | (1u64 << from)) == 0
+            && pusher_pawns & (1u64 << to) != 0;
+    }
+}
@@ -0,0 +1,298 @@
+use alloc::{format, string::String};
+
+use crate::{
+    BISHOP, BLACK, Bitboard, Board, CASTLING_RIGHTS_MASKS, EMPTY, Index, KNIGHT, Move, MoveContext,
+    NO_SQUARE, PAWN, Piece, QUEEN, ROOK, START_FEN, WHITE, get_piece_color, get_piece_type,
+    king_mask,
+};
+
+// Initial position of Horde: white has 36 pawns and no king.
+pub const HORDE_START_FEN: &str =
+    "rnbqkbnr/pppppppp/8/1PP2PP1/PPPPPPPP/PPPPPPPP/PPPPPPPP/PPPPPPPP w kq - 0 1";
+
+// Initial position of Antichess: the standard position, without castling rights.
+pub const ANTICHESS_START_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1";
+
+// The 4 central squares: d4, e4, d5 and e5.
+const CENTER: Bitboard = 0x0000_0018_1800_0000;
+
+// The rules played on the board. The variants change the way a game can be won, and the move
+// generation, the search and the game loop ask the board for the result of these rules.
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum Variant
+{
+    #[default]
+    Standard,
+    // A side also wins by bringing its king to one of the 4 central squares.
+    KingOfTheHill,
+    // A side without a king, usually white with pawns only, loses when all its pieces are
+    // captured, and wins by checkmate. Its pawns on the first rank can move two squares.
+    Horde,
+    // A capture explodes the capturing piece and every piece around the destination square, pawns
+    // excepted. A side wins by exploding the enemy king. Kings can't capture, and kings standing
+    // next to each other can't be checked, since capturing one would explode the other.
+    Atomic,
+    // Captures are compulsory, there is no check and no castling, and kings are captured like the
+    // other pieces. A side wins by losing all its pieces or by being stalemated. The board holds a
+    // single king for each side, so pawns can't promote to a king.
+    Antichess,
+}
+
+impl Variant
+{
+    pub const ALL: [Variant; 5] = [
+        Variant::Standard,
+        Variant::KingOfTheHill,
+        Variant::Horde,
+        Variant::Atomic,
+        Variant::Antichess,
+    ];
+
+    pub fn name(&self) -> &'static str
+    {
+        return match self
+        {
+            Variant::Standard => "standard",
+            Variant::KingOfTheHill => "kingofthehill",
+            Variant::Horde => "horde",
+            Variant::Atomic => "atomic",
+            Variant::Antichess => "antichess",
+        };
+    }
+
+    // Find a variant by its name, ignoring the case. "koth" is accepted for King of the Hill.
+    pub fn from_name(name: &str) -> Result<Variant, String>
+    {
+        let name = name.to_ascii_lowercase();
+        if name == "koth"
+        {
+            return Ok(Variant::KingOfTheHill);
+        }
+        return Variant::ALL.into_iter().find(|variant| variant.name() == name).ok_or(format!(
+            "Unknown variant '{}'. The variants are: {}.",
+            name,
+            Variant::ALL.map(|variant| variant.name()).join(", ")
+        ));
+    }
+
+    pub fn start_fen(&self) -> &'static str
+    {
+        return match self
+        {
+            Variant::Standard | Variant::KingOfTheHill | Variant::Atomic => START_FEN,
+            Variant::Horde => HORDE_START_FEN,
+            Variant::Antichess => ANTICHESS_START_FEN,
+        };
+    }
+
+    // Tell whether a side can have no king. Only Antichess allows both kings to be missing.
+    pub fn allows_missing_king(&self) -> bool
+    {
+        return matches!(self, Variant::Horde | Variant::Antichess);
+    }
+
+    // Tell whether the variant changes when a king is in check, so that the shortcuts of the move
+    // generation relying on the standard rules can't be used.
+    pub fn changes_check_rules(&self) -> bool
+    {
+        return matches!(self, Variant::Atomic | Variant::Antichess);
+    }
+}
+
+// The pieces removed by a capture in Atomic, to put them back when the move is unmade: the piece
+// that moved, and the pieces around the destination square in the order of the squares. The
+// capturing piece is EMPTY when nothing explodes.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Explosion
+{
+    pub capturer: Piece,
+    pub neighbors: [Piece; 8],
+}
+
+impl Explosion
+{
+    pub const NONE: Explosion = Explosion { capturer: EMPTY, neighbors: [EMPTY; 8] };
+
+    pub fn is_none(&self) -> bool
+    {
+        return self.capturer == EMPTY;
+    }
+}
+
+impl Board
+{
+    // Tell whether the side to move has already lost by a rule of the variant, and how.
+    // Checkmate is not included, since it is found by the move generation.
+    pub fn variant_loss(&self) -> Option<&'static str>
+    {
+        match self.variant
+        {
+            Variant::Standard => (),
+            Variant::KingOfTheHill =>
+            {
+                if self.king_bitboard(!self.white_to_play) & CENTER != 0
+                {
+                    return Some("by reaching the center");
+                }
+            },
+            Variant::Horde =>
+            {
+                let own = if self.white_to_play { self.white_pieces } else { self.black_pieces };
+                if own == 0
+                {
+                    return Some("by capturing the whole horde");
+                }
+            },
+            Variant::Atomic =>
+            {
+                if self.king_bitboard(self.white_to_play) == 0
+                {
+                    return Some("by exploding the king");
+                }
+            },
+            Variant::Antichess => (),
+        }
+        return None;
+    }
+
+    // Tell whether the side to move, having no legal moves, has won by a rule of the variant, and
+    // how. Otherwise, it is either checkmated or stalemated.
+    pub fn variant_win(&self) -> Option<&'static str>
+    {
+        if self.variant != Variant::Antichess
+        {
+            return None;
+        }
+        let own = if self.white_to_play { self.white_pieces } else { self.black_pieces };
+        return Some(if own == 0 { "by losing all its pieces" } else { "by being stalemated" });
+    }
+
+    // Get the pieces removed by a move from 'from' to 'to', that captures or not.
+    // Nothing explodes outside of Atomic.
+    pub fn explosion(&self, from: Index, to: Index, captures: bool) -> Explosion
+    {
+        if self.variant != Variant::Atomic || !captures
+        {
+            return Explosion::NONE;
+        }
+        let mut explosion = Explosion { capturer: self.colored_piece(from), ..Explosion::NONE };
+        let pawns = self.white_pawns | self.black_pawns;
+        let mut bits = king_mask(to);
+        let mut i = 0;
+        while bits != 0
+        {
+            let sq = bits.trailing_zeros() as usize;
+            bits &= bits - 1;
+            // The square left by the capturing piece is empty after the move.
+            if sq != from && (self.pieces & !pawns) & (1u64 << sq) != 0
+            {
+                explosion.neighbors[i] = self.colored_piece(sq);
+            }
+            i += 1;
+        }
+        return explosion;
+    }
+
+    // Remove the capturing piece standing on 'to' and the pieces around it, pawns excepted.
+    pub(crate) fn explode(&mut self, to: Index)
+    {
+        let blast = (1u64 << to) | (king_mask(to) & !(self.white_pawns | self.black_pawns));
+        for bitboard in [
+            &mut self.white_pawns,
+            &mut self.white_rooks,
+            &mut self.white_knights,
+            &mut self.white_bishops,
+            &mut self.white_queens,
+            &mut self.black_pawns,
+            &mut self.black_rooks,
+            &mut self.black_knights,
+            &mut self.black_bishops,
+            &mut self.black_queens,
+            &mut self.white_pieces,
+            &mut self.black_pieces,
+        ]
+        {
+            *bitboard &= !blast;
+        }
+        if self.king_bitboard(true) & blast != 0
+        {
+            self.white_king = NO_SQUARE;
+        }
+        if self.king_bitboard(false) & blast != 0
+        {
+            self.black_king = NO_SQUARE;
+        }
+
+        // An exploded king or rook loses its castling rights.
+        let mut bits = blast;
+        while bits != 0
+        {
+            self.castling_rights &= CASTLING_RIGHTS_MASKS[bits.trailing_zeros() as usize];
+            bits &= bits - 1;
+        }
+    }
+
+    // Put back the pieces removed by the explosion of a move that is being unmade, the capturing
+    // piece on the destination square.
+    pub(crate) fn restore_explosion(&mut self, mv: &Move)
+    {
+        let explosion = mv.exploded;
+        let capturer = match mv.context
+        {
+            MoveContext::Promotion(promoted) => get_piece_color(explosion.capturer) | promoted,
+            _ => explosion.capturer,
+        };
+        self.put_piece(mv.end, capturer);
+        let mut bits = king_mask(mv.end);
+        let mut i = 0;
+        while bits != 0
+        {
+            let sq = bits.trailing_zeros() as usize;
+            bits &= bits - 1;
+            if explosion.neighbors[i] != EMPTY
+            {
+                self.put_piece(sq, explosion.neighbors[i]);
+            }
+            i += 1;
+        }
+    }
+
+    // Get the piece on a square, with its color.
+    fn colored_piece(&self, sq: Index) -> Piece
+    {
+        let color = if self.white_pieces & (1u64 << sq) != 0 { WHITE } else { BLACK };
+        return color | self.piece_at(sq);
+    }
+
+    // Add a piece on an empty square. The bitboard of all the pieces is left to the caller.
+    fn put_piece(&mut self, sq: Index, piece: Piece)
+    {
+        let mask = 1u64 << sq;
+        if get_piece_color(piece) == WHITE
+        {
+            self.white_pieces |= mask;
+            match get_piece_type(piece)
+            {
+                PAWN => self.white_pawns |= mask,
+                ROOK => self.white_rooks |= mask,
+                KNIGHT => self.white_knights |= mask,
+                BISHOP => self.white_bishops |= mask,
+                QUEEN => self.white_queens |= mask,
+                _ => self.white_king = sq,
+            }
+        }
+        else
+        {
+            self.black_pieces |= mask;
+            match get_piece_type(piece)
+            {
+                PAWN => self.black_pawns |= mask,
+                ROOK => self.black_rooks |= mask,
+                KNIGHT => self.black_knights |= mask,
+                BISHOP => self.black_bishops |= mask,
+                QUEEN => self.black_queens |= mask,
+                _ => self.black_king = sq,
+            }
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use js_sys::{Function, Promise, Reflect};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::{JsFuture, future_to_promise};
+
+use crate::*;
+
+// A board usable from JavaScript, with the moves written in UCI.
+#[wasm_bindgen]
+pub struct WasmBoard
+{
+    board: Board,
+}
+
+#[wasm_bindgen]
+impl WasmBoard
+{
+    // Create a board from a FEN, or in the initial position without one.
+    #[wasm_bindgen(constructor)]
+    pub fn new(fen: Option<String>) -> Result<WasmBoard, JsError>
+    {
+        let board = Board::from_fen(fen.as_deref().unwrap_or(START_FEN))
+            .map_err(|err| JsError::new(&err))?;
+        return Ok(WasmBoard { board });
+    }
+
+    #[wasm_bindgen(js_name = setFen)]
+    pub fn set_fen(&mut self, fen: &str) -> Result<(), JsError>
+    {
+        self.board = Board::from_fen(fen).map_err(|err| JsError::new(&err))?;
+        return Ok(());
+    }
+
+    #[wasm_bindgen(js_name = whiteToPlay)]
+    pub fn white_to_play(&self) -> bool
+    {
+        return self.board.white_to_play;
+    }
+
+    #[wasm_bindgen(js_name = legalMoves)]
+    pub fn legal_moves(&mut self) -> Vec<String>
+    {
+        return self.board.get_legal_moves().iter().map(|mv| mv.to_uci()).collect();
+    }
+
+    // Play a move given in UCI or SAN.
+    #[wasm_bindgen(js_name = makeMove)]
+    pub fn make_move(&mut self, name: &str) -> Result<(), JsError>
+    {
+        let mv = parse_move(&mut self.board, name)
+            .ok_or_else(|| JsError::new(&format!("'{}' is not a legal move.", name)))?;
+        self.board.make_move(mv);
+        return Ok(());
+    }
+
+    // Get the text of the board, as printed by the command line.
+    #[wasm_bindgen(js_name = toText)]
+    pub fn to_text(&self) -> String
+    {
+        return self.board.to_text(&DisplayOptions::default());
+    }
+
+    // Search the best move at the given depth, blocking until the search is done.
+    // Return undefined if there are no legal moves.
+    #[wasm_bindgen(js_name = bestMove)]
+    pub fn best_move(&mut self, depth: u8) -> Option<String>
+    {
+        let (_, best) = launch_alpha_beta_quiesce(&mut self.board, depth);
+        return best.map(|mv| mv.to_uci());
+    }
+
+    // Same as 'bestMove', but return a promise, and give control back to the browser between the
+    // iterations of the iterative deepening so that the page stays responsive.
+    #[wasm_bindgen(js_name = bestMoveAsync)]
+    pub fn best_move_async(&self, depth: u8) -> Promise
+    {
+        let mut board = self.board;
+        return future_to_promise(async move {
+            let mut search = Search::default();
+            let mut best = None;
+            for d in 1 ..= depth.max(1)
+            {
+                // The table keeps the results of the previous iterations, so that each call only
+                // does the work of one more iteration.
+                (_, best) = search.search(&mut board, d);
+                yield_to_browser().await;
+            }
+            return Ok(best.map_or(JsValue::UNDEFINED, |mv| JsValue::from_str(&mv.to_uci())));
+        });
+    }
+}
+
+// Wait for the next turn of the browser's event loop, with 'setTimeout(resolve, 0)'.
+async fn yield_to_browser()
+{
+    let promise = Promise::new(&mut |resolve, _| {
+        let global = js_sys::global();
+        let set_timeout = Reflect::get(&global, &JsValue::from_str("setTimeout"))
+            .ok()
+            .and_then(|set_timeout| set_timeout.dyn_into::<Function>().ok());
+        let _ = match set_timeout
+        {
+            Some(set_timeout) => set_timeout.call2(&global, &resolve, &JsValue::from(0)),
+            None => resolve.call0(&JsValue::UNDEFINED),
+        };
+    });
+    let _ = JsFuture::from(promise).await;
+}
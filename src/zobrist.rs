@@ -0,0 +1,174 @@
+use crate::{
+    BLACK, Bitboard, Board, CastlingRights, Index, PAWN, Piece, WHITE, get_piece_type_on_square,
+};
+
+// Arrays containing precomputed random keys.
+// Each part of the position (a piece on a square, a castling right, an en passant file, the side
+// to move) has its own key, and the hash of a position is the XOR of the keys of its parts.
+const PIECE_KEYS: [[u64; 64]; 12] = make_piece_keys();
+const CASTLING_KEYS: [u64; 16] = make_castling_keys(make_keys::<4>(0x9E37_79B9_7F4A_7C15));
+const EN_PASSANT_KEYS: [u64; 8] = make_keys::<8>(0xD1B5_4A32_D192_ED03);
+const SIDE_KEY: u64 = make_keys::<1>(0x8CB9_2BA7_2F3D_8DD7)[0];
+
+// Getters for precomputed keys:
+
+// Get the key of a piece of the given color and type on a square.
+#[inline(always)]
+pub fn piece_key(color: Piece, piece_type: Piece, sq: Index) -> u64
+{
+    let color_offset = if color == WHITE { 0 } else { 6 };
+    return PIECE_KEYS[color_offset + piece_type as usize - 1][sq];
+}
+
+// Get the key of a set of castling rights, which is the XOR of the keys of the rights it contains.
+#[inline(always)]
+pub fn castling_key(rights: CastlingRights) -> u64
+{
+    return CASTLING_KEYS[rights.bits() as usize];
+}
+
+#[inline(always)]
+pub fn en_passant_key(sq: Index) -> u64
+{
+    return EN_PASSANT_KEYS[sq % 8];
+}
+
+#[inline(always)]
+pub fn side_key() -> u64
+{
+    return SIDE_KEY;
+}
+
+// Step of the SplitMix64 generator, used to fill the tables at compile time.
+const fn split_mix(state: u64) -> (u64, u64)
+{
+    let next = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = next;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    return (next, z ^ (z >> 31));
+}
+
+const fn make_keys<const N: usize>(seed: u64) -> [u64; N]
+{
+    let mut keys = [0u64; N];
+    let mut state = seed;
+    let mut i = 0;
+    while i < N
+    {
+        let (next, key) = split_mix(state);
+        state = next;
+        keys[i] = key;
+        i += 1;
+    }
+    return keys;
+}
+
+// Combine the keys of the 4 castling rights into the keys of the 16 sets of rights.
+const fn make_castling_keys(rights_keys: [u64; 4]) -> [u64; 16]
+{
+    let mut keys = [0u64; 16];
+    let mut rights = 0;
+    while rights < 16
+    {
+        let mut bit = 0;
+        while bit < 4
+        {
+            if rights & (1 << bit) != 0
+            {
+                keys[rights] ^= rights_keys[bit];
+            }
+            bit += 1;
+        }
+        rights += 1;
+    }
+    return keys;
+}
+
+const fn make_piece_keys() -> [[u64; 64]; 12]
+{
+    let mut keys = [[0u64; 64]; 12];
+    let mut state = 0x2545_F491_4F6C_DD1D;
+    let mut p = 0;
+    while p < 12
+    {
+        let mut sq = 0;
+        while sq < 64
+        {
+            let (next, key) = split_mix(state);
+            state = next;
+            keys[p][sq] = key;
+            sq += 1;
+        }
+        p += 1;
+    }
+    return keys;
+}
+
+impl Board
+{
+    // Compute the hash of the position from scratch.
+    pub fn compute_hash(&self) -> u64
+    {
+        return self.hash_parts(self.pieces);
+    }
+
+    // Compute the hash of the pawns alone from scratch.
+    pub fn compute_pawn_hash(&self) -> u64
+    {
+        return self.pawn_hash_parts(self.pieces);
+    }
+
+    // Compute the XOR of the keys of the pawns standing on 'squares', the part of 'hash_parts'
+    // that the pawn hash keeps.
+    pub(crate) fn pawn_hash_parts(&self, squares: Bitboard) -> u64
+    {
+        let mut hash = 0u64;
+        for (color, pawns) in [(WHITE, self.white_pawns), (BLACK, self.black_pawns)]
+        {
+            let mut bits = squares & pawns;
+            while bits != 0
+            {
+                hash ^= piece_key(color, PAWN, bits.trailing_zeros() as usize);
+                bits &= bits - 1;
+            }
+        }
+        return hash;
+    }
+
+    // Compute the XOR of the keys of the pieces standing on 'squares', and of the keys of the
+    // castling rights, en passant target and side to move.
+    // A move only changes a few squares, so XORing this value before and after the move gives the
+    // difference between the two hashes.
+    pub(crate) fn hash_parts(&self, squares: Bitboard) -> u64
+    {
+        let mut hash = 0u64;
+
+        // Add the pieces.
+        let mut bits = squares & self.pieces;
+        while bits != 0
+        {
+            let sq = bits.trailing_zeros() as usize;
+            bits &= bits - 1;
+            let color = if self.white_pieces & (1u64 << sq) != 0 { WHITE } else { BLACK };
+            hash ^= piece_key(color, get_piece_type_on_square(self, sq), sq);
+        }
+
+        // Add the castling rights.
+        hash ^= castling_key(self.castling_rights);
+
+        // Add the en passant target.
+        if let Some(ep_sq) = self.en_passant_target
+        {
+            hash ^= en_passant_key(ep_sq);
+        }
+
+        // Add the side to move.
+        if !self.white_to_play
+        {
+            hash ^= side_key();
+        }
+
+        return hash;
+    }
+}